@@ -0,0 +1,65 @@
+/*
+ * C API for debugdb. Build the library with
+ *
+ *     cargo rustc --release --features capi --lib --crate-type cdylib
+ *
+ * Strings returned as `char *` belong to the caller and are freed with
+ * debugdb_string_free. Strings returned as `const char *` are borrowed from
+ * the object they came from. Functions that fail return NULL or false and
+ * leave a description in debugdb_last_error.
+ *
+ * Types are identified by a handle: the type's offset in .debug_info, or in
+ * .debug_types with the top bit set.
+ */
+
+#ifndef DEBUGDB_H
+#define DEBUGDB_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct DebugDb DebugDb;
+typedef struct DebugdbFrames DebugdbFrames;
+
+typedef struct DebugdbFrame {
+    const char *function; /* may be NULL */
+    const char *file;     /* may be NULL */
+    uint32_t line;        /* 0 if unknown */
+    uint32_t column;      /* 0 if unknown */
+} DebugdbFrame;
+
+/* Valid until the next failure on the same thread. */
+const char *debugdb_last_error(void);
+void debugdb_string_free(char *s);
+
+DebugDb *debugdb_open(const char *path);
+void debugdb_close(DebugDb *db);
+
+bool debugdb_type_by_name(const DebugDb *db, const char *name, uint64_t *out);
+char *debugdb_type_name(const DebugDb *db, uint64_t ty);
+/* Static string, such as "struct" or "ptr". */
+const char *debugdb_type_kind(const DebugDb *db, uint64_t ty);
+bool debugdb_type_size(const DebugDb *db, uint64_t ty, uint64_t *out);
+bool debugdb_type_align(const DebugDb *db, uint64_t ty, uint64_t *out);
+
+/* Members of structs and unions, in declaration order. Outputs may be NULL. */
+size_t debugdb_member_count(const DebugDb *db, uint64_t ty);
+bool debugdb_member(const DebugDb *db, uint64_t ty, size_t index,
+                    char **name_out, uint64_t *type_out, uint64_t *offset_out);
+
+/* Innermost frame first. Frames live until debugdb_frames_free. */
+DebugdbFrames *debugdb_addr2line(const DebugDb *db, uint64_t address);
+size_t debugdb_frames_len(const DebugdbFrames *frames);
+const DebugdbFrame *debugdb_frames_get(const DebugdbFrames *frames, size_t index);
+void debugdb_frames_free(DebugdbFrames *frames);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
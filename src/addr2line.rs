@@ -0,0 +1,302 @@
+//! An adapter with the lookup interface of the `addr2line` crate, so that
+//! code written against `addr2line::Context` can be switched over to a
+//! `DebugDb` with few changes.
+//!
+//! The types here mirror their `addr2line` counterparts, with two
+//! differences: borrowed strings take the place of `addr2line`'s reader type
+//! parameter, and `Frame::dw_die_offset` is relative to the start of
+//! `.debug_info` rather than to its unit.
+
+use std::borrow::Cow;
+
+use fallible_iterator::FallibleIterator;
+
+use crate::{DebugDb, ProgramId, Subprogram};
+
+/// Looks up source locations and inlined frames for addresses, like
+/// `addr2line::Context`.
+#[derive(Copy, Clone)]
+pub struct Context<'a> {
+    db: &'a DebugDb,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(db: &'a DebugDb) -> Self {
+        Self { db }
+    }
+
+    /// Finds the source location of the code at `probe`, ignoring inlining.
+    pub fn find_location(&self, probe: u64) -> Result<Option<Location<'a>>, gimli::Error> {
+        Ok(self.db.lookup_line_row(probe).map(|row| Location {
+            file: row.file.as_deref(),
+            line: row.line.and_then(|l| u32::try_from(l.get()).ok()),
+            column: row.column.and_then(|c| u32::try_from(c.get()).ok()),
+        }))
+    }
+
+    /// Lists the source locations of the code in `probe_low..probe_high`, as
+    /// `(address, size, location)`, in address order.
+    pub fn find_location_range(
+        &self,
+        probe_low: u64,
+        probe_high: u64,
+    ) -> Result<LocationRangeIter<'a>, gimli::Error> {
+        // Include a row that starts before the range but overlaps it.
+        let first = self.db.lookup_line_row(probe_low)
+            .filter(|row| row.pc_range.start < probe_low);
+        let rows = first.into_iter()
+            .chain(self.db.line_rows_in(probe_low..probe_high))
+            .map(|row| (
+                row.pc_range.start,
+                row.pc_range.end - row.pc_range.start,
+                Location {
+                    file: row.file.as_deref(),
+                    line: row.line.and_then(|l| u32::try_from(l.get()).ok()),
+                    column: row.column.and_then(|c| u32::try_from(c.get()).ok()),
+                },
+            ))
+            .collect::<Vec<_>>();
+        Ok(LocationRangeIter { rows: rows.into_iter() })
+    }
+
+    /// Finds the functions executing at `probe`, innermost (most recently
+    /// inlined) first, each with its current source location.
+    pub fn find_frames(&self, probe: u64) -> Result<FrameIter<'a>, gimli::Error> {
+        let mut frames = vec![];
+        let Some((pid, subp, calls)) = self.db.inlined_calls_for_pc(probe) else {
+            return Ok(FrameIter { frames: frames.into_iter() });
+        };
+
+        // Each frame's location is the call site of the next one in.
+        let mut outer = (pid, pid.0, subp);
+        for (inl, origin) in calls {
+            frames.push(self.frame(outer, Some(Location {
+                file: inl.call_coord.file.as_deref(),
+                line: inl.call_coord.line.and_then(|l| u32::try_from(l.get()).ok()),
+                column: inl.call_coord.column.and_then(|c| u32::try_from(c.get()).ok()),
+            })));
+            let Some(origin_subp) = self.db.subprogram_by_id(origin) else { break };
+            outer = (origin, inl.offset, origin_subp);
+        }
+        frames.push(self.frame(outer, self.find_location(probe)?));
+
+        frames.reverse();
+        Ok(FrameIter { frames: frames.into_iter() })
+    }
+
+    fn frame(
+        &self,
+        (pid, die, subp): (ProgramId, gimli::UnitSectionOffset, &'a Subprogram),
+        location: Option<Location<'a>>,
+    ) -> Frame<'a> {
+        // Concrete instances often leave names to their abstract origin.
+        let origin = subp.abstract_origin
+            .and_then(|o| self.db.subprogram_by_id(o));
+        let name = subp.linkage_name.as_deref()
+            .or(subp.name.as_deref())
+            .or_else(|| origin.and_then(|o| o.linkage_name.as_deref().or(o.name.as_deref())));
+        let language = self.db.unit_containing(pid)
+            .and_then(|(_, unit)| unit.language);
+        Frame {
+            dw_die_offset: Some(die),
+            function: name.map(|name| FunctionName { name, language }),
+            location,
+        }
+    }
+}
+
+/// A source location, like `addr2line::Location`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Location<'a> {
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// A function executing at an address, like `addr2line::Frame`.
+#[derive(Clone, Debug)]
+pub struct Frame<'a> {
+    /// Offset in `.debug_info` of the subprogram, or of the inlined
+    /// subroutine for inlined frames.
+    pub dw_die_offset: Option<gimli::UnitSectionOffset>,
+    pub function: Option<FunctionName<'a>>,
+    pub location: Option<Location<'a>>,
+}
+
+/// The name of a function, like `addr2line::FunctionName`.
+#[derive(Clone, Debug)]
+pub struct FunctionName<'a> {
+    /// The linkage name if there is one, and otherwise the plain name.
+    pub name: &'a str,
+    /// Source language of the compilation unit containing the function.
+    pub language: Option<gimli::DwLang>,
+}
+
+impl<'a> FunctionName<'a> {
+    pub fn raw_name(&self) -> Result<Cow<'a, str>, gimli::Error> {
+        Ok(Cow::Borrowed(self.name))
+    }
+
+    /// Demangles the name if it's a Rust or C++ symbol, and otherwise
+    /// returns it unchanged.
+    pub fn demangle(&self) -> Result<Cow<'a, str>, gimli::Error> {
+        Ok(crate::demangle::demangle_symbol(self.name)
+            .map_or(Cow::Borrowed(self.name), Cow::Owned))
+    }
+}
+
+/// Frames found by `Context::find_frames`, like `addr2line::FrameIter`.
+pub struct FrameIter<'a> {
+    frames: std::vec::IntoIter<Frame<'a>>,
+}
+
+impl<'a> FallibleIterator for FrameIter<'a> {
+    type Item = Frame<'a>;
+    type Error = gimli::Error;
+
+    fn next(&mut self) -> Result<Option<Frame<'a>>, gimli::Error> {
+        Ok(self.frames.next())
+    }
+}
+
+/// Locations found by `Context::find_location_range`, like
+/// `addr2line::LocationRangeIter`.
+pub struct LocationRangeIter<'a> {
+    rows: std::vec::IntoIter<(u64, u64, Location<'a>)>,
+}
+
+impl<'a> Iterator for LocationRangeIter<'a> {
+    type Item = (u64, u64, Location<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+    use crate::{DeclCoord, InlinedSubroutine, LineNumberRow};
+    use std::num::NonZeroU64;
+
+    fn subprogram(
+        name: &str,
+        pc_range: Option<std::ops::Range<u64>>,
+        inlines: Vec<InlinedSubroutine>,
+        offset: gimli::UnitSectionOffset,
+    ) -> Subprogram {
+        Subprogram {
+            name: Some(name.to_string()),
+            pc_ranges: pc_range.iter().cloned().collect(),
+            pc_range,
+            decl_coord: Default::default(),
+            template_type_parameters: vec![],
+            return_type_id: None,
+            formal_parameters: vec![],
+            inlines,
+            abstract_origin: None,
+            linkage_name: None,
+            noreturn: false,
+            frame_base: None,
+            offset,
+        }
+    }
+
+    fn call(
+        origin: gimli::UnitSectionOffset,
+        range: std::ops::Range<u64>,
+        line: u64,
+        offset: gimli::UnitSectionOffset,
+    ) -> InlinedSubroutine {
+        InlinedSubroutine {
+            abstract_origin: Some(ProgramId(origin)),
+            pc_ranges: vec![range],
+            call_coord: DeclCoord {
+                file: Some("main.c".to_string()),
+                line: NonZeroU64::new(line),
+                column: None,
+            },
+            inlines: vec![],
+            formal_parameters: vec![],
+            offset,
+        }
+    }
+
+    /// Builds a database with `outer` at 0x1000..0x1100, which has `inner`
+    /// inlined into it at 0x1010..0x1020, and again in code the linker
+    /// discarded, leaving its range starting at zero.
+    fn world() -> (DebugDb, [gimli::UnitSectionOffset; 3]) {
+        let mut types = TestTypes::little();
+        let [outer, inner, inlined] = [(); 3].map(|_| types.offset());
+        let discarded = types.offset();
+        types.builder.record_subprogram(subprogram("inner", None, vec![], inner));
+        types.builder.record_subprogram(subprogram(
+            "outer",
+            Some(0x1000..0x1100),
+            vec![call(inner, 0..0x2000, 5, discarded), call(inner, 0x1010..0x1020, 7, inlined)],
+            outer,
+        ));
+        types.builder.record_line_table_row(0x1000, LineNumberRow {
+            pc_range: 0x1000..0x1100,
+            file: Some("main.c".to_string()),
+            line: NonZeroU64::new(3),
+            column: None,
+        });
+        (types.build(), [outer, inner, inlined])
+    }
+
+    /// Lists the DIE offset, name and line of each frame at `probe`.
+    fn frames<'a>(ctx: &Context<'a>, probe: u64) -> Vec<(gimli::UnitSectionOffset, &'a str, u32)> {
+        ctx.find_frames(probe).unwrap()
+            .map(|f| Ok((
+                f.dw_die_offset.unwrap(),
+                f.function.unwrap().name,
+                f.location.unwrap().line.unwrap(),
+            )))
+            .collect()
+            .unwrap()
+    }
+
+    #[test]
+    fn inlined_frames() {
+        let (db, [outer, _, inlined]) = world();
+        let ctx = Context::new(&db);
+        assert_eq!(frames(&ctx, 0x1010), [(inlined, "inner", 3), (outer, "outer", 7)]);
+
+        // The frames agree with the database's own symbolization.
+        let names: Vec<_> = db.symbolize_pc(0x1010).functions.into_iter()
+            .map(|f| f.name.unwrap())
+            .collect();
+        assert_eq!(names, ["inner", "outer"]);
+    }
+
+    #[test]
+    fn ignores_discarded_code() {
+        let (db, [outer, ..]) = world();
+        let ctx = Context::new(&db);
+        assert_eq!(frames(&ctx, 0x1050), [(outer, "outer", 3)]);
+        assert!(frames(&ctx, 0x1100).is_empty());
+    }
+
+    #[test]
+    fn stops_at_inlined_code_of_unknown_origin() {
+        let mut types = TestTypes::little();
+        let [outer, inlined] = [(); 2].map(|_| types.offset());
+        let mut unknown = call(outer, 0x1010..0x1020, 7, inlined);
+        unknown.abstract_origin = None;
+        types.builder.record_subprogram(subprogram(
+            "outer",
+            Some(0x1000..0x1100),
+            vec![unknown],
+            outer,
+        ));
+        let db = types.build();
+        assert_eq!(db.warnings().len(), 1);
+
+        let names: Vec<_> = db.symbolize_pc(0x1010).functions.into_iter()
+            .map(|f| f.name.unwrap())
+            .collect();
+        assert_eq!(names, ["outer"]);
+    }
+}
@@ -0,0 +1,321 @@
+//! Support for firmware archives: zip files bundling several programs that
+//! are loaded together, such as the kernel and tasks of a Hubris image.
+//!
+//! Only what such archives use is supported: stored and deflated entries, and
+//! no zip64 extensions. The programs found can be combined into a single
+//! database with `parse_images`.
+
+use std::io::Read;
+use thiserror::Error;
+
+const LOCAL_HEADER: &[u8; 4] = b"PK\x03\x04";
+const CENTRAL_HEADER: &[u8; 4] = b"PK\x01\x02";
+const END_OF_DIRECTORY: &[u8; 4] = b"PK\x05\x06";
+const END_OF_DIRECTORY_LEN: usize = 22;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+#[derive(Clone, Debug, Error)]
+pub enum ArchiveError {
+    #[error("not a zip archive")]
+    BadMagic,
+    #[error("zip archive has no end of central directory record")]
+    NoDirectory,
+    #[error("archive truncated at offset {0:#x}")]
+    Truncated(usize),
+    #[error("bad header at offset {0:#x}")]
+    BadHeader(usize),
+    #[error("entry {0} uses unsupported compression method {1}")]
+    UnsupportedMethod(String, u16),
+    #[error("entry {0} could not be decompressed")]
+    Decompress(String),
+    #[error("entry {0} is corrupt (CRC mismatch)")]
+    BadCrc(String),
+}
+
+/// The files in an archive.
+#[derive(Clone, Debug)]
+pub struct Archive {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl Archive {
+    /// Checks whether `data` starts like a zip archive.
+    pub fn is_archive(data: &[u8]) -> bool {
+        data.starts_with(LOCAL_HEADER)
+    }
+
+    /// Reads and decompresses every file in an archive.
+    pub fn parse(data: &[u8]) -> Result<Self, ArchiveError> {
+        if !Self::is_archive(data) {
+            return Err(ArchiveError::BadMagic);
+        }
+
+        // The end of central directory record is at the end of the file,
+        // followed by a comment of up to 64k.
+        let eocd = (0..=data.len().saturating_sub(END_OF_DIRECTORY_LEN))
+            .rev()
+            .take(0x10000 + 1)
+            .find(|&i| data[i..].starts_with(END_OF_DIRECTORY))
+            .ok_or(ArchiveError::NoDirectory)?;
+        let count = u16le(data, eocd + 10)? as usize;
+        let mut pos = u32le(data, eocd + 16)? as usize;
+
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            if !data.get(pos..).is_some_and(|d| d.starts_with(CENTRAL_HEADER)) {
+                return Err(ArchiveError::BadHeader(pos));
+            }
+            let method = u16le(data, pos + 10)?;
+            let crc = u32le(data, pos + 16)?;
+            let compressed_size = u32le(data, pos + 20)? as usize;
+            let size = u32le(data, pos + 24)? as usize;
+            let name_len = u16le(data, pos + 28)? as usize;
+            let extra_len = u16le(data, pos + 30)? as usize;
+            let comment_len = u16le(data, pos + 32)? as usize;
+            let local = u32le(data, pos + 42)? as usize;
+            let name = bytes(data, pos + 46, name_len)?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            pos += 46 + name_len + extra_len + comment_len;
+
+            if name.ends_with('/') {
+                continue;
+            }
+
+            // The sizes in the local header may be left as zero and given
+            // after the data instead, so they're taken from the directory.
+            if !data.get(local..).is_some_and(|d| d.starts_with(LOCAL_HEADER)) {
+                return Err(ArchiveError::BadHeader(local));
+            }
+            let start = local + 30
+                + u16le(data, local + 26)? as usize
+                + u16le(data, local + 28)? as usize;
+            let raw = bytes(data, start, compressed_size)?;
+
+            let contents = match method {
+                METHOD_STORED => raw.to_vec(),
+                METHOD_DEFLATED => {
+                    let mut out = Vec::with_capacity(size);
+                    flate2::read::DeflateDecoder::new(raw)
+                        .read_to_end(&mut out)
+                        .map_err(|_| ArchiveError::Decompress(name.clone()))?;
+                    out
+                }
+                _ => return Err(ArchiveError::UnsupportedMethod(name, method)),
+            };
+            let mut check = flate2::Crc::new();
+            check.update(&contents);
+            if contents.len() != size || check.sum() != crc {
+                return Err(ArchiveError::BadCrc(name));
+            }
+            entries.push((name, contents));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Returns an iterator over the names and contents of the files in the
+    /// archive, in the order they're listed.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries.iter().map(|(n, c)| (n.as_str(), c.as_slice()))
+    }
+
+    /// Returns the contents of the file called `name`, if present.
+    pub fn entry(&self, name: &str) -> Option<&[u8]> {
+        self.entries().find(|(n, _)| *n == name).map(|(_, c)| c)
+    }
+
+    /// Finds the programs in the archive, giving each a name.
+    ///
+    /// Hubris archives keep the kernel at `elf/kernel` and each task at
+    /// `elf/task/<name>`; if there are ELF files under `elf/`, only those are
+    /// used, and they're named by the rest of their path (so tasks are named
+    /// after the task). Otherwise every ELF file in the archive is used, named
+    /// by its path.
+    pub fn images(&self) -> Vec<(&str, &[u8])> {
+        let elves = self.entries()
+            .filter(|(_, c)| c.starts_with(b"\x7fELF"))
+            .collect::<Vec<_>>();
+        if elves.iter().any(|(n, _)| n.starts_with("elf/")) {
+            elves.into_iter()
+                .filter_map(|(n, c)| {
+                    let n = n.strip_prefix("elf/")?;
+                    Some((n.strip_prefix("task/").unwrap_or(n), c))
+                })
+                .collect()
+        } else {
+            elves
+        }
+    }
+}
+
+fn bytes(data: &[u8], pos: usize, len: usize) -> Result<&[u8], ArchiveError> {
+    pos.checked_add(len)
+        .and_then(|end| data.get(pos..end))
+        .ok_or(ArchiveError::Truncated(pos))
+}
+
+fn u16le(data: &[u8], pos: usize) -> Result<u16, ArchiveError> {
+    Ok(u16::from_le_bytes(bytes(data, pos, 2)?.try_into().unwrap()))
+}
+
+fn u32le(data: &[u8], pos: usize) -> Result<u32, ArchiveError> {
+    Ok(u32::from_le_bytes(bytes(data, pos, 4)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// An entry for `zip`, which is compressed with `method`.
+    struct Entry<'a> {
+        name: &'a str,
+        contents: &'a [u8],
+        method: u16,
+    }
+
+    fn stored<'a>(name: &'a str, contents: &'a [u8]) -> Entry<'a> {
+        Entry { name, contents, method: METHOD_STORED }
+    }
+
+    fn deflated<'a>(name: &'a str, contents: &'a [u8]) -> Entry<'a> {
+        Entry { name, contents, method: METHOD_DEFLATED }
+    }
+
+    /// Writes a zip archive. The local headers leave the CRC and sizes as zero,
+    /// as streaming writers do, so they have to be taken from the directory.
+    fn zip(entries: &[Entry<'_>]) -> Vec<u8> {
+        let mut out = vec![];
+        let mut directory = vec![];
+        for e in entries {
+            let data = match e.method {
+                METHOD_DEFLATED => {
+                    let mut enc = flate2::write::DeflateEncoder::new(
+                        vec![],
+                        flate2::Compression::default(),
+                    );
+                    enc.write_all(e.contents).unwrap();
+                    enc.finish().unwrap()
+                }
+                _ => e.contents.to_vec(),
+            };
+            let mut crc = flate2::Crc::new();
+            crc.update(e.contents);
+            let local = out.len() as u32;
+
+            out.extend_from_slice(LOCAL_HEADER);
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&e.method.to_le_bytes());
+            out.extend_from_slice(&[0; 16]);
+            out.extend_from_slice(&(e.name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(e.name.as_bytes());
+            out.extend_from_slice(&data);
+
+            directory.extend_from_slice(CENTRAL_HEADER);
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            directory.extend_from_slice(&e.method.to_le_bytes());
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&crc.sum().to_le_bytes());
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(e.contents.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(e.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&local.to_le_bytes());
+            directory.extend_from_slice(e.name.as_bytes());
+        }
+        let directory_start = out.len() as u32;
+        out.extend_from_slice(&directory);
+        out.extend_from_slice(END_OF_DIRECTORY);
+        out.extend_from_slice(&[0; 4]);
+        for _ in 0..2 {
+            out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        }
+        out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&directory_start.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out
+    }
+
+    const TEXT: &[u8] = b"the quick brown fox jumps over the lazy dog, \
+        over and over and over again";
+
+    #[test]
+    fn stored_and_deflated() {
+        let data = zip(&[
+            stored("app.toml", b"name = \"demo\""),
+            stored("elf/", b""),
+            deflated("README", TEXT),
+            deflated("empty", b""),
+        ]);
+        assert!(Archive::is_archive(&data));
+        let archive = Archive::parse(&data).unwrap();
+        let names: Vec<_> = archive.entries().map(|(n, _)| n).collect();
+        assert_eq!(names, ["app.toml", "README", "empty"]);
+        assert_eq!(archive.entry("app.toml"), Some(&b"name = \"demo\""[..]));
+        assert_eq!(archive.entry("README"), Some(TEXT));
+        assert_eq!(archive.entry("empty"), Some(&b""[..]));
+        assert_eq!(archive.entry("missing"), None);
+    }
+
+    #[test]
+    fn hubris_images() {
+        let data = zip(&[
+            deflated("elf/kernel", b"\x7fELF kernel"),
+            deflated("elf/task/idle", b"\x7fELF idle"),
+            stored("img/final.bin", b"\x7fELF but not under elf/"),
+            stored("elf/task/notes.txt", b"not an ELF file"),
+        ]);
+        let archive = Archive::parse(&data).unwrap();
+        let names: Vec<_> = archive.images().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["kernel", "idle"]);
+
+        let data = zip(&[stored("a/one", b"\x7fELF 1"), stored("two", b"\x7fELF 2")]);
+        let archive = Archive::parse(&data).unwrap();
+        let names: Vec<_> = archive.images().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["a/one", "two"]);
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let mut data = zip(&[stored("file", b"contents")]);
+        let at = data.windows(8).position(|w| w == b"contents").unwrap();
+        data[at] ^= 1;
+        assert!(matches!(Archive::parse(&data), Err(ArchiveError::BadCrc(n)) if n == "file"));
+    }
+
+    #[test]
+    fn bad_deflate_stream() {
+        let mut data = zip(&[deflated("file", TEXT)]);
+        // Make the first block a reserved block type.
+        let at = LOCAL_HEADER.len() + 26 + "file".len();
+        data[at] |= 0b110;
+        assert!(matches!(Archive::parse(&data), Err(ArchiveError::Decompress(n)) if n == "file"));
+    }
+
+    #[test]
+    fn unsupported_method() {
+        let data = zip(&[Entry { name: "file", contents: b"bz", method: 12 }]);
+        assert!(matches!(
+            Archive::parse(&data),
+            Err(ArchiveError::UnsupportedMethod(n, 12)) if n == "file"
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = zip(&[stored("a", b"one"), deflated("b", TEXT)]);
+        for len in 0..data.len() {
+            assert!(Archive::parse(&data[..len]).is_err(), "parsed {len} bytes");
+        }
+        assert!(matches!(Archive::parse(b"not a zip"), Err(ArchiveError::BadMagic)));
+        assert!(matches!(Archive::parse(&data[..40]), Err(ArchiveError::NoDirectory)));
+
+        // An entry claiming to be bigger than the archive.
+        let mut data = zip(&[stored("a", b"one")]);
+        let dir = data.windows(4).position(|w| w == CENTRAL_HEADER).unwrap();
+        data[dir + 20..dir + 24].copy_from_slice(&0x1000u32.to_le_bytes());
+        assert!(matches!(Archive::parse(&data), Err(ArchiveError::Truncated(_))));
+    }
+}
@@ -10,9 +10,10 @@ fn main() -> Result<()> {
     let args = Sketch::parse();
 
     let buffer = std::fs::read(args.filename)?;
-    let object = object::File::parse(&*buffer)?;
-
-    debugdb::parse_file(&object)?;
+    let db = debugdb::parse_bytes(&buffer)?;
+    for warning in db.warnings() {
+        eprintln!("WARN: {warning}");
+    }
 
     Ok(())
 }
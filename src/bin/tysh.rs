@@ -1,50 +1,175 @@
-use std::{fmt::Display, io::BufRead};
+use std::{borrow::Cow, fmt::{Display, Write as _}, io::BufRead};
 
+use ansi_term::{Colour, Style};
 use anyhow::Result;
 use clap::Parser;
 use debugdb::value::ValueWithDb;
-use object::{Object, ObjectSegment};
 use rangemap::{RangeMap, RangeInclusiveMap};
 
-use debugdb::{Type, Encoding, TypeId, Struct, Member, DebugDb, Enum, VariantShape, value::Value};
+use debugdb::{Type, Encoding, TypeId, Struct, Member, DebugDb, Enum, VariantShape, Dependency, NameMatch, value::Value};
 use debugdb::load::{Load, ImgMachine};
+use debugdb::wasm::WasmModule;
+use debugdb::archive::Archive;
+#[cfg(feature = "pdb")]
+use debugdb::{pdb::is_pdb, parse_pdb};
+use debugdb::coroutine::StateKind;
+use debugdb::diff::Difference;
+use object::Object;
 use regex::Regex;
 
 #[derive(Debug, Parser)]
 struct TySh {
     filename: std::path::PathBuf,
+    /// Core file to read memory contents from, instead of the executable's
+    /// initial image.
+    #[arg(long)]
+    core: Option<std::path::PathBuf>,
+    /// Load split DWARF for the program from `.dwo` files.
+    #[arg(long)]
+    split_dwarf: bool,
+    /// Load split DWARF for the program from a DWARF package.
+    #[arg(long)]
+    dwp: Option<std::path::PathBuf>,
+    /// Cache the parsed debug info in this file. If it was made from this
+    /// build of the program, it's loaded instead of parsing the program again.
+    #[arg(long)]
+    cache: Option<std::path::PathBuf>,
+    /// Print the results of `list`, `info`, `sizeof`, `def` and `addr2line`
+    /// as JSON, for consumption by scripts.
+    #[arg(long)]
+    json: bool,
+    /// Print the results of `list`, `vars`, `funcs` and `members` as CSV,
+    /// for loading into a spreadsheet.
+    #[arg(long, conflicts_with = "json")]
+    csv: bool,
+    /// Don't load or save command history. By default it's kept in
+    /// `$XDG_DATA_HOME/tysh/history`, or `~/.local/share/tysh/history`.
+    #[arg(long)]
+    no_history: bool,
+    /// Instead of prompting for commands, answer JSON-RPC 2.0 requests on
+    /// stdin, one per line. The methods are `lookup`, `sizeof`, `addr2line`
+    /// and `decode`.
+    #[arg(long, conflicts_with = "listen")]
+    serve: bool,
+    /// Like `--serve`, but accept connections on this address, such as
+    /// `localhost:7070`, and answer each in turn.
+    #[arg(long)]
+    listen: Option<String>,
+    /// CMSIS-SVD file describing the target's peripherals, so that `addr`
+    /// can name registers and `layout` and `members` can match struct
+    /// members to them.
+    #[arg(long)]
+    svd: Option<std::path::PathBuf>,
+}
+
+/// Number of commands kept in the history file.
+const HISTORY_SIZE: usize = 1000;
+
+/// Works out where to keep command history, following the XDG base directory
+/// conventions.
+fn history_path() -> Option<std::path::PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|d| !d.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            let home = std::env::var_os("HOME").filter(|h| !h.is_empty())?;
+            Some(std::path::Path::new(&home).join(".local/share"))
+        })?;
+    Some(data_home.join("tysh").join("history"))
 }
 
 fn main() -> Result<()> {
     let args = TySh::parse();
 
-    let buffer = std::fs::read(args.filename)?;
-    let object = object::File::parse(&*buffer)?;
-    let mut segments = RangeInclusiveMap::new();
-    for seg in object.segments() {
-        if seg.size() == 0 {
-            continue;
+    let first = load_program(&args.filename, LoadOptions {
+        core: args.core.clone(),
+        split_dwarf: args.split_dwarf,
+        dwp: args.dwp.clone(),
+        cache: args.cache.clone(),
+    })?;
+
+    let mut ctx = Ctx {
+        segments: RangeInclusiveMap::new(),
+        sp_register: None,
+        defmt: None,
+        json: args.json,
+        csv: args.csv,
+        depth: 1,
+        width: None,
+        list_sort: None,
+        unit: None,
+        svd: match &args.svd {
+            Some(path) => Some(load_svd(path)?),
+            None => None,
+        },
+        #[cfg(any(feature = "gdb", feature = "probe-rs"))]
+        target: None,
+    };
+
+    if args.serve || args.listen.is_some() {
+        eprintln!("Loaded; {} types found in program.", first.db.type_count());
+        let mut programs = vec![first];
+        switch_program(&mut programs, &mut 0, 0, &mut ctx);
+        let db = &programs[0].db;
+        COLOR.store(false, std::sync::atomic::Ordering::Relaxed);
+        if let Some(addr) = &args.listen {
+            let listener = std::net::TcpListener::bind(addr)?;
+            eprintln!("Listening on {}", listener.local_addr()?);
+            for stream in listener.incoming() {
+                let stream = stream?;
+                let reader = std::io::BufReader::new(stream.try_clone()?);
+                // A client going away only ends its own connection.
+                if let Err(e) = serve_rpc(db, &mut ctx, reader, stream) {
+                    eprintln!("connection closed: {e}");
+                }
+            }
+        } else {
+            serve_rpc(db, &mut ctx, std::io::stdin().lock(), std::io::stdout().lock())?;
         }
-        segments.insert(
-            seg.address()..=seg.address() + (seg.size() - 1),
-            seg.data()?.to_vec(),
-        );
+        return Ok(());
     }
-    let everything = debugdb::parse_file(&object)?;
 
-    println!("Loaded; {} types found in program.", everything.type_count());
-    println!("To quit: ^D or exit");
+    // Keep stdout clean for JSON consumers.
+    let banner = format!(
+        "Loaded; {} types found in program.\nTo quit: ^D or exit",
+        first.db.type_count(),
+    );
+    if args.json || args.csv {
+        eprintln!("{banner}");
+    } else {
+        println!("{banner}");
+    }
 
-    let mut rl = rustyline::Editor::<(), _>::new()?;
-    let prompt = ansi_term::Colour::Green.paint(">> ").to_string();
-    let mut ctx = Ctx { segments };
+    let config = rustyline::Config::builder()
+        .completion_type(rustyline::CompletionType::List)
+        .max_history_size(HISTORY_SIZE)?
+        .history_ignore_dups(true)?
+        .build();
+    let mut rl = rustyline::Editor::with_config(config)?;
+    rl.set_helper(Some(TyshHelper::new(first.db.clone())));
+    let history = if args.no_history { None } else { history_path() };
+    if let Some(path) = &history {
+        // A missing file just means there's no history yet.
+        let _ = rl.load_history(path);
+    }
+    let mut programs = vec![first];
+    let mut current = 0;
+    switch_program(&mut programs, &mut current, 0, &mut ctx);
     'lineloop:
     loop {
+        let prompt = if programs.len() > 1 {
+            colour(Colour::Green).paint(format!("[{}] >> ", current + 1)).to_string()
+        } else {
+            colour(Colour::Green).paint(">> ").to_string()
+        };
         match rl.readline(&prompt) {
             Ok(line) => {
                 let line = line.trim();
-                let (cmd, rest) = line.split_once(char::is_whitespace)
-                    .unwrap_or((line, ""));
+                // `=` doesn't need a space after it.
+                let (cmd, rest) = match line.strip_prefix('=') {
+                    Some(rest) => ("=", rest),
+                    None => line.split_once(char::is_whitespace).unwrap_or((line, "")),
+                };
                 if line.is_empty() {
                     continue 'lineloop;
                 }
@@ -59,14 +184,83 @@ fn main() -> Result<()> {
                             .map(|(name, _, _)| name.len())
                             .max()
                             .unwrap_or(12);
-                        for (name, _, desc) in COMMANDS {
+                        let commands = SESSION_COMMANDS.iter().copied()
+                            .chain(COMMANDS.iter().map(|&(name, _, desc)| (name, desc)));
+                        for (name, desc) in commands {
+                            println!("{:name_len$} {}", name, desc);
+                        }
+                        let tables = TABLE_COMMANDS.iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>();
+                        println!();
+                        println!("the results of {} can be piped through these, \
+                            like `list Buffer | sort size:desc | head 20`:", tables.join(", "));
+                        for (name, desc) in PIPE_STAGES {
                             println!("{:name_len$} {}", name, desc);
                         }
                     }
+                    "open" => {
+                        let path = std::path::Path::new(rest.trim());
+                        if rest.trim().is_empty() {
+                            println!("usage: open FILENAME");
+                            continue 'lineloop;
+                        }
+                        match load_program(path, LoadOptions::default()) {
+                            Ok(p) => {
+                                println!("Loaded {} as file {}; {} types found in program.",
+                                    path.display(), programs.len() + 1, p.db.type_count());
+                                programs.push(p);
+                                let n = programs.len() - 1;
+                                switch_program(&mut programs, &mut current, n, &mut ctx);
+                                rl.set_helper(Some(TyshHelper::new(programs[n].db.clone())));
+                            }
+                            Err(e) => println!("can't open {}: {e}", path.display()),
+                        }
+                    }
+                    "reload" => {
+                        let p = &programs[current];
+                        match load_program(&p.path.clone(), p.options.clone()) {
+                            Ok(mut p) => {
+                                println!("Reloaded {}; {} types found in program.",
+                                    p.path.display(), p.db.type_count());
+                                ctx.segments = std::mem::take(&mut p.segments);
+                                ctx.sp_register = p.sp_register.take();
+                                ctx.defmt = p.defmt.take();
+                                ctx.unit = None;
+                                rl.set_helper(Some(TyshHelper::new(p.db.clone())));
+                                programs[current] = p;
+                            }
+                            Err(e) => println!("can't reload {}: {e}", p.path.display()),
+                        }
+                    }
+                    "file" => {
+                        let rest = rest.trim();
+                        if rest.is_empty() {
+                            for (i, p) in programs.iter().enumerate() {
+                                let marker = if i == current { "*" } else { " " };
+                                println!("{marker} {} {}", i + 1, p.path.display());
+                            }
+                            continue 'lineloop;
+                        }
+                        match rest.parse::<usize>() {
+                            Ok(n) if (1..=programs.len()).contains(&n) => {
+                                if n - 1 != current {
+                                    switch_program(&mut programs, &mut current, n - 1, &mut ctx);
+                                    rl.set_helper(Some(TyshHelper::new(programs[n - 1].db.clone())));
+                                }
+                                println!("Switched to {}", programs[n - 1].path.display());
+                            }
+                            _ => println!("no file {rest}; files are numbered 1 to {}",
+                                programs.len()),
+                        }
+                    }
+                    _ if line.contains(" | ") => {
+                        run_pipeline(&programs[current].db, &mut ctx, cmd, rest);
+                    }
                     _ => {
                         for (name, imp, _) in COMMANDS {
                             if *name == cmd {
-                                imp(&everything, &mut ctx, rest);
+                                imp(&programs[current].db, &mut ctx, rest);
                                 continue 'lineloop;
                             }
                         }
@@ -79,6 +273,7 @@ fn main() -> Result<()> {
                 println!("^C");
                 continue;
             }
+            Err(rustyline::error::ReadlineError::Eof) => break,
             Err(e) => {
                 println!("{:?}", e);
                 break;
@@ -86,14 +281,194 @@ fn main() -> Result<()> {
         }
     }
 
+    if let Some(path) = &history {
+        let saved = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir).map_err(Into::into),
+            None => Ok(()),
+        }.and_then(|()| rl.save_history(path));
+        if let Err(e) = saved {
+            eprintln!("couldn't save history to {}: {}", path.display(), e);
+        }
+    }
+
     Ok(())
 }
 
-struct Goff(gimli::UnitSectionOffset);
+/// Commands that act on the session rather than a program, and so are handled
+/// by the main loop instead of being in `COMMANDS`.
+const SESSION_COMMANDS: &[(&str, &str)] = &[
+    ("open", "load another program, and switch to it"),
+    ("file", "list the loaded programs, or switch to program N"),
+    ("reload", "read the current program again, e.g. after rebuilding it"),
+];
+
+/// A program loaded into the session.
+struct Program {
+    path: std::path::PathBuf,
+    db: std::rc::Rc<DebugDb>,
+    /// The program's memory image, stack pointer register and defmt strings.
+    /// While the program is current, these are moved into `Ctx`, and these
+    /// are left empty; see `switch_program`.
+    segments: RangeInclusiveMap<u64, Vec<u8>>,
+    sp_register: Option<gimli::Register>,
+    defmt: Option<debugdb::defmt::Table>,
+    /// How the program was loaded, so that `reload` can do it again.
+    options: LoadOptions,
+}
+
+/// Options for loading a program, from the command line. Programs loaded
+/// with `open` use the defaults.
+#[derive(Clone, Default)]
+struct LoadOptions {
+    core: Option<std::path::PathBuf>,
+    split_dwarf: bool,
+    dwp: Option<std::path::PathBuf>,
+    cache: Option<std::path::PathBuf>,
+}
+
+fn load_program(filename: &std::path::Path, opts: LoadOptions) -> Result<Program> {
+    let buffer = std::fs::read(filename)?;
+    let mut segments = RangeInclusiveMap::new();
+    let mut sp_register = None;
+    let mut defmt = None;
+    let identity = debugdb::cache::ProgramIdentity::of_file(filename, &buffer)?;
+    let mut cached = opts.cache.as_deref()
+        .and_then(|cache| load_cache(cache, &identity));
+    let from_cache = cached.is_some();
+    let everything = if WasmModule::is_wasm(&buffer) {
+        let module = WasmModule::parse(&buffer)?;
+        if let Some(offset) = module.code_offset() {
+            eprintln!("Code addresses are relative to module offset {offset:#x}");
+        }
+        match cached.take() {
+            Some(db) => db,
+            None => debugdb::parse_wasm(&module)?,
+        }
+    } else if is_pdb(&buffer) {
+        match cached.take() {
+            Some(db) => db,
+            None => parse_pdb(&buffer)?,
+        }
+    } else if Archive::is_archive(&buffer) {
+        let archive = Archive::parse(&buffer)?;
+        let images = archive.images().into_iter()
+            .map(|(name, data)| Ok((name, object::File::parse(data)?)))
+            .collect::<Result<Vec<_>>>()?;
+        for (_, object) in &images {
+            debugdb::load::load_segments(&mut segments, object)?;
+        }
+        if let Some((_, first)) = images.first() {
+            sp_register = debugdb::unwind::stack_pointer_register(first.architecture());
+        }
+        match cached.take() {
+            Some(db) => db,
+            None => {
+                let images = images.iter()
+                    .map(|(name, object)| (*name, object))
+                    .collect::<Vec<_>>();
+                debugdb::parse_images(&images)?
+            }
+        }
+    } else {
+        let object = object::File::parse(&*buffer)?;
+        debugdb::load::load_segments(&mut segments, &object)?;
+        sp_register = debugdb::unwind::stack_pointer_register(object.architecture());
+        defmt = debugdb::defmt::Table::parse(&object).unwrap_or_else(|e| {
+            eprintln!("can't read defmt strings: {e}");
+            None
+        });
+        if let Some(core) = &opts.core {
+            let core_buffer = std::fs::read(core)?;
+            let core_object = object::File::parse(&*core_buffer)?;
+            debugdb::load::load_segments(&mut segments, &core_object)?;
+        }
+        if let Some(db) = cached.take() {
+            db
+        } else if let Some(dwp) = &opts.dwp {
+            let dwp_buffer = std::fs::read(dwp)?;
+            let dwp_object = object::File::parse(&*dwp_buffer)?;
+            debugdb::parse_split_file(&object, Some(&dwp_object))?
+        } else if opts.split_dwarf {
+            debugdb::parse_split_file(&object, None)?
+        } else if let Some(dsym) = debugdb::dsym_path(filename) {
+            eprintln!("Reading debug info from {}", dsym.display());
+            let dsym_buffer = std::fs::read(dsym)?;
+            let dsym_object = object::File::parse(&*dsym_buffer)?;
+            debugdb::parse_file_with_dsym(&object, &dsym_object)?
+        } else {
+            debugdb::parse_file(&object)?
+        }
+    };
+
+    // Warnings from a cached database were already shown when it was parsed.
+    if !from_cache {
+        for warning in everything.warnings() {
+            eprintln!("WARN: {warning}");
+        }
+    }
+
+    if let (Some(cache), false) = (&opts.cache, from_cache) {
+        let file = std::fs::File::create(cache)?;
+        debugdb::cache::save(&everything, &identity, std::io::BufWriter::new(file))?;
+        eprintln!("Saved debug info to {}", cache.display());
+    }
+
+    Ok(Program {
+        path: filename.to_path_buf(),
+        db: std::rc::Rc::new(everything),
+        segments,
+        sp_register,
+        defmt,
+        options: opts,
+    })
+}
+
+/// Reads the peripheral descriptions in an SVD file.
+fn load_svd(path: &std::path::Path) -> Result<debugdb::svd::Device> {
+    let device = debugdb::svd::Device::parse(&std::fs::read_to_string(path)?)?;
+    eprintln!("Read {} peripherals of {} from {}",
+        device.peripherals.len(), device.name, path.display());
+    Ok(device)
+}
+
+/// Makes program `n` the current one, moving its memory image into `ctx` and
+/// the previous program's back out.
+///
+/// If `n` is already current, its image is assumed to still be in the
+/// program rather than `ctx`, as it is at startup.
+fn switch_program(programs: &mut [Program], current: &mut usize, n: usize, ctx: &mut Ctx) {
+    if n != *current {
+        let old = &mut programs[*current];
+        old.segments = std::mem::take(&mut ctx.segments);
+        old.sp_register = ctx.sp_register.take();
+        old.defmt = ctx.defmt.take();
+    }
+    let new = &mut programs[n];
+    ctx.segments = std::mem::take(&mut new.segments);
+    ctx.sp_register = new.sp_register.take();
+    ctx.defmt = new.defmt.take();
+    // Units are particular to a program.
+    ctx.unit = None;
+    *current = n;
+}
+
+/// Without the `pdb` feature, PDBs aren't recognized, and are rejected as
+/// unknown object files.
+#[cfg(not(feature = "pdb"))]
+fn is_pdb(_data: &[u8]) -> bool {
+    false
+}
+
+#[cfg(not(feature = "pdb"))]
+fn parse_pdb(_data: &[u8]) -> Result<DebugDb> {
+    unreachable!()
+}
+
+struct Goff<I>(I);
 
-impl std::fmt::Display for Goff {
+impl<I: debugdb::EntryId> std::fmt::Display for Goff<I> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self.0 {
+        match self.0.offset() {
             gimli::UnitSectionOffset::DebugInfoOffset(gimli::DebugInfoOffset(x)) => {
                 write!(f, "<.debug_info+0x{:08x}>", x)
             }
@@ -108,8 +483,8 @@ struct NamedGoff<'a>(&'a debugdb::DebugDb, TypeId);
 
 impl std::fmt::Display for NamedGoff<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let bold = ansi_term::Style::new().bold();
-        let dim = ansi_term::Style::new().dimmed();
+        let bold = bold();
+        let dim = dimmed();
 
         let n = if let Some(name) = self.0.type_name(self.1) {
             name
@@ -118,7 +493,7 @@ impl std::fmt::Display for NamedGoff<'_> {
         };
 
         write!(f, "{}", bold.paint(n))?;
-        match self.1.0 {
+        match self.1.offset() {
             gimli::UnitSectionOffset::DebugInfoOffset(gimli::DebugInfoOffset(x)) => {
                 write!(f, " {}<.debug_info+0x{:08x}>{}", dim.prefix(), x, dim.suffix())
             }
@@ -131,157 +506,1546 @@ impl std::fmt::Display for NamedGoff<'_> {
 
 struct Ctx {
     segments: RangeInclusiveMap<u64, Vec<u8>>,
+    sp_register: Option<gimli::Register>,
+    defmt: Option<debugdb::defmt::Table>,
+    /// Commands that support it print JSON instead of text.
+    json: bool,
+    /// Commands that print tables print CSV instead of text.
+    csv: bool,
+    /// Levels of nested members that `layout` expands.
+    depth: usize,
+    /// Bytes per row drawn by `layout`, or `None` to pick based on the
+    /// pointer size.
+    width: Option<usize>,
+    /// Order for `list` when it isn't given `--sort`.
+    list_sort: ListOrder,
+    /// Compilation unit selected with `cu`, which `list` and `funcs` are
+    /// restricted to.
+    unit: Option<debugdb::UnitId>,
+    /// Peripherals of the target, from `--svd` or `svd load`. These belong to
+    /// the chip rather than a program, so they're kept across `file`.
+    svd: Option<debugdb::svd::Device>,
+    /// Live target attached with `attach` or `probe`, which values are read
+    /// from instead of `segments`.
+    #[cfg(any(feature = "gdb", feature = "probe-rs"))]
+    target: Option<Target>,
+}
+
+/// A live target, and how it's reached.
+#[cfg(any(feature = "gdb", feature = "probe-rs"))]
+enum Target {
+    #[cfg(feature = "gdb")]
+    Gdb(debugdb::gdb::GdbClient),
+    #[cfg(feature = "probe-rs")]
+    Probe(debugdb::probe::ProbeTarget),
+}
+
+impl Ctx {
+    /// Returns the memory that values should be read from.
+    fn memory(&self) -> Memory<'_> {
+        Memory(self)
+    }
+}
+
+#[cfg(any(feature = "gdb", feature = "probe-rs"))]
+#[derive(Debug, thiserror::Error)]
+enum MemoryError {
+    #[cfg(feature = "gdb")]
+    #[error(transparent)]
+    Gdb(#[from] debugdb::gdb::GdbError),
+    #[cfg(feature = "probe-rs")]
+    #[error(transparent)]
+    Probe(#[from] debugdb::probe::ProbeError),
+}
+#[cfg(not(any(feature = "gdb", feature = "probe-rs")))]
+type MemoryError = std::convert::Infallible;
+
+/// The attached target if there is one, and otherwise the loaded segments.
+struct Memory<'a>(&'a Ctx);
+
+impl debugdb::load::Machine for Memory<'_> {
+    type Error = MemoryError;
+
+    fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error> {
+        #[cfg(any(feature = "gdb", feature = "probe-rs"))]
+        match &self.0.target {
+            #[cfg(feature = "gdb")]
+            Some(Target::Gdb(target)) => return Ok(target.read_memory(address, dest)?),
+            #[cfg(feature = "probe-rs")]
+            Some(Target::Probe(target)) => return Ok(target.read_memory(address, dest)?),
+            None => (),
+        }
+        let Ok(n) = self.0.segments.read_memory(address, dest);
+        Ok(n)
+    }
+}
+
+/// Whether to use color and other terminal styling in output. This is global,
+/// rather than in `Ctx`, because it's needed by `Display` impls.
+static COLOR: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+
+/// Returns `style` if color is enabled, and a plain style otherwise.
+fn styled(style: Style) -> Style {
+    if COLOR.load(std::sync::atomic::Ordering::Relaxed) {
+        style
+    } else {
+        Style::new()
+    }
+}
+
+fn bold() -> Style {
+    styled(Style::new().bold())
+}
+
+fn dimmed() -> Style {
+    styled(Style::new().dimmed())
+}
+
+fn colour(c: Colour) -> Style {
+    styled(c.normal())
+}
+
+/// Shell options that can be changed with `set`, and their descriptions.
+static OPTIONS: &[(&str, &str)] = &[
+    ("output", "output format for commands that support it: text, json or csv"),
+    ("color", "use color and bold text: on or off"),
+    ("depth", "levels of nested members expanded by layout"),
+    ("width", "bytes per row drawn by layout, or auto"),
+    ("sort", "default order for list, like size:desc, or none"),
+];
+
+fn show_option(ctx: &Ctx, name: &str) -> Option<String> {
+    Some(match name {
+        "output" => match (ctx.json, ctx.csv) {
+            (true, _) => "json",
+            (_, true) => "csv",
+            _ => "text",
+        }.to_string(),
+        "color" => if COLOR.load(std::sync::atomic::Ordering::Relaxed) { "on" } else { "off" }
+            .to_string(),
+        "depth" => ctx.depth.to_string(),
+        "width" => ctx.width.map_or_else(|| "auto".to_string(), |w| w.to_string()),
+        "sort" => match ctx.list_sort {
+            None => "none".to_string(),
+            Some((key, desc)) => format!("{}:{}", key.name(), if desc { "desc" } else { "asc" }),
+        },
+        _ => return None,
+    })
+}
+
+fn set_option(ctx: &mut Ctx, name: &str, value: &str) -> Result<(), String> {
+    let on_off = || match value {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("expected on or off, not `{value}`")),
+    };
+    match name {
+        // Also accepted as `format`; see `cmd_show`.
+        "output" | "format" => (ctx.json, ctx.csv) = match value {
+            "text" => (false, false),
+            "json" => (true, false),
+            "csv" => (false, true),
+            _ => return Err(format!("expected text, json or csv, not `{value}`")),
+        },
+        "color" => COLOR.store(on_off()?, std::sync::atomic::Ordering::Relaxed),
+        "depth" => ctx.depth = value.parse().map_err(|e| format!("bad depth: {e}"))?,
+        "width" => ctx.width = match value {
+            "auto" => None,
+            w => match w.parse::<usize>() {
+                Ok(w) if w > 0 => Some(w),
+                _ => return Err(format!("bad width: {w}")),
+            },
+        },
+        "sort" => ctx.list_sort = match value {
+            "none" => None,
+            spec => parse_sort_spec(spec)?,
+        },
+        _ => return Err(format!("unknown option `{name}`; try show")),
+    }
+    Ok(())
+}
+
+fn cmd_set(_db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let Some((name, value)) = args.trim().split_once(char::is_whitespace) else {
+        println!("usage: set OPTION VALUE");
+        return;
+    };
+    if let Err(e) = set_option(ctx, name, value.trim()) {
+        println!("{e}");
+    }
+}
+
+fn cmd_show(_db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    // `format` is another name for `output`.
+    let name = match args.trim() {
+        "format" => "output",
+        name => name,
+    };
+    for &(option, desc) in OPTIONS {
+        if name.is_empty() || name == option {
+            println!("{option:8} {:10} {desc}", show_option(ctx, option).unwrap_or_default());
+        }
+    }
+    if !name.is_empty() && show_option(ctx, name).is_none() {
+        println!("unknown option `{name}`");
+    }
 }
 
 type Command = fn(&debugdb::DebugDb, &mut Ctx, &str);
 
 static COMMANDS: &[(&str, Command, &str)] = &[
-    ("list", cmd_list, "print names of ALL types, or types containing a string"),
+    ("set", cmd_set, "change a shell option, e.g. set color off"),
+    ("show", cmd_show, "show shell options"),
+    ("list", cmd_list, "print names of ALL types, types containing a string, types matching /REGEX/, or types matching filters like kind=struct size>256 align=4 name~Buffer; --sort name|size|align|goff[:desc] first to order them"),
+    ("padding", cmd_padding, "list the N structs with the most padding (default 20); -r ranks by the proportion of padding instead"),
+    ("top", cmd_top, "list the N largest types (default 10); -s only counts types in static variables, -r TYPE only those reachable from TYPE"),
+    ("stats", cmd_stats, "summarize the program's debug info, with the N largest types (default 10)"),
+    ("images", cmd_images, "list the programs combined from a firmware archive, with their address ranges"),
+    ("cu", cmd_cu, "list compilation units, or restrict list and funcs to unit N (`cu off` to undo)"),
+    ("sources", cmd_sources, "list source files in the line tables with row counts; with -u, per compilation unit"),
+    ("modules", cmd_modules, "list modules, or the types in a module"),
+    ("mod", cmd_mod, "browse the module tree: submodules, types and functions under a path; -r for the whole tree"),
     ("info", cmd_info, "print a summary of a type"),
     ("load", cmd_load, "loads additional segment data"),
-    ("def", cmd_def, "print a type as a pseudo-Rust definition"),
+    #[cfg(feature = "gdb")]
+    ("attach", cmd_attach, "read memory from a live target through a GDB server, e.g. attach localhost:3333"),
+    #[cfg(feature = "probe-rs")]
+    ("probe", cmd_probe, "read memory from a live target through a debug probe, e.g. probe STM32F407VGTx"),
+    #[cfg(any(feature = "gdb", feature = "probe-rs"))]
+    ("detach", cmd_detach, "stop reading from the attached target, and let it run"),
+    ("def", cmd_def, "print a type as a pseudo-Rust definition; with -r, along with everything it uses"),
+    ("uses", cmd_uses, "list types that refer to a type; with -r, transitively"),
+    ("users", cmd_uses, "same as uses"),
+    ("members", cmd_members, "list members of a type with offsets, sizes and padding; @PERIPHERAL adds SVD registers"),
+    ("whybig", cmd_whybig, "break down the size of a type by member, recursively, largest first; -a shows parts under 1%, -d N limits depth"),
+    ("pahole", cmd_pahole, "print a struct or union as pahole does, with holes and cache lines; with no type, all of them; -c BYTES sets the cache line size"),
+    ("diff", cmd_diff, "compare the layouts of two types"),
+    ("tree", cmd_tree, "print the members inside a type as a tree, to any depth; -d N limits it"),
+    ("codegen", cmd_codegen, "write #[repr(C)] Rust definitions mirroring a type and everything in it; -o FILE to save them"),
+    ("dot", cmd_dot, "write the types a type contains (-p: and points to), or types matching filters, as a Graphviz graph; -o FILE to save it"),
+    ("layout", cmd_layout, "draw a diagram of the bytes of a type; @PERIPHERAL labels members with SVD registers"),
     ("sizeof", cmd_sizeof, "print size of type in bytes"),
+    ("findsize", cmd_findsize, "list types of a given size, or range of sizes, e.g. >=1024"),
+    ("offsetof", cmd_offsetof, "print the offset of a member path like a.b[2] within a type"),
     ("alignof", cmd_alignof, "print alignment of type in bytes"),
+    ("=", cmd_eval, "evaluate arithmetic over layout facts, like = sizeof(Foo) * N_SLOTS + offsetof(Bar, hdr.len)"),
     ("addr", cmd_addr, "look up information about an address"),
+    ("svd", cmd_svd, "list SVD peripherals, or the registers of one, or the fields of PERIPH.REG; svd load FILE reads an SVD file"),
     ("addr2line", cmd_addr2line, "look up line number information"),
     ("addr2stack", cmd_addr2stack, "display inlined stack frames"),
-    ("vars", cmd_vars, "list static variables"),
+    ("symbolize", cmd_symbolize, "describe a list of return addresses"),
+    ("stacktrace", cmd_stacktrace, "symbolize pasted hex return addresses as a backtrace"),
+    ("line2addr", cmd_line2addr, "find code addresses for a file:line"),
+    ("vars", cmd_vars, "list static variables, with size and section"),
+    ("generics", cmd_generics, "group instantiations of generic types by base name, most first; -v lists each instantiation's size"),
+    ("codesize", cmd_codesize, "attribute machine code to compilation units, largest first; -f to source files, -c to crates"),
+    ("memmap", cmd_memmap, "list static variables grouped by section, largest first, with their types and section totals; or just one SECTION"),
     ("var", cmd_var, "get info on a static variable"),
+    ("demangle", cmd_demangle, "demangle Rust or C++ symbol names"),
+    ("ranges", cmd_ranges, "print the address ranges and source lines of a function"),
+    ("funcs", cmd_funcs, "list functions with address, size and signature; with -s, largest first"),
     ("unwind", cmd_unwind, "get unwind info for an address"),
-    ("decode", cmd_decode, "interpret RAM/ROM as a type"),
+    ("inlined", cmd_inlined, "list functions by how much code their inlined copies add up to; -v shows whom they were inlined into"),
+    ("framesize", cmd_framesize, "list the stack frame sizes of functions, from their CFI, largest first; dynamic frames may be bigger"),
+    ("bt", cmd_bt, "unwind the stack from a pc and sp"),
+    ("decode", cmd_decode, "interpret RAM/ROM at an address, or hex bytes like `decode Foo de ad be ef`, as a type"),
     ("decode-async", cmd_decode_async, "interpret RAM/ROM as a suspended future"),
+    ("variant-of", cmd_variant_of, "find the enum variant that a discriminator value selects"),
+    ("coroutines", cmd_coroutines, "list async fn and generator state machines, or the states of one"),
     ("decode-blob", cmd_decode_blob, "attempt to interpret bytes as a type"),
     ("decode-async-blob", cmd_decode_async_blob, "attempt to interpret bytes as a suspended future"),
+    ("defmt", cmd_defmt, "decode defmt log frames given as hex bytes, naming the types they log; with no bytes, list the types that derive Format"),
 ];
 
-fn cmd_list(
-    db: &debugdb::DebugDb,
-    _ctx: &mut Ctx,
-    args: &str,
-) {
-    // We're gonna make a copy to sort it, because alphabetical order seems
-    // polite.
-    let mut types_copy = db.canonical_types()
-        .filter(|(goff, _ty)| {
-            if !args.is_empty() {
-                if let Some(name) = db.type_name(*goff) {
-                    return name.contains(args);
-                } else {
-                    return false;
-                }
-            }
-            true
-        })
-        .collect::<Vec<_>>();
+type TableCommand = fn(&debugdb::DebugDb, &mut Ctx, &str) -> Table;
 
-    types_copy.sort_by_key(|(goff, _ty)| db.type_name(*goff));
-
-    for (goff, ty) in types_copy {
-        let kind = match ty {
-            Type::Base(_) => "base",
-            Type::Struct(_) => "struct",
-            Type::Enum(_) => "enum",
-            Type::CEnum(_) => "c-enum",
-            Type::Array(_) => "array",
-            Type::Pointer(_) => "ptr",
-            Type::Union(_) => "union",
-            Type::Subroutine(_) => "subr",
-            Type::Unresolved(_) => "missing",
-        };
+/// Commands whose results can be piped through `PIPE_STAGES`.
+static TABLE_COMMANDS: &[(&str, TableCommand)] = &[
+    ("list", |db, ctx, args| list_table(db, &query_list(db, ctx, args).unwrap_or_default())),
+    ("vars", vars_table),
+    ("funcs", funcs_table),
+    ("top", top_table),
+    ("padding", padding_table),
+    ("memmap", memmap_table),
+    ("codesize", codesize_table),
+    ("generics", generics_table),
+    ("framesize", framesize_table),
+    ("inlined", inlined_table),
+];
 
-        let aliases = db.aliases_of_type(goff);
-        if let Some(aliases) = aliases {
-            println!("{:6} {} ({} aliases)", kind, NamedGoff(db, goff), aliases.len());
-        } else {
-            println!("{:6} {}", kind, NamedGoff(db, goff));
+/// Stages that can follow a command in a pipeline, and their descriptions.
+static PIPE_STAGES: &[(&str, &str)] = &[
+    ("sort", "sort by a column, e.g. sort size:desc"),
+    ("head", "keep the first N rows (default 10)"),
+    ("tail", "keep the last N rows (default 10)"),
+    ("grep", "keep rows with a field matching a regex"),
+    ("count", "count the rows"),
+];
+
+/// Runs a command like `list Buffer | sort size | head 20`, where `cmd` and
+/// `rest` are the first command and everything after it.
+fn run_pipeline(db: &debugdb::DebugDb, ctx: &mut Ctx, cmd: &str, rest: &str) {
+    // With no arguments, `rest` starts with the first `| `.
+    let rest = format!(" {rest}");
+    let mut stages = rest.split(" | ");
+    let args = stages.next().unwrap_or_default().trim();
+    let Some(&(_, produce)) = TABLE_COMMANDS.iter().find(|(name, _)| *name == cmd) else {
+        let names = TABLE_COMMANDS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        println!("can't pipe the output of {cmd}; try {}", names.join(", "));
+        return;
+    };
+    let mut table = produce(db, ctx, args);
+    for stage in stages {
+        let stage = stage.trim();
+        let (name, arg) = stage.split_once(char::is_whitespace).unwrap_or((stage, ""));
+        if let Err(e) = apply_stage(&mut table, name, arg.trim()) {
+            println!("{name}: {e}");
+            return;
         }
     }
+    print_table(ctx, &table);
 }
 
-fn parse_type_name(s: &str) -> Option<ParsedTypeName<'_>> {
-    if s.starts_with("<.debug_") && s.ends_with('>') {
-        // Try parsing as a debug section reference.
-        let rest = &s[8..];
-        return if rest.starts_with("info+0x") {
-            let num = &rest[7..rest.len() - 1];
-            if let Ok(n) = usize::from_str_radix(num, 16) {
-                Some(ParsedTypeName::Goff(TypeId(gimli::DebugInfoOffset(n).into())))
-            } else {
-                println!("can't parse {} as hex", num);
-                None
-            }
-        } else if rest.starts_with("types+0x") {
-            let num = &rest[8..rest.len() - 1];
-            if let Ok(n) = usize::from_str_radix(num, 16) {
-                Some(ParsedTypeName::Goff(TypeId(gimli::DebugTypesOffset(n).into())))
+fn apply_stage(table: &mut Table, name: &str, arg: &str) -> Result<(), String> {
+    let count = || match arg {
+        "" => Ok(10),
+        n => n.parse::<usize>().map_err(|e| format!("bad count `{n}`: {e}")),
+    };
+    match name {
+        "sort" => {
+            let (col, dir) = arg.split_once(':').unwrap_or((arg, "asc"));
+            let Some(i) = table.columns.iter().position(|&c| c == col) else {
+                return Err(format!("no column `{col}`; try {}", table.columns.join(", ")));
+            };
+            let desc = match dir {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(format!("bad sort direction `{dir}`; try asc or desc")),
+            };
+            // Stable, so ties keep the command's own order.
+            if desc {
+                table.rows.sort_by(|a, b| b.fields[i].cmp(&a.fields[i]));
             } else {
-                println!("can't parse {} as hex", num);
-                None
+                table.rows.sort_by(|a, b| a.fields[i].cmp(&b.fields[i]));
             }
-        } else {
-            println!("bad offset reference: {}", s);
-            None
+        }
+        "head" => table.rows.truncate(count()?),
+        "tail" => {
+            let n = count()?;
+            let skip = table.rows.len().saturating_sub(n);
+            table.rows.drain(..skip);
+        }
+        "grep" => {
+            let re = Regex::new(arg).map_err(|e| format!("bad pattern: {e}"))?;
+            table.rows.retain(|row| row.fields.iter().any(|f| re.is_match(&f.to_string())));
+        }
+        "count" => {
+            let n = table.rows.len();
+            *table = Table {
+                columns: &["count"],
+                rows: vec![Row {
+                    fields: vec![Field::Number(n as u64)],
+                    text: n.to_string(),
+                }],
+            };
+        }
+        _ => {
+            let names = PIPE_STAGES.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            return Err(format!("unknown stage; try {}", names.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Commands whose argument ends with a type name, and the number of
+/// whitespace-separated arguments that come before it, for completion.
+static TYPE_NAME_COMMANDS: &[(&str, usize)] = &[
+    ("info", 0),
+    ("def", 0),
+    ("uses", 0),
+    ("users", 0),
+    ("members", 0),
+    ("tree", 0),
+    ("dot", 0),
+    ("codegen", 0),
+    ("diff", 0),
+    ("variant-of", 0),
+    ("sizeof", 0),
+    ("alignof", 0),
+    ("decode", 1),
+    ("decode-async", 1),
+    ("coroutines", 0),
+    ("decode-blob", 0),
+    ("decode-async-blob", 0),
+];
+
+/// Line editor support for tab completion of commands and type names.
+struct TyshHelper {
+    db: std::rc::Rc<DebugDb>,
+    files: rustyline::completion::FilenameCompleter,
+}
+
+impl TyshHelper {
+    fn new(db: std::rc::Rc<DebugDb>) -> Self {
+        Self { db, files: rustyline::completion::FilenameCompleter::new() }
+    }
+}
+
+impl rustyline::completion::Completer for TyshHelper {
+    type Candidate = rustyline::completion::Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let line = &line[..pos];
+        let pair = |s: &str| rustyline::completion::Pair {
+            display: s.to_string(),
+            replacement: s.to_string(),
+        };
+
+        let Some((cmd, mut rest)) = line.split_once(char::is_whitespace) else {
+            let names = ["help", "exit"].into_iter()
+                .chain(SESSION_COMMANDS.iter().map(|(name, _)| *name))
+                .chain(COMMANDS.iter().map(|(name, _, _)| *name))
+                .filter(|name| name.starts_with(line))
+                .map(pair)
+                .collect();
+            return Ok((0, names));
         };
+
+        if cmd == "open" {
+            return self.files.complete_path(line, pos);
+        }
+
+        let Some(&(_, skip)) = TYPE_NAME_COMMANDS.iter()
+            .find(|(name, _)| *name == cmd)
+        else {
+            return Ok((pos, vec![]));
+        };
+        for _ in 0..skip {
+            match rest.trim_start().split_once(char::is_whitespace) {
+                Some((_, r)) => rest = r,
+                None => return Ok((pos, vec![])),
+            }
+        }
+        let partial = rest.trim_start();
+        let names = complete_type_name(&self.db, partial).into_iter()
+            .map(pair)
+            .collect();
+        Ok((pos - partial.len(), names))
     }
+}
 
-    Some(ParsedTypeName::Name(s))
+/// Finds type names that `partial` could be the start of. If there are none,
+/// falls back to names that contain `partial`, and then to names that contain
+/// its characters in order -- so `optu8` finds `core::option::Option<u8>`.
+/// Differences in formatting are ignored, so `Option::<u` finds
+/// `core::option::Option<u8>` too.
+fn complete_type_name<'d>(db: &'d DebugDb, partial: &str) -> Vec<&'d str> {
+    let partial = &*debugdb::typename::canonicalize(partial);
+    let prefixed = db.type_names()
+        .filter(|name| name.starts_with(partial))
+        .collect::<Vec<_>>();
+    if !prefixed.is_empty() {
+        return prefixed;
+    }
+    let containing = db.type_names()
+        .filter(|name| name.contains(partial))
+        .collect::<Vec<_>>();
+    if !containing.is_empty() {
+        return containing;
+    }
+    db.type_names()
+        .filter(|name| {
+            let mut chars = name.chars();
+            partial.chars().all(|p| chars.any(|c| c.eq_ignore_ascii_case(&p)))
+        })
+        .collect()
 }
 
-enum ParsedTypeName<'a> {
-    Name(&'a str),
-    Goff(TypeId),
+impl rustyline::hint::Hinter for TyshHelper {
+    type Hint = String;
 }
 
-fn simple_query_cmd(
+impl rustyline::highlight::Highlighter for TyshHelper {}
+
+impl rustyline::validate::Validator for TyshHelper {}
+
+impl rustyline::Helper for TyshHelper {}
+
+/// Loads the debug info cached in `cache`, if it's there and was made from
+/// the program `identity`.
+fn load_cache(
+    cache: &std::path::Path,
+    identity: &debugdb::cache::ProgramIdentity,
+) -> Option<debugdb::DebugDb> {
+    let file = std::fs::File::open(cache).ok()?;
+    match debugdb::cache::load(std::io::BufReader::new(file), identity) {
+        Ok(db) => {
+            eprintln!("Read debug info from {}", cache.display());
+            Some(db)
+        }
+        Err(debugdb::cache::CacheError::WrongProgram(_)) => {
+            eprintln!("Cache {} is out of date; reparsing", cache.display());
+            None
+        }
+        Err(e) => {
+            eprintln!("Can't read cache {}: {e}", cache.display());
+            None
+        }
+    }
+}
+
+fn cmd_list(
     db: &debugdb::DebugDb,
+    ctx: &mut Ctx,
     args: &str,
-    q: fn(&debugdb::DebugDb, &debugdb::Type),
 ) {
-    let type_name = args.trim();
-    let types: Vec<_> = match parse_type_name(type_name) {
-        None => return,
-        Some(ParsedTypeName::Name(n)) => {
-            db.types_by_name(n).collect()
-        }
-        Some(ParsedTypeName::Goff(o)) => {
-            db.type_by_id(o).into_iter()
-                .map(|t| (o, t))
-                .collect()
+    let Some(types) = query_list(db, ctx, args) else { return };
+    if ctx.json {
+        print_json(types.into_iter().map(|(goff, ty)| list_entry_json(db, goff, ty)).collect());
+        return;
+    }
+    if ctx.csv {
+        print_table_csv(&list_table(db, &types));
+        return;
+    }
+    for (goff, ty) in types {
+        println!("{}", list_entry_line(db, goff, ty));
+    }
+}
+
+/// Finds the types that `list` shows for `args`, in order. Problems with the
+/// arguments are printed, and give `None`.
+fn query_list<'db>(
+    db: &'db debugdb::DebugDb,
+    ctx: &Ctx,
+    args: &str,
+) -> Option<Vec<(TypeId, &'db Type)>> {
+    let (sort, args) = match parse_list_sort(args) {
+        Ok((sort, args)) => (sort.or(ctx.list_sort), args),
+        Err(e) => {
+            println!("{e}");
+            return None;
         }
     };
-    if type_name.starts_with("<.debug_") && type_name.ends_with('>') {
-        // Try parsing as a debug section reference.
-        let rest = &type_name[8..];
-        if rest.starts_with("info+0x") {
-        } else if rest.starts_with("types+0x") {
+
+    // A pattern between slashes is a regex, which the database can handle
+    // for us -- and it returns results in order already.
+    if let Some(pattern) = args.strip_prefix('/').and_then(|a| a.strip_suffix('/')) {
+        let pattern = match Regex::new(pattern) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("bad pattern: {e}");
+                return None;
+            }
+        };
+        // The matches borrow the pattern, so look them up again to return
+        // them.
+        let mut types = db.types_matching(&pattern)
+            .filter(|&(goff, _)| in_selected_unit(db, ctx, goff))
+            .map(|(goff, _)| goff)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|goff| Some((goff, db.type_by_id(goff)?)))
+            .collect::<Vec<_>>();
+        if let Some((key, desc)) = sort {
+            sort_types(db, &mut types, key, desc);
         }
+        return Some(types);
     }
 
-    let many = match types.len() {
-        0 => {
-            println!("{}", ansi_term::Colour::Red.paint("No types found."));
-            return;
-        }
-        1 => false,
-        n => {
-            println!("{}{} types found with that name:",
-                ansi_term::Color::Yellow.paint("note: "),
-                n,
-            );
-            true
+    // Something like `kind=struct size>8` is a filter expression.
+    let filter = if looks_like_filter(args) {
+        match debugdb::filter::TypeFilter::parse(args) {
+            Ok(f) => Some(f),
+            Err(e) => {
+                println!("bad filter: {e}");
+                return None;
+            }
         }
+    } else {
+        None
     };
 
-    for (goff, t) in types {
-        if many { println!() }
-        print!("{}: ", NamedGoff(db, goff));
-        q(db, t);
-    }
-}
-
-fn cmd_info(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    simple_query_cmd(db, args, |db, t| {
-        match t {
+    // Alphabetical order seems polite, and doesn't change between builds.
+    let mut types_copy = db.canonical_types_sorted_by_name()
+        .into_iter()
+        .filter(|&(goff, _)| in_selected_unit(db, ctx, goff))
+        .filter(|&(goff, ty)| {
+            if let Some(f) = &filter {
+                return f.matches(db, goff, ty);
+            }
+            if !args.is_empty() {
+                if let Some(name) = db.type_name(goff) {
+                    return name.contains(args);
+                } else {
+                    return false;
+                }
+            }
+            true
+        })
+        .collect::<Vec<_>>();
+
+    if let Some((key, desc)) = sort {
+        sort_types(db, &mut types_copy, key, desc);
+    }
+    Some(types_copy)
+}
+
+fn list_entry_line(db: &debugdb::DebugDb, goff: TypeId, ty: &Type) -> String {
+    let kind = ty.kind_name();
+
+    let mut line = format!("{:6} {}", kind, NamedGoff(db, goff));
+    if let Some(aliases) = db.aliases_of_type(goff) {
+        write!(line, " ({} aliases)", aliases.len()).unwrap();
+    }
+    // Closure types all look alike, so say where they come from.
+    if let Some(closure) = db.closure(goff) {
+        write!(line, " {}", dimmed().paint(format!("({closure})"))).unwrap();
+    }
+    line
+}
+
+fn list_table(db: &debugdb::DebugDb, types: &[(TypeId, &Type)]) -> Table {
+    Table {
+        columns: &["kind", "name", "id", "size", "align", "aliases"],
+        rows: types.iter().map(|&(goff, ty)| Row {
+            fields: vec![
+                Field::Text(ty.kind_name().to_string()),
+                db.type_name(goff).map(|n| Field::Text(n.into_owned())).into(),
+                Field::Text(Goff(goff).to_string()),
+                ty.byte_size(db).map(Field::Number).into(),
+                ty.alignment(db).map(Field::Number).into(),
+                Field::Number(db.aliases_of_type(goff).map_or(0, |a| a.len() as u64)),
+            ],
+            text: list_entry_line(db, goff, ty),
+        }).collect(),
+    }
+}
+
+/// Orders that `list` can sort its output in.
+#[derive(Copy, Clone, Debug)]
+enum ListSort {
+    Name,
+    Size,
+    Align,
+    Goff,
+}
+
+impl ListSort {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Align => "align",
+            Self::Goff => "goff",
+        }
+    }
+}
+
+/// A sort order for `list`, and whether it's descending.
+type ListOrder = Option<(ListSort, bool)>;
+
+/// Strips a leading `--sort KEY[:asc|:desc]` option from the arguments to
+/// `list`, returning the sort order (if any) and the remaining arguments.
+fn parse_list_sort(args: &str) -> Result<(ListOrder, &str), String> {
+    let Some(rest) = args.strip_prefix("--sort") else {
+        return Ok((None, args));
+    };
+    let rest = rest.trim_start();
+    let (spec, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Ok((parse_sort_spec(spec)?, rest.trim()))
+}
+
+/// Parses a sort order for `list`, like `size` or `size:desc`.
+fn parse_sort_spec(spec: &str) -> Result<ListOrder, String> {
+    let (key, dir) = spec.split_once(':').unwrap_or((spec, "asc"));
+    let key = match key {
+        "name" => ListSort::Name,
+        "size" => ListSort::Size,
+        "align" => ListSort::Align,
+        "goff" => ListSort::Goff,
+        _ => return Err(format!("can't sort by `{key}`; try name, size, align or goff")),
+    };
+    let desc = match dir {
+        "asc" => false,
+        "desc" => true,
+        _ => return Err(format!("bad sort direction `{dir}`; try asc or desc")),
+    };
+    Ok(Some((key, desc)))
+}
+
+/// Sorts a list of types by `key`, keeping the existing order of types that
+/// compare equal. Types without a size or alignment sort first.
+fn sort_types(db: &DebugDb, types: &mut [(TypeId, &Type)], key: ListSort, desc: bool) {
+    fn by<K: Ord>(types: &mut [(TypeId, &Type)], desc: bool, f: impl Fn(TypeId, &Type) -> K) {
+        types.sort_by_cached_key(|&(goff, ty)| {
+            let k = f(goff, ty);
+            if desc { Err(std::cmp::Reverse(k)) } else { Ok(k) }
+        });
+    }
+    match key {
+        ListSort::Name => by(types, desc, |goff, _| db.type_name(goff)),
+        ListSort::Size => by(types, desc, |_, ty| ty.byte_size(db)),
+        ListSort::Align => by(types, desc, |_, ty| ty.alignment(db)),
+        ListSort::Goff => by(types, desc, |goff, _| goff),
+    }
+}
+
+/// Checks whether `list` arguments start with a filter condition, like
+/// `kind=struct`, rather than being part of a type name.
+fn looks_like_filter(args: &str) -> bool {
+    ["kind", "name", "size", "align"].iter().any(|field| {
+        args.strip_prefix(field)
+            .is_some_and(|rest| rest.starts_with(['=', '!', '<', '>', '~']))
+    })
+}
+
+fn list_entry_json(db: &debugdb::DebugDb, goff: TypeId, ty: &Type) -> serde_json::Value {
+    let mut obj = type_ref_json(db, goff);
+    obj["kind"] = ty.kind_name().into();
+    obj["aliases"] = db.aliases_of_type(goff).map_or(0, |a| a.len()).into();
+    obj
+}
+
+/// Rows of results from a command like `list`, kept structured so that they
+/// can be passed through pipeline stages like `sort` and `head` before being
+/// printed.
+struct Table {
+    columns: &'static [&'static str],
+    rows: Vec<Row>,
+}
+
+struct Row {
+    /// One field for each column.
+    fields: Vec<Field>,
+    /// How the row is printed as text.
+    text: String,
+}
+
+/// A value in a `Table`. Unknown values sort first.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Field {
+    Unknown,
+    Number(u64),
+    Address(u64),
+    Text(String),
+}
+
+impl From<Option<Field>> for Field {
+    fn from(f: Option<Field>) -> Self {
+        f.unwrap_or(Field::Unknown)
+    }
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Field::Unknown => Ok(()),
+            Field::Number(n) => write!(f, "{n}"),
+            Field::Address(a) => write!(f, "0x{a:x}"),
+            Field::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+impl Field {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Field::Unknown => serde_json::Value::Null,
+            Field::Number(n) => (*n).into(),
+            Field::Address(_) | Field::Text(_) => self.to_string().into(),
+        }
+    }
+}
+
+/// Prints a table in the format selected by `set output`.
+fn print_table(ctx: &Ctx, table: &Table) {
+    if ctx.json {
+        print_json(table.rows.iter()
+            .map(|row| table.columns.iter()
+                .zip(&row.fields)
+                .map(|(&c, f)| (c.to_string(), f.to_json()))
+                .collect::<serde_json::Map<_, _>>())
+            .map(serde_json::Value::Object)
+            .collect());
+    } else if ctx.csv {
+        print_table_csv(table);
+    } else {
+        for row in &table.rows {
+            println!("{}", row.text);
+        }
+    }
+}
+
+fn print_table_csv(table: &Table) {
+    print_csv_row(table.columns);
+    for row in &table.rows {
+        print_csv_row(row.fields.iter().map(|f| f.to_string()));
+    }
+}
+
+/// Prints one row of CSV, quoting fields that need it. Unknown values are
+/// left empty, so that spreadsheets see numeric columns as numbers.
+fn print_csv_row<S: AsRef<str>>(fields: impl IntoIterator<Item = S>) {
+    let mut line = String::new();
+    for (i, f) in fields.into_iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        let f = f.as_ref();
+        if f.contains([',', '"', '\n', '\r']) {
+            write!(line, "\"{}\"", f.replace('"', "\"\"")).unwrap();
+        } else {
+            line.push_str(f);
+        }
+    }
+    println!("{line}");
+}
+
+/// Prints a JSON value on a line of its own.
+fn print_json(v: serde_json::Value) {
+    println!("{v}");
+}
+
+/// Describes a type by its ID and name, in the form used throughout the JSON
+/// output. The ID is in the same format accepted by commands.
+fn type_ref_json(db: &debugdb::DebugDb, goff: TypeId) -> serde_json::Value {
+    serde_json::json!({
+        "id": Goff(goff).to_string(),
+        "name": db.type_name(goff),
+    })
+}
+
+fn parse_type_name(s: &str) -> Option<ParsedTypeName<'_>> {
+    if s.starts_with("<.debug_") && s.ends_with('>') {
+        // Try parsing as a debug section reference.
+        return match parse_type_id(s) {
+            Ok(id) => Some(ParsedTypeName::Goff(id)),
+            Err(e) => {
+                println!("{e}");
+                None
+            }
+        };
+    }
+
+    Some(ParsedTypeName::Name(s))
+}
+
+/// Parses a type ID in the form printed by `Goff`.
+fn parse_type_id(s: &str) -> Result<TypeId, String> {
+    let bad = || format!("bad offset reference: {s}");
+    let rest = s.strip_prefix("<.debug_").and_then(|s| s.strip_suffix('>')).ok_or_else(bad)?;
+    let (section, num) = rest.split_once("+0x").ok_or_else(bad)?;
+    let n = usize::from_str_radix(num, 16)
+        .map_err(|_| format!("can't parse {num} as hex"))?;
+    match section {
+        "info" => Ok(TypeId::from(gimli::UnitSectionOffset::from(gimli::DebugInfoOffset(n)))),
+        "types" => Ok(TypeId::from(gimli::UnitSectionOffset::from(gimli::DebugTypesOffset(n)))),
+        _ => Err(bad()),
+    }
+}
+
+enum ParsedTypeName<'a> {
+    Name(&'a str),
+    Goff(TypeId),
+}
+
+fn simple_query_cmd(
+    db: &debugdb::DebugDb,
+    args: &str,
+    q: impl Fn(&debugdb::DebugDb, &debugdb::Type),
+) {
+    let type_name = args.trim();
+    let types: Vec<_> = match parse_type_name(type_name) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if type_name.starts_with("<.debug_") && type_name.ends_with('>') {
+        // Try parsing as a debug section reference.
+        let rest = &type_name[8..];
+        if rest.starts_with("info+0x") {
+        } else if rest.starts_with("types+0x") {
+        }
+    }
+
+    let many = match types.len() {
+        0 => {
+            println!("{}", colour(Colour::Red).paint("No types found."));
+            return;
+        }
+        1 => false,
+        n => {
+            println!("{}{} types found with that name:",
+                colour(Colour::Yellow).paint("note: "),
+                n,
+            );
+            true
+        }
+    };
+
+    for (goff, t) in types {
+        if many { println!() }
+        print!("{}: ", NamedGoff(db, goff));
+        q(db, t);
+    }
+}
+
+/// Like `simple_query_cmd`, but prints a JSON array with `q`'s result for
+/// each matching type.
+fn json_query_cmd(
+    db: &debugdb::DebugDb,
+    args: &str,
+    q: fn(&debugdb::DebugDb, TypeId, &debugdb::Type) -> serde_json::Value,
+) {
+    let types: Vec<_> = match parse_type_name(args.trim()) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    print_json(types.into_iter().map(|(goff, t)| q(db, goff, t)).collect());
+}
+
+/// Like `simple_query_cmd`, but prints a CSV table with `header` and the rows
+/// returned by `q` for each matching type.
+fn csv_query_cmd(
+    db: &debugdb::DebugDb,
+    args: &str,
+    header: &[&str],
+    q: fn(&debugdb::DebugDb, TypeId, &debugdb::Type) -> Vec<Vec<String>>,
+) {
+    let types: Vec<_> = match parse_type_name(args.trim()) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    print_csv_row(header);
+    for (goff, t) in types {
+        for row in q(db, goff, t) {
+            print_csv_row(row);
+        }
+    }
+}
+
+fn cmd_modules(
+    db: &debugdb::DebugDb,
+    _ctx: &mut Ctx,
+    args: &str,
+) {
+    let args = args.trim();
+    if args.is_empty() {
+        for module in db.modules() {
+            println!("{} ({} types)", module, db.types_in_module(module).count());
+        }
+        return;
+    }
+
+    let mut types = db.types_in_module(args).collect::<Vec<_>>();
+    if types.is_empty() {
+        println!("no types in module {args}");
+        return;
+    }
+    types.sort_by_key(|(goff, _ty)| db.type_short_name(*goff));
+    for (goff, ty) in types {
+        println!(
+            "{:6} {} {}",
+            ty.kind_name(),
+            db.type_short_name(goff).unwrap_or_default(),
+            Goff(goff),
+        );
+    }
+}
+
+/// Numbers of types and functions in a module and its submodules.
+type ModuleTotals = (usize, usize);
+
+fn cmd_mod(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let (recursive, path) = match args.trim().strip_prefix("-r") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim()),
+        _ => (false, args.trim()),
+    };
+    let path = path.trim_end_matches("::");
+
+    // Count what's in each module, including everything in its submodules.
+    let mut totals = std::collections::BTreeMap::<&str, ModuleTotals>::new();
+    for module in db.modules() {
+        let here = (db.types_in_module(module).count(), db.subprograms_in_module(module).count());
+        let mut prefix = module;
+        loop {
+            let t = totals.entry(prefix).or_default();
+            t.0 += here.0;
+            t.1 += here.1;
+            match prefix.rsplit_once("::") {
+                Some((parent, _)) => prefix = parent,
+                None => break,
+            }
+        }
+    }
+    if !path.is_empty() && !totals.contains_key(path) {
+        println!("{}", colour(Colour::Red).paint(format!("no module {path}")));
+        return;
+    }
+
+    let children = |path: &str| {
+        totals.iter()
+            .filter(move |(m, _)| match path {
+                "" => !m.contains("::"),
+                _ => m.strip_prefix(path)
+                    .and_then(|rest| rest.strip_prefix("::"))
+                    .is_some_and(|rest| !rest.contains("::")),
+            })
+            .map(|(&m, &t)| (m, t))
+            .collect::<Vec<_>>()
+    };
+    let bold = bold();
+    let dim = dimmed();
+    let print_module = |m: &str, (types, funcs): ModuleTotals, indent: usize| {
+        let short = m.rsplit("::").next().unwrap_or(m);
+        println!("{:indent$}{}::  {}", "", bold.paint(short),
+            dim.paint(format!("({types} types, {funcs} functions)")));
+    };
+
+    if recursive {
+        fn walk<'m>(
+            m: &'m str,
+            depth: usize,
+            children: &dyn Fn(&'m str) -> Vec<(&'m str, ModuleTotals)>,
+            print_module: &dyn Fn(&str, ModuleTotals, usize),
+        ) {
+            for (child, t) in children(m) {
+                print_module(child, t, depth * 2);
+                walk(child, depth + 1, children, print_module);
+            }
+        }
+        walk(path, 0, &children, &print_module);
+        return;
+    }
+
+    for (child, t) in children(path) {
+        print_module(child, t, 0);
+    }
+    if path.is_empty() {
+        return;
+    }
+
+    let mut types = db.types_in_module(path).collect::<Vec<_>>();
+    types.sort_by_key(|(goff, _)| db.type_short_name(*goff));
+    for (goff, ty) in types {
+        println!("{:6} {} {}", ty.kind_name(),
+            db.type_short_name(goff).unwrap_or_default(),
+            dim.paint(Goff(goff).to_string()));
+    }
+
+    // Inlined functions have an abstract definition with no code, which is
+    // only worth listing if there are no concrete ones.
+    let mut funcs = db.subprograms_in_module(path)
+        .filter_map(|(_, p)| {
+            let addr = p.pc_range.as_ref().map(|r| r.start).filter(|&a| a != 0);
+            let name = p.name.as_deref()?;
+            let name = name.strip_prefix(path)
+                .and_then(|n| n.strip_prefix("::"))
+                .unwrap_or(name);
+            Some((name, std::cmp::Reverse(addr)))
+        })
+        .collect::<Vec<_>>();
+    funcs.sort();
+    funcs.dedup_by(|b, a| a.0 == b.0 && (b.1.0.is_none() || a.1 == b.1));
+    for (name, std::cmp::Reverse(addr)) in funcs {
+        match addr {
+            Some(a) => println!("fn     {name} {}", dim.paint(format!("0x{a:x}"))),
+            None => println!("fn     {name} {}", dim.paint("(inlined or not generated)")),
+        }
+    }
+}
+
+/// Checks whether type `goff` was defined in the unit selected with `cu`, if
+/// there is one.
+fn in_selected_unit(db: &debugdb::DebugDb, ctx: &Ctx, goff: TypeId) -> bool {
+    ctx.unit.is_none_or(|u| db.type_defined_in_unit(goff, u))
+}
+
+fn cmd_images(db: &debugdb::DebugDb, ctx: &mut Ctx, _args: &str) {
+    if ctx.json {
+        print_json(db.images().iter().map(|image| serde_json::json!({
+            "name": image.name,
+            "debug_info": [image.debug_info.start, image.debug_info.end],
+            "address_ranges": image.address_ranges.iter()
+                .map(|r| [r.start, r.end])
+                .collect::<Vec<_>>(),
+        })).collect());
+        return;
+    }
+    if db.images().is_empty() {
+        println!("Not loaded from a firmware archive.");
+        return;
+    }
+    let bold = bold();
+    let dim = dimmed();
+    for image in db.images() {
+        println!("{} {}..{}", bold.paint(&image.name),
+            dim.paint(format!("<.debug_info+0x{:08x}>", image.debug_info.start)),
+            dim.paint(format!("<.debug_info+0x{:08x}>", image.debug_info.end)));
+        for r in &image.address_ranges {
+            println!("- 0x{:x}..0x{:x}", r.start, r.end);
+        }
+    }
+}
+
+fn cmd_cu(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let units = db.units().collect::<Vec<_>>();
+    let args = args.trim();
+    if args.is_empty() {
+        if ctx.json {
+            print_json(units.iter().map(|(offset, unit)| serde_json::json!({
+                "offset": Goff(*offset).to_string(),
+                "name": unit.name,
+                "comp_dir": unit.comp_dir,
+                "producer": unit.producer,
+                "language": unit.language_name(),
+                "address_size": unit.address_size,
+                "selected": ctx.unit == Some(*offset),
+            })).collect());
+            return;
+        }
+        let bold = bold();
+        let dim = dimmed();
+        for (i, (offset, unit)) in units.iter().enumerate() {
+            let marker = if ctx.unit == Some(*offset) { "*" } else { " " };
+            println!("{marker} {:>4} {} {} {}",
+                i + 1,
+                bold.paint(unit.name.as_deref().unwrap_or("<unnamed unit>")),
+                unit.language_name().unwrap_or_default(),
+                dim.paint(Goff(*offset).to_string()));
+        }
+        return;
+    }
+    if args == "off" {
+        ctx.unit = None;
+        println!("No longer restricted to a unit.");
+        return;
+    }
+
+    // Select by number, or by a unique part of the name.
+    let selected = match args.parse::<usize>() {
+        Ok(n) => units.get(n.wrapping_sub(1)).ok_or_else(|| {
+            format!("no unit {n}; units are numbered 1 to {}", units.len())
+        }),
+        Err(_) => {
+            let matching = units.iter()
+                .filter(|(_, u)| u.name.as_deref().is_some_and(|n| n.contains(args)))
+                .collect::<Vec<_>>();
+            match matching[..] {
+                [u] => Ok(u),
+                [] => Err(format!("no unit named like {args}")),
+                _ => Err(format!("{} units are named like {args}; pick one by number",
+                    matching.len())),
+            }
+        }
+    };
+    let (offset, unit) = match selected {
+        Ok(u) => u,
+        Err(e) => {
+            println!("{}", colour(Colour::Red).paint(e));
+            return;
+        }
+    };
+    ctx.unit = Some(*offset);
+    println!("{} {}", bold().paint(unit.name.as_deref().unwrap_or("<unnamed unit>")),
+        Goff(*offset));
+    if let Some(dir) = &unit.comp_dir {
+        println!("- compiled in: {dir}");
+    }
+    if let Some(lang) = unit.language_name() {
+        println!("- language: {lang}");
+    }
+    if let Some(producer) = &unit.producer {
+        println!("- producer: {producer}");
+    }
+    println!("- address size: {} bytes", unit.address_size);
+    println!("list and funcs now only show this unit's types and functions; `cu off` undoes this");
+}
+
+fn cmd_sources(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let (per_unit, filter) = match args.trim().strip_prefix("-u") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim()),
+        _ => (false, args.trim()),
+    };
+
+    if per_unit {
+        let bold = bold();
+        for (offset, unit) in db.units() {
+            let files = db.source_files_in_unit(offset)
+                .filter(|(file, _)| file.contains(filter))
+                .collect::<Vec<_>>();
+            if files.is_empty() {
+                continue;
+            }
+            println!("{} {}", bold.paint(unit.name.as_deref().unwrap_or("<unnamed unit>")), Goff(offset));
+            for (file, rows) in files {
+                println!("    {rows:8} {file}");
+            }
+        }
+    } else {
+        let files = db.source_files();
+        let mut count = 0;
+        for (file, rows) in files.into_iter().filter(|(file, _)| file.contains(filter)) {
+            println!("{rows:8} {file}");
+            count += 1;
+        }
+        println!("{count} files");
+    }
+}
+
+fn cmd_stats(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let top = match args.trim() {
+        "" => 10,
+        n => match n.parse::<usize>() {
+            Ok(n) => n,
+            Err(e) => {
+                println!("bad count {n}: {e}");
+                return;
+            }
+        },
+    };
+    let stats = db.stats(top);
+    let bold = bold();
+
+    println!("{} types, {} distinct", stats.type_count, stats.canonical_type_count);
+    for (kind, totals) in &stats.by_kind {
+        println!("    {:8} {:8} ({} bytes)", kind, totals.count, totals.byte_size);
+    }
+    println!("{} compilation units", db.units().count());
+    println!("{} functions, {} static variables",
+        db.subprograms().count(), db.static_variables().count());
+    let rows = db.line_table_rows().map(|(_, rows)| rows.len()).sum::<usize>();
+    println!("{} line table rows, in {} source files", rows, db.source_files().len());
+    println!("{} warnings while parsing", db.parse_warnings());
+
+    if !stats.largest.is_empty() {
+        println!("{}", bold.paint("Largest types:"));
+        for (id, size) in &stats.largest {
+            println!("    {:8} {}", size, NamedGoff(db, *id));
+        }
+    }
+}
+
+fn cmd_top(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = top_table(db, ctx, args);
+    print_table(ctx, &table);
+}
+
+/// Ranks types by size for `top`. The arguments are an optional count, then
+/// `-s` to only rank types used by static variables, or `-r TYPE` to only
+/// rank types reachable from `TYPE`.
+fn top_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    use debugdb::stats::TopScope;
+
+    let mut args = args.trim();
+    let mut count = 10;
+    let mut scope = TopScope::All;
+    while !args.is_empty() {
+        let (word, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        if word == "-s" {
+            scope = TopScope::Statics;
+        } else if word == "-r" {
+            let roots: Vec<_> = match parse_type_name(rest.trim()) {
+                None => return Table { columns: TOP_COLUMNS, rows: vec![] },
+                Some(ParsedTypeName::Name(n)) => lookup_types(db, n).into_iter().map(|(id, _)| id).collect(),
+                Some(ParsedTypeName::Goff(o)) => vec![o],
+            };
+            if roots.is_empty() {
+                println!("{}", colour(Colour::Red).paint("No types found."));
+            }
+            scope = TopScope::ReachableFrom(roots);
+            break;
+        } else if let Ok(n) = word.parse::<usize>() {
+            count = n;
+        } else {
+            println!("usage: top [N] [-s | -r TYPE]");
+            return Table { columns: TOP_COLUMNS, rows: vec![] };
+        }
+        args = rest.trim_start();
+    }
+
+    Table {
+        columns: TOP_COLUMNS,
+        rows: db.largest_types(count, &scope).into_iter().map(|(id, size)| Row {
+            fields: vec![
+                Field::Number(size),
+                db.type_by_id(id).map(|t| Field::Text(t.kind_name().to_string())).into(),
+                db.type_name(id).map(|n| Field::Text(n.into_owned())).into(),
+                Field::Text(Goff(id).to_string()),
+            ],
+            text: format!("{:8} {}", size, NamedGoff(db, id)),
+        }).collect(),
+    }
+}
+
+const TOP_COLUMNS: &[&str] = &["size", "kind", "name", "id"];
+
+fn cmd_padding(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = padding_table(db, ctx, args);
+    if ctx.json || ctx.csv {
+        print_table(ctx, &table);
+        return;
+    }
+    println!("{:>8} {:>8} {:>6} {:>5} {:>5}  type", "padding", "size", "ratio", "holes", "tail");
+    print_table(ctx, &table);
+}
+
+/// Ranks structs by padding for `padding`. The arguments are an optional
+/// count, and `-r` to rank by the proportion of padding rather than bytes.
+fn padding_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    use debugdb::layout::PaddingRank;
+
+    let mut count = 20;
+    let mut rank = PaddingRank::Bytes;
+    for word in args.split_whitespace() {
+        if word == "-r" {
+            rank = PaddingRank::Ratio;
+        } else if let Ok(n) = word.parse::<usize>() {
+            count = n;
+        } else {
+            println!("usage: padding [N] [-r]");
+            return Table { columns: PADDING_COLUMNS, rows: vec![] };
+        }
+    }
+
+    let mut found = db.padded_structs(rank);
+    found.truncate(count);
+    Table {
+        columns: PADDING_COLUMNS,
+        rows: found.into_iter().map(|p| Row {
+            fields: vec![
+                Field::Number(p.padding),
+                Field::Number(p.byte_size),
+                Field::Text(format!("{:.3}", p.ratio())),
+                Field::Number(p.holes as u64),
+                Field::Number(p.trailing_padding),
+                db.type_name(p.type_id).map(|n| Field::Text(n.into_owned())).into(),
+                Field::Text(Goff(p.type_id).to_string()),
+            ],
+            text: format!("{:>8} {:>8} {:>5.1}% {:>5} {:>5}  {}",
+                p.padding, p.byte_size, p.ratio() * 100.0, p.holes, p.trailing_padding,
+                NamedGoff(db, p.type_id)),
+        }).collect(),
+    }
+}
+
+const PADDING_COLUMNS: &[&str] = &["padding", "size", "ratio", "holes", "tail", "name", "id"];
+
+fn cmd_memmap(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    if ctx.json || ctx.csv {
+        let table = memmap_table(db, ctx, args);
+        print_table(ctx, &table);
+        return;
+    }
+    let section = args.trim();
+    let map = db.static_memory_map();
+    let mut total = 0;
+    for s in &map {
+        if !section.is_empty() && s.name.as_deref() != Some(section) {
+            continue;
+        }
+        let name = s.name.as_deref().unwrap_or("(no section)");
+        match &s.address_range {
+            Some(r) => println!("{} 0x{:x}..0x{:x}: {} variables, {} of {} bytes",
+                bold().paint(name), r.start, r.end, s.variables.len(),
+                s.byte_size, r.end - r.start),
+            None => println!("{}: {} variables, {} bytes",
+                bold().paint(name), s.variables.len(), s.byte_size),
+        }
+        for &(id, size) in &s.variables {
+            let Some(v) = db.static_variable_by_id(id) else { continue };
+            println!("    {:>8} 0x{:0width$x} {}: {}",
+                size.map(|z| z.to_string()).unwrap_or_else(|| "???".into()),
+                v.location,
+                v.demangled_name().unwrap_or_else(|| v.name.clone()),
+                NamedGoff(db, v.type_id),
+                width = db.pointer_size() * 2);
+        }
+        total += s.byte_size;
+    }
+    if section.is_empty() {
+        println!("{} bytes of statics in {} sections", total,
+            map.iter().filter(|s| s.name.is_some()).count());
+    } else if !map.iter().any(|s| s.name.as_deref() == Some(section)) {
+        println!("{}", colour(Colour::Red).paint("No statics in that section."));
+    }
+}
+
+fn cmd_generics(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let (verbose, filter) = match args.trim().strip_prefix("-v") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim()),
+        _ => (false, args.trim()),
+    };
+    if ctx.json || ctx.csv || !verbose {
+        let table = generics_table(db, ctx, filter);
+        if !ctx.json && !ctx.csv {
+            println!("{:>6} {:>8} {:>8}  generic", "count", "bytes", "largest");
+        }
+        print_table(ctx, &table);
+        return;
+    }
+
+    for g in db.generic_instances() {
+        if !g.base_name.contains(filter) {
+            continue;
+        }
+        println!("{}: {} instantiations, {} bytes",
+            bold().paint(&g.base_name), g.instances.len(), g.byte_size());
+        for (id, size) in g.instances {
+            println!("    {:>8} {}",
+                size.map(|z| z.to_string()).unwrap_or_else(|| "???".into()),
+                NamedGoff(db, id));
+        }
+    }
+}
+
+/// Lists generic types whose base names contain `args` for `generics`, with
+/// the most instantiated first.
+fn generics_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    let filter = args.trim();
+    Table {
+        columns: &["count", "size", "largest", "name"],
+        rows: db.generic_instances().into_iter()
+            .filter(|g| g.base_name.contains(filter))
+            .map(|g| {
+                let largest = g.instances.first().and_then(|&(_, size)| size);
+                Row {
+                    text: format!("{:>6} {:>8} {:>8}  {}",
+                        g.instances.len(), g.byte_size(),
+                        largest.map(|z| z.to_string()).unwrap_or_else(|| "???".into()),
+                        g.base_name),
+                    fields: vec![
+                        Field::Number(g.instances.len() as u64),
+                        Field::Number(g.byte_size()),
+                        largest.map(Field::Number).into(),
+                        Field::Text(g.base_name),
+                    ],
+                }
+            })
+            .collect(),
+    }
+}
+
+fn cmd_codesize(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = codesize_table(db, ctx, args);
+    if ctx.json || ctx.csv {
+        print_table(ctx, &table);
+        return;
+    }
+    println!("{:>8} {:>6} {:>6}  group", "bytes", "share", "funcs");
+    print_table(ctx, &table);
+    let total = table.rows.iter()
+        .filter_map(|row| match row.fields[0] {
+            Field::Number(z) => Some(z),
+            _ => None,
+        })
+        .sum::<u64>();
+    println!("{} bytes of code in {} groups", total, table.rows.len());
+}
+
+/// Attributes machine code to compilation units for `codesize`, or with `-f`
+/// to source files, or with `-c` to crates.
+fn codesize_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    use debugdb::stats::CodeGrouping;
+
+    let grouping = match args.trim() {
+        "" => CodeGrouping::Unit,
+        "-f" => CodeGrouping::File,
+        "-c" => CodeGrouping::Crate,
+        _ => {
+            println!("usage: codesize [-f | -c]");
+            return Table { columns: CODESIZE_COLUMNS, rows: vec![] };
+        }
+    };
+    let groups = db.code_size(grouping);
+    let total = groups.iter().map(|g| g.byte_size).sum::<u64>().max(1);
+    Table {
+        columns: CODESIZE_COLUMNS,
+        rows: groups.into_iter().map(|g| {
+            let share = g.byte_size as f64 / total as f64;
+            Row {
+                text: format!("{:>8} {:>5.1}% {:>6}  {}",
+                    g.byte_size, share * 100.0, g.subprograms,
+                    g.name.as_deref().unwrap_or("(unknown)")),
+                fields: vec![
+                    Field::Number(g.byte_size),
+                    Field::Text(format!("{share:.3}")),
+                    Field::Number(g.subprograms as u64),
+                    g.name.map(Field::Text).into(),
+                ],
+            }
+        }).collect(),
+    }
+}
+
+const CODESIZE_COLUMNS: &[&str] = &["size", "share", "functions", "name"];
+
+/// Lists static variables for `memmap`, grouped by section and largest first
+/// within each. The argument, if any, is the one section to list.
+fn memmap_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    let section = args.trim();
+    Table {
+        columns: &["section", "size", "address", "name", "type"],
+        rows: db.static_memory_map().into_iter()
+            .filter(|s| section.is_empty() || s.name.as_deref() == Some(section))
+            .flat_map(|s| s.variables.into_iter().map(move |(id, size)| (s.name.clone(), id, size)))
+            .filter_map(|(section, id, size)| {
+                let v = db.static_variable_by_id(id)?;
+                let name = v.demangled_name().unwrap_or_else(|| v.name.clone());
+                Some(Row {
+                    text: format!("{:<10} {:>8} 0x{:0width$x} {}: {}",
+                        section.as_deref().unwrap_or("?"),
+                        size.map(|z| z.to_string()).unwrap_or_else(|| "???".into()),
+                        v.location,
+                        name,
+                        NamedGoff(db, v.type_id),
+                        width = db.pointer_size() * 2),
+                    fields: vec![
+                        section.map(Field::Text).into(),
+                        size.map(Field::Number).into(),
+                        Field::Address(v.location),
+                        Field::Text(name),
+                        db.type_name(v.type_id).map(|n| Field::Text(n.into_owned())).into(),
+                    ],
+                })
+            })
+            .collect(),
+    }
+}
+
+fn cmd_info(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    if ctx.json {
+        json_query_cmd(db, args, type_info_json);
+        return;
+    }
+    simple_query_cmd(db, args, |db, t| {
+        match t {
             Type::Base(s) => {
                 println!("base type");
                 println!("- encoding: {:?}", s.encoding);
@@ -290,6 +2054,26 @@ fn cmd_info(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
             Type::Pointer(s) => {
                 println!("pointer type");
                 println!("- points to: {}", NamedGoff(db, s.type_id));
+                println!("- kind: {:?}", s.kind(db));
+                println!("- pointee is {}", if s.is_mut(db) { "mutable" } else { "const" });
+                if let Some(z) = s.byte_size {
+                    println!("- byte size: {z}");
+                }
+                if let Some(c) = s.address_class {
+                    println!("- address class: {c}");
+                }
+            }
+            Type::Qualified(s) => {
+                println!("{} qualified type", s.qualifier);
+                println!("- qualifies: {}", NamedGoff(db, s.type_id));
+            }
+            Type::Typedef(s) => {
+                println!("typedef");
+                println!("- alias for: {}", NamedGoff(db, s.type_id));
+                let underlying = db.resolve_alias(s.type_id);
+                if underlying != s.type_id {
+                    println!("- underlying type: {}", NamedGoff(db, underlying));
+                }
             }
             Type::Array(s) => {
                 println!("array type");
@@ -314,17 +2098,144 @@ fn cmd_info(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                     } else {
                         print!(":???");
                     }
-                    // Be more tolerant of missing column number.
-                    if let Some(n) = s.decl_coord.column {
-                        print!(":{n}");
+                    // Be more tolerant of missing column number.
+                    if let Some(n) = s.decl_coord.column {
+                        print!(":{n}");
+                    }
+                    println!();
+                }
+                if let Some(z) = s.byte_size {
+                    println!("- byte size: {z}");
+                }
+                if let Some(a) = t.alignment(db) {
+                    println!("- alignment: {a}");
+                } else {
+                    println!("- not aligned");
+                }
+                if !s.template_type_parameters.is_empty() {
+                    println!("- template type parameters:");
+                    for ttp in &s.template_type_parameters {
+                        println!("  - {} = {}", ttp.name, NamedGoff(db, ttp.type_id));
+                    }
+                }
+                if !s.members.is_empty() {
+                    println!("- members:");
+                    for (i, mem) in s.members.iter().enumerate() {
+                        if let Some(name) = &mem.name {
+                            println!("  {i}. {name}: {}", NamedGoff(db, mem.type_id));
+                        } else {
+                            println!("  - <unnamed>: {}", NamedGoff(db, mem.type_id));
+                        }
+                        println!("    - offset: {} bytes", mem.location);
+                        if let Some(s) = db.type_by_id(mem.type_id).unwrap().byte_size(db) {
+                            println!("    - size: {} bytes", s);
+                        }
+                        if let Some(a) = mem.alignment {
+                            println!("    - aligned: {} bytes", a);
+                        }
+                        if mem.artificial {
+                            println!("    - artificial");
+                        }
+                    }
+                } else {
+                    println!("- no members");
+                }
+
+                struct_picture(db, s, db.pointer_size() as usize);
+            }
+            Type::Enum(s) => {
+                println!("enum type");
+                if let Some(z) = s.byte_size {
+                    println!("- byte size: {z}");
+                }
+                if let Some(a) = t.alignment(db) {
+                    println!("- alignment: {a}");
+                } else {
+                    println!("- not aligned");
+                }
+                if !s.template_type_parameters.is_empty() {
+                    println!("- type parameters:");
+                    for ttp in &s.template_type_parameters {
+                        println!("  - {} = {}", ttp.name, NamedGoff(db, ttp.type_id));
+                    }
+                }
+
+                match &s.shape {
+                    debugdb::VariantShape::Zero => {
+                        println!("- empty (uninhabited) enum");
+                    }
+                    debugdb::VariantShape::One(v) => {
+                        println!("- single variant enum w/o discriminator");
+                        println!("  - content type: {}", NamedGoff(db, v.member.type_id));
+                        println!("  - offset: {} bytes", v.member.location);
+                        if let Some(a) = v.member.alignment {
+                            println!("  - aligned: {} bytes", a);
+                        }
+                        if !v.member.artificial {
+                            println!("  - not artificial, oddly");
+                        }
+                    }
+                    debugdb::VariantShape::Many { member, variants, .. }=> {
+                        if let Some(dname) = db.type_name(member.type_id) {
+                            println!("- {} variants discriminated by {} at offset {}", variants.len(), dname, member.location);
+                        } else {
+                            println!("- {} variants discriminated by an anonymous type at offset {}", variants.len(), member.location);
+                        }
+                        if !member.artificial {
+                            println!("  - not artificial, oddly");
+                        }
+                        
+                        // Print explicit values first
+                        for (val, var) in variants {
+                            if let Some(val) = val {
+                                println!("- when discriminator == {}", val);
+                                println!("  - contains type: {}", NamedGoff(db, var.member.type_id));
+                                println!("  - at offset: {} bytes", var.member.location);
+                                if let Some(a) = var.member.alignment {
+                                    println!("  - aligned: {} bytes", a);
+                                }
+                            }
+                        }
+                        // Now, default.
+                        for (val, var) in variants {
+                            if val.is_none() {
+                                println!("- any other discriminator value");
+                                let ranges = s.discriminant_ranges(db);
+                                for (r, _) in ranges.iter().filter(|(_, v)| std::ptr::eq(*v, var)) {
+                                    if r.start() == r.end() {
+                                        println!("  - {:#x}", r.start());
+                                    } else {
+                                        println!("  - {:#x} ..= {:#x}", r.start(), r.end());
+                                    }
+                                }
+                                println!("  - contains type: {}", NamedGoff(db, var.member.type_id));
+                                println!("  - at offset: {} bytes", var.member.location);
+                                if let Some(a) = var.member.alignment {
+                                    println!("  - aligned: {} bytes", a);
+                                }
+                            }
+                        }
                     }
-                    println!();
                 }
-                if let Some(z) = s.byte_size {
-                    println!("- byte size: {z}");
+                enum_picture(db, s, db.pointer_size() as usize);
+            }
+            Type::CEnum(s) => {
+                println!("C-like enum type");
+                println!("- byte size: {}", s.byte_size);
+                if let Some(a) = t.alignment(db) {
+                    println!("- alignment: {a}");
+                }
+                println!("- {} values defined", s.enumerators.len());
+                for e in s.enumerators.values() {
+                    println!("  - {} = 0x{:x}", e.name, e.const_value);
+
                 }
-                if let Some(a) = s.alignment {
-                    println!("- alignment: {}", a);
+            }
+            Type::Union(s) => {
+                println!("union type");
+                println!("- byte size: {}", s.byte_size);
+                if let Some(a) = t.alignment(db) {
+                    println!("- alignment: {a}");
                 } else {
                     println!("- not aligned");
                 }
@@ -336,9 +2247,9 @@ fn cmd_info(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                 }
                 if !s.members.is_empty() {
                     println!("- members:");
-                    for (i, mem) in s.members.iter().enumerate() {
+                    for mem in &s.members {
                         if let Some(name) = &mem.name {
-                            println!("  {i}. {name}: {}", NamedGoff(db, mem.type_id));
+                            println!("  - {}: {}", name, NamedGoff(db, mem.type_id));
                         } else {
                             println!("  - <unnamed>: {}", NamedGoff(db, mem.type_id));
                         }
@@ -356,249 +2267,1316 @@ fn cmd_info(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                 } else {
                     println!("- no members");
                 }
+            }
+            Type::Subroutine(s) => {
+                println!("subroutine type");
+                if let Some(rt) = s.return_type_id {
+                    println!("- return type: {}", NamedGoff(db, rt));
+                } else {
+                    println!("- returns nothing");
+                }
+                if !s.formal_parameters.is_empty() {
+                    println!("- formal parameters:");
+                    for (fp, name) in s.formal_parameters.iter().zip(&s.parameter_names) {
+                        if let Some(name) = name {
+                            println!("  - {}: {}", name, NamedGoff(db, *fp));
+                        } else {
+                            println!("  - {}", NamedGoff(db, *fp));
+                        }
+                    }
+                } else if !s.variadic {
+                    println!("- no parameters");
+                }
+                if s.variadic {
+                    println!("- variadic: takes further arguments");
+                }
+                if s.prototyped {
+                    println!("- prototyped");
+                }
+                if let Some(cc) = s.calling_convention {
+                    println!("- calling convention: {}", cc);
+                }
+            }
+            Type::Unresolved(_) => {
+                println!("type not found in debug info!");
+            }
+        }
+        if let Some(closure) = db.closure(t.id()) {
+            println!("- closure #{} in {}{}", closure.index, bold().paint(closure.function),
+                closure.generic_args);
+            if let Some(decl) = closure.decl {
+                println!("- written at: {}", format_coord(decl));
+            }
+            if let Some(body) = closure.body {
+                // The demangled name would be `...::{{closure}}`, without the
+                // closure's number.
+                let name = db.subprogram_by_id(body)
+                    .and_then(|p| p.name.clone())
+                    .unwrap_or_else(|| program_name(db, body));
+                println!("- code: {} {}", name, dimmed().paint(Goff(body).to_string()));
+            }
+        }
+        if let Some(image) = db.image_containing(t.id()) {
+            println!("- image: {}", image.name);
+        }
+        if let Some(fp) = db.fingerprint(t.id()) {
+            println!("- fingerprint: {fp}");
+        }
+    })
+}
+
+/// Summarizes a type as JSON, for `info`. This covers the same ground as the
+/// text output, in a more regular form.
+fn type_info_json(db: &debugdb::DebugDb, goff: TypeId, t: &Type) -> serde_json::Value {
+    use serde_json::json;
+
+    let members_json = |members: &[Member]| -> Vec<serde_json::Value> {
+        members.iter()
+            .map(|m| json!({
+                "name": m.name.as_deref(),
+                "type": type_ref_json(db, m.type_id),
+                "offset": m.location,
+                "byte_size": db.type_by_id(m.type_id).and_then(|t| t.byte_size(db)),
+                "artificial": m.artificial,
+            }))
+            .collect()
+    };
+    let params_json = |params: &[debugdb::TemplateTypeParameter]| -> Vec<serde_json::Value> {
+        params.iter()
+            .map(|p| json!({
+                "name": &*p.name,
+                "type": type_ref_json(db, p.type_id),
+            }))
+            .collect()
+    };
+
+    let mut obj = type_ref_json(db, goff);
+    obj["kind"] = t.kind_name().into();
+    obj["summary"] = t.display(db).to_string().into();
+    obj["byte_size"] = t.byte_size(db).into();
+    obj["alignment"] = t.alignment(db).into();
+    match t {
+        Type::Base(s) => {
+            obj["encoding"] = format!("{:?}", s.encoding).into();
+        }
+        Type::Pointer(s) => {
+            obj["pointee"] = type_ref_json(db, s.type_id);
+            obj["pointer_kind"] = format!("{:?}", s.kind(db)).into();
+            obj["mutable"] = s.is_mut(db).into();
+            obj["address_class"] = s.address_class.into();
+        }
+        Type::Array(s) => {
+            obj["element"] = type_ref_json(db, s.element_type_id);
+            obj["lower_bound"] = s.lower_bound.into();
+            obj["count"] = s.count.into();
+        }
+        Type::Struct(s) => {
+            obj["tuple_like"] = s.tuple_like.into();
+            obj["type_parameters"] = params_json(&s.template_type_parameters).into();
+            obj["members"] = members_json(&s.members).into();
+        }
+        Type::Union(s) => {
+            obj["type_parameters"] = params_json(&s.template_type_parameters).into();
+            obj["members"] = members_json(&s.members).into();
+        }
+        Type::Enum(s) => {
+            obj["type_parameters"] = params_json(&s.template_type_parameters).into();
+            let variant_json = |d: Option<u64>, v: &debugdb::Variant| json!({
+                "name": v.member.name.as_deref(),
+                "discriminant": d,
+                "type": type_ref_json(db, v.member.type_id),
+                "offset": v.member.location,
+            });
+            match &s.shape {
+                VariantShape::Zero => {
+                    obj["variants"] = json!([]);
+                }
+                VariantShape::One(v) => {
+                    obj["variants"] = json!([variant_json(None, v)]);
+                }
+                VariantShape::Many { member, variants, .. } => {
+                    obj["discriminator"] = json!({
+                        "type": type_ref_json(db, member.type_id),
+                        "offset": member.location,
+                    });
+                    obj["variants"] = variants.iter()
+                        .map(|(&d, v)| variant_json(d, v))
+                        .collect();
+                }
+            }
+        }
+        Type::CEnum(s) => {
+            obj["enum_class"] = s.enum_class.into();
+            obj["enumerators"] = s.enumerators.values()
+                .map(|e| json!({"name": &*e.name, "value": e.const_value}))
+                .collect();
+        }
+        Type::Subroutine(s) => {
+            obj["parameters"] = s.formal_parameters.iter()
+                .zip(&s.parameter_names)
+                .map(|(&p, name)| json!({
+                    "name": name.as_deref(),
+                    "type": type_ref_json(db, p),
+                }))
+                .collect();
+            obj["return_type"] = s.return_type_id
+                .map(|rt| type_ref_json(db, rt))
+                .into();
+            obj["variadic"] = s.variadic.into();
+        }
+        Type::Typedef(s) => {
+            obj["target"] = type_ref_json(db, s.type_id);
+        }
+        Type::Qualified(s) => {
+            obj["qualifier"] = s.qualifier.to_string().into();
+            obj["target"] = type_ref_json(db, s.type_id);
+        }
+        Type::Unresolved(_) => (),
+    }
+    if let Some(closure) = db.closure(goff) {
+        obj["closure"] = json!({
+            "function": closure.function,
+            "index": closure.index,
+            "generic_args": closure.generic_args,
+            "decl": closure.decl.map(format_coord),
+        });
+    }
+    if let Some(image) = db.image_containing(goff) {
+        obj["image"] = image.name.as_str().into();
+    }
+    obj["fingerprint"] = db.fingerprint(goff).map(|fp| fp.to_string()).into();
+    obj
+}
+
+fn cmd_uses(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    match args.strip_prefix("-r") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => {
+            simple_query_cmd(db, rest, |db, t| print_users(db, t.id(), true))
+        }
+        _ => simple_query_cmd(db, args, |db, t| print_users(db, t.id(), false)),
+    }
+}
+
+/// Prints the types that refer to `id`. If `recursive`, this continues on to
+/// the types that refer to those, and so on, printed as a tree.
+fn print_users(db: &debugdb::DebugDb, id: TypeId, recursive: bool) {
+    let how = |dep| match dep {
+        Dependency::Contains => "contains",
+        Dependency::PointsTo => "points to",
+        Dependency::Aliases => "aliases",
+        Dependency::Signature => "signature",
+        Dependency::Parameter => "parameter",
+    };
+
+    if !recursive {
+        let users = db.types_referencing(id).collect::<Vec<_>>();
+        if users.is_empty() {
+            println!("not used by any type");
+            return;
+        }
+        println!("used by {} types", users.len());
+        for (goff, dep, ty) in users {
+            println!("- {:9} {:6} {}", how(dep), ty.kind_name(), NamedGoff(db, goff));
+        }
+        return;
+    }
+
+    // Find everything breadth-first, so that each type shows up at the
+    // shortest distance from `id`, and remember who led to it.
+    let mut seen = std::collections::BTreeSet::from([id]);
+    let mut children: std::collections::BTreeMap<TypeId, Vec<(TypeId, Dependency)>> =
+        Default::default();
+    let mut queue = std::collections::VecDeque::from([id]);
+    while let Some(next) = queue.pop_front() {
+        for (user, dep, _) in db.types_referencing(next) {
+            if seen.insert(user) {
+                children.entry(next).or_default().push((user, dep));
+                queue.push_back(user);
+            }
+        }
+    }
+    if seen.len() == 1 {
+        println!("not used by any type");
+        return;
+    }
+    println!("used by {} types, directly or indirectly", seen.len() - 1);
+    // Print depth-first, so each type appears under the one it refers to.
+    let mut stack = children.get(&id).into_iter().flatten().rev()
+        .map(|&(user, dep)| (user, dep, 0))
+        .collect::<Vec<_>>();
+    while let Some((user, dep, depth)) = stack.pop() {
+        let ty = db.type_by_id(user).unwrap();
+        println!(
+            "{:indent$}- {:9} {:6} {}",
+            "",
+            how(dep),
+            ty.kind_name(),
+            NamedGoff(db, user),
+            indent = depth * 2,
+        );
+        stack.extend(
+            children.get(&user).into_iter().flatten().rev()
+                .map(|&(next, dep)| (next, dep, depth + 1)),
+        );
+    }
+}
+
+fn cmd_members(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    if ctx.csv {
+        csv_query_cmd(
+            db,
+            args,
+            &["type", "variant", "offset", "size", "align", "padding_before", "member", "member_type"],
+            members_csv,
+        );
+        return;
+    }
+    let (args, target) = split_svd_target(args);
+    simple_query_cmd(db, args, |db, t| {
+        let layouts = db.layouts(t.id());
+        if layouts.is_empty() {
+            println!("no members (not a struct, union or enum, or unsized)");
+            return;
+        }
+        let periph = svd_peripheral(db, ctx, t.id(), target);
+        for layout in layouts {
+            println!();
+            if let Some(v) = &layout.variant {
+                print!("variant {v}: ");
+            }
+            println!("{} bytes, {} padding", layout.byte_size, layout.padding());
+            println!("{:>6} {:>6} {:>5} {:>5}  member", "offset", "size", "align", "pad");
+            for m in &layout.members {
+                let size = m.byte_size
+                    .map(|z| z.to_string())
+                    .unwrap_or_else(|| "?".to_string());
+                let align = db.type_by_id(m.type_id)
+                    .and_then(|t| t.alignment(db))
+                    .map(|a| a.to_string())
+                    .unwrap_or_else(|| "?".to_string());
+                let pad = if m.padding_before == 0 {
+                    "".to_string()
+                } else {
+                    m.padding_before.to_string()
+                };
+                let note = periph
+                    .and_then(|p| svd_note(p, m.offset..m.end()))
+                    .map(|n| format!("  {}", dimmed().paint(n)))
+                    .unwrap_or_default();
+                println!(
+                    "{:>6} {:>6} {:>5} {:>5}  {}: {}{}",
+                    m.offset,
+                    size,
+                    align,
+                    pad,
+                    m.name.as_deref().unwrap_or("_"),
+                    NamedGoff(db, m.type_id),
+                    note,
+                );
+            }
+            if layout.trailing_padding != 0 {
+                println!(
+                    "{:>6} {:>6} {:>5} {:>5}  (trailing padding)",
+                    layout.byte_size - layout.trailing_padding,
+                    "",
+                    "",
+                    layout.trailing_padding,
+                );
+            }
+        }
+    })
+}
+
+fn cmd_whybig(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let mut args = args.trim();
+    let mut all = false;
+    let mut depth = usize::MAX;
+    loop {
+        if let Some(rest) = args.strip_prefix("-a") {
+            all = true;
+            args = rest.trim_start();
+        } else if let Some(rest) = args.strip_prefix("-d") {
+            let rest = rest.trim_start();
+            let (n, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            match n.parse::<usize>() {
+                Ok(n) => depth = n,
+                Err(_) => {
+                    println!("bad depth: {n}");
+                    return;
+                }
+            }
+            args = rest.trim_start();
+        } else {
+            break;
+        }
+    }
+    if args.is_empty() {
+        println!("usage: whybig [-a] [-d DEPTH] TYPE");
+        return;
+    }
+
+    let types: Vec<_> = match parse_type_name(args) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => lookup_types(db, n).into_iter().map(|(id, _)| id).collect(),
+        Some(ParsedTypeName::Goff(o)) => vec![o],
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+    for id in types {
+        let Some(whole) = db.size_breakdown(id) else {
+            println!("{} has no known size", NamedGoff(db, id));
+            continue;
+        };
+        if ctx.json {
+            print_json(size_part_json(db, &whole));
+            continue;
+        }
+        println!("{}: {} bytes, {} bytes of padding",
+            NamedGoff(db, id), whole.byte_size, whole.padding());
+        // Parts under 1% of the whole are summarized unless asked for.
+        let threshold = if all { 0 } else { whole.byte_size / 100 };
+        print_size_parts(db, &whole, whole.byte_size, 1, threshold, 0, depth);
+    }
+}
+
+/// Prints the parts of `part` for `whybig`, largest first. `mult` is how many
+/// times `part` is repeated within the whole type, which is `total` bytes.
+fn print_size_parts(
+    db: &DebugDb,
+    part: &debugdb::layout::SizePart,
+    total: u64,
+    mult: u64,
+    threshold: u64,
+    level: usize,
+    depth: usize,
+) {
+    use debugdb::layout::PartKind;
+
+    // An array of something that can't be broken down has nothing to add.
+    if level >= depth || matches!(&part.parts[..], [p] if p.parts.is_empty() && matches!(p.kind, PartKind::Member(None))) {
+        return;
+    }
+    let mult = mult * part.repeat;
+    let indent = "  ".repeat(level);
+    let dim = dimmed();
+
+    // Padding is named after the member it comes before, which needs the
+    // parts in offset order.
+    let labels = part.parts.iter().enumerate().map(|(i, p)| match &p.kind {
+        PartKind::Padding => match part.parts[i + 1..].iter().find(|n| !matches!(n.kind, PartKind::Padding)) {
+            Some(next) if !part.overlapping => match &next.kind {
+                PartKind::Member(Some(name)) => format!("(padding before {name})"),
+                _ => "(padding)".to_string(),
+            },
+            _ => "(trailing padding)".to_string(),
+        },
+        PartKind::Member(name) => {
+            let name = name.as_deref().unwrap_or("_");
+            match p.type_id {
+                Some(t) => format!("{name}: {}", NamedGoff(db, t)),
+                None => name.to_string(),
+            }
+        }
+        PartKind::Variant(name) => format!("variant {}", name.as_deref().unwrap_or("_")),
+        PartKind::Whole => String::new(),
+    }).collect::<Vec<_>>();
+
+    let mut order = (0..part.parts.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(part.parts[i].byte_size));
+    let (mut hidden, mut hidden_bytes) = (0, 0);
+    for i in order {
+        let p = &part.parts[i];
+        let bytes = p.byte_size * mult;
+        if bytes < threshold || bytes == 0 {
+            hidden += 1;
+            hidden_bytes += bytes;
+            continue;
+        }
+        let mut notes = vec![];
+        if p.repeat > 1 {
+            notes.push(format!("{} × {}", p.repeat, p.byte_size / p.repeat));
+        }
+        if mult > 1 {
+            notes.push(format!("{} each", p.byte_size));
+        }
+        if p.overlapping && !p.parts.is_empty() {
+            notes.push("parts overlap".to_string());
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!("  {}", dim.paint(format!("({})", notes.join(", "))))
+        };
+        println!("{bytes:>8} {:>5.1}%  {indent}{}{notes}",
+            bytes as f64 * 100.0 / total.max(1) as f64, labels[i]);
+        print_size_parts(db, p, total, mult, threshold, level + 1, depth);
+    }
+    if hidden != 0 {
+        println!("{:>8} {:>5}   {indent}{}", hidden_bytes, "",
+            dim.paint(format!("… {hidden} smaller parts (-a shows them)")));
+    }
+}
+
+/// Converts a `whybig` tree to JSON.
+fn size_part_json(db: &DebugDb, part: &debugdb::layout::SizePart) -> serde_json::Value {
+    use debugdb::layout::PartKind;
+    let kind = match &part.kind {
+        PartKind::Whole => "whole",
+        PartKind::Member(_) => "member",
+        PartKind::Variant(_) => "variant",
+        PartKind::Padding => "padding",
+    };
+    let name = match &part.kind {
+        PartKind::Member(n) | PartKind::Variant(n) => n.as_deref(),
+        _ => None,
+    };
+    serde_json::json!({
+        "kind": kind,
+        "name": name,
+        "type": part.type_id.map(|t| type_ref_json(db, t)),
+        "offset": part.offset,
+        "byte_size": part.byte_size,
+        "repeat": part.repeat,
+        "overlapping": part.overlapping,
+        "padding": part.padding(),
+        "parts": part.parts.iter().map(|p| size_part_json(db, p)).collect::<Vec<_>>(),
+    })
+}
+
+/// Bytes per cache line assumed by `pahole` unless told otherwise.
+const DEFAULT_CACHELINE: u64 = 64;
+
+fn cmd_pahole(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let mut args = args.trim();
+    let mut cacheline = DEFAULT_CACHELINE;
+    if let Some(rest) = args.strip_prefix("-c") {
+        let rest = rest.trim_start();
+        let (n, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match n.parse::<u64>() {
+            Ok(n) if n > 0 => cacheline = n,
+            _ => {
+                println!("bad cache line size: {n}");
+                return;
+            }
+        }
+        args = rest.trim();
+    }
+
+    let types: Vec<_> = if args.is_empty() {
+        // Like pahole run on a whole program: every named struct and union.
+        let mut all = db.canonical_types()
+            .filter(|(_, t)| matches!(t, Type::Struct(_) | Type::Union(_)))
+            .filter_map(|(id, _)| Some((db.type_name(id)?.into_owned(), id)))
+            .collect::<Vec<_>>();
+        all.sort();
+        all.into_iter().map(|(_, id)| id).collect()
+    } else {
+        match parse_type_name(args) {
+            None => return,
+            Some(ParsedTypeName::Name(n)) => lookup_types(db, n).into_iter().map(|(id, _)| id).collect(),
+            Some(ParsedTypeName::Goff(o)) => vec![o],
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+
+    for (i, id) in types.into_iter().enumerate() {
+        let layouts = db.layouts(id);
+        let [layout] = &layouts[..] else {
+            println!("{} is not a struct or union", NamedGoff(db, id));
+            continue;
+        };
+        if i != 0 {
+            println!();
+        }
+        pahole_report(db, layout, cacheline);
+    }
+}
+
+/// Prints a struct or union the way `pahole` does, so that its output can be
+/// fed to tools written for `pahole`.
+fn pahole_report(db: &DebugDb, layout: &debugdb::layout::Layout, cacheline: u64) {
+    let is_union = matches!(
+        db.type_by_id(layout.type_id),
+        Some(Type::Union(_)),
+    );
+    println!("{} {{", pahole_type_name(db, layout.type_id));
+
+    let mut line = 0;
+    for m in &layout.members {
+        if m.padding_before != 0 && !is_union {
+            println!();
+            println!("\t/* XXX {} hole, try to pack */", pahole_bytes(m.padding_before));
+            println!();
+        }
+        if m.offset / cacheline > line {
+            line = m.offset / cacheline;
+            let boundary = line * cacheline;
+            if m.offset == boundary {
+                println!("\t/* --- cacheline {line} boundary ({boundary} bytes) --- */");
+            } else {
+                println!("\t/* --- cacheline {line} boundary ({boundary} bytes) was {} ago --- */",
+                    pahole_bytes(m.offset - boundary));
+            }
+        }
+
+        // C declarator syntax: arrays go after the name.
+        let mut type_id = m.type_id;
+        let mut dims = String::new();
+        while let Some(Type::Array(a)) = db.type_by_id(type_id) {
+            match a.count {
+                Some(n) => dims += &format!("[{n}]"),
+                None => dims += "[]",
+            }
+            type_id = a.element_type_id;
+        }
+        let decl = format!("{}{dims};", m.name.as_deref().unwrap_or(""));
+        println!("\t{:<26} {:<21} /* {:5} {:5} */",
+            pahole_type_name(db, type_id), decl, m.offset, m.byte_size.unwrap_or(0));
+    }
+
+    let member_bytes: u64 = layout.members.iter().filter_map(|m| m.byte_size).sum();
+    let holes = layout.members.iter().filter(|m| m.padding_before != 0).count();
+    println!();
+    println!("\t/* size: {}, cachelines: {}, members: {} */",
+        layout.byte_size, layout.byte_size.div_ceil(cacheline), layout.members.len());
+    if holes != 0 && !is_union {
+        println!("\t/* sum members: {member_bytes}, holes: {holes}, sum holes: {} */",
+            layout.internal_padding());
+    }
+    if layout.trailing_padding != 0 {
+        println!("\t/* padding: {} */", layout.trailing_padding);
+    }
+    if !layout.byte_size.is_multiple_of(cacheline) {
+        println!("\t/* last cacheline: {} */", pahole_bytes(layout.byte_size % cacheline));
+    }
+    println!("}};");
+}
+
+/// Names a type the way C would declare it, with `struct`, `union` or `enum`
+/// in front of tagged types, and `*` after the pointee of unnamed pointers.
+fn pahole_type_name(db: &DebugDb, id: TypeId) -> String {
+    let name = db.type_name(id).map(Cow::into_owned).unwrap_or_else(|| "?".to_string());
+    match db.type_by_id(id) {
+        Some(Type::Struct(_)) => format!("struct {name}"),
+        Some(Type::Union(_)) => format!("union {name}"),
+        Some(Type::CEnum(_)) => format!("enum {name}"),
+        // Function pointers would need the name in the middle, so they keep
+        // the usual spelling.
+        Some(Type::Pointer(p)) if p.name.is_none() => match db.type_by_id(p.type_id) {
+            Some(Type::Subroutine(_)) | None => name,
+            _ => format!("{} *", pahole_type_name(db, p.type_id)),
+        },
+        _ => name,
+    }
+}
+
+/// Formats a number of bytes as `pahole` does, minding the plural.
+fn pahole_bytes(n: u64) -> String {
+    if n == 1 {
+        "1 byte".to_string()
+    } else {
+        format!("{n} bytes")
+    }
+}
+
+/// Produces the rows of `members` CSV output for one type: one for each
+/// member, and one for any trailing padding, with no member name.
+fn members_csv(db: &debugdb::DebugDb, goff: TypeId, _t: &Type) -> Vec<Vec<String>> {
+    let type_name = db.type_name(goff).map(Cow::into_owned).unwrap_or_default();
+    let mut rows = vec![];
+    for layout in db.layouts(goff) {
+        let variant = layout.variant.as_deref().unwrap_or_default().to_string();
+        for m in &layout.members {
+            rows.push(vec![
+                type_name.clone(),
+                variant.clone(),
+                m.offset.to_string(),
+                m.byte_size.map(|z| z.to_string()).unwrap_or_default(),
+                db.type_by_id(m.type_id)
+                    .and_then(|t| t.alignment(db))
+                    .map(|a| a.to_string())
+                    .unwrap_or_default(),
+                m.padding_before.to_string(),
+                m.name.as_deref().unwrap_or("_").to_string(),
+                db.type_name(m.type_id).map(Cow::into_owned).unwrap_or_default(),
+            ]);
+        }
+        if layout.trailing_padding != 0 {
+            rows.push(vec![
+                type_name.clone(),
+                variant,
+                (layout.byte_size - layout.trailing_padding).to_string(),
+                String::new(),
+                String::new(),
+                layout.trailing_padding.to_string(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+    }
+    rows
+}
+
+fn cmd_offsetof(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let Some((type_name, path)) = args.trim().rsplit_once(char::is_whitespace) else {
+        println!("usage: offsetof [typename] [path.to.field[2]]");
+        return;
+    };
+    let types: Vec<_> = match parse_type_name(type_name.trim()) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+
+    for (goff, _) in types {
+        println!("{}:", NamedGoff(db, goff));
+        match resolve_member_path(db, goff, path) {
+            Ok(steps) => {
+                let (offset, ty) = steps.last()
+                    .map(|(_, _, offset, ty)| (*offset, *ty))
+                    .unwrap_or((0, goff));
+                println!("- offset: {offset} ({offset:#x}) bytes");
+                println!("- type: {}", NamedGoff(db, ty));
+                for (step, delta, offset, ty) in steps {
+                    println!("  - {step}: +{delta} = {offset} ({})", NamedGoff(db, ty));
+                }
+            }
+            Err(e) => println!("- {e}"),
+        }
+    }
+}
 
-                struct_picture(db, s, db.pointer_size() as usize);
+/// Follows a path like `a.b[2].c` through the members of `id`, returning each
+/// step along with its offset from the enclosing step, its offset from the
+/// start of `id`, and its type.
+///
+/// Path components name members of structs and unions, or variants of
+/// enums. `[n]` indexes into an array. Typedefs and qualifiers are looked
+/// through along the way.
+fn resolve_member_path(
+    db: &DebugDb,
+    id: TypeId,
+    path: &str,
+) -> Result<Vec<(String, u64, u64, TypeId)>, String> {
+    // Split into components, keeping the brackets on indices so they can be
+    // told apart from names.
+    let mut components = vec![];
+    for part in path.split('.') {
+        let (name, mut indices) = part.split_once('[')
+            .map(|(n, rest)| (n, Some(rest)))
+            .unwrap_or((part, None));
+        if !name.is_empty() {
+            components.push(name.to_string());
+        }
+        while let Some(rest) = indices {
+            let (index, after) = rest.split_once(']')
+                .ok_or_else(|| format!("missing ] in {part}"))?;
+            components.push(format!("[{index}]"));
+            indices = after.strip_prefix('[');
+            if indices.is_none() && !after.is_empty() {
+                return Err(format!("unexpected {after:?} in {part}"));
             }
-            Type::Enum(s) => {
-                println!("enum type");
-                if let Some(z) = s.byte_size {
-                    println!("- byte size: {z}");
-                }
-                if let Some(a) = s.alignment {
-                    println!("- alignment: {}", a);
-                } else {
-                    println!("- not aligned");
-                }
-                if !s.template_type_parameters.is_empty() {
-                    println!("- type parameters:");
-                    for ttp in &s.template_type_parameters {
-                        println!("  - {} = {}", ttp.name, NamedGoff(db, ttp.type_id));
-                    }
-                }
+        }
+    }
 
-                match &s.shape {
-                    debugdb::VariantShape::Zero => {
-                        println!("- empty (uninhabited) enum");
-                    }
-                    debugdb::VariantShape::One(v) => {
-                        println!("- single variant enum w/o discriminator");
-                        println!("  - content type: {}", NamedGoff(db, v.member.type_id));
-                        println!("  - offset: {} bytes", v.member.location);
-                        if let Some(a) = v.member.alignment {
-                            println!("  - aligned: {} bytes", a);
-                        }
-                        if !v.member.artificial {
-                            println!("  - not artificial, oddly");
-                        }
-                    }
-                    debugdb::VariantShape::Many { member, variants, .. }=> {
-                        if let Some(dname) = db.type_name(member.type_id) {
-                            println!("- {} variants discriminated by {} at offset {}", variants.len(), dname, member.location);
-                        } else {
-                            println!("- {} variants discriminated by an anonymous type at offset {}", variants.len(), member.location);
-                        }
-                        if !member.artificial {
-                            println!("  - not artificial, oddly");
-                        }
-                        
-                        // Print explicit values first
-                        for (val, var) in variants {
-                            if let Some(val) = val {
-                                println!("- when discriminator == {}", val);
-                                println!("  - contains type: {}", NamedGoff(db, var.member.type_id));
-                                println!("  - at offset: {} bytes", var.member.location);
-                                if let Some(a) = var.member.alignment {
-                                    println!("  - aligned: {} bytes", a);
-                                }
-                            }
-                        }
-                        // Now, default.
-                        for (val, var) in variants {
-                            if val.is_none() {
-                                println!("- any other discriminator value");
-                                println!("  - contains type: {}", NamedGoff(db, var.member.type_id));
-                                println!("  - at offset: {} bytes", var.member.location);
-                                if let Some(a) = var.member.alignment {
-                                    println!("  - aligned: {} bytes", a);
-                                }
-                            }
-                        }
-                    }
+    let mut steps = vec![];
+    let mut offset = 0;
+    let mut current = id;
+    for c in components {
+        let ty = db.type_by_id(db.resolve_alias(current))
+            .ok_or_else(|| format!("type of {c} is missing"))?;
+        let (delta, next) = if let Some(index) = c.strip_prefix('[') {
+            let index = index.trim_end_matches(']');
+            let index = parse_int::parse::<u64>(index)
+                .map_err(|e| format!("bad index {index}: {e}"))?;
+            let Type::Array(a) = ty else {
+                return Err(format!("can't index {}, it's not an array", ty.name(db)));
+            };
+            if let Some(count) = a.count {
+                if index >= count {
+                    return Err(format!("index {index} out of bounds for {}", ty.name(db)));
                 }
-                enum_picture(db, s, db.pointer_size() as usize);
             }
-            Type::CEnum(s) => {
-                println!("C-like enum type");
-                println!("- byte size: {}", s.byte_size);
-                if let Some(a) = s.alignment {
-                    println!("- alignment: {a}");
+            let elty = db.type_by_id(a.element_type_id)
+                .ok_or("array element type is missing")?;
+            let elt_size = elty.byte_size(db)
+                .ok_or("array element type has no size")?;
+            let elt_size = elt_size.max(elty.alignment(db).unwrap_or(0));
+            (index * elt_size, a.element_type_id)
+        } else {
+            let member = match ty {
+                Type::Struct(s) => s.unique_member(&c).cloned(),
+                Type::Union(u) => u.members.iter()
+                    .find(|m| m.name.as_deref() == Some(c.as_str()))
+                    .cloned(),
+                Type::Enum(e) => {
+                    let variants: Vec<&debugdb::Variant> = match &e.shape {
+                        VariantShape::Zero => vec![],
+                        VariantShape::One(v) => vec![v],
+                        VariantShape::Many { variants, .. } => variants.values().collect(),
+                    };
+                    variants.into_iter()
+                        .find(|v| v.member.name.as_deref() == Some(c.as_str()))
+                        .map(|v| v.member.clone())
                 }
-                println!("- {} values defined", s.enumerators.len());
-                for e in s.enumerators.values() {
-                    println!("  - {} = 0x{:x}", e.name, e.const_value);
+                _ => None,
+            };
+            let member = member
+                .ok_or_else(|| format!("{} has no member {c}", ty.name(db)))?;
+            (member.location, member.type_id)
+        };
+        offset += delta;
+        current = next;
+        let step = if c.starts_with('[') { c } else { format!(".{c}") };
+        steps.push((step, delta, offset, current));
+    }
+    Ok(steps)
+}
 
-                }
+fn cmd_findsize(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    use std::ops::Bound;
+
+    let args = args.replace(' ', "");
+    let parse = |s: &str| parse_int::parse::<u64>(s)
+        .map_err(|e| println!("bad size {s:?}: {e}"))
+        .ok();
+    let bounds = if let Some(n) = args.strip_prefix(">=") {
+        (Bound::Included(parse(n)), Bound::Unbounded)
+    } else if let Some(n) = args.strip_prefix('>') {
+        (Bound::Excluded(parse(n)), Bound::Unbounded)
+    } else if let Some(n) = args.strip_prefix("<=") {
+        (Bound::Unbounded, Bound::Included(parse(n)))
+    } else if let Some(n) = args.strip_prefix('<') {
+        (Bound::Unbounded, Bound::Excluded(parse(n)))
+    } else if let Some((lo, hi)) = args.split_once("..=") {
+        (Bound::Included(parse(lo)), Bound::Included(parse(hi)))
+    } else if let Some((lo, hi)) = args.split_once("..") {
+        (Bound::Included(parse(lo)), Bound::Excluded(parse(hi)))
+    } else if !args.is_empty() {
+        let n = parse(&args);
+        (Bound::Included(n), Bound::Included(n))
+    } else {
+        println!("usage: findsize [N | >N | >=N | <N | <=N | LO..HI | LO..=HI]");
+        return;
+    };
+    // Turn a Bound<Option<u64>> into an Option<Bound<u64>>, so that a
+    // failure to parse fails the whole thing.
+    let lift = |b: Bound<Option<u64>>| match b {
+        Bound::Included(n) => n.map(Bound::Included),
+        Bound::Excluded(n) => n.map(Bound::Excluded),
+        Bound::Unbounded => Some(Bound::Unbounded),
+    };
+    let (Some(lo), Some(hi)) = (lift(bounds.0), lift(bounds.1)) else {
+        return;
+    };
+
+    let mut types = db.types_with_size((lo, hi)).collect::<Vec<_>>();
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+    types.sort_by_key(|&(goff, _, size)| (std::cmp::Reverse(size), db.type_name(goff)));
+    for (goff, ty, size) in types {
+        println!("{:>8} {:6} {}", size, ty.kind_name(), NamedGoff(db, goff));
+    }
+}
+
+fn cmd_sizeof(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    if ctx.json {
+        json_query_cmd(db, args, |db, goff, t| {
+            let mut obj = type_ref_json(db, goff);
+            obj["byte_size"] = t.byte_size(db).into();
+            obj
+        });
+        return;
+    }
+    simple_query_cmd(db, args, |db, t| {
+        if let Some(sz) = t.byte_size(db) {
+            println!("{} bytes", sz);
+        } else {
+            println!("unsized");
+        }
+    })
+}
+
+fn cmd_alignof(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    simple_query_cmd(db, args, |db, t| {
+        if let Some(sz) = t.alignment(db) {
+            println!("align to {} bytes", sz);
+        } else {
+            println!("no alignment information");
+        }
+    })
+}
+
+fn cmd_eval(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let mut eval = Eval { db, ctx, s: args, pos: 0 };
+    let result = eval.expr().and_then(|v| {
+        eval.skip_space();
+        if eval.pos == args.len() {
+            Ok(v)
+        } else {
+            Err(format!("unexpected `{}`", &args[eval.pos..]))
+        }
+    });
+    match result {
+        Ok(v) if ctx.json => print_json(serde_json::json!({ "value": v as i64 })),
+        Ok(v) if v < 0 => println!("{v}"),
+        Ok(v) => println!("{v} ({v:#x})"),
+        Err(e) => println!("{}", colour(Colour::Red).paint(e)),
+    }
+}
+
+/// A recursive descent evaluator for the expressions taken by `=`: integers,
+/// `+ - * / %` and parentheses, `sizeof(T)`, `alignof(T)` and
+/// `offsetof(T, path)`, and names of C enumerators or of integer static
+/// variables, whose values are read from the loaded segments.
+struct Eval<'a> {
+    db: &'a debugdb::DebugDb,
+    ctx: &'a Ctx,
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Eval<'a> {
+    fn skip_space(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `c` if it's the next non-space character.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.s[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<i128, String> {
+        let mut v = self.term()?;
+        loop {
+            if self.eat('+') {
+                v = v.checked_add(self.term()?).ok_or("overflow")?;
+            } else if self.eat('-') {
+                v = v.checked_sub(self.term()?).ok_or("overflow")?;
+            } else {
+                return Ok(v);
             }
-            Type::Union(s) => {
-                println!("union type");
-                println!("- byte size: {}", s.byte_size);
-                println!("- alignment: {}", s.alignment);
-                if !s.template_type_parameters.is_empty() {
-                    println!("- template type parameters:");
-                    for ttp in &s.template_type_parameters {
-                        println!("  - {} = {}", ttp.name, NamedGoff(db, ttp.type_id));
-                    }
-                }
-                if !s.members.is_empty() {
-                    println!("- members:");
-                    for mem in &s.members {
-                        if let Some(name) = &mem.name {
-                            println!("  - {}: {}", name, NamedGoff(db, mem.type_id));
-                        } else {
-                            println!("  - <unnamed>: {}", NamedGoff(db, mem.type_id));
-                        }
-                        println!("    - offset: {} bytes", mem.location);
-                        if let Some(a) = mem.alignment {
-                            println!("    - aligned: {} bytes", a);
-                        }
-                        if mem.artificial {
-                            println!("    - artificial");
-                        }
-                    }
-                } else {
-                    println!("- no members");
-                }
+        }
+    }
+
+    fn term(&mut self) -> Result<i128, String> {
+        let mut v = self.unary()?;
+        loop {
+            if self.eat('*') {
+                v = v.checked_mul(self.unary()?).ok_or("overflow")?;
+            } else if self.eat('/') {
+                v = v.checked_div(self.unary()?).ok_or("division by zero")?;
+            } else if self.eat('%') {
+                v = v.checked_rem(self.unary()?).ok_or("division by zero")?;
+            } else {
+                return Ok(v);
             }
-            Type::Subroutine(s) => {
-                println!("subroutine type");
-                if let Some(rt) = s.return_type_id {
-                    println!("- return type: {}", NamedGoff(db, rt));
-                }
-                if !s.formal_parameters.is_empty() {
-                    println!("- formal parameters:");
-                    for &fp in &s.formal_parameters {
-                        println!("  - {}", NamedGoff(db, fp));
-                    }
-                }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i128, String> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('(') {
+            let v = self.expr()?;
+            if !self.eat(')') {
+                return Err("expected `)`".to_string());
             }
-            Type::Unresolved(_) => {
-                println!("type not found in debug info!");
+            return Ok(v);
+        }
+        self.skip_space();
+        let rest = &self.s[self.pos..];
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(rest.len());
+        let word = &rest[..len];
+        if word.is_empty() {
+            return Err(match rest.chars().next() {
+                Some(c) => format!("unexpected `{c}`"),
+                None => "unexpected end of expression".to_string(),
+            });
+        }
+        self.pos += len;
+
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            return parse_int(word);
+        }
+        match word {
+            "sizeof" | "alignof" | "offsetof" => {
+                let arg = self.call_argument(word)?;
+                self.layout_fact(word, arg.trim())
+            }
+            name => self.constant(name),
+        }
+    }
+
+    /// Takes the text between the parentheses after `sizeof` and friends,
+    /// which is a type name rather than an expression.
+    fn call_argument(&mut self, func: &str) -> Result<&'a str, String> {
+        if !self.eat('(') {
+            return Err(format!("expected `(` after {func}"));
+        }
+        let start = self.pos;
+        let mut depth = 1;
+        for (i, c) in self.s[start..].char_indices() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                self.pos = start + i + 1;
+                return Ok(&self.s[start..start + i]);
             }
         }
-    })
-}
+        Err(format!("expected `)` to end {func}"))
+    }
+
+    fn layout_fact(&self, func: &str, arg: &str) -> Result<i128, String> {
+        let (type_name, path) = match func {
+            "offsetof" => arg.rsplit_once(',')
+                .map(|(t, p)| (t.trim(), Some(p.trim())))
+                .ok_or("usage: offsetof(TYPE, path.to.field)")?,
+            _ => (arg, None),
+        };
+        let goff = self.type_named(type_name)?;
+        let ty = self.db.type_by_id(goff).ok_or("type not found")?;
+        let v = match (func, path) {
+            ("offsetof", Some(path)) => resolve_member_path(self.db, goff, path)?
+                .last()
+                .map_or(0, |&(_, _, offset, _)| offset),
+            ("alignof", _) => ty.alignment(self.db)
+                .ok_or_else(|| format!("no alignment information for {type_name}"))?,
+            _ => ty.byte_size(self.db)
+                .ok_or_else(|| format!("{type_name} is unsized"))?,
+        };
+        Ok(v.into())
+    }
+
+    fn type_named(&self, name: &str) -> Result<TypeId, String> {
+        let id = match parse_type_name(name) {
+            Some(ParsedTypeName::Name(n)) => lookup_types(self.db, n).first().map(|&(id, _)| id),
+            Some(ParsedTypeName::Goff(o)) => self.db.type_by_id(o).map(|_| o),
+            None => None,
+        };
+        id.ok_or_else(|| format!("no type named {name}"))
+    }
+
+    /// Finds the value of a C enumerator, or of a static variable of integer
+    /// type.
+    fn constant(&self, name: &str) -> Result<i128, String> {
+        for (_, ty) in self.db.canonical_types() {
+            if let Type::CEnum(e) = ty {
+                if let Some(e) = e.enumerators.values().find(|e| &*e.name == name) {
+                    return Ok(e.const_value.into());
+                }
+            }
+        }
+        let Some((_, v)) = self.db.static_variables_by_name(name).next() else {
+            return Err(format!("no enumerator or static variable named {name}"));
+        };
+        let ty = self.db.type_by_id(v.type_id)
+            .ok_or_else(|| format!("no type for {name}"))?;
+        let value = Value::from_state(&self.ctx.memory(), v.location, self.db, ty)
+            .map_err(|e| format!("can't read {name}: {e}"))?;
+        value.u64_value()
+            .map(Into::into)
+            .ok_or_else(|| format!("{name} isn't an integer"))
+    }
+}
+
+/// Parses an integer literal in decimal, or hex, octal or binary with a
+/// `0x`, `0o` or `0b` prefix. Underscores are ignored, as in Rust.
+fn parse_int(word: &str) -> Result<i128, String> {
+    let digits = word.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..]),
+    };
+    i128::from_str_radix(digits, radix).map_err(|e| format!("bad number `{word}`: {e}"))
+}
+
+fn cmd_def(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    if let Some(rest) = args.trim().strip_prefix("-r ") {
+        def_recursive(db, ctx, rest.trim());
+        return;
+    }
+    if ctx.json {
+        json_query_cmd(db, args, |db, goff, t| {
+            let mut def = String::new();
+            write_def(db, t, &mut def).unwrap();
+            let mut obj = type_ref_json(db, goff);
+            obj["definition"] = def.into();
+            obj
+        });
+        return;
+    }
+    simple_query_cmd(db, args, |db, t| {
+        println!();
+        let mut def = String::new();
+        write_def(db, t, &mut def).unwrap();
+        print!("{def}");
+    })
+}
+
+/// Handles `def -r`, printing definitions for a type and everything it
+/// refers to, with each definition before any that use it.
+fn def_recursive(db: &debugdb::DebugDb, ctx: &mut Ctx, type_name: &str) {
+    let types: Vec<_> = match parse_type_name(type_name) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+
+    let mut seen = std::collections::BTreeSet::new();
+    let mut order = vec![];
+    for &(goff, _) in &types {
+        def_order(db, goff, &mut seen, &mut order);
+    }
+
+    // Variants are printed as part of their enums, not separately.
+    let variants = order.iter()
+        .filter_map(|&id| match db.type_by_id(id) {
+            Some(Type::Enum(e)) => Some(e.shape.variants().map(|v| v.member.type_id)),
+            _ => None,
+        })
+        .flatten()
+        .collect::<std::collections::BTreeSet<_>>();
+
+    // The same type may appear under several IDs if it was defined in more
+    // than one compilation unit; print each name once.
+    let mut printed_names = std::collections::BTreeSet::new();
+    let mut defs = vec![];
+    for id in order {
+        if variants.contains(&id) {
+            continue;
+        }
+        let Some(t) = db.type_by_id(id) else { continue };
+        if !matches!(t, Type::Struct(_) | Type::Union(_) | Type::Enum(_)
+            | Type::CEnum(_) | Type::Typedef(_))
+        {
+            continue;
+        }
+        if !printed_names.insert(db.type_name(id)) {
+            continue;
+        }
+        let mut def = String::new();
+        write_def(db, t, &mut def).unwrap();
+        defs.push((id, def));
+    }
 
-fn cmd_sizeof(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    simple_query_cmd(db, args, |db, t| {
-        if let Some(sz) = t.byte_size(db) {
-            println!("{} bytes", sz);
-        } else {
-            println!("unsized");
+    if ctx.json {
+        print_json(defs.into_iter()
+            .map(|(id, def)| {
+                let mut obj = type_ref_json(db, id);
+                obj["definition"] = def.into();
+                obj
+            })
+            .collect());
+        return;
+    }
+    for (i, (_, def)) in defs.iter().enumerate() {
+        if i > 0 {
+            println!();
         }
-    })
+        print!("{def}");
+    }
 }
 
-fn cmd_alignof(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    simple_query_cmd(db, args, |db, t| {
-        if let Some(sz) = t.alignment(db) {
-            println!("align to {} bytes", sz);
-        } else {
-            println!("no alignment information");
+/// Adds `id` and the types it depends on to `order`, dependencies first,
+/// skipping any already in `seen`.
+fn def_order(
+    db: &debugdb::DebugDb,
+    id: TypeId,
+    seen: &mut std::collections::BTreeSet<TypeId>,
+    order: &mut Vec<TypeId>,
+) {
+    if !seen.insert(id) {
+        return;
+    }
+    if let Some(t) = db.type_by_id(id) {
+        for (_, next) in t.dependencies() {
+            def_order(db, next, seen, order);
         }
-    })
+    }
+    order.push(id);
 }
 
-fn cmd_def(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    simple_query_cmd(db, args, |db, t| {
-        println!();
-        match t {
-            Type::Base(s) => {
-                print!("type _ = ");
-                match (s.encoding, s.byte_size) {
-                    (_, 0) => print!("()"),
-                    (Encoding::Unsigned, 1) => print!("u8"),
-                    (Encoding::Unsigned, 2) => print!("u16"),
-                    (Encoding::Unsigned, 4) => print!("u32"),
-                    (Encoding::Unsigned, 8) => print!("u64"),
-                    (Encoding::Unsigned, 16) => print!("u128"),
-                    (Encoding::Signed, 1) => print!("i8"),
-                    (Encoding::Signed, 2) => print!("i16"),
-                    (Encoding::Signed, 4) => print!("i32"),
-                    (Encoding::Signed, 8) => print!("i64"),
-                    (Encoding::Signed, 16) => print!("i128"),
-                    (Encoding::Float, 4) => print!("f32"),
-                    (Encoding::Float, 8) => print!("f64"),
-                    (Encoding::Boolean, 1) => print!("bool"),
-                    (Encoding::UnsignedChar, 1) => print!("c_uchar"),
-                    (Encoding::SignedChar, 1) => print!("c_schar"),
-                    (Encoding::UtfChar, 4) => print!("char"),
-
-                    (e, s) => print!("Unhandled{:?}{}", e, s),
-                }
-                println!(";");
-            }
-            Type::Pointer(_s) => {
-                print!("type _ = {};", t.name(db));
+/// Writes `t` as a pseudo-Rust definition, for `def`.
+fn write_def(
+    db: &debugdb::DebugDb,
+    t: &Type,
+    out: &mut String,
+) -> std::fmt::Result {
+    match t {
+        Type::Base(s) => {
+            write!(out, "type _ = ")?;
+            match (s.encoding, s.byte_size) {
+                (_, 0) => write!(out, "()"),
+                (Encoding::Unsigned, 1) => write!(out, "u8"),
+                (Encoding::Unsigned, 2) => write!(out, "u16"),
+                (Encoding::Unsigned, 4) => write!(out, "u32"),
+                (Encoding::Unsigned, 8) => write!(out, "u64"),
+                (Encoding::Unsigned, 16) => write!(out, "u128"),
+                (Encoding::Signed, 1) => write!(out, "i8"),
+                (Encoding::Signed, 2) => write!(out, "i16"),
+                (Encoding::Signed, 4) => write!(out, "i32"),
+                (Encoding::Signed, 8) => write!(out, "i64"),
+                (Encoding::Signed, 16) => write!(out, "i128"),
+                (Encoding::Float, 4) => write!(out, "f32"),
+                (Encoding::Float, 8) => write!(out, "f64"),
+                (Encoding::Boolean, 1) => write!(out, "bool"),
+                (Encoding::UnsignedChar, 1) => write!(out, "c_uchar"),
+                (Encoding::SignedChar, 1) => write!(out, "c_schar"),
+                (Encoding::UtfChar, 4) => write!(out, "char"),
+
+                (e, s) => write!(out, "Unhandled{:?}{}", e, s),
+            }?;
+            writeln!(out, ";")?;
+        }
+        Type::Pointer(_s) => {
+            write!(out, "type _ = {};", t.name(db))?;
+        }
+        Type::Typedef(s) => {
+            // Not `NamedGoff`, since this may end up in JSON, which
+            // shouldn't contain terminal escapes.
+            let target = db.type_name(s.type_id)
+                .unwrap_or_else(|| "<anonymous type>".into());
+            writeln!(out, "type {} = {};", s.name, target)?;
+        }
+        Type::Qualified(_) => {
+            writeln!(out, "type _ = {};", t.name(db))?;
+        }
+        Type::Array(s) => {
+            let name = db.type_name(s.element_type_id).unwrap();
+            if let Some(n) = s.count {
+                writeln!(out, "[{}; {}]", name, n)?;
+            } else {
+                writeln!(out, "[{}]", name)?;
             }
-            Type::Array(s) => {
-                let name = db.type_name(s.element_type_id).unwrap();
-                if let Some(n) = s.count {
-                    println!("[{}; {}]", name, n);
-                } else {
-                    println!("[{}]", name);
+        }
+        Type::Struct(s) => {
+            write!(out, "struct {}", s.name)?;
+
+            if !s.template_type_parameters.is_empty() {
+                write!(out, "<")?;
+                for ttp in &s.template_type_parameters {
+                    write!(out, "{},", ttp.name)?;
                 }
+                write!(out, ">")?;
             }
-            Type::Struct(s) => {
-                print!("struct {}", s.name);
-
-                if !s.template_type_parameters.is_empty() {
-                    print!("<");
-                    for ttp in &s.template_type_parameters {
-                        print!("{},", ttp.name);
+            
+            if s.members.is_empty() {
+                writeln!(out, ";")?;
+            } else {
+                if s.tuple_like {
+                    writeln!(out, "(")?;
+                    for mem in &s.members {
+                        writeln!(out, "    {},", db.type_name(mem.type_id).unwrap())?;
                     }
-                    print!(">");
-                }
-                
-                if s.members.is_empty() {
-                    println!(";");
+                    writeln!(out, ");")?;
                 } else {
-                    if s.tuple_like {
-                        println!("(");
-                        for mem in &s.members {
-                            println!("    {},", db.type_name(mem.type_id).unwrap());
+                    writeln!(out, " {{")?;
+                    for mem in &s.members {
+                        if let Some(name) = &mem.name {
+                            writeln!(out, "    {}: {},", name, db.type_name(mem.type_id).unwrap())?;
+                        } else {
+                            writeln!(out, "    ANON: {},", db.type_name(mem.type_id).unwrap())?;
                         }
-                        println!(");");
+                    }
+                    writeln!(out, "}}")?;
+                }
+            }
+        }
+        Type::Enum(s) => {
+            write!(out, "enum {}", s.name)?;
+            if !s.template_type_parameters.is_empty() {
+                write!(out, "<")?;
+                for ttp in &s.template_type_parameters {
+                    write!(out, "{}", ttp.name)?;
+                }
+                write!(out, ">")?;
+            }
+            writeln!(out, " {{")?;
+
+            match &s.shape {
+                debugdb::VariantShape::Zero => (),
+                debugdb::VariantShape::One(var) => {
+                    if let Some(name) = &var.member.name {
+                        write!(out, "    {}", name)?;
                     } else {
-                        println!(" {{");
-                        for mem in &s.members {
-                            if let Some(name) = &mem.name {
-                                println!("    {}: {},", name, db.type_name(mem.type_id).unwrap());
+                        write!(out, "    ANON")?;
+                    }
+
+                    let mty = db.type_by_id(var.member.type_id)
+                        .unwrap();
+                    if let Type::Struct(s) = mty {
+                        if !s.members.is_empty() {
+                            if s.tuple_like {
+                                writeln!(out, "(")?;
+                                for mem in &s.members {
+                                    let mtn = db.type_name(mem.type_id).unwrap();
+                                    writeln!(out, "        {},", mtn)?;
+                                }
+                                write!(out, "    )")?;
                             } else {
-                                println!("    ANON: {},", db.type_name(mem.type_id).unwrap());
+                                writeln!(out, " {{")?;
+                                for mem in &s.members {
+                                    let mtn = db.type_name(mem.type_id).unwrap();
+                                    writeln!(out, "        {}: {},", mem.name.as_ref().unwrap(), mtn)?;
+                                }
+                                write!(out, "    }}")?;
                             }
                         }
-                        println!("}}");
-                    }
-                }
-            }
-            Type::Enum(s) => {
-                print!("enum {}", s.name);
-                if !s.template_type_parameters.is_empty() {
-                    print!("<");
-                    for ttp in &s.template_type_parameters {
-                        print!("{}", ttp.name);
+                    } else {
+                        write!(out, "(unexpected weirdness)")?;
                     }
-                    print!(">");
-                }
-                println!(" {{");
 
-                match &s.shape {
-                    debugdb::VariantShape::Zero => (),
-                    debugdb::VariantShape::One(var) => {
+                    writeln!(out, ",")?;
+                }
+                debugdb::VariantShape::Many { variants, .. }=> {
+                    for var in variants.values() {
                         if let Some(name) = &var.member.name {
-                            print!("    {}", name);
+                            write!(out, "    {}", name)?;
                         } else {
-                            print!("    ANON");
+                            write!(out, "    ANON")?;
                         }
 
                         let mty = db.type_by_id(var.member.type_id)
@@ -606,133 +3584,118 @@ fn cmd_def(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                         if let Type::Struct(s) = mty {
                             if !s.members.is_empty() {
                                 if s.tuple_like {
-                                    println!("(");
+                                    writeln!(out, "(")?;
                                     for mem in &s.members {
                                         let mtn = db.type_name(mem.type_id).unwrap();
-                                        println!("        {},", mtn);
+                                        writeln!(out, "        {},", mtn)?;
                                     }
-                                    print!("    )");
+                                    write!(out, "    )")?;
                                 } else {
-                                    println!(" {{");
+                                    writeln!(out, " {{")?;
                                     for mem in &s.members {
                                         let mtn = db.type_name(mem.type_id).unwrap();
-                                        println!("        {}: {},", mem.name.as_ref().unwrap(), mtn);
+                                        writeln!(out, "        {}: {},", mem.name.as_ref().unwrap(), mtn)?;
                                     }
-                                    print!("    }}");
+                                    write!(out, "    }}")?;
                                 }
                             }
                         } else {
-                            print!("(unexpected weirdness)");
+                            write!(out, "(unexpected weirdness)")?;
                         }
 
-                        println!(",");
-                    }
-                    debugdb::VariantShape::Many { variants, .. }=> {
-                        for var in variants.values() {
-                            if let Some(name) = &var.member.name {
-                                print!("    {}", name);
-                            } else {
-                                print!("    ANON");
-                            }
-
-                            let mty = db.type_by_id(var.member.type_id)
-                                .unwrap();
-                            if let Type::Struct(s) = mty {
-                                if !s.members.is_empty() {
-                                    if s.tuple_like {
-                                        println!("(");
-                                        for mem in &s.members {
-                                            let mtn = db.type_name(mem.type_id).unwrap();
-                                            println!("        {},", mtn);
-                                        }
-                                        print!("    )");
-                                    } else {
-                                        println!(" {{");
-                                        for mem in &s.members {
-                                            let mtn = db.type_name(mem.type_id).unwrap();
-                                            println!("        {}: {},", mem.name.as_ref().unwrap(), mtn);
-                                        }
-                                        print!("    }}");
-                                    }
-                                }
-                            } else {
-                                print!("(unexpected weirdness)");
-                            }
-
-                            println!(",");
-                        }
+                        writeln!(out, ",")?;
                     }
                 }
-                println!("}}");
-
-            }
-            Type::CEnum(s) => {
-                println!("enum {} {{", s.name);
-                for (val, e) in &s.enumerators {
-                    println!("    {} = 0x{:x},", e.name, val);
-                }
-                println!("}}");
             }
-            Type::Union(s) => {
-                print!("union {}", s.name);
+            writeln!(out, "}}")?;
 
-                if !s.template_type_parameters.is_empty() {
-                    print!("<");
-                    for ttp in &s.template_type_parameters {
-                        print!("{},", ttp.name);
-                    }
-                    print!(">");
-                }
+        }
+        Type::CEnum(s) => {
+            writeln!(out, "enum {} {{", s.name)?;
+            for (val, e) in &s.enumerators {
+                writeln!(out, "    {} = 0x{:x},", e.name, val)?;
+            }
+            writeln!(out, "}}")?;
+        }
+        Type::Union(s) => {
+            write!(out, "union {}", s.name)?;
 
-                println!(" {{");
-                for mem in &s.members {
-                    if let Some(name) = &mem.name {
-                        println!("    {}: {},", name, db.type_name(mem.type_id).unwrap());
-                    } else {
-                        println!("    ANON: {},", db.type_name(mem.type_id).unwrap());
-                    }
+            if !s.template_type_parameters.is_empty() {
+                write!(out, "<")?;
+                for ttp in &s.template_type_parameters {
+                    write!(out, "{},", ttp.name)?;
                 }
-                println!("}}");
+                write!(out, ">")?;
             }
-            Type::Subroutine(s) => {
-                println!("fn(");
-                for &p in &s.formal_parameters {
-                    println!("    {},", db.type_name(p).unwrap());
-                }
-                if let Some(rt) = s.return_type_id {
-                    println!(") -> {} {{", db.type_name(rt).unwrap());
+
+            writeln!(out, " {{")?;
+            for mem in &s.members {
+                if let Some(name) = &mem.name {
+                    writeln!(out, "    {}: {},", name, db.type_name(mem.type_id).unwrap())?;
                 } else {
-                    println!(") {{");
+                    writeln!(out, "    ANON: {},", db.type_name(mem.type_id).unwrap())?;
                 }
-                println!("    // code goes here");
-                println!("    // (this is a subroutine type, _not_ a fn ptr)");
-                println!("    unimplemented!();");
-                println!("}}");
             }
-            Type::Unresolved(_) => {
-                println!("(type not found in debug info!)");
+            writeln!(out, "}}")?;
+        }
+        Type::Subroutine(s) => {
+            if let Some(cc) = s.calling_convention {
+                writeln!(out, "// calling convention: {}", cc)?;
+            }
+            writeln!(out, "fn(")?;
+            for (&p, name) in s.formal_parameters.iter().zip(&s.parameter_names) {
+                let name = name.as_deref().unwrap_or("_");
+                writeln!(out, "    {}: {},", name, db.type_name(p).unwrap())?;
+            }
+            if s.variadic {
+                writeln!(out, "    ...")?;
+            }
+            if let Some(rt) = s.return_type_id {
+                writeln!(out, ") -> {} {{", db.type_name(rt).unwrap())?;
+            } else {
+                writeln!(out, ") {{")?;
             }
+            writeln!(out, "    // code goes here")?;
+            writeln!(out, "    // (this is a subroutine type, _not_ a fn ptr)")?;
+            writeln!(out, "    unimplemented!();")?;
+            writeln!(out, "}}")?;
         }
-    })
+        Type::Unresolved(_) => {
+            writeln!(out, "(type not found in debug info!)")?;
+        }
+    }
+    Ok(())
 }
 
-fn cmd_addr2line(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    let addr = if args.starts_with("0x") {
-        if let Ok(a) = u64::from_str_radix(&args[2..], 16) {
-            a
+fn cmd_addr2line(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let addr = match args.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => args.parse::<u64>().ok(),
+    };
+    let Some(addr) = addr else {
+        if ctx.json {
+            print_json(serde_json::json!({
+                "error": format!("can't parse {} as an address", args),
+            }));
         } else {
             println!("can't parse {} as an address", args);
-            return;
         }
-    } else if let Ok(a) = args.parse::<u64>() {
-        a
-    } else {
-        println!("can't parse {} as an address", args);
         return;
     };
 
-    if let Some(row) = db.lookup_line_row(addr) {
-        print!("{}:", row.file);
+    let row = db.lookup_line_row(addr);
+    if ctx.json {
+        print_json(serde_json::json!({
+            "address": addr,
+            "file": row.and_then(|row| row.file.as_ref()),
+            "line": row.and_then(|row| row.line),
+            "column": row.and_then(|row| row.column),
+        }));
+        return;
+    }
+
+    if let Some(row) = row {
+        print!("{}:", row.file.as_deref().unwrap_or("?"));
         if let Some(line) = row.line {
             print!("{}:", line);
         } else {
@@ -764,8 +3727,8 @@ fn cmd_addr2stack(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
         return;
     };
 
-    let bold = ansi_term::Style::new().bold();
-    let dim = ansi_term::Style::new().dimmed();
+    let bold = bold();
+    let dim = dimmed();
 
     match db.static_stack_for_pc(addr) {
         Ok(Some(trc)) => {
@@ -781,7 +3744,7 @@ fn cmd_addr2stack(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                     println!("{}", bold.paint("<unknown-subprogram>"));
                 }
                 print!("{}", dim.prefix());
-                print!("    {}:", record.file);
+                print!("    {}:", record.file.as_deref().unwrap_or("?"));
                 if let Some(line) = record.line {
                     print!("{}:", line);
                 } else {
@@ -805,21 +3768,172 @@ fn cmd_addr2stack(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
     }
 }
 
-fn cmd_vars(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
-    for (_id, v) in db.static_variables() {
-        if !args.is_empty() {
-            if !v.name.contains(args) {
-                continue;
+fn cmd_line2addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let Some((file, line)) = args.trim().rsplit_once(':') else {
+        println!("usage: line2addr [file]:[line]");
+        return;
+    };
+    let Ok(line) = line.parse::<u64>() else {
+        println!("can't parse {} as a line number", line);
+        return;
+    };
+
+    let mut found = false;
+    for row in db.lookup_addrs_for_line(file, line) {
+        found = true;
+        print!("{:#x}..{:#x} {}:{}:", row.pc_range.start, row.pc_range.end, row.file.as_deref().unwrap_or("?"), line);
+        if let Some(col) = row.column {
+            print!("{}", col);
+        } else {
+            print!("?");
+        }
+        if let Some((_, subp)) = db.subprogram_for_pc(row.pc_range.start) {
+            if let Some(n) = &subp.name {
+                print!(" (in {})", n);
+            }
+        }
+        println!();
+    }
+    if !found {
+        println!("no code found for that line");
+    }
+}
+
+fn cmd_demangle(_db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    if args.is_empty() {
+        println!("usage: demangle SYMBOL, or a line of text containing symbols");
+        return;
+    }
+    // Demangle each symbol-like word in place, so lines pasted from a linker
+    // map keep their addresses and sizes.
+    let symbol = Regex::new(r"[A-Za-z0-9_$.]+").unwrap();
+    let mut found = false;
+    let out = symbol.replace_all(args, |c: &regex::Captures| {
+        match debugdb::demangle::demangle_symbol(&c[0]) {
+            Some(d) => {
+                found = true;
+                d
+            }
+            None => c[0].to_string(),
+        }
+    });
+    if found {
+        println!("{out}");
+    } else {
+        println!("{}", colour(Colour::Red).paint("Nothing to demangle."));
+    }
+}
+
+fn cmd_ranges(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let name = args.trim();
+    if name.is_empty() {
+        println!("usage: ranges FUNCTION");
+        return;
+    }
+    let mut results = db.subprograms_by_name(name).collect::<Vec<_>>();
+    if results.is_empty() {
+        results = db.subprograms_by_linkage_name(name).collect();
+    }
+    // Only subprograms with code of their own are interesting here.
+    results.retain(|(_, p)| p.pc_ranges.iter().any(|r| r.start != 0 && r.end > r.start));
+    if results.is_empty() {
+        println!("no code found for a function by that name");
+        return;
+    }
+
+    let bold = bold();
+    for (pid, p) in results {
+        let display_name = p.demangled_name()
+            .or_else(|| p.name.clone())
+            .unwrap_or_else(|| name.to_string());
+        println!("{} @ {}", bold.paint(display_name), Goff(pid));
+        let mut total = 0;
+        for r in &p.pc_ranges {
+            println!("- 0x{:x}..0x{:x} ({} bytes)", r.start, r.end, r.end - r.start);
+            total += r.end - r.start;
+        }
+        if p.pc_ranges.len() > 1 {
+            println!("- total {} bytes in {} pieces", total, p.pc_ranges.len());
+        }
+
+        // Summarize the lines that the code is attributed to, per file.
+        let mut spans = std::collections::BTreeMap::<&str, (u64, u64)>::new();
+        for r in &p.pc_ranges {
+            for row in db.line_rows_in(r.clone()) {
+                let (Some(file), Some(line)) = (&row.file, row.line) else { continue };
+                let span = spans.entry(file).or_insert((u64::MAX, 0));
+                span.0 = span.0.min(line.get());
+                span.1 = span.1.max(line.get());
             }
         }
+        for (file, (lo, hi)) in spans {
+            println!("- lines {file}:{lo}-{hi}");
+        }
+    }
+}
+
+fn cmd_vars(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = vars_table(db, ctx, args);
+    if ctx.csv {
+        print_table_csv(&table);
+        return;
+    }
+    let total = table.rows.iter()
+        .filter_map(|row| match row.fields[1] {
+            Field::Number(z) => Some(z),
+            _ => None,
+        })
+        .sum::<u64>();
+    for row in &table.rows {
+        println!("{}", row.text);
+    }
+    println!("{} variables, {} bytes", table.rows.len(), total);
+}
+
+/// Lists static variables whose names contain `args`, in address order.
+fn vars_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    let mut vars = db.static_variables()
+        .map(|(_id, v)| {
+            // Prefer the fully qualified name, for C++ variables in namespaces.
+            let name = v.demangled_name().unwrap_or_else(|| v.name.clone());
+            (v, name)
+        })
+        .filter(|(_, name)| name.contains(args))
+        .collect::<Vec<_>>();
+    vars.sort_by_key(|(v, _)| v.location);
 
-        println!("0x{:0width$x} {}: {}", v.location, v.name, NamedGoff(db, v.type_id),
-            width = db.pointer_size() as usize * 2);
+    Table {
+        columns: &["address", "size", "section", "name", "type"],
+        rows: vars.into_iter().map(|(v, name)| {
+            let size = db.type_by_id(v.type_id).and_then(|t| t.byte_size(db));
+            let section = db.section_for_address(v.location);
+            let text = format!("0x{:0width$x} {:>8} {:<10} {}: {}",
+                v.location,
+                size.map(|z| z.to_string()).unwrap_or_else(|| "???".into()),
+                section.unwrap_or("?"),
+                name,
+                NamedGoff(db, v.type_id),
+                width = db.pointer_size() * 2);
+            Row {
+                fields: vec![
+                    Field::Address(v.location),
+                    size.map(Field::Number).into(),
+                    section.map(|s| Field::Text(s.to_string())).into(),
+                    Field::Text(name.to_string()),
+                    db.type_name(v.type_id).map(|n| Field::Text(n.into_owned())).into(),
+                ],
+                text,
+            }
+        }).collect(),
     }
 }
 
 fn cmd_var(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
-    let results = db.static_variables_by_name(args).collect::<Vec<_>>();
+    let mut results = db.static_variables_by_name(args).collect::<Vec<_>>();
+    if results.is_empty() {
+        results = db.static_variables_by_linkage_name(args).collect();
+    }
 
     match results.len() {
         0 => println!("no variables found by that name"),
@@ -827,14 +3941,21 @@ fn cmd_var(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
         n => println!("note: {} variables found by that name", n),
     }
 
-    for (_id, v) in results {
-        println!("{} @ {}", v.name, Goff(v.offset));
+    for (id, v) in results {
+        println!("{} @ {}", v.name, Goff(id));
         println!("- type: {}", NamedGoff(db, v.type_id));
         println!("- address: 0x{:x}", v.location);
+        if let Some(ln) = &v.linkage_name {
+            println!("- linkage name: {ln}");
+        }
+        if let Some(dn) = v.demangled_name() {
+            println!("- demangled: {dn}");
+        }
         let Some(ty) = db.type_by_id(v.type_id) else { continue };
 
-        match Value::from_state(&ctx.segments, v.location, db, &ty) {
+        match Value::from_state(&ctx.memory(), v.location, db, ty) {
             Ok(v) => {
+                print_slice_contents(db, &ctx.memory(), &v, "- points to: ");
                 println!("- current contents: {}",
                     ValueWithDb(v, db));
             }
@@ -845,7 +3966,98 @@ fn cmd_var(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     }
 }
 
-fn cmd_addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+fn cmd_funcs(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = funcs_table(db, ctx, args);
+    if ctx.csv {
+        print_table_csv(&table);
+        return;
+    }
+    for row in &table.rows {
+        println!("{}", row.text);
+    }
+    println!("{} functions", table.rows.len());
+}
+
+/// Lists functions whose names contain the filter in `args`, in address
+/// order, or largest first with `-s`.
+fn funcs_table(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) -> Table {
+    let (by_size, filter) = match args.strip_prefix("-s") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim()),
+        _ => (false, args),
+    };
+
+    let mut funcs = db.subprograms()
+        .filter(|(id, _)| {
+            ctx.unit.is_none_or(|u| db.unit_containing(*id).is_some_and(|(o, _)| o == u))
+        })
+        .filter_map(|(_, p)| {
+            // Skip subprograms with no code of their own, such as the abstract
+            // originals of inlined functions.
+            let range = p.pc_range.clone().filter(|r| r.start != 0 && !r.is_empty())?;
+            // Concrete instances often defer to their abstract origin for
+            // names and parameters.
+            let origin = p.abstract_origin
+                .and_then(|o| db.subprogram_by_id(o));
+            let name = p.demangled_name()
+                .or_else(|| p.name.clone())
+                .or_else(|| origin.and_then(|o| o.demangled_name().or_else(|| o.name.clone())))?;
+            if !name.contains(filter) {
+                return None;
+            }
+            Some((range, name, p, origin))
+        })
+        .collect::<Vec<_>>();
+    if by_size {
+        funcs.sort_by_key(|(range, ..)| std::cmp::Reverse(range.end - range.start));
+    } else {
+        funcs.sort_by_key(|(range, ..)| range.start);
+    }
+
+    let rows = funcs.into_iter().map(|(range, name, p, origin)| {
+        let params = if p.formal_parameters.is_empty() {
+            origin.map(|o| &o.formal_parameters[..]).unwrap_or(&[])
+        } else {
+            &p.formal_parameters[..]
+        };
+        let mut sig = String::new();
+        for (i, fp) in params.iter().enumerate() {
+            if i > 0 {
+                sig.push_str(", ");
+            }
+            if let Some(n) = &fp.name {
+                write!(sig, "{n}: ").unwrap();
+            }
+            match fp.type_id {
+                Some(t) => write!(sig, "{}", db.type_name(t).as_deref().unwrap_or("???")).unwrap(),
+                None => sig.push_str("???"),
+            }
+        }
+        let ret = p.return_type_id.or_else(|| origin.and_then(|o| o.return_type_id));
+        if let Some(ret) = ret {
+            write!(sig, ") -> {}", db.type_name(ret).as_deref().unwrap_or("???")).unwrap();
+        } else {
+            sig.push(')');
+        }
+        let text = format!("0x{:0width$x} {:>8} {}({}",
+            range.start, range.end - range.start, name, sig,
+            width = db.pointer_size() * 2);
+        Row {
+            fields: vec![
+                Field::Address(range.start),
+                Field::Number(range.end - range.start),
+                Field::Text(name.to_string()),
+                Field::Text(format!("({sig}")),
+            ],
+            text,
+        }
+    }).collect();
+    Table {
+        columns: &["address", "size", "name", "signature"],
+        rows,
+    }
+}
+
+fn cmd_addr(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     let addr = if args.starts_with("0x") {
         if let Ok(a) = u64::from_str_radix(&args[2..], 16) {
             a
@@ -862,15 +4074,47 @@ fn cmd_addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
 
     let es = db.entities_by_address(addr).collect::<Vec<_>>();
 
+    let bold = bold();
+    let dim = dimmed();
+
+    if let Some(image) = db.image_at(addr) {
+        println!("In image {}", bold.paint(&image.name));
+    }
+    if let Some((p, r)) = ctx.svd.as_ref().and_then(|d| d.register_at(addr)) {
+        let offset = addr - p.base_address - r.address_offset;
+        println!("Offset +0x{:x} into register {}", offset,
+            bold.paint(format!("{}.{}", p.name, r.name)));
+        if let Some(d) = &r.description {
+            println!("- {d}");
+        }
+        println!("- {} bits at 0x{:x}", r.size, p.base_address + r.address_offset);
+        for f in &r.fields {
+            match &f.description {
+                Some(d) => println!("  - {f}: {}", dim.paint(d)),
+                None => println!("  - {f}"),
+            }
+        }
+    }
+
     match es.len() {
-        0 => println!("Nothing known about address 0x{:x}.", addr),
+        0 => {
+            println!("Nothing in the debug info covers address 0x{:x}.", addr);
+            if let Some(section) = db.section_for_address(addr) {
+                println!("- in section {}", section);
+                if let Some((sym_addr, name)) = db.raw_symbol_before(addr) {
+                    let name = debugdb::demangle::demangle_symbol(name)
+                        .unwrap_or_else(|| name.to_string());
+                    println!("- nearest symbol below: {} +0x{:x}",
+                        bold.paint(name), addr - sym_addr);
+                }
+            } else {
+                println!("- not in any section of the program");
+            }
+        }
         1 => (),
         n => println!("note: {} overlapping entities claim address 0x{:x}", n, addr),
     }
 
-    let bold = ansi_term::Style::new().bold();
-    let dim = ansi_term::Style::new().dimmed();
-
     for e in es {
         let offset = addr - e.range.start;
         print!("Offset +0x{:x} into ", offset);
@@ -880,6 +4124,9 @@ fn cmd_addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                 println!("static {}", bold.paint(&v.name));
                 println!("- range 0x{:x}..0x{:x}", 
                     e.range.start, e.range.end);
+                if let Some(section) = db.section_for_address(addr) {
+                    println!("- section {}", section);
+                }
                 println!("- type {}", NamedGoff(db, v.type_id));
 
                 // Try to determine path within type.
@@ -887,7 +4134,7 @@ fn cmd_addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
             }
             debugdb::EntityId::Prog(pid) => {
                 let p = db.subprogram_by_id(pid).unwrap();
-                if let Some(n) = &p.name {
+                if let Some(n) = p.demangled_name().or_else(|| p.name.clone()) {
                     println!("subprogram {}", bold.paint(n));
                 } else {
                     println!("subprogram {}", bold.paint("ANON"));
@@ -907,7 +4154,7 @@ fn cmd_addr(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                                 println!("{}", bold.paint("<unknown-subprogram>"));
                             }
                             print!("{}", dim.prefix());
-                            print!("        {}:", record.file);
+                            print!("        {}:", record.file.as_deref().unwrap_or("?"));
                             if let Some(line) = record.line {
                                 print!("{}:", line);
                             } else {
@@ -962,7 +4209,7 @@ fn offset_to_path(
                 let new_offset = offset - m.location;
                 let mt = db.type_by_id(m.type_id).unwrap();
                 if let Some(msz) = mt.byte_size(db) {
-                    if msz > 0 {
+                    if new_offset < msz {
                         if let Some(n) = &m.name {
                             println!("  - .{} +0x{:x} (in {})", n, new_offset, s.name);
                         } else {
@@ -974,7 +4221,117 @@ fn offset_to_path(
                 }
             }
         }
-        _ => (),
+        Type::Union(u) => {
+            // Every member overlaps, so list each one that's big enough to
+            // contain the offset, without going any deeper.
+            for m in &u.members {
+                let big_enough = db.type_by_id(m.type_id)
+                    .and_then(|t| t.byte_size(db))
+                    .is_some_and(|msz| offset < msz);
+                if big_enough {
+                    let n = m.name.as_deref().unwrap_or("_");
+                    println!("  - .{} +0x{:x} (in union {})", n, offset, u.name);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn cmd_symbolize(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let mut addrs = vec![];
+    for w in args.split_whitespace() {
+        match parse_int::parse::<u64>(w) {
+            Ok(x) => addrs.push(x),
+            Err(e) => {
+                println!("bad address {w}: {e}");
+                return;
+            }
+        }
+    }
+    if addrs.is_empty() {
+        println!("usage: symbolize ADDR...");
+        return;
+    }
+    print_backtrace(db, &addrs);
+}
+
+fn cmd_stacktrace(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let mut addrs = vec![];
+    if !args.trim().is_empty() {
+        if let Err(e) = parse_hex_addresses(args, &mut addrs) {
+            println!("{e}");
+            return;
+        }
+    } else {
+        println!("Paste hex return addresses, one per line or space-separated.");
+        println!("Enter a blank line to end.");
+
+        let stdin = std::io::stdin().lock();
+        for line in stdin.lines() {
+            let line = match line {
+                Err(e) => {
+                    println!("input error: {e}");
+                    return;
+                }
+                Ok(v) => v,
+            };
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Err(e) = parse_hex_addresses(&line, &mut addrs) {
+                println!("{e}");
+                return;
+            }
+        }
+    }
+    if addrs.is_empty() {
+        println!("no addresses given");
+        return;
+    }
+    print_backtrace(db, &addrs);
+}
+
+/// Parses whitespace- or comma-separated hex addresses, with or without `0x`,
+/// appending them to `addrs`.
+fn parse_hex_addresses(s: &str, addrs: &mut Vec<u64>) -> Result<(), String> {
+    for w in s.split(|c: char| c.is_whitespace() || c == ',').filter(|w| !w.is_empty()) {
+        let digits = w.strip_prefix("0x").or_else(|| w.strip_prefix("0X")).unwrap_or(w);
+        match u64::from_str_radix(digits, 16) {
+            Ok(x) => addrs.push(x),
+            Err(e) => return Err(format!("bad address {w}: {e}")),
+        }
+    }
+    Ok(())
+}
+
+/// Prints a numbered, symbolized backtrace for a list of return addresses,
+/// including inlined frames.
+fn print_backtrace(db: &DebugDb, addrs: &[u64]) {
+    let bold = bold();
+    let dim = dimmed();
+    for (i, frame) in db.symbolize(addrs).iter().enumerate() {
+        if frame.functions.is_empty() {
+            println!("{i:4}   0x{:x} {}", frame.address, bold.paint("<unknown>"));
+            continue;
+        }
+        for (j, f) in frame.functions.iter().enumerate() {
+            let name = f.name.as_deref().unwrap_or("<unknown-subprogram>");
+            if j == 0 {
+                print!("{i:4}   0x{:x} ", frame.address);
+            } else {
+                print!("       {:>w$} ", "inlined by", w = format!("0x{:x}", frame.address).len());
+            }
+            print!("{}", bold.paint(name));
+            if let Some(file) = &f.file {
+                print!(" {}", dim.paint(format!(
+                    "{}:{}",
+                    file,
+                    f.line.map(|l| l.to_string()).unwrap_or_else(|| "?".into()),
+                )));
+            }
+            println!();
+        }
     }
 }
 
@@ -996,7 +4353,7 @@ fn cmd_unwind(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
     use gimli::UnwindSection;
     let mut ctx = gimli::UnwindContext::new();
     let bases = gimli::BaseAddresses::default();
-    match db.debug_frame.unwind_info_for_address(&bases, &mut ctx, addr, gimli::DebugFrame::cie_from_offset) {
+    match db.debug_frame().unwind_info_for_address(&bases, &mut ctx, addr, gimli::DebugFrame::cie_from_offset) {
         Ok(ui) => {
             println!("saved args: {} bytes", ui.saved_args_size());
             print!("cfa: ");
@@ -1039,6 +4396,182 @@ fn cmd_unwind(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
     }
 }
 
+fn cmd_framesize(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let table = framesize_table(db, ctx, args);
+    print_table(ctx, &table);
+    if !ctx.json && !ctx.csv {
+        println!("{} functions", table.rows.len());
+    }
+}
+
+/// Lists the stack frame sizes of functions whose names contain `args`,
+/// largest first, for `framesize`.
+fn framesize_table(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) -> Table {
+    let columns = &["size", "kind", "address", "name"];
+    let Some(sp_register) = ctx.sp_register else {
+        println!("don't know the stack pointer register for this architecture");
+        return Table { columns, rows: vec![] };
+    };
+    let filter = args.trim();
+
+    let mut funcs = db.subprograms()
+        .filter(|(id, _)| {
+            ctx.unit.is_none_or(|u| db.unit_containing(*id).is_some_and(|(o, _)| o == u))
+        })
+        .filter_map(|(id, p)| {
+            let range = p.pc_range.clone().filter(|r| r.start != 0 && !r.is_empty())?;
+            let origin = p.abstract_origin
+                .and_then(|o| db.subprogram_by_id(o));
+            let name = p.demangled_name()
+                .or_else(|| p.name.clone())
+                .or_else(|| origin.and_then(|o| o.demangled_name().or_else(|| o.name.clone())))?;
+            if !name.contains(filter) {
+                return None;
+            }
+            match db.frame_size(sp_register, id) {
+                Ok(size) => Some((size?, range.start, name)),
+                Err(e) => {
+                    println!("{}", colour(Colour::Red).paint(format!("{name}: {e}")));
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    funcs.sort_by(|a, b| b.0.byte_size.cmp(&a.0.byte_size).then_with(|| a.1.cmp(&b.1)));
+
+    Table {
+        columns,
+        rows: funcs.into_iter().map(|(size, addr, name)| {
+            // The same words as GCC's .su files.
+            let kind = if size.dynamic { "dynamic" } else { "static" };
+            Row {
+                text: format!("{:>8} {:<8} 0x{:0width$x} {}",
+                    size.byte_size, kind, addr, name,
+                    width = db.pointer_size() * 2),
+                fields: vec![
+                    Field::Number(size.byte_size),
+                    Field::Text(kind.to_string()),
+                    Field::Address(addr),
+                    Field::Text(name),
+                ],
+            }
+        }).collect(),
+    }
+}
+
+fn cmd_inlined(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let (verbose, filter) = match args.trim().strip_prefix("-v") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim()),
+        _ => (false, args.trim()),
+    };
+    if ctx.json || ctx.csv || !verbose {
+        let table = inlined_table(db, ctx, filter);
+        if !ctx.json && !ctx.csv {
+            println!("{:>8} {:>6} {:>7}  function", "bytes", "copies", "callers");
+        }
+        print_table(ctx, &table);
+        return;
+    }
+
+    let dim = dimmed();
+    for i in db.inlining() {
+        let name = program_name(db, i.callee);
+        if !name.contains(filter) {
+            continue;
+        }
+        println!("{} {}", bold().paint(name), dim.paint(Goff(i.callee).to_string()));
+        println!("- inlined {} times, {} bytes", i.count, i.byte_size);
+        for (caller, n) in i.callers {
+            println!("    {:>6} into {}", n, program_name(db, caller));
+        }
+    }
+}
+
+/// Lists inlined functions whose names contain `args` for `inlined`, with
+/// the most inlined code first.
+fn inlined_table(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) -> Table {
+    let filter = args.trim();
+    Table {
+        columns: &["size", "count", "callers", "name", "id"],
+        rows: db.inlining().into_iter()
+            .map(|i| (program_name(db, i.callee), i))
+            .filter(|(name, _)| name.contains(filter))
+            .map(|(name, i)| Row {
+                text: format!("{:>8} {:>6} {:>7}  {}",
+                    i.byte_size, i.count, i.callers.len(), name),
+                fields: vec![
+                    Field::Number(i.byte_size),
+                    Field::Number(i.count as u64),
+                    Field::Number(i.callers.len() as u64),
+                    Field::Text(name),
+                    Field::Text(Goff(i.callee).to_string()),
+                ],
+            })
+            .collect(),
+    }
+}
+
+/// Names a subprogram for display, falling back on its abstract origin's name
+/// and then its location in the debug info.
+fn program_name(db: &debugdb::DebugDb, id: debugdb::ProgramId) -> String {
+    let Some(p) = db.subprogram_by_id(id) else {
+        return Goff(id).to_string();
+    };
+    let origin = p.abstract_origin
+        .and_then(|o| db.subprogram_by_id(o));
+    p.demangled_name()
+        .or_else(|| p.name.clone())
+        .or_else(|| origin.and_then(|o| o.demangled_name().or_else(|| o.name.clone())))
+        .unwrap_or_else(|| Goff(id).to_string())
+}
+
+fn cmd_bt(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let Some(sp_register) = ctx.sp_register else {
+        println!("don't know the stack pointer register for this architecture");
+        return;
+    };
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let mut values = vec![];
+    for w in &words {
+        match parse_int::parse::<u64>(w) {
+            Ok(x) => values.push(x),
+            Err(e) => {
+                println!("bad value {w}: {e}");
+                return;
+            }
+        }
+    }
+    let [pc, sp, rest @ ..] = &values[..] else {
+        println!("usage: bt PC SP [REG VALUE]...");
+        return;
+    };
+    let mut frame = debugdb::unwind::Frame::new(*pc, sp_register, *sp);
+    for pair in rest.chunks(2) {
+        let [reg, value] = pair else {
+            println!("register {} given without a value", pair[0]);
+            return;
+        };
+        let Ok(reg) = u16::try_from(*reg) else {
+            println!("bad register number {reg}");
+            return;
+        };
+        frame.registers.insert(gimli::Register(reg), *value);
+    }
+
+    let (frames, error) = db.backtrace(&ctx.memory(), sp_register, frame);
+    for (i, frame) in frames.iter().enumerate() {
+        let sp = frame.registers.get(&sp_register).copied().unwrap_or_default();
+        print!("#{i:<3} pc {:#x} sp {sp:#x}", frame.pc);
+        match db.subprogram_for_pc(frame.lookup_pc()).and_then(|(_, p)| p.name.as_deref()) {
+            Some(name) => println!(" in {name}"),
+            None => println!(),
+        }
+    }
+    if let Some(e) = error {
+        println!("{}", colour(Colour::Red).paint(format!("unwinding stopped: {e}")));
+    }
+}
+
 fn struct_picture(db: &DebugDb, s: &Struct, width: usize) {
     struct_picture_inner(
         db,
@@ -1165,15 +4698,605 @@ fn enum_picture(db: &DebugDb, s: &Enum, width: usize) {
                                     n = &n[..6];
                                 }
 
-                                (n, m, true)
-                            })
-                        );
-                        struct_picture_inner(db, s.byte_size, all_members, width);
-                    },
-                    _ => println!("(can't display non-struct)"),
-                }
+                                (n, m, true)
+                            })
+                        );
+                        struct_picture_inner(db, s.byte_size, all_members, width);
+                    },
+                    _ => println!("(can't display non-struct)"),
+                }
+            }
+            */
+        }
+    }
+}
+
+/// Splits `args` into two type names at the first space that isn't inside
+/// brackets, so that names like `Foo<u8, u32>` stay whole.
+fn split_two_type_names(args: &str) -> Option<(&str, &str)> {
+    let mut nesting = 0i32;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => nesting += 1,
+            '>' | ')' | ']' | '}' => nesting -= 1,
+            ' ' if nesting == 0 => {
+                let (a, b) = (args[..i].trim(), args[i..].trim());
+                return (!b.is_empty()).then_some((a, b));
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Looks up a single type for a command that needs exactly one, printing a
+/// note if the name is ambiguous and the first match is used.
+fn lookup_one_type(db: &debugdb::DebugDb, name: &str) -> Option<TypeId> {
+    let types: Vec<_> = match parse_type_name(name)? {
+        ParsedTypeName::Name(n) => lookup_types(db, n).into_iter().map(|(id, _)| id).collect(),
+        ParsedTypeName::Goff(o) => db.type_by_id(o).map(|_| o).into_iter().collect(),
+    };
+    match types.len() {
+        0 => {
+            println!("{} {name}", colour(Colour::Red).paint("No types found named"));
+            None
+        }
+        1 => Some(types[0]),
+        n => {
+            println!("{}{n} types named {name}, using {}",
+                colour(Colour::Yellow).paint("note: "),
+                NamedGoff(db, types[0]));
+            Some(types[0])
+        }
+    }
+}
+
+fn fmt_discriminants(ranges: &[std::ops::RangeInclusive<u128>]) -> String {
+    if ranges.is_empty() {
+        return "(none)".to_string();
+    }
+    ranges.iter()
+        .map(|r| if r.start() == r.end() {
+            format!("{:#x}", r.start())
+        } else {
+            format!("{:#x}..={:#x}", r.start(), r.end())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn cmd_diff(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let Some((name_a, name_b)) = split_two_type_names(args.trim()) else {
+        println!("usage: diff TYPE_A TYPE_B");
+        return;
+    };
+    let (Some(a), Some(b)) = (lookup_one_type(db, name_a), lookup_one_type(db, name_b)) else {
+        return;
+    };
+    let Some(diffs) = db.diff_types(a, b) else {
+        return;
+    };
+
+    let opt = |x: Option<u64>| x.map(|x| x.to_string()).unwrap_or_else(|| "?".into());
+    let json = ctx.json;
+    let ty = |id: TypeId| if json {
+        db.type_name(id).unwrap_or_default().into_owned()
+    } else {
+        NamedGoff(db, id).to_string()
+    };
+    let describe = |what: &Difference| match what {
+        Difference::Kind(x, y) => format!("kind {x} vs {y}"),
+        Difference::Size(x, y) => format!("size {} vs {} bytes", opt(*x), opt(*y)),
+        Difference::Alignment(x, y) => format!("alignment {} vs {} bytes", opt(*x), opt(*y)),
+        Difference::Offset(x, y) => format!("offset {x} vs {y}"),
+        Difference::MemberType(x, y) => format!("type {} vs {}", ty(*x), ty(*y)),
+        Difference::OnlyInFirst => "only in first type".to_string(),
+        Difference::OnlyInSecond => "only in second type".to_string(),
+        Difference::Discriminants(x, y) => format!("discriminants {} vs {}",
+            fmt_discriminants(x), fmt_discriminants(y)),
+        Difference::Value(x, y) => format!("value {x:#x} vs {y:#x}"),
+    };
+
+    if ctx.json {
+        print_json(serde_json::json!({
+            "a": type_ref_json(db, a),
+            "b": type_ref_json(db, b),
+            "differences": diffs.iter().map(|d| serde_json::json!({
+                "path": d.path,
+                "difference": describe(&d.what),
+            })).collect::<Vec<_>>(),
+        }));
+        return;
+    }
+
+    println!("{} {}", colour(Colour::Red).paint("---"), NamedGoff(db, a));
+    println!("{} {}", colour(Colour::Green).paint("+++"), NamedGoff(db, b));
+    if diffs.is_empty() {
+        println!("same layout");
+        return;
+    }
+    for d in &diffs {
+        let path = if d.path.is_empty() { "(type)" } else { &d.path };
+        let marker = match d.what {
+            Difference::OnlyInFirst => colour(Colour::Red).paint("-"),
+            Difference::OnlyInSecond => colour(Colour::Green).paint("+"),
+            _ => colour(Colour::Yellow).paint("~"),
+        };
+        println!("{marker} {}: {}", bold().paint(path), describe(&d.what));
+    }
+}
+
+fn cmd_dot(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let mut args = args.trim();
+    let mut pointers = false;
+    let mut path = None;
+    loop {
+        if let Some(rest) = args.strip_prefix("-p") {
+            pointers = true;
+            args = rest.trim_start();
+        } else if let Some(rest) = args.strip_prefix("-o") {
+            let rest = rest.trim_start();
+            let (p, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            path = Some(p);
+            args = rest.trim_start();
+        } else {
+            break;
+        }
+    }
+
+    // A filter graphs the matching types, and anything else a type name's
+    // dependencies.
+    let types = if args.is_empty() || looks_like_filter(args) {
+        let filter = if args.is_empty() {
+            None
+        } else {
+            match debugdb::filter::TypeFilter::parse(args) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    println!("bad filter: {e}");
+                    return;
+                }
+            }
+        };
+        db.canonical_types()
+            .filter(|&(goff, _)| in_selected_unit(db, ctx, goff))
+            .filter(|&(goff, ty)| filter.as_ref().is_none_or(|f| f.matches(db, goff, ty)))
+            .map(|(goff, _)| goff)
+            .collect()
+    } else {
+        let Some(id) = lookup_one_type(db, args) else { return };
+        let follow: fn(Dependency) -> bool = if pointers {
+            |_| true
+        } else {
+            |d| matches!(d, Dependency::Contains | Dependency::Aliases)
+        };
+        db.walk_dependencies(id)
+            .following(follow)
+            .map(|(goff, _)| goff)
+            .collect()
+    };
+
+    let mut dot = String::new();
+    db.write_dot(&mut dot, &types).unwrap();
+    match path {
+        Some(path) => match std::fs::write(path, dot) {
+            Ok(()) => println!("wrote {} types to {path}", types.len()),
+            Err(e) => println!("can't write {path}: {e}"),
+        },
+        None => print!("{dot}"),
+    }
+}
+
+fn cmd_codegen(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    let (path, type_name) = match args.strip_prefix("-o") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            let (p, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            (Some(p), rest.trim())
+        }
+        None => (None, args),
+    };
+    if type_name.is_empty() {
+        println!("usage: codegen [-o FILE] TYPE");
+        return;
+    }
+    let Some(id) = lookup_one_type(db, type_name) else { return };
+    let code = match db.rust_definitions(&[id]) {
+        Ok(code) => code,
+        Err(e) => {
+            println!("can't generate code: {e}");
+            return;
+        }
+    };
+    match path {
+        Some(path) => match std::fs::write(path, code) {
+            Ok(()) => println!("wrote {path}"),
+            Err(e) => println!("can't write {path}: {e}"),
+        },
+        None => print!("{code}"),
+    }
+}
+
+fn cmd_tree(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    let (max_depth, type_name) = match args.strip_prefix("-d") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            let (d, type_name) = rest.split_once(char::is_whitespace)
+                .unwrap_or((rest, ""));
+            match d.parse::<usize>() {
+                Ok(d) => (d, type_name.trim()),
+                Err(_) => {
+                    println!("bad depth: {d}");
+                    return;
+                }
+            }
+        }
+        None => (usize::MAX, args),
+    };
+    if type_name.is_empty() {
+        println!("usage: tree [-d DEPTH] [typename]");
+        return;
+    }
+
+    let types: Vec<_> = match parse_type_name(type_name) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+
+    for (goff, _) in types {
+        println!("{}", NamedGoff(db, goff));
+        if let Some(layout) = db.layout_of(goff) {
+            print_tree(db, &layout, 1, max_depth);
+        }
+    }
+}
+
+/// Prints the members inside `layout`, and recursively the members inside
+/// them, up to `max_depth` levels.
+fn print_tree(
+    db: &DebugDb,
+    layout: &debugdb::layout::TypeLayout,
+    depth: usize,
+    max_depth: usize,
+) {
+    use debugdb::layout::LayoutContents;
+
+    if depth > max_depth {
+        return;
+    }
+    let indent = "  ".repeat(depth);
+    let dim = dimmed();
+    let line = |label: &str, inner: &debugdb::layout::TypeLayout| {
+        let size = inner.byte_size
+            .map(|z| z.to_string())
+            .unwrap_or_else(|| "?".into());
+        println!("{} {indent}{label}: {} {}",
+            dim.paint(format!("+0x{:04x}", inner.offset)), NamedGoff(db, inner.type_id),
+            dim.paint(format!("({size} bytes)")));
+        if let LayoutContents::Recursive = inner.contents {
+            println!("{indent}  (contains itself!)");
+        } else {
+            print_tree(db, inner, depth + 1, max_depth);
+        }
+    };
+
+    match &layout.contents {
+        LayoutContents::Struct { members, .. } | LayoutContents::Union { members, .. } => {
+            for m in members {
+                line(m.name.as_deref().unwrap_or("_"), &m.layout);
+            }
+        }
+        LayoutContents::Enum { discriminator, variants } => {
+            if let Some(d) = discriminator {
+                line("(discriminator)", &d.layout);
+            }
+            for v in variants {
+                let name = v.name.as_deref().unwrap_or("_");
+                line(&format!("variant {name}"), &v.payload);
+            }
+        }
+        LayoutContents::Array { count, stride, element: Some(element) } => {
+            let label = match (count, stride) {
+                (Some(n), Some(z)) if *n > 1 => format!("[0..{n}] (stride {z})"),
+                _ => "[0]".to_string(),
+            };
+            line(&label, element);
+        }
+        _ => (),
+    }
+}
+
+fn cmd_layout(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    let (width, type_name) = match args.strip_prefix("-w") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            let (w, type_name) = rest.split_once(char::is_whitespace)
+                .unwrap_or((rest, ""));
+            match w.parse::<usize>() {
+                Ok(w) if w > 0 => (w, type_name),
+                _ => {
+                    println!("bad width: {w}");
+                    return;
+                }
+            }
+        }
+        None => (ctx.width.unwrap_or(db.pointer_size().max(8)), args),
+    };
+    let (type_name, target) = split_svd_target(type_name);
+    if type_name.trim().is_empty() {
+        println!("usage: layout [-w WIDTH] [typename] [@PERIPHERAL]");
+        return;
+    }
+
+    let types: Vec<_> = match parse_type_name(type_name.trim()) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+
+    for (goff, _) in types {
+        println!("{}:", NamedGoff(db, goff));
+        let layouts = db.layouts(goff);
+        if layouts.is_empty() {
+            println!("no layout (not a struct, union or enum, or unsized)");
+        }
+        for layout in layouts {
+            println!();
+            if let Some(v) = &layout.variant {
+                println!("variant {v}:");
+            }
+            let periph = svd_peripheral(db, ctx, goff, target);
+            layout_picture(db, &layout, width, ctx.depth, periph);
+        }
+    }
+}
+
+/// Splits a trailing `@PERIPHERAL` or `@ADDRESS` off the arguments of a
+/// command that can match a type's members to SVD registers.
+fn split_svd_target(args: &str) -> (&str, Option<&str>) {
+    match args.trim().rsplit_once(char::is_whitespace) {
+        Some((rest, target)) if target.len() > 1 && target.starts_with('@') => {
+            (rest, Some(&target[1..]))
+        }
+        _ => (args, None),
+    }
+}
+
+/// Finds the SVD peripheral whose registers a type's members should be
+/// matched to. That's the one named, or at the address given, by `target`;
+/// or else one whose name or group matches the type's module (as with
+/// svd2rust's `gpioa::RegisterBlock`) or the type's name (as with CMSIS's
+/// `GPIO_TypeDef`).
+fn svd_peripheral<'a>(
+    db: &DebugDb,
+    ctx: &'a Ctx,
+    goff: TypeId,
+    target: Option<&str>,
+) -> Option<&'a debugdb::svd::Peripheral> {
+    let device = ctx.svd.as_ref()?;
+    if let Some(target) = target {
+        let found = match parse_int::parse::<u64>(target) {
+            Ok(addr) => device.peripherals.iter().find(|p| p.base_address == addr),
+            Err(_) => device.peripheral(target),
+        };
+        if found.is_none() {
+            println!("no peripheral {target} in the SVD file");
+        }
+        return found;
+    }
+
+    let mut names = vec![];
+    if let Some(module) = db.type_module(goff) {
+        names.extend(module.rsplit("::").next().map(str::to_string));
+    }
+    if let Some(name) = db.type_name(goff) {
+        let name = name.rsplit("::").next().unwrap_or(&name);
+        for suffix in ["_TypeDef", "_Type", "_Regs", "RegisterBlock"] {
+            if let Some(stem) = name.strip_suffix(suffix).filter(|s| !s.is_empty()) {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.iter().find_map(|n| {
+        device.peripheral(n).or_else(|| device.peripherals.iter().find(|p| {
+            p.group_name.as_deref().is_some_and(|g| g.eq_ignore_ascii_case(n))
+        }))
+    })
+}
+
+/// Describes the registers of `periph` that a member occupying `offsets`
+/// corresponds to: those starting within it, or failing that the fields of
+/// the register it's part of.
+fn svd_note(
+    periph: &debugdb::svd::Peripheral,
+    offsets: std::ops::Range<u64>,
+) -> Option<String> {
+    const MAX_FIELDS: usize = 8;
+    let fields = |fs: &mut dyn Iterator<Item = &debugdb::svd::Field>| {
+        let fs = fs.map(|f| f.to_string()).collect::<Vec<_>>();
+        if fs.len() > MAX_FIELDS {
+            format!("{} …", fs[..MAX_FIELDS].join(" "))
+        } else {
+            fs.join(" ")
+        }
+    };
+
+    let regs = periph.registers_in(offsets.clone()).collect::<Vec<_>>();
+    match regs[..] {
+        [r] if r.byte_range().end <= offsets.end => {
+            if r.fields.is_empty() {
+                Some(format!("[{}.{}]", periph.name, r.name))
+            } else {
+                Some(format!("[{}.{}: {}]", periph.name, r.name, fields(&mut r.fields.iter())))
+            }
+        }
+        [] | [_] => {
+            // Part of a register, such as one byte of it.
+            let r = periph.registers.iter()
+                .find(|r| r.byte_range().contains(&offsets.start))?;
+            let bits = (offsets.start - r.address_offset) as u32 * 8
+                ..(offsets.end.min(r.byte_range().end) - r.address_offset) as u32 * 8;
+            let mut inside = r.fields.iter()
+                .filter(|f| f.bit_offset < bits.end && f.bit_offset + f.bit_width > bits.start)
+                .peekable();
+            let note = format!("{}.{} bits {}..{}", periph.name, r.name, bits.start, bits.end);
+            if inside.peek().is_none() {
+                Some(format!("[{note}]"))
+            } else {
+                Some(format!("[{note}: {}]", fields(&mut inside)))
+            }
+        }
+        _ => {
+            let names = regs.iter()
+                .map(|r| format!("{}.{}", periph.name, r.name))
+                .collect::<Vec<_>>();
+            Some(format!("[{}]", names.join(", ")))
+        }
+    }
+}
+
+fn cmd_svd(_db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let args = args.trim();
+    if let Some(path) = args.strip_prefix("load ") {
+        match load_svd(std::path::Path::new(path.trim())) {
+            Ok(device) => ctx.svd = Some(device),
+            Err(e) => println!("can't load {}: {e}", path.trim()),
+        }
+        return;
+    }
+    let Some(device) = &ctx.svd else {
+        println!("no SVD file loaded; use svd load FILE");
+        return;
+    };
+    let bold = bold();
+    let dim = dimmed();
+    if args.is_empty() {
+        for p in &device.peripherals {
+            println!("0x{:08x} {} {}", p.base_address, bold.paint(&p.name),
+                dim.paint(p.description.as_deref().unwrap_or_default()));
+        }
+        return;
+    }
+
+    let (periph, reg) = args.split_once('.').unwrap_or((args, ""));
+    let Some(p) = device.peripheral(periph) else {
+        println!("no peripheral {periph}");
+        return;
+    };
+    let regs = p.registers.iter()
+        .filter(|r| reg.is_empty() || r.name.eq_ignore_ascii_case(reg))
+        .collect::<Vec<_>>();
+    if regs.is_empty() {
+        println!("no register {reg} in {}", p.name);
+    }
+    for r in regs {
+        println!("+0x{:03x} 0x{:08x} {:>2} {} {}", r.address_offset,
+            p.base_address + r.address_offset, r.size, bold.paint(&r.name),
+            dim.paint(r.description.as_deref().unwrap_or_default()));
+        if !reg.is_empty() {
+            for f in &r.fields {
+                println!("  {f} {}", dim.paint(f.description.as_deref().unwrap_or_default()));
             }
-            */
+        }
+    }
+}
+
+/// Draws the bytes of `layout`, labelling each with the member that occupies
+/// it. Members that are structs are broken down one level further, with their
+/// own members labelled `outer.inner`.
+fn layout_picture(
+    db: &DebugDb,
+    layout: &debugdb::layout::Layout,
+    width: usize,
+    depth: usize,
+    periph: Option<&debugdb::svd::Peripheral>,
+) {
+    if layout.byte_size == 0 {
+        println!("(type is 0 bytes long)");
+        return;
+    }
+
+    let mut spans: RangeMap<u64, String> = RangeMap::new();
+    let mut legend = vec![];
+    layout_spans(db, &layout.members, 0, "", depth, periph, &mut spans, &mut legend);
+
+    byte_picture(layout.byte_size, width, |off| spans.get(&off).cloned());
+    if !legend.is_empty() {
+        println!("     where:");
+        for line in legend {
+            println!("       {line}");
+        }
+    }
+    if layout.padding() != 0 {
+        println!("     {} bytes of padding", layout.padding());
+    }
+}
+
+/// Labels the bytes covered by `members` of a layout at `base`, and describes
+/// them in `legend`. Struct members are broken down into their own members,
+/// to `depth` levels. If `periph` is given, members are annotated with its
+/// registers at the same offsets.
+#[allow(clippy::too_many_arguments)]
+fn layout_spans(
+    db: &DebugDb,
+    members: &[debugdb::layout::MemberLayout],
+    base: u64,
+    prefix: &str,
+    depth: usize,
+    periph: Option<&debugdb::svd::Peripheral>,
+    spans: &mut RangeMap<u64, String>,
+    legend: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(prefix.matches('.').count());
+    for (i, m) in members.iter().enumerate() {
+        let label = format!("{prefix}{i}");
+        let name = m.name.as_deref().unwrap_or("_");
+        let nested = match db.type_by_id(db.resolve_alias(m.type_id)) {
+            Some(Type::Struct(_)) if depth > 0 => db.layouts(m.type_id),
+            _ => vec![],
+        };
+        // Nested structs get their registers against their own members.
+        let note = periph
+            .filter(|_| nested.len() != 1)
+            .and_then(|p| svd_note(p, base + m.offset..base + m.end()))
+            .map(|n| format!("  {}", dimmed().paint(n)))
+            .unwrap_or_default();
+
+        if m.byte_size == Some(0) {
+            legend.push(format!("{indent}(ZST) {name}: {}", NamedGoff(db, m.type_id)));
+        } else {
+            legend.push(format!("{indent}{label} = {name}: {}{note}", NamedGoff(db, m.type_id)));
+        }
+
+        if let [inner] = &nested[..] {
+            let prefix = format!("{label}.");
+            layout_spans(db, &inner.members, base + m.offset, &prefix, depth - 1, periph, spans, legend);
+        } else if m.end() > m.offset {
+            spans.insert(base + m.offset..base + m.end(), label);
         }
     }
 }
@@ -1249,24 +5372,48 @@ fn byte_picture(
     println!();
 }
 
+/// If `v` is a Rust slice or `str` fat pointer, follows it and prints what it
+/// refers to, after `label`.
+fn print_slice_contents<M: debugdb::load::Machine>(
+    db: &debugdb::DebugDb,
+    machine: &M,
+    v: &Value,
+    label: &str,
+) where M::Error: Display {
+    let Value::Struct(s) = v else { return };
+    let Some((data_ptr, _)) = s.slice_parts() else { return };
+    if data_ptr.value == 0 {
+        println!("{label}null (data_ptr is 0)");
+        return;
+    }
+    if s.is_str() {
+        match s.deref_str(machine) {
+            Ok(text) => println!("{label}{text:?}"),
+            Err(e) => println!("{label}(unable to load: {e})"),
+        }
+    } else {
+        match s.deref_slice(machine, db) {
+            Ok(elts) => println!("{label}{}", ValueWithDb(Value::Array(elts), db)),
+            Err(e) => println!("{label}(unable to load: {e})"),
+        }
+    }
+}
+
 fn cmd_decode(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     let (addrstr, typestr) = if let Some(space) = args.find(' ') {
         args.split_at(space)
     } else {
-        println!("usage: decode [addr] [typename blah blah]");
+        println!("usage: decode ADDR TYPENAME, or decode TYPENAME HEXBYTES...");
         return;
     };
-    let addr = match parse_int::parse::<u64>(addrstr) {
-        Ok(x) => x,
-        Err(e) => {
-            println!("bad address: {e}");
-            return;
-        }
+    let Ok(addr) = parse_int::parse::<u64>(addrstr) else {
+        decode_hex_args(db, args);
+        return;
     };
     let types: Vec<_> = match parse_type_name(typestr.trim()) {
         None => return,
         Some(ParsedTypeName::Name(n)) => {
-            db.types_by_name(n).collect()
+            lookup_types(db, n)
         }
         Some(ParsedTypeName::Goff(o)) => {
             db.type_by_id(o).into_iter()
@@ -1277,13 +5424,13 @@ fn cmd_decode(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
 
     let many = match types.len() {
         0 => {
-            println!("{}", ansi_term::Colour::Red.paint("No types found."));
+            println!("{}", colour(Colour::Red).paint("No types found."));
             return;
         }
         1 => false,
         n => {
             println!("{}{} types found with that name:",
-                ansi_term::Color::Yellow.paint("note: "),
+                colour(Colour::Yellow).paint("note: "),
                 n,
             );
             true
@@ -1293,8 +5440,9 @@ fn cmd_decode(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     for (goff, t) in types {
         if many { println!() }
         println!("{}: ", NamedGoff(db, goff));
-        match Value::from_state(&ctx.segments, addr, db, t) {
+        match Value::from_state(&ctx.memory(), addr, db, t) {
             Ok(v) => {
+                print_slice_contents(db, &ctx.memory(), &v, "points to: ");
                 println!("{}", ValueWithDb(v, db));
             }
             Err(e) => {
@@ -1304,6 +5452,50 @@ fn cmd_decode(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     }
 }
 
+/// Handles `decode TYPENAME HEXBYTES...`, where the bytes are the trailing
+/// words that consist only of hex digits.
+fn decode_hex_args(db: &DebugDb, args: &str) {
+    let words = args.split_whitespace().collect::<Vec<_>>();
+    let nbytes = words.iter().rev()
+        .take_while(|w| w.bytes().all(|b| b.is_ascii_hexdigit()))
+        .count();
+    let type_name = words[..words.len() - nbytes].join(" ");
+    if type_name.is_empty() || nbytes == 0 {
+        println!("usage: decode ADDR TYPENAME, or decode TYPENAME HEXBYTES...");
+        return;
+    }
+    let img = match parse_hex_bytes(&words[words.len() - nbytes..].join(" ")) {
+        Ok(img) => img,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+
+    let types: Vec<_> = match parse_type_name(&type_name) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => {
+            lookup_types(db, n)
+        }
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter()
+                .map(|t| (o, t))
+                .collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+    if types.len() > 1 {
+        println!("{}{} types found with that name:",
+            colour(Colour::Yellow).paint("note: "),
+            types.len(),
+        );
+    }
+    decode_bytes(db, &types, types.len() > 1, &img);
+}
+
 fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     let (addrstr, typestr) = if let Some(space) = args.find(' ') {
         args.split_at(space)
@@ -1321,7 +5513,7 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     let types: Vec<_> = match parse_type_name(typestr.trim()) {
         None => return,
         Some(ParsedTypeName::Name(n)) => {
-            db.types_by_name(n).collect()
+            lookup_types(db, n)
         }
         Some(ParsedTypeName::Goff(o)) => {
             db.type_by_id(o).into_iter()
@@ -1332,13 +5524,13 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
 
     let many = match types.len() {
         0 => {
-            println!("{}", ansi_term::Colour::Red.paint("No types found."));
+            println!("{}", colour(Colour::Red).paint("No types found."));
             return;
         }
         1 => false,
         n => {
             println!("{}{} types found with that name:",
-                ansi_term::Color::Yellow.paint("note: "),
+                colour(Colour::Yellow).paint("note: "),
                 n,
             );
             true
@@ -1348,7 +5540,7 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     for (goff, t) in types {
         if many { println!() }
         println!("{}: ", NamedGoff(db, goff));
-        let mut v = &match Value::from_state(&ctx.segments, addr, db, t) {
+        let mut v = &match Value::from_state(&ctx.memory(), addr, db, t) {
             Ok(v) => v,
             Err(e) => {
                 println!("could not parse as this type: {e}");
@@ -1358,7 +5550,7 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
         let parts = Regex::new(r#"^(.*)::\{async_fn_env#0\}(<.*)?$"#).unwrap();
         let suspend_state = Regex::new(r#"::Suspend([0-9]+)$"#).unwrap();
         let mut first = true;
-        let bold = ansi_term::Style::new().bold();
+        let bold = bold();
         loop {
             if !first {
                 print!("waiting on: ");
@@ -1390,7 +5582,11 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
                 break;
             } else if let Some(sc) = suspend_state.captures(state_name) {
                 if let Ok(n) = sc[1].parse::<usize>() {
-                    println!("    suspended at await point {n}");
+                    print!("    suspended at await point {n}");
+                    if let Some(coord) = await_point(db, &e.name, n) {
+                        print!(" ({})", format_coord(&coord));
+                    }
+                    println!();
                 } else {
                     println!("    unrecognized state {state}: {state_name}");
                 }
@@ -1412,12 +5608,218 @@ fn cmd_decode_async(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
     }
 }
 
+fn cmd_variant_of(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let Some((type_name, value)) = args.trim().rsplit_once(char::is_whitespace) else {
+        println!("usage: variant-of TYPENAME VALUE");
+        return;
+    };
+    // Accept negative values for signed discriminators; they're truncated to
+    // the discriminator's size below.
+    let parsed = match value.strip_prefix('-') {
+        Some(v) => parse_int::parse::<i128>(v).map(|v| (-v) as u128),
+        None => parse_int::parse::<u128>(value),
+    };
+    let Ok(value) = parsed else {
+        println!("can't parse {value} as a number");
+        return;
+    };
+    let Some(goff) = lookup_one_type(db, type_name.trim()) else {
+        return;
+    };
+    let bold = bold();
+
+    match db.type_by_id(db.resolve_alias(goff)) {
+        Some(Type::CEnum(e)) => {
+            match e.enumerator_for_value(value as i128) {
+                Some(en) => println!("{:#x} selects {}", value, bold.paint(&*en.name)),
+                None => println!("{}", colour(Colour::Red).paint(
+                    format!("{value:#x} is not the value of any enumerator"))),
+            }
+        }
+        Some(Type::Enum(e)) => match &e.shape {
+            VariantShape::Zero => println!("enum has no variants"),
+            VariantShape::One(v) => {
+                println!("enum has only one variant, and no discriminator");
+                print_variant_payload(db, v);
+            }
+            VariantShape::Many { member, variants, .. } => {
+                let dsize = db.type_by_id(member.type_id)
+                    .and_then(|t| t.byte_size(db));
+                println!("discriminator: {} bytes at offset {}",
+                    dsize.map(|z| z.to_string()).unwrap_or_else(|| "?".into()),
+                    member.location);
+                let value = match dsize {
+                    Some(n @ 1..=15) => value & ((1 << (8 * n)) - 1),
+                    _ => value,
+                };
+                let ranges = e.discriminant_ranges(db);
+                let Some((_, v)) = ranges.iter().find(|(r, _)| r.contains(&value)) else {
+                    println!("{}", colour(Colour::Red).paint(
+                        format!("{value:#x} does not select any variant")));
+                    return;
+                };
+                let name = v.member.name.as_deref().unwrap_or("_");
+                println!("{value:#x} selects variant {}", bold.paint(name));
+                if variants.get(&None).is_some_and(|d| std::ptr::eq(d, *v)) {
+                    let own = ranges.iter()
+                        .filter(|(_, rv)| std::ptr::eq(*rv, *v))
+                        .map(|(r, _)| r.clone())
+                        .collect::<Vec<_>>();
+                    println!("- as the default variant, which has {}", fmt_discriminants(&own));
+                    println!("  (the discriminator is stored in a niche of its data)");
+                }
+                print_variant_payload(db, v);
+            }
+        },
+        _ => println!("{} is not an enum", NamedGoff(db, goff)),
+    }
+}
+
+fn print_variant_payload(db: &debugdb::DebugDb, v: &debugdb::Variant) {
+    println!("- payload: {}", NamedGoff(db, v.member.type_id));
+    println!("- at offset: {} bytes", v.member.location);
+    if let Some(Type::Struct(s)) = db.type_by_id(v.member.type_id) {
+        for m in &s.members {
+            println!("  - {}: {} at offset {}",
+                m.name.as_deref().unwrap_or("_"),
+                NamedGoff(db, m.type_id),
+                v.member.location + m.location);
+        }
+    }
+}
+
+fn cmd_coroutines(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
+    let bold = bold();
+    let args = args.trim();
+    if args.is_empty() {
+        for co in db.coroutines() {
+            let suspends = co.states.iter()
+                .filter(|s| matches!(s.kind, StateKind::Suspend(_)))
+                .count();
+            println!("{:?} {}{}: {} suspend points, {} bytes",
+                co.kind, bold.paint(co.function), co.generic_args, suspends,
+                co.ty.byte_size.unwrap_or(0));
+        }
+        return;
+    }
+
+    let types: Vec<_> = match parse_type_name(args) {
+        None => return,
+        Some(ParsedTypeName::Name(n)) => lookup_types(db, n),
+        Some(ParsedTypeName::Goff(o)) => {
+            db.type_by_id(o).into_iter().map(|t| (o, t)).collect()
+        }
+    };
+    if types.is_empty() {
+        println!("{}", colour(Colour::Red).paint("No types found."));
+        return;
+    }
+    for (goff, _) in types {
+        let Some(co) = db.coroutine(goff) else {
+            println!("{} is not a coroutine", NamedGoff(db, goff));
+            continue;
+        };
+        println!("{:?} {}{} {}", co.kind, bold.paint(co.function), co.generic_args,
+            NamedGoff(db, goff));
+        if !co.upvars.is_empty() {
+            println!("- upvars:");
+            for m in &co.upvars {
+                print_coroutine_member(db, m);
+            }
+        }
+        for state in &co.states {
+            let discs = state.discriminants.iter()
+                .map(|r| if r.start() == r.end() {
+                    r.start().to_string()
+                } else {
+                    format!("{}..={}", r.start(), r.end())
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            print!("- state {} ({})", bold.paint(state.name), discs);
+            if let Some(coord) = state.await_point() {
+                print!(" at {}", format_coord(coord));
+                let code = db.await_point_code(&co, state);
+                if !code.is_empty() {
+                    let addrs = code.iter()
+                        .map(|row| format!("{:#x}", row.pc_range.start))
+                        .collect::<Vec<_>>();
+                    print!(", code at {}", addrs.join(" "));
+                }
+            }
+            println!();
+            for m in &state.locals {
+                print_coroutine_member(db, m);
+            }
+        }
+    }
+}
+
+/// Looks up types by a name typed in by the user: exactly if anything has
+/// that name, or else ignoring case and whitespace, or else by abbreviated
+/// name (`Vec<u8>`). If an abbreviation could mean types with different
+/// names, this lists them and finds nothing.
+fn lookup_types<'d>(db: &'d DebugDb, name: &str) -> Vec<(TypeId, &'d Type)> {
+    let found = db.types_by_name(name, NameMatch::EXACT).collect::<Vec<_>>();
+    if !found.is_empty() {
+        return found;
+    }
+    let found = db.types_by_name(name, NameMatch::LOOSE).collect::<Vec<_>>();
+    if !found.is_empty() {
+        return found;
+    }
+    let found = db.types_by_short_name(name).collect::<Vec<_>>();
+    let names = found.iter()
+        .filter_map(|&(id, ty)| Some((db.type_name(id)?, ty)))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    if names.len() > 1 {
+        // Printed to stderr so as not to get mixed up with JSON output.
+        eprintln!("{}{} is ambiguous; it could mean:",
+            colour(Colour::Yellow).paint("note: "), name);
+        for (n, ty) in names {
+            eprintln!("    {n} {}", dimmed().paint(format!("({})", ty.display(db))));
+        }
+        return vec![];
+    }
+    found
+}
+
+/// Finds the source location of suspend point `n` of the coroutine type named
+/// `name`.
+fn await_point(db: &DebugDb, name: &str, n: usize) -> Option<debugdb::DeclCoord> {
+    db.types_by_name(name, NameMatch::EXACT)
+        .filter_map(|(id, _)| db.coroutine(id))
+        .find_map(|co| co.suspend_state(n)?.await_point().cloned())
+}
+
+/// Formats a source location as `file:line:column`, leaving out any parts that
+/// aren't known.
+fn format_coord(coord: &debugdb::DeclCoord) -> String {
+    let mut s = coord.file.as_deref().unwrap_or("???").to_string();
+    if let Some(line) = coord.line {
+        write!(s, ":{line}").unwrap();
+        if let Some(col) = coord.column {
+            write!(s, ":{col}").unwrap();
+        }
+    }
+    s
+}
+
+fn print_coroutine_member(db: &DebugDb, m: &Member) {
+    let size = db.type_by_id(m.type_id)
+        .and_then(|t| t.byte_size(db))
+        .map(|z| z.to_string())
+        .unwrap_or_else(|| "???".into());
+    println!("    +0x{:<4x} {:>6} {}: {}", m.location, size,
+        m.name.as_deref().unwrap_or("_"), NamedGoff(db, m.type_id));
+}
+
 fn cmd_decode_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
     let type_name = args.trim();
     let types: Vec<_> = match parse_type_name(type_name) {
         None => return,
         Some(ParsedTypeName::Name(n)) => {
-            db.types_by_name(n).collect()
+            lookup_types(db, n)
         }
         Some(ParsedTypeName::Goff(o)) => {
             db.type_by_id(o).into_iter()
@@ -1428,13 +5830,13 @@ fn cmd_decode_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
 
     let many = match types.len() {
         0 => {
-            println!("{}", ansi_term::Colour::Red.paint("No types found."));
+            println!("{}", colour(Colour::Red).paint("No types found."));
             return;
         }
         1 => false,
         n => {
             println!("{}{} types found with that name:",
-                ansi_term::Color::Yellow.paint("note: "),
+                colour(Colour::Yellow).paint("note: "),
                 n,
             );
             true
@@ -1463,33 +5865,45 @@ fn cmd_decode_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
             line = &line.split_at(colon).1[1..];
         }
 
-        let mut hexits = vec![];
-        for b in line.bytes() {
-            match b {
-                b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f' => {
-                    hexits.push(b);
-                }
-                b' ' | b'\t' | b'\r' | b'\n' => (),
-                _ => {
-                    println!("unexpected byte in input: {b:#x?}");
-                    return;
-                }
-            }
-        }
-
-        let bytes = hexits.chunks_exact(2)
-            .map(|chunk| u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16))
-            .collect::<Result<Vec<_>, _>>();
-        match bytes {
+        match parse_hex_bytes(line) {
             Err(e) => {
-                println!("couldn't parse that: {e}");
+                println!("{e}");
                 return;
             }
             Ok(b) => img.extend(b),
         }
     }
 
-    for (goff, t) in types {
+    decode_bytes(db, &types, many, &img);
+}
+
+/// Parses hex-encoded bytes, like `de ad be ef` or `deadbeef`, ignoring
+/// whitespace.
+fn parse_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    let mut hexits = vec![];
+    for b in s.bytes() {
+        match b {
+            b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f' => {
+                hexits.push(b);
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => (),
+            _ => return Err(format!("unexpected byte in input: {b:#x?}")),
+        }
+    }
+    if hexits.len() % 2 != 0 {
+        return Err("odd number of hex digits in input".to_string());
+    }
+
+    hexits.chunks_exact(2)
+        .map(|chunk| u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("couldn't parse that: {e}"))
+}
+
+/// Interprets `img` as each of `types` in turn and prints the results. Short
+/// input is padded with zeroes.
+fn decode_bytes(db: &DebugDb, types: &[(TypeId, &Type)], many: bool, img: &[u8]) {
+    for &(goff, t) in types {
         if many { println!() }
         println!("{}: ", NamedGoff(db, goff));
         let Some(size) = t.byte_size(db) else {
@@ -1500,13 +5914,12 @@ fn cmd_decode_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
             println!("  (type too big for this platform)");
             continue;
         };
-        let mut this_img = img.clone();
+        let mut this_img = img.to_vec();
         if size > this_img.len() {
             println!("(padding entered data to {size} bytes)");
             this_img.resize(size, 0);
         }
-        let machine = ImgMachine::new(this_img);
-        match Value::from_state(&machine, 0, db, t) {
+        match Value::from_bytes(&this_img, db, t) {
             Ok(v) => {
                 println!("{}", ValueWithDb(v, db));
             }
@@ -1522,7 +5935,7 @@ fn cmd_decode_async_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
     let types: Vec<_> = match parse_type_name(type_name) {
         None => return,
         Some(ParsedTypeName::Name(n)) => {
-            db.types_by_name(n).collect()
+            lookup_types(db, n)
         }
         Some(ParsedTypeName::Goff(o)) => {
             db.type_by_id(o).into_iter()
@@ -1533,13 +5946,13 @@ fn cmd_decode_async_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
 
     let many = match types.len() {
         0 => {
-            println!("{}", ansi_term::Colour::Red.paint("No types found."));
+            println!("{}", colour(Colour::Red).paint("No types found."));
             return;
         }
         1 => false,
         n => {
             println!("{}{} types found with that name:",
-                ansi_term::Color::Yellow.paint("note: "),
+                colour(Colour::Yellow).paint("note: "),
                 n,
             );
             true
@@ -1568,26 +5981,9 @@ fn cmd_decode_async_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
             line = &line.split_at(colon).1[1..];
         }
 
-        let mut hexits = vec![];
-        for b in line.bytes() {
-            match b {
-                b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f' => {
-                    hexits.push(b);
-                }
-                b' ' | b'\t' | b'\r' | b'\n' => (),
-                _ => {
-                    println!("unexpected byte in input: {b:#x?}");
-                    return;
-                }
-            }
-        }
-
-        let bytes = hexits.chunks_exact(2)
-            .map(|chunk| u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16))
-            .collect::<Result<Vec<_>, _>>();
-        match bytes {
+        match parse_hex_bytes(line) {
             Err(e) => {
-                println!("couldn't parse that: {e}");
+                println!("{e}");
                 return;
             }
             Ok(b) => img.extend(b),
@@ -1652,7 +6048,11 @@ fn cmd_decode_async_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
                 break;
             } else if let Some(sc) = suspend_state.captures(state_name) {
                 if let Ok(n) = sc[1].parse::<usize>() {
-                    println!("    suspended at await point {n}");
+                    print!("    suspended at await point {n}");
+                    if let Some(coord) = await_point(db, &e.name, n) {
+                        print!(" ({})", format_coord(&coord));
+                    }
+                    println!();
                 } else {
                     println!("    unrecognized state {state}: {state_name}");
                 }
@@ -1675,6 +6075,70 @@ fn cmd_decode_async_blob(db: &debugdb::DebugDb, _ctx: &mut Ctx, args: &str) {
 }
 
 
+fn cmd_defmt(db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let Some(table) = &ctx.defmt else {
+        println!("program has no defmt strings");
+        return;
+    };
+    if args.trim().is_empty() {
+        for (index, entry) in table.entries() {
+            if entry.tag != debugdb::defmt::Tag::Derived {
+                continue;
+            }
+            println!("{index:5} {}", entry.format);
+            for id in db.defmt_types(entry) {
+                println!("      {}", NamedGoff(db, id));
+            }
+        }
+        return;
+    }
+
+    let bytes = match parse_hex_bytes(args) {
+        Ok(b) => b,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+    // Several frames may be pasted at once.
+    let mut rest = &bytes[..];
+    while !rest.is_empty() {
+        let (frame, len) = match table.decode(rest) {
+            Ok(f) => f,
+            Err(e) => {
+                println!("can't decode frame: {e}");
+                return;
+            }
+        };
+        rest = &rest[len..];
+        if let Some(ts) = frame.timestamp_text() {
+            print!("{} ", dimmed().paint(ts));
+        }
+        if let Some(level) = frame.entry.tag.level() {
+            print!("{} ", bold().paint(level));
+        }
+        println!("{}", frame.message(Some(db)));
+        let mut seen = vec![];
+        for value in frame.formatted_values() {
+            if seen.iter().any(|&e| std::ptr::eq(e, value.entry)) {
+                continue;
+            }
+            seen.push(value.entry);
+            let types = db.defmt_types(value.entry);
+            if types.is_empty() {
+                println!("  {}: no matching type", value.entry.format);
+            }
+            for id in types {
+                let size = db.type_by_id(id).and_then(|t| t.byte_size(db));
+                match size {
+                    Some(z) => println!("  {}, {z} bytes", NamedGoff(db, id)),
+                    None => println!("  {}", NamedGoff(db, id)),
+                }
+            }
+        }
+    }
+}
+
 fn cmd_load(
     _db: &debugdb::DebugDb,
     ctx: &mut Ctx,
@@ -1703,7 +6167,214 @@ fn cmd_load(
         }
     };
 
-    let end = address + u64::try_from(image.len()).unwrap();
+    debugdb::load::overlay_segment(&mut ctx.segments, address, image);
+}
+
+#[cfg(feature = "gdb")]
+fn cmd_attach(_db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let addr = args.trim();
+    if addr.is_empty() {
+        println!("usage: attach HOST:PORT");
+        return;
+    }
+    match debugdb::gdb::GdbClient::connect(addr) {
+        Ok(target) => {
+            println!("Attached to {addr}; values are now read from the target.");
+            ctx.target = Some(Target::Gdb(target));
+        }
+        Err(e) => println!("can't attach to {addr}: {e}"),
+    }
+}
+
+#[cfg(feature = "probe-rs")]
+fn cmd_probe(_db: &debugdb::DebugDb, ctx: &mut Ctx, args: &str) {
+    let chip = args.trim();
+    if chip.is_empty() {
+        println!("usage: probe CHIP");
+        return;
+    }
+    match debugdb::probe::ProbeTarget::attach(chip) {
+        Ok(target) => {
+            println!("Attached to {chip}; values are now read from the target.");
+            ctx.target = Some(Target::Probe(target));
+        }
+        Err(e) => println!("can't attach to {chip}: {e}"),
+    }
+}
+
+#[cfg(any(feature = "gdb", feature = "probe-rs"))]
+fn cmd_detach(_db: &debugdb::DebugDb, ctx: &mut Ctx, _args: &str) {
+    let Some(target) = ctx.target.take() else {
+        println!("not attached");
+        return;
+    };
+    match target {
+        #[cfg(feature = "gdb")]
+        Target::Gdb(target) => match target.detach() {
+            Ok(()) => println!("Detached."),
+            Err(e) => println!("detached, but the target said: {e}"),
+        },
+        // The target was never halted, so dropping the session, which
+        // closes it, is enough.
+        #[cfg(feature = "probe-rs")]
+        Target::Probe(_) => println!("Detached."),
+    }
+}
+
+/// Answers JSON-RPC 2.0 requests read from `input`, one per line, until it
+/// ends. Each response is written to `output` as a single line.
+///
+/// Methods, with their parameters (by name):
+///
+/// - `lookup {type}`: everything `info` knows about each type with that name
+///   or ID, as an array.
+/// - `sizeof {type}`: the size and alignment of each matching type.
+/// - `addr2line {address}`: the functions executing at an address, innermost
+///   first, with their source locations.
+/// - `decode {type, address}`: the value of each matching type at an
+///   address, as text, read from the attached target or the program's image.
+///
+/// Addresses may be numbers or strings like `"0x1000"`.
+fn serve_rpc(
+    db: &DebugDb,
+    ctx: &mut Ctx,
+    input: impl BufRead,
+    mut output: impl std::io::Write,
+) -> std::io::Result<()> {
+    use serde_json::{json, Value as Json};
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Json>(&line) {
+            Err(e) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": RpcError::new(-32700, e.to_string()).to_json(),
+            })),
+            Ok(request) => {
+                // Requests without an ID are notifications, which aren't
+                // answered.
+                let id = request.get("id").cloned();
+                let result = match request.get("method").and_then(Json::as_str) {
+                    None => Err(RpcError::new(-32600, "no method")),
+                    Some(method) => {
+                        let params = request.get("params").unwrap_or(&Json::Null);
+                        rpc_call(db, ctx, method, params)
+                    }
+                };
+                id.map(|id| match result {
+                    Ok(r) => json!({"jsonrpc": "2.0", "id": id, "result": r}),
+                    Err(e) => json!({"jsonrpc": "2.0", "id": id, "error": e.to_json()}),
+                })
+            }
+        };
+        if let Some(response) = response {
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(-32602, message)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({"code": self.code, "message": self.message})
+    }
+}
+
+fn rpc_call(
+    db: &DebugDb,
+    ctx: &mut Ctx,
+    method: &str,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    use serde_json::json;
+
+    match method {
+        "lookup" => {
+            let types = rpc_types(db, params)?;
+            Ok(types.into_iter().map(|(goff, t)| type_info_json(db, goff, t)).collect())
+        }
+        "sizeof" => {
+            let types = rpc_types(db, params)?;
+            Ok(types.into_iter()
+                .map(|(goff, t)| {
+                    let mut obj = type_ref_json(db, goff);
+                    obj["byte_size"] = t.byte_size(db).into();
+                    obj["alignment"] = t.alignment(db).into();
+                    obj
+                })
+                .collect())
+        }
+        "addr2line" => {
+            use fallible_iterator::FallibleIterator;
+
+            let address = rpc_address(params)?;
+            let frames = debugdb::addr2line::Context::new(db).find_frames(address)
+                .and_then(|frames| frames.collect::<Vec<_>>())
+                .map_err(|e| RpcError::new(-32000, e.to_string()))?;
+            let frames = frames.iter()
+                .map(|f| json!({
+                    "function": f.function.as_ref().and_then(|n| n.demangle().ok()),
+                    "file": f.location.as_ref().and_then(|l| l.file),
+                    "line": f.location.as_ref().and_then(|l| l.line),
+                    "column": f.location.as_ref().and_then(|l| l.column),
+                }))
+                .collect::<Vec<_>>();
+            Ok(json!({"address": address, "frames": frames}))
+        }
+        "decode" => {
+            let address = rpc_address(params)?;
+            let types = rpc_types(db, params)?;
+            Ok(types.into_iter()
+                .map(|(goff, t)| {
+                    let mut obj = type_ref_json(db, goff);
+                    match Value::from_state(&ctx.memory(), address, db, t) {
+                        Ok(v) => obj["value"] = ValueWithDb(v, db).to_string().into(),
+                        Err(e) => obj["error"] = e.to_string().into(),
+                    }
+                    obj
+                })
+                .collect())
+        }
+        _ => Err(RpcError::new(-32601, format!("no method {method}"))),
+    }
+}
+
+/// Finds the types named by the `type` parameter, which is a name or an ID.
+fn rpc_types<'db>(
+    db: &'db DebugDb,
+    params: &serde_json::Value,
+) -> Result<Vec<(TypeId, &'db Type)>, RpcError> {
+    let name = params.get("type").and_then(serde_json::Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params("expected a type parameter"))?;
+    if name.starts_with("<.debug_") {
+        let id = parse_type_id(name).map_err(RpcError::invalid_params)?;
+        Ok(db.type_by_id(id).into_iter().map(|t| (id, t)).collect())
+    } else {
+        Ok(lookup_types(db, name))
+    }
+}
 
-    ctx.segments.insert(address..=end, image);
+fn rpc_address(params: &serde_json::Value) -> Result<u64, RpcError> {
+    let address = params.get("address");
+    address.and_then(serde_json::Value::as_u64)
+        .or_else(|| address?.as_str().and_then(|s| parse_int::parse::<u64>(s).ok()))
+        .ok_or_else(|| RpcError::invalid_params("expected an address parameter"))
 }
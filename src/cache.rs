@@ -0,0 +1,854 @@
+//! Saving a `DebugDb` to a file and loading it back, so that a program only
+//! needs to be parsed once.
+//!
+//! The format is a compact binary encoding private to this crate. Only the
+//! primary tables are stored -- types, subprograms, variables, line numbers,
+//! symbols, and the call frame information -- and the indices are rebuilt on
+//! load, which is cheap compared to parsing and unifying the debug info.
+//!
+//! A cache records the identity of the program it came from -- its path,
+//! size, modification time and build ID -- and won't load for any other, so a
+//! cache left over from an older build is noticed rather than used.
+
+use crate::*;
+use crate::intern::Interner;
+use gimli::Section;
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::num::NonZeroU64;
+use std::ops::Range;
+use indexmap::IndexMap;
+
+/// Identifies a cache file.
+const MAGIC: &[u8; 8] = b"debugdb\0";
+
+/// Version of the format. This needs to change whenever the encoding of
+/// anything does, including when fields are added to the model.
+const VERSION: u64 = 20;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("not a debugdb cache file")]
+    NotACache,
+    #[error("cache file has version {0}, but only version {VERSION} is supported")]
+    WrongVersion(u64),
+    #[error("cache file is truncated")]
+    Truncated,
+    #[error("cache file is corrupt: {0}")]
+    Corrupt(&'static str),
+    #[error("cache file is for {0}, or an older build of this program")]
+    WrongProgram(String),
+}
+
+/// Identifies the program a cache was made from.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProgramIdentity {
+    /// Path the program was read from.
+    pub path: String,
+    /// Size of the program file, in bytes.
+    pub size: u64,
+    /// Modification time of the program file, in nanoseconds since the Unix
+    /// epoch, where the filesystem records one.
+    pub modified: Option<u64>,
+    /// GNU build ID of the program, if it has one.
+    pub build_id: Option<Vec<u8>>,
+}
+
+impl ProgramIdentity {
+    /// Identifies the program file at `path`, whose contents are `data`.
+    pub fn of_file(path: &std::path::Path, data: &[u8]) -> std::io::Result<Self> {
+        use object::Object;
+
+        let meta = std::fs::metadata(path)?;
+        let modified = meta.modified().ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .and_then(|d| u64::try_from(d.as_nanos()).ok());
+        let build_id = object::File::parse(data).ok()
+            .and_then(|o| o.build_id().ok().flatten().map(<[u8]>::to_vec));
+        Ok(Self {
+            path: path.display().to_string(),
+            size: meta.len(),
+            modified,
+            build_id,
+        })
+    }
+}
+
+/// Writes `db`, which was parsed from the program `identity`, to `out` in
+/// cache format.
+pub fn save(
+    db: &DebugDb,
+    identity: &ProgramIdentity,
+    mut out: impl Write,
+) -> Result<(), CacheError> {
+    let mut w = Vec::new();
+    w.extend_from_slice(MAGIC);
+    VERSION.encode(&mut w);
+    identity.encode(&mut w);
+
+    db.endian.encode(&mut w);
+    db.is_64.encode(&mut w);
+    db.types.encode(&mut w);
+    db.type_canon.encode(&mut w);
+    db.type_name_index.encode(&mut w);
+    db.type_modules.encode(&mut w);
+    db.program_modules.encode(&mut w);
+    db.units.encode(&mut w);
+    db.subprograms.encode(&mut w);
+    db.line_table.encode(&mut w);
+    db.line_files.encode(&mut w);
+    db.variables.encode(&mut w);
+    db.debug_frame.reader().bytes().encode(&mut w);
+    match &db.eh_frame {
+        Some((eh_frame, bases)) => {
+            true.encode(&mut w);
+            eh_frame.reader().bytes().encode(&mut w);
+            bases.eh_frame_hdr.encode(&mut w);
+            bases.eh_frame.encode(&mut w);
+        }
+        None => false.encode(&mut w),
+    }
+    let raw_symbols = db.raw_symbols_by_address.iter()
+        .flat_map(|(&addr, names)| names.iter().map(move |n| (n.clone(), addr)))
+        .collect::<Vec<_>>();
+    raw_symbols.encode(&mut w);
+    let sections = db.sections().collect::<Vec<_>>();
+    sections.encode(&mut w);
+    db.images().len().encode(&mut w);
+    for image in db.images() {
+        image.name.encode(&mut w);
+        image.debug_info.start.encode(&mut w);
+        image.debug_info.end.encode(&mut w);
+        image.address_ranges.encode(&mut w);
+    }
+    db.warnings.encode(&mut w);
+
+    out.write_all(&w)?;
+    Ok(())
+}
+
+/// Reads a database from `input`, which must have been written by `save` for
+/// the program `identity`.
+pub fn load(
+    mut input: impl Read,
+    identity: &ProgramIdentity,
+) -> Result<DebugDb, CacheError> {
+    let mut buf = Vec::new();
+    input.read_to_end(&mut buf)?;
+    let Some(rest) = buf.strip_prefix(MAGIC) else {
+        return Err(CacheError::NotACache);
+    };
+    let r = &mut Reader {
+        bytes: rest,
+        names: Interner::new(),
+    };
+    let version = u64::decode(r)?;
+    if version != VERSION {
+        return Err(CacheError::WrongVersion(version));
+    }
+    let cached_identity = ProgramIdentity::decode(r)?;
+    if cached_identity != *identity {
+        return Err(CacheError::WrongProgram(cached_identity.path));
+    }
+
+    let endian = gimli::RunTimeEndian::decode(r)?;
+    let is_64 = bool::decode(r)?;
+    let types = Decode::decode(r)?;
+    let type_canon = Decode::decode(r)?;
+    let type_name_index = Decode::decode(r)?;
+    let type_modules = Decode::decode(r)?;
+    let program_modules = Decode::decode(r)?;
+    let units = Decode::decode(r)?;
+    let subprograms = Decode::decode(r)?;
+    let line_table = Decode::decode(r)?;
+    let line_files = Decode::decode(r)?;
+    let variables = Decode::decode(r)?;
+    let mut debug_frame = gimli::DebugFrame::from(section_reader(r, endian)?);
+    debug_frame.set_address_size(if is_64 { 8 } else { 4 });
+    let eh_frame = if bool::decode(r)? {
+        let mut eh_frame = gimli::EhFrame::from(section_reader(r, endian)?);
+        eh_frame.set_address_size(if is_64 { 8 } else { 4 });
+        let bases = gimli::BaseAddresses {
+            eh_frame_hdr: Decode::decode(r)?,
+            eh_frame: Decode::decode(r)?,
+        };
+        Some((eh_frame, bases))
+    } else {
+        None
+    };
+    let raw_symbols = Decode::decode(r)?;
+    let sections = Decode::decode(r)?;
+    let image_count = usize::decode(r)?;
+    let mut images = Vec::with_capacity(image_count.min(r.bytes.len()));
+    for _ in 0..image_count {
+        images.push(Image {
+            name: Decode::decode(r)?,
+            debug_info: usize::decode(r)?..usize::decode(r)?,
+            address_ranges: Decode::decode(r)?,
+        });
+    }
+    let warnings = Decode::decode(r)?;
+    if !r.bytes.is_empty() {
+        return Err(CacheError::Corrupt("trailing data"));
+    }
+
+    Ok(Tables {
+        endian,
+        is_64,
+        types,
+        type_canon,
+        type_name_index,
+        type_modules,
+        program_modules,
+        units,
+        subprograms,
+        line_table,
+        line_files,
+        variables,
+        debug_frame,
+        eh_frame,
+        raw_symbols,
+        sections,
+        images,
+        warnings,
+    }.index())
+}
+
+fn section_reader(
+    r: &mut Reader<'_>,
+    endian: gimli::RunTimeEndian,
+) -> Result<RtArcReader, CacheError> {
+    let len = usize::decode(r)?;
+    let bytes = take(r, len)?;
+    Ok(gimli::EndianReader::new(Arc::from(bytes), endian))
+}
+
+trait Encode {
+    fn encode(&self, w: &mut Vec<u8>);
+}
+
+trait Decode: Sized {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError>;
+}
+
+/// Input to decoding: the remaining bytes, and the names seen so far, so that
+/// equal names share storage as they do in a freshly parsed database.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    names: Interner,
+}
+
+fn take<'a>(r: &mut Reader<'a>, n: usize) -> Result<&'a [u8], CacheError> {
+    if r.bytes.len() < n {
+        return Err(CacheError::Truncated);
+    }
+    let (head, tail) = r.bytes.split_at(n);
+    r.bytes = tail;
+    Ok(head)
+}
+
+// Integers are stored as unsigned LEB128, since most of them are small.
+impl Encode for u64 {
+    fn encode(&self, w: &mut Vec<u8>) {
+        let mut v = *self;
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                w.push(byte);
+                break;
+            }
+            w.push(byte | 0x80);
+        }
+    }
+}
+
+impl Decode for u64 {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let mut v = 0;
+        let mut shift = 0;
+        loop {
+            let byte = take(r, 1)?[0];
+            if shift >= 64 {
+                return Err(CacheError::Corrupt("integer too long"));
+            }
+            v |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl Encode for usize {
+    fn encode(&self, w: &mut Vec<u8>) {
+        (*self as u64).encode(w)
+    }
+}
+
+impl Decode for usize {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        usize::try_from(u64::decode(r)?)
+            .map_err(|_| CacheError::Corrupt("integer too large"))
+    }
+}
+
+impl Encode for NonZeroU64 {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.get().encode(w)
+    }
+}
+
+impl Decode for NonZeroU64 {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        NonZeroU64::new(u64::decode(r)?)
+            .ok_or(CacheError::Corrupt("zero where nonzero expected"))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, w: &mut Vec<u8>) {
+        w.push(u8::from(*self))
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, w: &mut Vec<u8>) {
+        w.push(*self)
+    }
+}
+
+impl Decode for u8 {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        Ok(take(r, 1)?[0])
+    }
+}
+
+impl Decode for bool {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        match take(r, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CacheError::Corrupt("bad bool")),
+        }
+    }
+}
+
+impl Encode for [u8] {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.len().encode(w);
+        w.extend_from_slice(self);
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.as_bytes().encode(w)
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.as_str().encode(w)
+    }
+}
+
+impl Decode for String {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let len = usize::decode(r)?;
+        let bytes = take(r, len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| CacheError::Corrupt("bad UTF-8"))
+    }
+}
+
+impl Encode for Name {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.as_str().encode(w)
+    }
+}
+
+impl Decode for Name {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let len = usize::decode(r)?;
+        let bytes = take(r, len)?;
+        let s = std::str::from_utf8(bytes)
+            .map_err(|_| CacheError::Corrupt("bad UTF-8"))?;
+        Ok(r.names.intern(s))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        match self {
+            None => false.encode(w),
+            Some(x) => {
+                true.encode(w);
+                x.encode(w);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        if bool::decode(r)? {
+            Ok(Some(T::decode(r)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.0.encode(w);
+        self.1.encode(w);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        Ok((A::decode(r)?, B::decode(r)?))
+    }
+}
+
+impl Encode for Range<u64> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        self.start.encode(w);
+        self.end.encode(w);
+    }
+}
+
+impl Decode for Range<u64> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        Ok(u64::decode(r)?..u64::decode(r)?)
+    }
+}
+
+// Collections are stored as a length followed by their items.
+fn encode_seq<T: Encode>(
+    w: &mut Vec<u8>,
+    len: usize,
+    items: impl IntoIterator<Item = T>,
+) {
+    len.encode(w);
+    for item in items {
+        item.encode(w);
+    }
+}
+
+fn decode_seq<T: Decode, C: FromIterator<T>>(r: &mut Reader<'_>) -> Result<C, CacheError> {
+    let len = usize::decode(r)?;
+    (0..len).map(|_| T::decode(r)).collect()
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        encode_seq(w, self.len(), self)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        decode_seq(r)
+    }
+}
+
+impl<T: Encode> Encode for BTreeSet<T> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        encode_seq(w, self.len(), self)
+    }
+}
+
+impl<T: Decode + Ord> Decode for BTreeSet<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        decode_seq(r)
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        encode_seq(w, self.len(), self)
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        decode_seq(r)
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for IndexMap<K, V> {
+    fn encode(&self, w: &mut Vec<u8>) {
+        encode_seq(w, self.len(), self)
+    }
+}
+
+impl<K: Decode + Hash + Eq, V: Decode> Decode for IndexMap<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        decode_seq(r)
+    }
+}
+
+// Collections iterate by reference, and maps by pairs of references.
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, w: &mut Vec<u8>) {
+        (**self).encode(w)
+    }
+}
+
+/// Encodes a struct as each of its fields in turn.
+macro_rules! impl_struct {
+    ($($name:ident { $($field:ident),* $(,)? })*) => {$(
+        impl Encode for $name {
+            fn encode(&self, w: &mut Vec<u8>) {
+                $(self.$field.encode(w);)*
+            }
+        }
+
+        impl Decode for $name {
+            fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+                Ok($name {
+                    $($field: Decode::decode(r)?,)*
+                })
+            }
+        }
+    )*};
+}
+
+use gimli::SectionBaseAddresses;
+
+impl_struct! {
+    Base { name, encoding, byte_size, alignment, offset }
+    Struct {
+        name, byte_size, alignment, template_type_parameters, tuple_like,
+        members, offset, decl_coord,
+    }
+    Enum { name, byte_size, alignment, template_type_parameters, shape, offset }
+    CEnum { name, enum_class, byte_size, alignment, enumerators, offset }
+    Array { element_type_id, index_type_id, lower_bound, count, offset }
+    Pointer { type_id, name, byte_size, address_class, offset }
+    Union { name, byte_size, alignment, template_type_parameters, members, offset }
+    Subroutine {
+        return_type_id, formal_parameters, parameter_names, variadic,
+        prototyped, calling_convention, offset,
+    }
+    Typedef { name, type_id, offset }
+    Qualified { qualifier, type_id, offset }
+    Unresolved { offset }
+    TemplateTypeParameter { name, type_id }
+    Member { name, artificial, type_id, alignment, location, offset, decl_coord }
+    Variant { member, offset, decl_coord }
+    Enumerator { name, const_value, offset }
+    Subprogram {
+        name, pc_range, pc_ranges, decl_coord, template_type_parameters, return_type_id,
+        formal_parameters, inlines, abstract_origin, linkage_name, noreturn,
+        frame_base, offset,
+    }
+    SubParameter { name, decl_coord, type_id, abstract_origin, const_value, offset }
+    DeclCoord { file, line, column }
+    InlinedSubroutine {
+        abstract_origin, pc_ranges, call_coord, inlines, formal_parameters,
+        offset,
+    }
+    LineNumberRow { pc_range, file, line, column }
+    StaticVariable { name, linkage_name, type_id, decl, location, offset }
+    SectionBaseAddresses { section, text, data }
+    CompUnit { name, comp_dir, producer, language, address_size }
+    ProgramIdentity { path, size, modified, build_id }
+}
+
+/// Encodes a unit-only enum as the index of its variant.
+macro_rules! impl_unit_enum {
+    ($($name:path { $($variant:ident),* $(,)? })*) => {$(
+        impl Encode for $name {
+            fn encode(&self, w: &mut Vec<u8>) {
+                use $name as E;
+                let tags = [$(E::$variant),*];
+                let i = tags.iter().position(|t| t == self).unwrap();
+                i.encode(w)
+            }
+        }
+
+        impl Decode for $name {
+            fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+                use $name as E;
+                let tags = [$(E::$variant),*];
+                tags.get(usize::decode(r)?)
+                    .copied()
+                    .ok_or(CacheError::Corrupt(concat!("bad ", stringify!($name))))
+            }
+        }
+    )*};
+}
+
+impl_unit_enum! {
+    Encoding {
+        Unsigned, Signed, UnsignedChar, SignedChar, Boolean, Float,
+        ComplexFloat, UtfChar,
+    }
+    Qualifier { Const, Volatile, Restrict }
+    gimli::RunTimeEndian { Little, Big }
+}
+
+/// Encodes an ID type as its offset.
+macro_rules! impl_id {
+    ($($name:ident)*) => {$(
+        impl Encode for $name {
+            fn encode(&self, w: &mut Vec<u8>) {
+                self.0.encode(w)
+            }
+        }
+
+        impl Decode for $name {
+            fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+                Ok($name(Decode::decode(r)?))
+            }
+        }
+    )*};
+}
+
+impl_id!(TypeId ProgramId VarId ParamId);
+
+impl Encode for gimli::UnitSectionOffset {
+    fn encode(&self, w: &mut Vec<u8>) {
+        match self {
+            gimli::UnitSectionOffset::DebugInfoOffset(o) => (false, o.0).encode(w),
+            gimli::UnitSectionOffset::DebugTypesOffset(o) => (true, o.0).encode(w),
+        }
+    }
+}
+
+impl Decode for gimli::UnitSectionOffset {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let (types, offset) = <(bool, usize)>::decode(r)?;
+        Ok(if types {
+            gimli::DebugTypesOffset(offset).into()
+        } else {
+            gimli::DebugInfoOffset(offset).into()
+        })
+    }
+}
+
+impl Encode for gimli::DwCc {
+    fn encode(&self, w: &mut Vec<u8>) {
+        u64::from(self.0).encode(w)
+    }
+}
+
+impl Decode for gimli::DwCc {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let cc = u8::try_from(u64::decode(r)?)
+            .map_err(|_| CacheError::Corrupt("bad calling convention"))?;
+        Ok(gimli::DwCc(cc))
+    }
+}
+
+impl Encode for gimli::DwLang {
+    fn encode(&self, w: &mut Vec<u8>) {
+        u64::from(self.0).encode(w)
+    }
+}
+
+impl Decode for gimli::DwLang {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let lang = u16::try_from(u64::decode(r)?)
+            .map_err(|_| CacheError::Corrupt("bad language"))?;
+        Ok(gimli::DwLang(lang))
+    }
+}
+
+impl Encode for Type {
+    fn encode(&self, w: &mut Vec<u8>) {
+        match self {
+            Type::Struct(t) => (0u64, t).encode(w),
+            Type::Enum(t) => (1u64, t).encode(w),
+            Type::Base(t) => (2u64, t).encode(w),
+            Type::CEnum(t) => (3u64, t).encode(w),
+            Type::Array(t) => (4u64, t).encode(w),
+            Type::Pointer(t) => (5u64, t).encode(w),
+            Type::Union(t) => (6u64, t).encode(w),
+            Type::Subroutine(t) => (7u64, t).encode(w),
+            Type::Typedef(t) => (8u64, t).encode(w),
+            Type::Qualified(t) => (9u64, t).encode(w),
+            Type::Unresolved(t) => (10u64, t).encode(w),
+        }
+    }
+}
+
+impl Decode for Type {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        Ok(match u64::decode(r)? {
+            0 => Type::Struct(Decode::decode(r)?),
+            1 => Type::Enum(Decode::decode(r)?),
+            2 => Type::Base(Decode::decode(r)?),
+            3 => Type::CEnum(Decode::decode(r)?),
+            4 => Type::Array(Decode::decode(r)?),
+            5 => Type::Pointer(Decode::decode(r)?),
+            6 => Type::Union(Decode::decode(r)?),
+            7 => Type::Subroutine(Decode::decode(r)?),
+            8 => Type::Typedef(Decode::decode(r)?),
+            9 => Type::Qualified(Decode::decode(r)?),
+            10 => Type::Unresolved(Decode::decode(r)?),
+            _ => return Err(CacheError::Corrupt("bad type kind")),
+        })
+    }
+}
+
+impl Encode for VariantShape {
+    fn encode(&self, w: &mut Vec<u8>) {
+        match self {
+            VariantShape::Zero => 0u64.encode(w),
+            VariantShape::One(v) => (1u64, v).encode(w),
+            VariantShape::Many { member, variants } => {
+                2u64.encode(w);
+                member.encode(w);
+                variants.encode(w);
+            }
+        }
+    }
+}
+
+impl Encode for FrameBase {
+    fn encode(&self, w: &mut Vec<u8>) {
+        match self {
+            FrameBase::Cfa => 0u64.encode(w),
+            FrameBase::Register(r) => (1u64, u64::from(r.0)).encode(w),
+            FrameBase::RegisterOffset(r, n) => (2u64, (u64::from(r.0), *n as u64)).encode(w),
+            FrameBase::Other => 3u64.encode(w),
+        }
+    }
+}
+
+impl Decode for FrameBase {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        let register = |r: &mut Reader<'_>| {
+            u16::try_from(u64::decode(r)?)
+                .map(gimli::Register)
+                .map_err(|_| CacheError::Corrupt("bad register"))
+        };
+        Ok(match u64::decode(r)? {
+            0 => FrameBase::Cfa,
+            1 => FrameBase::Register(register(r)?),
+            2 => FrameBase::RegisterOffset(register(r)?, u64::decode(r)? as i64),
+            3 => FrameBase::Other,
+            _ => return Err(CacheError::Corrupt("bad frame base")),
+        })
+    }
+}
+
+impl Decode for VariantShape {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CacheError> {
+        Ok(match u64::decode(r)? {
+            0 => VariantShape::Zero,
+            1 => VariantShape::One(Decode::decode(r)?),
+            2 => VariantShape::Many {
+                member: Decode::decode(r)?,
+                variants: Decode::decode(r)?,
+            },
+            _ => return Err(CacheError::Corrupt("bad variant shape")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+
+    fn round_trip<T: Encode + Decode>(value: &T) -> (T, usize) {
+        let mut w = Vec::new();
+        value.encode(&mut w);
+        let r = &mut Reader { bytes: &w, names: Interner::new() };
+        let decoded = T::decode(r).unwrap();
+        assert!(r.bytes.is_empty(), "bytes left over");
+        (decoded, w.len())
+    }
+
+    #[test]
+    fn leb128() {
+        for (n, len) in [(0, 1), (1, 1), (127, 1), (128, 2), (300, 2), (1 << 35, 6), (u64::MAX, 10)] {
+            assert_eq!(round_trip(&n), (n, len), "encoding {n}");
+        }
+        assert_eq!(round_trip(&usize::MAX).0, usize::MAX);
+    }
+
+    #[test]
+    fn leb128_too_long() {
+        let bytes = [0x80; 10].iter().copied().chain([1]).collect::<Vec<_>>();
+        let r = &mut Reader { bytes: &bytes, names: Interner::new() };
+        assert!(matches!(u64::decode(r), Err(CacheError::Corrupt(_))));
+
+        let r = &mut Reader { bytes: &[0x80, 0x80], names: Interner::new() };
+        assert!(matches!(u64::decode(r), Err(CacheError::Truncated)));
+    }
+
+    #[test]
+    fn sections() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let mut w = Vec::new();
+        bytes[..].encode(&mut w);
+        assert_eq!(w.len(), bytes.len() + 2);
+        let r = &mut Reader { bytes: &w, names: Interner::new() };
+        let section = section_reader(r, gimli::RunTimeEndian::Big).unwrap();
+        assert_eq!(section.bytes(), &bytes[..]);
+        assert!(r.bytes.is_empty());
+    }
+
+    #[test]
+    fn values() {
+        let names = vec![Some("core::option::Option<u8>".to_string()), None, Some(String::new())];
+        assert_eq!(round_trip(&names).0, names);
+        let ranges = vec![0..0x1000, 0x8000_0000..u64::MAX];
+        assert_eq!(round_trip(&ranges).0, ranges);
+        let map = BTreeMap::from([(1u64, true), (u64::MAX, false)]);
+        assert_eq!(round_trip(&map).0, map);
+    }
+
+    fn identity() -> ProgramIdentity {
+        ProgramIdentity {
+            path: "target/firmware.elf".into(),
+            size: 123_456,
+            modified: Some(1_700_000_000_000_000_000),
+            build_id: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+        }
+    }
+
+    /// Builds a database without any types, but with a warning.
+    fn empty_db() -> DebugDb {
+        let mut types = TestTypes::little();
+        warn!(types.builder, "something odd at {:#x}", 0x100);
+        types.build()
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut w = Vec::new();
+        save(&empty_db(), &identity(), &mut w).unwrap();
+        let db = load(&w[..], &identity()).unwrap();
+        assert_eq!(db.pointer_size(), 4);
+        assert_eq!(db.endian(), gimli::RunTimeEndian::Little);
+        assert_eq!(db.warnings(), ["something odd at 0x100"]);
+
+        for i in 1..w.len() {
+            assert!(load(&w[..i], &identity()).is_err(), "loaded {i} bytes");
+        }
+    }
+
+    #[test]
+    fn rejects_other_programs() {
+        let mut w = Vec::new();
+        save(&empty_db(), &identity(), &mut w).unwrap();
+
+        let rebuilt = ProgramIdentity { build_id: Some(vec![0xca, 0xfe]), ..identity() };
+        let touched = ProgramIdentity { modified: Some(1), ..identity() };
+        let other = ProgramIdentity { path: "other.elf".into(), ..identity() };
+        for id in [rebuilt, touched, other] {
+            assert!(matches!(load(&w[..], &id), Err(CacheError::WrongProgram(p)) if p == "target/firmware.elf"));
+        }
+    }
+}
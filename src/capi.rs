@@ -0,0 +1,458 @@
+//! A flat C API, for embedding the database in C and C++ tools such as
+//! debugger plugins. The declarations are in `include/debugdb.h`.
+//!
+//! This is only built with the `capi` feature. To get a shared library, build
+//! with e.g. `cargo rustc --release --features capi --lib --crate-type cdylib`.
+//!
+//! Ownership follows a few rules:
+//!
+//! - A `DebugDb *` from `debugdb_open` is freed with `debugdb_close`.
+//! - Strings returned as `char *` belong to the caller, and are freed with
+//!   `debugdb_string_free`. Strings returned as `const char *` are borrowed,
+//!   and live as long as the object they came from (or forever, for kinds).
+//! - Functions that fail return null or `false`, and leave a description in
+//!   `debugdb_last_error`. That includes panics, which are caught rather than
+//!   unwound into C.
+//!
+//! Types are identified by a `uint64_t` handle: the offset of the type in
+//! `.debug_info`, or in `.debug_types` with the top bit set.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+use crate::{DebugDb, Type, TypeId, TypeKind};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: impl std::fmt::Display) {
+    let msg = CString::new(e.to_string().replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|l| *l.borrow_mut() = Some(msg));
+}
+
+/// Runs the body of entry point `name`, returning `fail` with an error if it
+/// panics: unwinding into C is undefined, and the parser can panic on debug
+/// info it doesn't understand.
+fn guard<T>(name: &str, fail: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|_| {
+        set_error(format_args!("{name} panicked"));
+        fail
+    })
+}
+
+const DEBUG_TYPES_BIT: u64 = 1 << 63;
+
+fn type_handle(id: TypeId) -> u64 {
+    match id.0 {
+        gimli::UnitSectionOffset::DebugInfoOffset(o) => o.0 as u64,
+        gimli::UnitSectionOffset::DebugTypesOffset(o) => o.0 as u64 | DEBUG_TYPES_BIT,
+    }
+}
+
+fn type_id(handle: u64) -> TypeId {
+    let offset = (handle & !DEBUG_TYPES_BIT) as usize;
+    TypeId(if handle & DEBUG_TYPES_BIT != 0 {
+        gimli::DebugTypesOffset(offset).into()
+    } else {
+        gimli::DebugInfoOffset(offset).into()
+    })
+}
+
+/// Borrows a C string argument, reporting an error if it's null or not
+/// UTF-8.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string that outlives `'a`.
+unsafe fn arg_str<'a>(s: *const c_char, what: &str) -> Option<&'a str> {
+    if s.is_null() {
+        set_error(format_args!("{what} is null"));
+        return None;
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_error(format_args!("{what} is not UTF-8"));
+            None
+        }
+    }
+}
+
+fn owned_string(s: &str) -> *mut c_char {
+    CString::new(s.replace('\0', " ")).unwrap().into_raw()
+}
+
+/// Gets the type for `handle`, reporting an error if there isn't one.
+fn lookup(db: &DebugDb, handle: u64) -> Option<&Type> {
+    let t = db.type_by_id(type_id(handle));
+    if t.is_none() {
+        set_error(format_args!("no type with handle {handle:#x}"));
+    }
+    t
+}
+
+/// Returns a description of the last failure on this thread, or null if
+/// nothing has failed. The string lives until the next failure on the same
+/// thread.
+#[no_mangle]
+pub extern "C" fn debugdb_last_error() -> *const c_char {
+    guard("debugdb_last_error", std::ptr::null(), || {
+        LAST_ERROR.with(|l| l.borrow().as_ref().map_or(std::ptr::null(), |s| s.as_ptr()))
+    })
+}
+
+/// Frees a string returned by one of these functions. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned as `char *` from this API, not
+/// already freed.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_string_free(s: *mut c_char) {
+    guard("debugdb_string_free", (), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+/// Loads the debug info from the program at `path`. Returns null on
+/// failure.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_open(path: *const c_char) -> *mut DebugDb {
+    guard("debugdb_open", std::ptr::null_mut(), || {
+        let Some(path) = arg_str(path, "path") else { return std::ptr::null_mut() };
+        let load = || -> Result<DebugDb, String> {
+            let data = std::fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+            crate::parse_bytes(&data).map_err(|e| format!("can't load {path}: {e}"))
+        };
+        match load() {
+            Ok(db) => Box::into_raw(Box::new(db)),
+            Err(e) => {
+                set_error(e);
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees a database returned by `debugdb_open`. Null is ignored.
+///
+/// # Safety
+///
+/// `db` must be null or a database from `debugdb_open`, not already closed.
+/// Nothing borrowed from it may be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_close(db: *mut DebugDb) {
+    guard("debugdb_close", (), || {
+        if !db.is_null() {
+            drop(Box::from_raw(db));
+        }
+    })
+}
+
+/// Finds a type by name, storing its handle in `out`. If several types have
+/// the name, the first is chosen.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`, `name` a NUL-terminated
+/// string, and `out` valid for writing.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_type_by_name(
+    db: *const DebugDb,
+    name: *const c_char,
+    out: *mut u64,
+) -> bool {
+    guard("debugdb_type_by_name", false, || {
+        let db = &*db;
+        let Some(name) = arg_str(name, "name") else { return false };
+        match db.types_by_name(name, crate::NameMatch::EXACT).next() {
+            Some((id, _)) => {
+                *out = type_handle(id);
+                true
+            }
+            None => {
+                set_error(format_args!("no type named {name}"));
+                false
+            }
+        }
+    })
+}
+
+/// Returns the name of a type, to be freed with `debugdb_string_free`, or
+/// null if it has none.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_type_name(db: *const DebugDb, ty: u64) -> *mut c_char {
+    guard("debugdb_type_name", std::ptr::null_mut(), || {
+        let db = &*db;
+        lookup(db, ty)
+            .and_then(|_| db.type_name(type_id(ty)))
+            .map_or(std::ptr::null_mut(), |n| owned_string(&n))
+    })
+}
+
+/// Returns the kind of a type, such as `struct` or `ptr`, or null if there's
+/// no such type. The string is static.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_type_kind(db: *const DebugDb, ty: u64) -> *const c_char {
+    guard("debugdb_type_kind", std::ptr::null(), || {
+        let Some(t) = lookup(&*db, ty) else { return std::ptr::null() };
+        kind_name(t.kind()).as_ptr()
+    })
+}
+
+/// Gets `TypeKind::name` as a C string that lives forever.
+fn kind_name(kind: TypeKind) -> &'static CStr {
+    static NAMES: OnceLock<Vec<CString>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        TypeKind::ALL.iter().map(|k| CString::new(k.name()).unwrap()).collect()
+    });
+    // `ALL` is in declaration order, so the discriminant indexes it.
+    &names[kind as usize]
+}
+
+/// Stores the size of a type in bytes in `out`. Fails for unsized types.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`, and `out` valid for
+/// writing.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_type_size(db: *const DebugDb, ty: u64, out: *mut u64) -> bool {
+    guard("debugdb_type_size", false, || {
+        let db = &*db;
+        let Some(t) = lookup(db, ty) else { return false };
+        match t.byte_size(db) {
+            Some(z) => {
+                *out = z;
+                true
+            }
+            None => {
+                set_error("type has no size");
+                false
+            }
+        }
+    })
+}
+
+/// Stores the alignment of a type in bytes in `out`. Fails if the debug info
+/// doesn't give it.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`, and `out` valid for
+/// writing.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_type_align(db: *const DebugDb, ty: u64, out: *mut u64) -> bool {
+    guard("debugdb_type_align", false, || {
+        let db = &*db;
+        let Some(t) = lookup(db, ty) else { return false };
+        match t.alignment(db) {
+            Some(a) => {
+                *out = a;
+                true
+            }
+            None => {
+                set_error("type has no alignment information");
+                false
+            }
+        }
+    })
+}
+
+/// Returns the number of members of a struct or union, or zero for other
+/// types.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_member_count(db: *const DebugDb, ty: u64) -> usize {
+    guard("debugdb_member_count", 0, || {
+        lookup(&*db, ty).map_or(0, |t| members(t).len())
+    })
+}
+
+/// Describes member `index` of a struct or union, in declaration order: its
+/// name (to be freed with `debugdb_string_free`, or null if it has none), its
+/// type's handle, and its offset in bytes. Any of the outputs may be null if
+/// they aren't wanted.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`, and each output null or
+/// valid for writing.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_member(
+    db: *const DebugDb,
+    ty: u64,
+    index: usize,
+    name_out: *mut *mut c_char,
+    type_out: *mut u64,
+    offset_out: *mut u64,
+) -> bool {
+    guard("debugdb_member", false, || {
+        let Some(t) = lookup(&*db, ty) else { return false };
+        let Some(m) = members(t).get(index) else {
+            set_error(format_args!("no member {index}"));
+            return false;
+        };
+        if !name_out.is_null() {
+            *name_out = m.name.as_deref().map_or(std::ptr::null_mut(), owned_string);
+        }
+        if !type_out.is_null() {
+            *type_out = type_handle(m.type_id);
+        }
+        if !offset_out.is_null() {
+            *offset_out = m.location;
+        }
+        true
+    })
+}
+
+fn members(t: &Type) -> &[crate::Member] {
+    match t {
+        Type::Struct(s) => &s.members,
+        Type::Union(u) => &u.members,
+        _ => &[],
+    }
+}
+
+/// One function executing at an address, as listed by `debugdb_addr2line`.
+/// The strings are borrowed from the `DebugdbFrames` and may be null.
+#[repr(C)]
+pub struct DebugdbFrame {
+    pub function: *const c_char,
+    pub file: *const c_char,
+    /// Line number, or zero if unknown.
+    pub line: u32,
+    /// Column number, or zero if unknown.
+    pub column: u32,
+}
+
+/// The frames at an address, which own the strings they point to.
+pub struct DebugdbFrames {
+    frames: Vec<DebugdbFrame>,
+    _strings: Vec<CString>,
+}
+
+/// Finds the functions executing at `address`, innermost (most recently
+/// inlined) first, with their source locations. The result is freed with
+/// `debugdb_frames_free`. An address with no debug info gives no frames.
+///
+/// # Safety
+///
+/// `db` must be a database from `debugdb_open`.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_addr2line(db: *const DebugDb, address: u64) -> *mut DebugdbFrames {
+    guard("debugdb_addr2line", std::ptr::null_mut(), || {
+        use fallible_iterator::FallibleIterator;
+
+        let ctx = crate::addr2line::Context::new(&*db);
+        let mut strings = vec![];
+        let mut intern = |s: Option<&str>| match s {
+            Some(s) => {
+                let s = CString::new(s.replace('\0', " ")).unwrap();
+                // The heap allocation doesn't move when the CString does.
+                let p = s.as_ptr();
+                strings.push(s);
+                p
+            }
+            None => std::ptr::null(),
+        };
+        let mut frames = vec![];
+        if let Ok(mut iter) = ctx.find_frames(address) {
+            while let Ok(Some(frame)) = iter.next() {
+                let function = frame.function.as_ref().and_then(|f| f.demangle().ok());
+                let location = frame.location.as_ref();
+                frames.push(DebugdbFrame {
+                    function: intern(function.as_deref()),
+                    file: intern(location.and_then(|l| l.file)),
+                    line: location.and_then(|l| l.line).unwrap_or(0),
+                    column: location.and_then(|l| l.column).unwrap_or(0),
+                });
+            }
+        }
+        Box::into_raw(Box::new(DebugdbFrames { frames, _strings: strings }))
+    })
+}
+
+/// Returns the number of frames in `frames`.
+///
+/// # Safety
+///
+/// `frames` must come from `debugdb_addr2line`, and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_frames_len(frames: *const DebugdbFrames) -> usize {
+    guard("debugdb_frames_len", 0, || {
+        let frames = &*frames;
+        frames.frames.len()
+    })
+}
+
+/// Returns frame `index`, or null if there aren't that many. It lives as long
+/// as `frames`.
+///
+/// # Safety
+///
+/// `frames` must come from `debugdb_addr2line`, and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_frames_get(
+    frames: *const DebugdbFrames,
+    index: usize,
+) -> *const DebugdbFrame {
+    guard("debugdb_frames_get", std::ptr::null(), || {
+        let frames = &*frames;
+        frames.frames.get(index).map_or(std::ptr::null(), |f| f as *const _)
+    })
+}
+
+/// Frees frames from `debugdb_addr2line`. Null is ignored.
+///
+/// # Safety
+///
+/// `frames` must be null or come from `debugdb_addr2line`, not already
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn debugdb_frames_free(frames: *mut DebugdbFrames) {
+    guard("debugdb_frames_free", (), || {
+        if !frames.is_null() {
+            drop(Box::from_raw(frames));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_names() {
+        for kind in TypeKind::ALL {
+            assert_eq!(kind_name(kind).to_str(), Ok(kind.name()));
+        }
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        assert_eq!(guard("debugdb_test", 0, || panic!("oops")), 0);
+        let error = unsafe { CStr::from_ptr(debugdb_last_error()) };
+        assert_eq!(error.to_str(), Ok("debugdb_test panicked"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Recognition of the types that rustc generates for closures.
+//!
+//! The variables a closure captures are stored in a struct named after the
+//! function the closure is written in, e.g. `app::main::{closure_env#0}`, with
+//! the closures in each function numbered from zero. The closure's code is a
+//! subprogram with the same path and number, e.g. `app::main::{closure#0}`,
+//! which is declared where the closure is written. Closures in generic
+//! functions have the function's generic arguments after the number.
+
+use crate::model::{DeclCoord, Struct, Type, TypeId};
+use crate::{DebugDb, ProgramId};
+
+/// A closure environment type, as found by `DebugDb::closure`.
+#[derive(Clone, Debug)]
+pub struct Closure<'a> {
+    /// ID of the struct type.
+    pub type_id: TypeId,
+    /// The struct type itself, whose members are the captured variables.
+    pub ty: &'a Struct,
+    /// Path of the function the closure is written in, e.g. `app::main`.
+    pub function: &'a str,
+    /// Number of the closure among those in `function`.
+    pub index: usize,
+    /// Generic arguments of the closure including angle brackets, or the
+    /// empty string if it isn't generic.
+    pub generic_args: &'a str,
+    /// The subprogram holding the closure's code, if any was generated.
+    pub body: Option<ProgramId>,
+    /// Where the closure is written in the source, if the debug info says.
+    pub decl: Option<&'a DeclCoord>,
+}
+
+impl std::fmt::Display for Closure<'_> {
+    /// Describes the closure like `closure #0 in app::main at src/main.rs:12`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "closure #{} in {}{}", self.index, self.function, self.generic_args)?;
+        if let Some(decl) = self.decl {
+            write!(f, " at {}", decl.file.as_deref().unwrap_or("???"))?;
+            if let Some(line) = decl.line {
+                write!(f, ":{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a closure environment type name into the path of the defining
+/// function, the closure's number, and any generic arguments.
+fn parse_closure_name(name: &str) -> Option<(&str, usize, &str)> {
+    // Generic arguments may hold other closures' names, so they're split off
+    // first.
+    let (base, generic_args) = match name.strip_suffix('>') {
+        Some(_) => {
+            let mut depth = 0;
+            let start = name.char_indices().rev().find_map(|(i, c)| {
+                match c {
+                    '>' => depth += 1,
+                    '<' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i);
+                        }
+                    }
+                    _ => (),
+                }
+                None
+            })?;
+            name.split_at(start)
+        }
+        None => (name, ""),
+    };
+    let (function, env) = base.rsplit_once("::{")?;
+    let index = env.strip_prefix("closure_env#")?.strip_suffix('}')?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((function, index.parse().ok()?, generic_args))
+}
+
+impl DebugDb {
+    /// Checks whether type `id` holds the captures of a closure and, if so,
+    /// finds the closure's code and where it's written.
+    pub fn closure(&self, id: TypeId) -> Option<Closure<'_>> {
+        let Type::Struct(ty) = self.type_by_id(id)? else {
+            return None;
+        };
+        let (function, index, generic_args) = parse_closure_name(&ty.name)?;
+
+        // Subprogram names don't include generic arguments, so for a closure
+        // in a generic function this finds one of its instances, which are
+        // all declared in the same place.
+        let body_name = format!("{function}::{{closure#{index}}}");
+        let body = self.subprograms_by_name(&body_name)
+            .find(|(_, p)| p.pc_ranges.iter().any(|r| r.start != 0 && r.end > r.start))
+            .or_else(|| self.subprograms_by_name(&body_name).next());
+        let decl = body.and_then(|(_, p)| {
+            let origin = p.abstract_origin
+                .and_then(|o| self.subprogram_by_id(o));
+            std::iter::once(p).chain(origin)
+                .map(|p| &p.decl_coord)
+                .find(|d| d.is_useful())
+        });
+
+        Some(Closure {
+            type_id: id,
+            ty,
+            function,
+            index,
+            generic_args,
+            body: body.map(|(pid, _)| pid),
+            decl,
+        })
+    }
+
+    /// Returns an iterator over the canonical closure environment types in
+    /// the program.
+    pub fn closures(&self) -> impl Iterator<Item = Closure<'_>> + '_ {
+        self.canonical_types()
+            .filter_map(move |(id, _)| self.closure(id))
+    }
+}
@@ -0,0 +1,361 @@
+//! Generation of Rust definitions that mirror the layout of types in the
+//! program, for reading its data somewhere else -- such as parsing telemetry
+//! blobs from firmware on a host machine.
+//!
+//! Structs and unions become `#[repr(C)]` types with explicit padding fields,
+//! falling back to `#[repr(C, packed)]` where the host's alignment rules
+//! would move a member. C-style enums become newtypes around an integer,
+//! since a blob may hold any value. Pointers become integers the size of a
+//! pointer on the target, as they can't be followed on the host. Types whose
+//! layout can't be expressed in Rust, like Rust enums, become opaque byte
+//! arrays of the right size.
+//!
+//! Every generated type is `Copy`, and is checked against the original size
+//! with a `const` assertion, so a mismatch fails to compile rather than
+//! silently misparsing.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::{DebugDb, Encoding, Type, TypeId};
+
+#[derive(Clone, Debug, Error)]
+pub enum CodegenError {
+    #[error("type {0:?} not found")]
+    NoSuchType(TypeId),
+    #[error("type {0} has no known size")]
+    Unsized(String),
+}
+
+impl DebugDb {
+    /// Generates Rust definitions for the types in `roots`, along with every
+    /// type they contain, as the text of a Rust module.
+    ///
+    /// Types are named after their last path component, made into a valid
+    /// identifier (`core::option::Option<u8>` becomes `Option_u8`), with a
+    /// number appended where that would clash.
+    pub fn rust_definitions(&self, roots: &[TypeId]) -> Result<String, CodegenError> {
+        let mut gen = Codegen {
+            db: self,
+            names: BTreeMap::new(),
+            used_names: BTreeSet::new(),
+            aligns: BTreeMap::new(),
+            queue: VecDeque::new(),
+            out: String::new(),
+        };
+        writeln!(gen.out, "#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]")
+            .unwrap();
+        writeln!(gen.out, "// Generated from debug info; layouts match a {}-bit {} target.",
+            self.pointer_size() * 8,
+            if self.endian() == gimli::RunTimeEndian::Little { "little-endian" } else { "big-endian" },
+        ).unwrap();
+        for &id in roots {
+            gen.name_of(id)?;
+        }
+        while let Some(id) = gen.queue.pop_front() {
+            gen.define(id)?;
+        }
+        Ok(gen.out)
+    }
+}
+
+struct Codegen<'a> {
+    db: &'a DebugDb,
+    /// Names given to types that get a definition of their own.
+    names: BTreeMap<TypeId, String>,
+    used_names: BTreeSet<String>,
+    /// Alignment of the generated type on the host, by original type.
+    aligns: BTreeMap<TypeId, u64>,
+    /// Types that have been named but not yet defined.
+    queue: VecDeque<TypeId>,
+    out: String,
+}
+
+impl<'a> Codegen<'a> {
+    fn ty(&self, id: TypeId) -> Result<&'a Type, CodegenError> {
+        self.db.type_by_id(id).ok_or(CodegenError::NoSuchType(id))
+    }
+
+    fn size(&self, id: TypeId) -> Result<u64, CodegenError> {
+        self.ty(id)?.byte_size(self.db).ok_or_else(|| {
+            CodegenError::Unsized(self.db.type_name(id).unwrap_or_default().into_owned())
+        })
+    }
+
+    /// Gives the Rust type to use for a member of type `id`.
+    fn type_expr(&mut self, id: TypeId) -> Result<String, CodegenError> {
+        Ok(match self.ty(id)? {
+            Type::Base(b) => base_type(b.encoding, b.byte_size),
+            Type::Pointer(_) => pointer_type(self.db),
+            Type::Qualified(q) => self.type_expr(q.type_id)?,
+            Type::Array(a) => {
+                format!("[{}; {}]", self.type_expr(a.element_type_id)?, a.count.unwrap_or(0))
+            }
+            _ => self.name_of(id)?,
+        })
+    }
+
+    /// Gets the name of the definition for `id`, queueing it to be defined
+    /// if this is the first time it's come up.
+    fn name_of(&mut self, id: TypeId) -> Result<String, CodegenError> {
+        if let Some(name) = self.names.get(&id) {
+            return Ok(name.clone());
+        }
+        let ty = self.ty(id)?;
+        let base = match self.db.type_name(id) {
+            Some(name) if !matches!(ty, Type::Pointer(_) | Type::Array(_)) => rust_ident(&name),
+            _ => String::new(),
+        };
+        let base = if base.is_empty() {
+            format!("Anon_{}", offset_hex(id))
+        } else {
+            base
+        };
+        let mut name = base.clone();
+        let mut n = 2;
+        while !self.used_names.insert(name.clone()) {
+            name = format!("{base}_{n}");
+            n += 1;
+        }
+        self.names.insert(id, name.clone());
+        self.queue.push_back(id);
+        Ok(name)
+    }
+
+    /// Gets the alignment of the generated type for `id` on the host.
+    fn host_align(&mut self, id: TypeId) -> Result<u64, CodegenError> {
+        if let Some(&a) = self.aligns.get(&id) {
+            return Ok(a);
+        }
+        let a = match self.ty(id)? {
+            Type::Base(b) => primitive_align(b.encoding, b.byte_size),
+            Type::Pointer(_) => self.db.pointer_size() as u64,
+            Type::CEnum(e) => e.byte_size.max(1),
+            Type::Qualified(q) => self.host_align(q.type_id)?,
+            Type::Typedef(t) => self.host_align(t.type_id)?,
+            Type::Array(a) => self.host_align(a.element_type_id)?,
+            Type::Struct(_) | Type::Union(_) => {
+                match self.member_layout(id)? {
+                    Some((members, packed)) if !packed => {
+                        let mut align = 1;
+                        for (_, ty, _) in members {
+                            align = align.max(self.host_align(ty)?);
+                        }
+                        align
+                    }
+                    _ => 1,
+                }
+            }
+            _ => 1,
+        };
+        self.aligns.insert(id, a);
+        Ok(a)
+    }
+
+    /// Lists the members of a struct or union as `(name, type, offset)`,
+    /// and whether the type needs to be packed to keep them in place.
+    /// Returns `None` if the members overlap, other than in a union, and so
+    /// can't be expressed as fields.
+    #[allow(clippy::type_complexity)]
+    fn member_layout(
+        &mut self,
+        id: TypeId,
+    ) -> Result<Option<(Vec<(String, TypeId, u64)>, bool)>, CodegenError> {
+        let is_union = matches!(self.ty(id)?, Type::Union(_));
+        let size = self.size(id)?;
+        let Some(layout) = self.db.layouts(id).into_iter().next() else {
+            return Ok(None);
+        };
+        let mut members = vec![];
+        let mut end = 0;
+        let mut max_align = 1;
+        let mut packed = false;
+        for (i, m) in layout.members.iter().enumerate() {
+            if !is_union && m.offset < end {
+                return Ok(None);
+            }
+            end = m.end();
+            let align = self.host_align(m.type_id)?;
+            max_align = max_align.max(align);
+            packed |= m.offset % align != 0;
+            let name = match &m.name {
+                Some(n) => field_ident(n),
+                None => format!("_unnamed{i}"),
+            };
+            members.push((name, m.type_id, m.offset));
+        }
+        packed |= size % max_align != 0;
+        Ok(Some((members, packed)))
+    }
+
+    fn define(&mut self, id: TypeId) -> Result<(), CodegenError> {
+        let name = self.names[&id].clone();
+        let original = self.db.type_name(id).unwrap_or_default().into_owned();
+        writeln!(self.out).unwrap();
+        match self.ty(id)? {
+            Type::Typedef(t) => {
+                let target = self.type_expr(t.type_id)?;
+                writeln!(self.out, "pub type {name} = {target};").unwrap();
+                return Ok(());
+            }
+            Type::CEnum(e) => {
+                let size = e.byte_size;
+                let repr = base_type(Encoding::Unsigned, size);
+                let mask = if size >= 8 { u64::MAX } else { (1 << (size * 8)) - 1 };
+                writeln!(self.out, "/// `{original}`, {size} bytes.").unwrap();
+                writeln!(self.out, "#[repr(transparent)]").unwrap();
+                writeln!(self.out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+                writeln!(self.out, "pub struct {name}(pub {repr});").unwrap();
+                writeln!(self.out).unwrap();
+                writeln!(self.out, "impl {name} {{").unwrap();
+                for en in e.enumerators.values() {
+                    writeln!(self.out, "    pub const {}: Self = Self({});",
+                        field_ident(&en.name), en.const_value & mask).unwrap();
+                }
+                writeln!(self.out, "}}").unwrap();
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let size = self.size(id)?;
+        let is_union = matches!(self.ty(id)?, Type::Union(_));
+        let layout = match self.ty(id)? {
+            Type::Struct(_) | Type::Union(_) => self.member_layout(id)?,
+            _ => None,
+        };
+        writeln!(self.out, "/// `{original}`, {size} bytes.").unwrap();
+        match layout {
+            Some((members, packed)) => {
+                let repr = if packed { "C, packed" } else { "C" };
+                let kind = if is_union { "union" } else { "struct" };
+                writeln!(self.out, "#[repr({repr})]").unwrap();
+                writeln!(self.out, "#[derive(Clone, Copy)]").unwrap();
+                writeln!(self.out, "pub {kind} {name} {{").unwrap();
+                let mut end = 0;
+                let mut pads = 0;
+                for (field, ty, offset) in members {
+                    if !is_union && offset > end {
+                        writeln!(self.out, "    pub _pad{pads}: [u8; {}],", offset - end).unwrap();
+                        pads += 1;
+                    }
+                    let expr = self.type_expr(ty)?;
+                    writeln!(self.out, "    pub {field}: {expr},").unwrap();
+                    end = offset + self.size(ty).unwrap_or(0);
+                }
+                if is_union {
+                    // Keeps the size right, and gives access to the raw
+                    // bytes.
+                    writeln!(self.out, "    pub _bytes: [u8; {size}],").unwrap();
+                } else if size > end {
+                    writeln!(self.out, "    pub _pad{pads}: [u8; {}],", size - end).unwrap();
+                }
+                writeln!(self.out, "}}").unwrap();
+            }
+            None => {
+                // Opaque: the bytes are all we can offer.
+                writeln!(self.out, "#[repr(C)]").unwrap();
+                writeln!(self.out, "#[derive(Clone, Copy)]").unwrap();
+                writeln!(self.out, "pub struct {name}(pub [u8; {size}]);").unwrap();
+            }
+        }
+        writeln!(self.out, "const _: () = assert!(core::mem::size_of::<{name}>() == {size});")
+            .unwrap();
+        Ok(())
+    }
+}
+
+/// Gives the Rust type for a base type. Booleans and `char` become integers,
+/// since not every bit pattern is valid for the Rust types.
+fn base_type(encoding: Encoding, size: u64) -> String {
+    match (encoding, size) {
+        (_, 0) => "()".into(),
+        (Encoding::Float, 4) => "f32".into(),
+        (Encoding::Float, 8) => "f64".into(),
+        (Encoding::Signed | Encoding::SignedChar, 1 | 2 | 4 | 8 | 16) => format!("i{}", size * 8),
+        (
+            Encoding::Unsigned | Encoding::UnsignedChar | Encoding::Boolean | Encoding::UtfChar,
+            1 | 2 | 4 | 8 | 16,
+        ) => format!("u{}", size * 8),
+        _ => format!("[u8; {size}]"),
+    }
+}
+
+fn primitive_align(encoding: Encoding, size: u64) -> u64 {
+    match base_type(encoding, size).as_str() {
+        "()" => 1,
+        s if s.starts_with('[') => 1,
+        _ => size,
+    }
+}
+
+fn pointer_type(db: &DebugDb) -> String {
+    format!("u{}", db.pointer_size() * 8)
+}
+
+fn offset_hex(id: TypeId) -> String {
+    match id.0 {
+        gimli::UnitSectionOffset::DebugInfoOffset(o) => format!("{:x}", o.0),
+        gimli::UnitSectionOffset::DebugTypesOffset(o) => format!("t{:x}", o.0),
+    }
+}
+
+/// Makes a type name into an identifier, dropping the module path.
+fn rust_ident(name: &str) -> String {
+    // The path ends at the last `::` outside of any generic arguments.
+    let mut depth = 0;
+    let mut start = 0;
+    let bytes = name.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' | b')' | b']' => depth -= 1,
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => start = i + 2,
+            _ => (),
+        }
+    }
+    let mut ident = String::new();
+    for c in name[start..].chars() {
+        if c.is_ascii_alphanumeric() {
+            ident.push(c);
+        } else if !ident.ends_with('_') && !ident.is_empty() {
+            ident.push('_');
+        }
+    }
+    let ident = ident.trim_end_matches('_').to_string();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) || is_reserved(&ident) {
+        format!("_{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Makes a member or enumerator name into an identifier.
+fn field_ident(name: &str) -> String {
+    let ident: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) || is_reserved(&ident) {
+        format!("_{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Checks for keywords, and the names of primitive types, which generated
+/// types mustn't shadow.
+fn is_reserved(ident: &str) -> bool {
+    matches!(ident,
+        "as" | "async" | "await" | "break" | "const" | "continue" | "crate" | "dyn"
+        | "else" | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in"
+        | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return"
+        | "self" | "Self" | "static" | "struct" | "super" | "trait" | "true" | "type"
+        | "union" | "unsafe" | "use" | "where" | "while" | "abstract" | "become" | "box"
+        | "do" | "final" | "gen" | "macro" | "override" | "priv" | "try" | "typeof"
+        | "unsized" | "virtual" | "yield"
+        | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+        | "i8" | "i16" | "i32" | "i64" | "i128" | "isize"
+        | "f32" | "f64" | "bool" | "char" | "str")
+}
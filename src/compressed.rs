@@ -0,0 +1,201 @@
+//! Reading compressed debug sections.
+//!
+//! Sections can be compressed in two ways: ELF's own `SHF_COMPRESSED`, where
+//! the section starts with a header giving the compression format, and the
+//! older GNU `.zdebug_*` sections, which are always zlib. `object` handles
+//! both as long as the format is zlib; Zstandard, which newer toolchains
+//! produce with `--compress-debug-sections=zstd`, is decompressed here.
+
+use object::{Object, ObjectSection, SectionFlags};
+use std::borrow::Cow;
+
+/// `ch_type` of a section compressed with Zstandard.
+const ELFCOMPRESS_ZSTD: u32 = 2;
+
+/// Gets the contents of `section`, decompressed if need be. Errors are
+/// described as text, for `FileError::Decompress`.
+pub(crate) fn section_data<'data>(
+    object: &object::File<'data>,
+    section: &object::Section<'data, '_>,
+) -> Result<Cow<'data, [u8]>, String> {
+    if let Some(data) = zstd_data(object, section)? {
+        return Ok(Cow::Owned(data));
+    }
+    section.uncompressed_data().map_err(|e| e.to_string())
+}
+
+/// Decompresses `section` if it's an ELF section compressed with Zstandard,
+/// returning `None` for any other section.
+fn zstd_data(
+    object: &object::File,
+    section: &object::Section,
+) -> Result<Option<Vec<u8>>, String> {
+    let SectionFlags::Elf { sh_flags } = section.flags() else {
+        return Ok(None);
+    };
+    if sh_flags & u64::from(object::elf::SHF_COMPRESSED) == 0 {
+        return Ok(None);
+    }
+    let data = section.data().map_err(|e| e.to_string())?;
+
+    // The header is an `Elf32_Chdr` or `Elf64_Chdr`: the type, then (after
+    // padding, on 64-bit targets) the uncompressed size and alignment.
+    let little = object.is_little_endian();
+    let read = |at: usize, len: usize| -> Result<u64, String> {
+        let bytes = data.get(at..at + len).ok_or("compression header is truncated")?;
+        let mut buf = [0; 8];
+        if little {
+            buf[..len].copy_from_slice(bytes);
+            Ok(u64::from_le_bytes(buf))
+        } else {
+            buf[8 - len..].copy_from_slice(bytes);
+            Ok(u64::from_be_bytes(buf))
+        }
+    };
+    let (size, header_len) = if object.is_64() {
+        (read(8, 8)?, 24)
+    } else {
+        (read(4, 4)?, 12)
+    };
+    if read(0, 4)? != u64::from(ELFCOMPRESS_ZSTD) {
+        return Ok(None);
+    }
+
+    let size = usize::try_from(size).map_err(|_| "section is too large")?;
+    let mut out = Vec::with_capacity(size);
+    ruzstd::decoding::FrameDecoder::new()
+        .decode_all_to_vec(&data[header_len..], &mut out)
+        .map_err(|e| e.to_string())?;
+    if out.len() != size {
+        return Err(format!("expected {size} bytes, got {}", out.len()));
+    }
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const ELFCOMPRESS_ZLIB: u32 = 1;
+    const SHT_PROGBITS: u32 = 1;
+    const SHT_STRTAB: u32 = 3;
+
+    const TEXT: &[u8] = b"core::option::Option<u8>\0alloc::vec::Vec<u8>\0\
+        core::option::Option<u8>\0alloc::vec::Vec<u8>\0";
+
+    /// Builds a little-endian 64-bit relocatable ELF file holding the given
+    /// `(name, flags, contents)` sections.
+    fn elf(sections: &[(&str, u64, Vec<u8>)]) -> Vec<u8> {
+        let mut shstrtab = b"\0.shstrtab\0".to_vec();
+        let mut headers = vec![[0u8; 64]];
+        let mut out = vec![0u8; 64];
+        let mut header = |name: usize, kind: u32, flags: u64, offset: usize, size: usize| {
+            let mut h = [0u8; 64];
+            h[0..4].copy_from_slice(&(name as u32).to_le_bytes());
+            h[4..8].copy_from_slice(&kind.to_le_bytes());
+            h[8..16].copy_from_slice(&flags.to_le_bytes());
+            h[24..32].copy_from_slice(&(offset as u64).to_le_bytes());
+            h[32..40].copy_from_slice(&(size as u64).to_le_bytes());
+            h[48..56].copy_from_slice(&1u64.to_le_bytes());
+            headers.push(h);
+        };
+        for (name, flags, contents) in sections {
+            header(shstrtab.len(), SHT_PROGBITS, *flags, out.len(), contents.len());
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+            out.extend_from_slice(contents);
+        }
+        header(1, SHT_STRTAB, 0, out.len(), shstrtab.len());
+        out.extend_from_slice(&shstrtab);
+        out.resize(out.len().next_multiple_of(8), 0);
+
+        let shoff = out.len() as u64;
+        for h in &headers {
+            out.extend_from_slice(h);
+        }
+        out[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+        out[16..18].copy_from_slice(&1u16.to_le_bytes()); // ET_REL
+        out[18..20].copy_from_slice(&62u16.to_le_bytes()); // EM_X86_64
+        out[20..24].copy_from_slice(&1u32.to_le_bytes());
+        out[40..48].copy_from_slice(&shoff.to_le_bytes());
+        out[52..54].copy_from_slice(&64u16.to_le_bytes());
+        out[58..60].copy_from_slice(&64u16.to_le_bytes());
+        out[60..62].copy_from_slice(&(headers.len() as u16).to_le_bytes());
+        out[62..64].copy_from_slice(&(headers.len() as u16 - 1).to_le_bytes());
+        out
+    }
+
+    /// Prefixes `data`, compressed as `ch_type`, with an `Elf64_Chdr`.
+    fn compressed(ch_type: u32, size: usize, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        out.extend_from_slice(&ch_type.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(size as u64).to_le_bytes());
+        out.extend_from_slice(&1u64.to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        compressed(ELFCOMPRESS_ZLIB, data.len(), &encoder.finish().unwrap())
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        let frame = ruzstd::encoding::compress_to_vec(
+            data,
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+        compressed(ELFCOMPRESS_ZSTD, data.len(), &frame)
+    }
+
+    fn load(data: &[u8], name: &str) -> Result<Vec<u8>, String> {
+        let object = object::File::parse(data).unwrap();
+        let section = object.section_by_name(name).unwrap();
+        section_data(&object, &section).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn compressed_sections() {
+        let flags = u64::from(object::elf::SHF_COMPRESSED);
+        let data = elf(&[
+            (".debug_str", flags, zlib(TEXT)),
+            (".debug_line_str", flags, zstd(TEXT)),
+            (".debug_abbrev", 0, TEXT.to_vec()),
+        ]);
+        assert_eq!(load(&data, ".debug_str").unwrap(), TEXT);
+        assert_eq!(load(&data, ".debug_line_str").unwrap(), TEXT);
+        assert_eq!(load(&data, ".debug_abbrev").unwrap(), TEXT);
+    }
+
+    #[test]
+    fn loads_through_gimli() {
+        let flags = u64::from(object::elf::SHF_COMPRESSED);
+        let data = elf(&[(".debug_str", flags, zstd(TEXT))]);
+        let object = object::File::parse(&data[..]).unwrap();
+        let load = crate::section_loader(&object, gimli::RunTimeEndian::Little, false);
+        let debug_str = load(gimli::SectionId::DebugStr).unwrap();
+        assert_eq!(&*debug_str, TEXT);
+    }
+
+    #[test]
+    fn wrong_size() {
+        let flags = u64::from(object::elf::SHF_COMPRESSED);
+        let mut section = zstd(TEXT);
+        section[8] += 1;
+        let data = elf(&[(".debug_str", flags, section)]);
+        assert!(load(&data, ".debug_str").is_err());
+    }
+
+    #[test]
+    fn corrupt_zstd() {
+        let flags = u64::from(object::elf::SHF_COMPRESSED);
+        let mut section = zstd(TEXT);
+        // Break the frame's magic number.
+        section[24] ^= 0xff;
+        let data = elf(&[(".debug_str", flags, section)]);
+        assert!(load(&data, ".debug_str").is_err());
+    }
+}
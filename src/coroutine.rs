@@ -0,0 +1,245 @@
+//! Recognition of the state machines that rustc generates for `async fn`s,
+//! `async` blocks and generators, which it collectively calls coroutines.
+//!
+//! A coroutine is compiled to an enum named after the function that defines
+//! it, e.g. `app::main::{async_fn_env#0}`, with one variant per state:
+//! `Unresumed` before the first poll, `Returned` and `Panicked` once it's
+//! finished, and `Suspend0`, `Suspend1` and so on for each point where it can
+//! be suspended, which for an `async fn` means each `.await`. Each variant
+//! holds the data that's live in that state: the captured arguments ("upvars"),
+//! which appear in every state, and any locals that are held across the
+//! suspend point. A suspended `async fn` also holds the future it is awaiting,
+//! in a member named `__awaitee`.
+//!
+//! rustc also records the source location of each suspend point, on the enum
+//! member for its variant, so a discriminator value read from memory can be
+//! traced back to the `.await` where the coroutine is waiting; see
+//! `Coroutine::await_point_for_discriminant`.
+
+use crate::model::{DeclCoord, Enum, LineNumberRow, Member, Type, TypeId, Variant};
+use crate::DebugDb;
+use std::ops::RangeInclusive;
+
+/// What sort of source construct a coroutine was generated from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CoroutineKind {
+    /// An `async fn`.
+    AsyncFn,
+    /// An `async` block.
+    AsyncBlock,
+    /// A generator or other coroutine.
+    Generator,
+}
+
+/// What a coroutine is doing in a particular state.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StateKind {
+    /// The coroutine has been created but not yet resumed (polled).
+    Unresumed,
+    /// The coroutine has completed.
+    Returned,
+    /// The coroutine panicked while it was being resumed.
+    Panicked,
+    /// The coroutine is suspended at the given suspend point, numbered from
+    /// zero in the order rustc assigned them.
+    Suspend(usize),
+    /// A state whose name wasn't recognized.
+    Unknown,
+}
+
+/// A coroutine state machine type, as found by `DebugDb::coroutine`.
+#[derive(Clone, Debug)]
+pub struct Coroutine<'a> {
+    /// ID of the enum type.
+    pub type_id: TypeId,
+    /// The enum type itself.
+    pub ty: &'a Enum,
+    pub kind: CoroutineKind,
+    /// Path of the function that defines the coroutine, e.g. `app::main`.
+    pub function: &'a str,
+    /// Generic arguments of the coroutine including angle brackets, or the
+    /// empty string if it isn't generic.
+    pub generic_args: &'a str,
+    /// Members that are present in every state: the arguments of an `async
+    /// fn`, or the captures of a block. These are taken from the `Unresumed`
+    /// state.
+    pub upvars: Vec<&'a Member>,
+    /// States of the coroutine, in the order they appear in the debug info.
+    pub states: Vec<CoroutineState<'a>>,
+}
+
+/// One state of a `Coroutine`.
+#[derive(Clone, Debug)]
+pub struct CoroutineState<'a> {
+    pub kind: StateKind,
+    /// Name of the state, e.g. `Suspend0`.
+    pub name: &'a str,
+    /// Values of the coroutine's discriminator that select this state.
+    pub discriminants: Vec<RangeInclusive<u128>>,
+    /// The enum variant holding this state's data.
+    pub variant: &'a Variant,
+    /// Members that are live only in this state, i.e. excluding the
+    /// coroutine's upvars.
+    pub locals: Vec<&'a Member>,
+}
+
+impl<'a> CoroutineState<'a> {
+    /// Finds the future being awaited in this state, if this is a suspend
+    /// point of an `async fn` or block.
+    pub fn awaitee(&self) -> Option<&'a Member> {
+        self.locals.iter()
+            .copied()
+            .find(|m| m.name.as_deref() == Some("__awaitee"))
+    }
+
+    /// Gives the source location of this state's suspend point, e.g. the
+    /// `.await` expression, if this is a suspend state and rustc recorded one.
+    pub fn await_point(&self) -> Option<&'a DeclCoord> {
+        let StateKind::Suspend(_) = self.kind else {
+            return None;
+        };
+        let coord = &self.variant.member.decl_coord;
+        coord.line.is_some().then_some(coord)
+    }
+}
+
+impl<'a> Coroutine<'a> {
+    /// Finds the state selected by discriminator value `disc`.
+    pub fn state_for_discriminant(&self, disc: u128) -> Option<&CoroutineState<'a>> {
+        self.states.iter()
+            .find(|s| s.discriminants.iter().any(|r| r.contains(&disc)))
+    }
+
+    /// Finds the source location where the coroutine is suspended when its
+    /// discriminator holds `disc`. Returns `None` if `disc` doesn't select a
+    /// suspend state, or the location wasn't recorded.
+    pub fn await_point_for_discriminant(&self, disc: u128) -> Option<&'a DeclCoord> {
+        self.state_for_discriminant(disc)?.await_point()
+    }
+
+    /// Finds the state for suspend point `n`.
+    pub fn suspend_state(&self, n: usize) -> Option<&CoroutineState<'a>> {
+        self.states.iter()
+            .find(|s| s.kind == StateKind::Suspend(n))
+    }
+}
+
+/// Splits a coroutine type name into the path of the defining function, the
+/// kind of coroutine, and any generic arguments.
+fn parse_coroutine_name(name: &str) -> Option<(&str, CoroutineKind, &str)> {
+    let (function, rest) = name.rsplit_once("::{")?;
+    let (env, generic_args) = rest.split_once('}')?;
+    if !(generic_args.is_empty() || generic_args.starts_with('<')) {
+        return None;
+    }
+    let (env, index) = env.split_once('#')?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let kind = match env {
+        "async_fn_env" => CoroutineKind::AsyncFn,
+        "async_block_env" => CoroutineKind::AsyncBlock,
+        "coroutine_env" | "generator_env" => CoroutineKind::Generator,
+        _ => return None,
+    };
+    Some((function, kind, generic_args))
+}
+
+fn parse_state_name(name: &str) -> StateKind {
+    match name {
+        "Unresumed" => StateKind::Unresumed,
+        "Returned" => StateKind::Returned,
+        "Panicked" => StateKind::Panicked,
+        _ => name.strip_prefix("Suspend")
+            .and_then(|n| n.parse().ok())
+            .map(StateKind::Suspend)
+            .unwrap_or(StateKind::Unknown),
+    }
+}
+
+impl DebugDb {
+    /// Checks whether type `id` is a coroutine state machine and, if so,
+    /// works out its states.
+    pub fn coroutine(&self, id: TypeId) -> Option<Coroutine<'_>> {
+        let Type::Enum(ty) = self.type_by_id(id)? else {
+            return None;
+        };
+        let (function, kind, generic_args) = parse_coroutine_name(&ty.name)?;
+
+        // Group the discriminator ranges by variant, keeping variant order.
+        let ranges = ty.discriminant_ranges(self);
+        let mut states = ty.shape.variants()
+            .filter_map(|v| {
+                let Some(Type::Struct(s)) = self.type_by_id(v.member.type_id) else {
+                    return None;
+                };
+                let name = s.name.rsplit("::").next().unwrap_or(&s.name);
+                let discriminants = ranges.iter()
+                    .filter(|(_, rv)| std::ptr::eq(*rv, v))
+                    .map(|(r, _)| r.clone())
+                    .collect();
+                Some((parse_state_name(name), name, discriminants, v, s))
+            })
+            .collect::<Vec<_>>();
+
+        let upvars = states.iter()
+            .find(|(kind, ..)| *kind == StateKind::Unresumed)
+            .map(|(.., s)| s.members.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let is_upvar = |m: &Member| upvars.iter()
+            .any(|u| u.name == m.name && u.location == m.location);
+
+        let states = states.drain(..)
+            .map(|(kind, name, discriminants, variant, s)| CoroutineState {
+                kind,
+                name,
+                discriminants,
+                variant,
+                locals: s.members.iter().filter(|m| !is_upvar(m)).collect(),
+            })
+            .collect();
+
+        Some(Coroutine {
+            type_id: id,
+            ty,
+            kind,
+            function,
+            generic_args,
+            upvars,
+            states,
+        })
+    }
+
+    /// Finds the code generated for the suspend point of `state`, using the
+    /// line number table. This is where the coroutine yields, and where it
+    /// resumes when next polled. Only code within the coroutine's own
+    /// function (or functions, if it's generic) is included.
+    pub fn await_point_code<'d>(
+        &'d self,
+        coroutine: &Coroutine<'d>,
+        state: &CoroutineState<'d>,
+    ) -> Vec<&'d LineNumberRow> {
+        let Some(coord) = state.await_point() else {
+            return vec![];
+        };
+        let (Some(file), Some(line)) = (&coord.file, coord.line) else {
+            return vec![];
+        };
+        // The poll function of a coroutine is a closure-like subprogram nested
+        // inside the defining function, e.g. `app::main::{async_fn#0}`.
+        let prefix = format!("{}::{{", coroutine.function);
+        self.lookup_addrs_for_line(file, line.get())
+            .filter(|row| {
+                self.subprogram_for_pc(row.pc_range.start)
+                    .and_then(|(_, p)| p.demangled_name().or_else(|| p.name.clone()))
+                    .is_none_or(|n| n.starts_with(&prefix))
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the canonical coroutine types in the program.
+    pub fn coroutines(&self) -> impl Iterator<Item = Coroutine<'_>> + '_ {
+        self.canonical_types()
+            .filter_map(move |(id, _)| self.coroutine(id))
+    }
+}
@@ -0,0 +1,778 @@
+//! Decoding of log frames from the `defmt` framework, and matching of the
+//! types they log with their descriptions in the debug info.
+//!
+//! `defmt` doesn't send format strings to the host. Instead it interns them
+//! as symbols in the program's `.defmt` section, each named with a JSON
+//! object describing the string, and logs only the symbol's address as an
+//! index. Types that derive `Format` get an interned string of their own,
+//! like `Point {{ x: {=i32}, y: {=i32} }}`, which `DebugDb::defmt_types`
+//! matches with the type's DWARF.
+//!
+//! This implements version 4 of the wire format, used by `defmt` 0.3. Frames
+//! are expected to be whole, and already taken out of whatever framing the
+//! transport adds (such as rzCOBS). Bitfield parameters aren't supported.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use object::{Object, ObjectSection, ObjectSymbol};
+use thiserror::Error;
+
+use crate::{DebugDb, Type, TypeId};
+
+#[derive(Clone, Debug, Error)]
+pub enum DefmtError {
+    #[error("symbol in .defmt isn't a defmt format string: {0:?}")]
+    BadSymbol(String),
+    #[error("no format string with index {0}")]
+    UnknownIndex(u16),
+    #[error("frame ended unexpectedly")]
+    Truncated,
+    #[error("can't parse format string {0:?}")]
+    BadFormat(String),
+    #[error("parameter type {0:?} is not supported")]
+    Unsupported(String),
+    #[error("string argument is not UTF-8")]
+    Utf8,
+    #[error("{0:#x} is not a valid char")]
+    BadChar(u32),
+    #[error("variant {0} is out of range for {1:?}")]
+    BadVariant(u32, String),
+    #[error("object file could not be read")]
+    Obj(#[from] object::Error),
+}
+
+/// What an interned string is used for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tag {
+    Println,
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    /// The format of a type that derives `Format`.
+    Derived,
+    /// The format of a primitive type's `Format` impl.
+    Prim,
+    /// A string written by `defmt::write!`.
+    Write,
+    /// A string interned with `intern!`, logged as an `{=istr}` argument.
+    Str,
+    /// The format of the timestamp sent with every log frame.
+    Timestamp,
+    Other(String),
+}
+
+impl Tag {
+    fn parse(tag: &str) -> Self {
+        match tag {
+            "defmt_println" => Self::Println,
+            "defmt_trace" => Self::Trace,
+            "defmt_debug" => Self::Debug,
+            "defmt_info" => Self::Info,
+            "defmt_warn" => Self::Warn,
+            "defmt_error" => Self::Error,
+            "defmt_derived" => Self::Derived,
+            "defmt_prim" => Self::Prim,
+            "defmt_write" => Self::Write,
+            "defmt_str" => Self::Str,
+            "defmt_timestamp" => Self::Timestamp,
+            _ => Self::Other(tag.to_string()),
+        }
+    }
+
+    /// Returns the log level, for tags that have one.
+    pub fn level(&self) -> Option<&'static str> {
+        match self {
+            Self::Trace => Some("TRACE"),
+            Self::Debug => Some("DEBUG"),
+            Self::Info => Some("INFO"),
+            Self::Warn => Some("WARN"),
+            Self::Error => Some("ERROR"),
+            _ => None,
+        }
+    }
+}
+
+/// An interned string from the `.defmt` section.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub tag: Tag,
+    /// The string, which is a format string for most tags.
+    pub format: String,
+    /// Crate that interned the string, if recorded.
+    pub crate_name: Option<String>,
+}
+
+/// The interned strings of a program, by index.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    entries: BTreeMap<u16, Entry>,
+    timestamp: Option<Entry>,
+}
+
+impl Table {
+    /// Reads the interned strings from the `.defmt` section of `object`.
+    /// Returns `None` if the program doesn't use `defmt`.
+    pub fn parse(object: &object::File) -> Result<Option<Self>, DefmtError> {
+        let Some(section) = object.section_by_name(".defmt") else { return Ok(None) };
+        let mut table = Self::default();
+        for sym in object.symbols() {
+            if sym.section_index() != Some(section.index()) {
+                continue;
+            }
+            let name = sym.name()?;
+            // The section also holds markers such as the version symbol.
+            if !name.starts_with('{') {
+                continue;
+            }
+            let fields = parse_json_object(name)
+                .ok_or_else(|| DefmtError::BadSymbol(name.to_string()))?;
+            let field = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+            let (Some(tag), Some(format)) = (field("tag"), field("data")) else {
+                return Err(DefmtError::BadSymbol(name.to_string()));
+            };
+            let entry = Entry {
+                tag: Tag::parse(&tag),
+                format,
+                crate_name: field("crate_name"),
+            };
+            if entry.tag == Tag::Timestamp {
+                table.timestamp = Some(entry);
+            } else {
+                let index = u16::try_from(sym.address())
+                    .map_err(|_| DefmtError::BadSymbol(name.to_string()))?;
+                table.entries.insert(index, entry);
+            }
+        }
+        Ok(Some(table))
+    }
+
+    /// Gets the interned string with index `index`.
+    pub fn get(&self, index: u16) -> Option<&Entry> {
+        self.entries.get(&index)
+    }
+
+    /// Returns an iterator over the interned strings, in index order.
+    pub fn entries(&self) -> impl Iterator<Item = (u16, &Entry)> + '_ {
+        self.entries.iter().map(|(&i, e)| (i, e))
+    }
+
+    /// Decodes the log frame at the start of `bytes`, returning it and the
+    /// number of bytes it took up.
+    pub fn decode<'t>(&'t self, bytes: &[u8]) -> Result<(Frame<'t>, usize), DefmtError> {
+        let mut d = Decoder { table: self, bytes, pos: 0 };
+        let index = d.u16()?;
+        let entry = self.get(index).ok_or(DefmtError::UnknownIndex(index))?;
+        let timestamp = self.timestamp.as_ref()
+            .map(|ts| Ok::<_, DefmtError>((ts.format.as_str(), d.args(&ts.format)?)))
+            .transpose()?;
+        let args = d.args(&entry.format)?;
+        Ok((Frame { index, entry, timestamp, args }, d.pos))
+    }
+}
+
+/// A decoded log frame.
+#[derive(Clone, Debug)]
+pub struct Frame<'t> {
+    pub index: u16,
+    pub entry: &'t Entry,
+    /// The timestamp's format string and arguments, if the program sends
+    /// timestamps.
+    pub timestamp: Option<(&'t str, Args<'t>)>,
+    pub args: Args<'t>,
+}
+
+impl Frame<'_> {
+    /// Returns the message with its arguments filled in. If `db` is given,
+    /// values of types that derive `Format` are labeled with the full name of
+    /// the type they were matched with, where there's a single match.
+    pub fn message(&self, db: Option<&DebugDb>) -> String {
+        let mut out = String::new();
+        render(&mut out, &self.entry.format, &self.args, db).unwrap();
+        out
+    }
+
+    /// Returns the timestamp, formatted, if the program sends timestamps.
+    pub fn timestamp_text(&self) -> Option<String> {
+        let (format, args) = self.timestamp.as_ref()?;
+        let mut out = String::new();
+        render(&mut out, format, args, None).unwrap();
+        Some(out)
+    }
+
+    /// Lists the values of types that derive `Format` logged in the frame,
+    /// including those nested in other values, depth first.
+    pub fn formatted_values(&self) -> Vec<&Formatted<'_>> {
+        fn walk<'a, 't>(args: &'a Args<'t>, out: &mut Vec<&'a Formatted<'t>>) {
+            for arg in args.values.values() {
+                match arg {
+                    Arg::Format(f) => {
+                        out.push(f);
+                        walk(&f.args, out);
+                    }
+                    Arg::FormatSlice(fs) => for f in fs {
+                        out.push(f);
+                        walk(&f.args, out);
+                    },
+                    _ => (),
+                }
+            }
+        }
+        let mut out = vec![];
+        walk(&self.args, &mut out);
+        out
+    }
+}
+
+impl fmt::Display for Frame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ts) = self.timestamp_text() {
+            write!(f, "{ts} ")?;
+        }
+        if let Some(level) = self.entry.tag.level() {
+            write!(f, "{level} ")?;
+        }
+        render(f, &self.entry.format, &self.args, None)
+    }
+}
+
+/// The arguments for a format string, by parameter position.
+#[derive(Clone, Debug, Default)]
+pub struct Args<'t> {
+    pub values: BTreeMap<usize, Arg<'t>>,
+}
+
+/// A decoded argument.
+#[derive(Clone, Debug)]
+pub enum Arg<'t> {
+    Bool(bool),
+    Uint(u128),
+    Int(i128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    /// An interned string.
+    IStr(&'t str),
+    Bytes(Vec<u8>),
+    /// A value of a type with its own `Format` impl.
+    Format(Formatted<'t>),
+    /// A slice or array of values with `Format` impls.
+    FormatSlice(Vec<Formatted<'t>>),
+}
+
+/// A value formatted by its type's `Format` impl.
+#[derive(Clone, Debug)]
+pub struct Formatted<'t> {
+    /// The interned format of the type.
+    pub entry: &'t Entry,
+    /// The format of the value. For enums, this is the part of the entry's
+    /// format for the value's variant.
+    pub format: &'t str,
+    pub args: Args<'t>,
+}
+
+/// The type of a format string parameter, from the part after `=`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ParamType {
+    Bool,
+    Uint(usize),
+    Int(usize),
+    F32,
+    F64,
+    Char,
+    /// `str`, and also the internal types that `Debug2Format` and
+    /// `Display2Format` send as strings.
+    Str,
+    IStr,
+    U8Slice,
+    U8Array(usize),
+    /// `?`, also written as a parameter with no type.
+    Format,
+    FormatSlice,
+    FormatArray(usize),
+}
+
+impl ParamType {
+    fn parse(ty: &str) -> Result<Self, DefmtError> {
+        let unsupported = || DefmtError::Unsupported(ty.to_string());
+        Ok(match ty {
+            "bool" => Self::Bool,
+            "u8" => Self::Uint(1),
+            "u16" => Self::Uint(2),
+            "u32" | "usize" => Self::Uint(4),
+            "u64" => Self::Uint(8),
+            "u128" => Self::Uint(16),
+            "i8" => Self::Int(1),
+            "i16" => Self::Int(2),
+            "i32" | "isize" => Self::Int(4),
+            "i64" => Self::Int(8),
+            "i128" => Self::Int(16),
+            "f32" => Self::F32,
+            "f64" => Self::F64,
+            "char" => Self::Char,
+            "str" | "__internal_Debug" | "__internal_Display" => Self::Str,
+            "istr" => Self::IStr,
+            "[u8]" => Self::U8Slice,
+            "" | "?" => Self::Format,
+            "[?]" => Self::FormatSlice,
+            _ => {
+                let inner = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+                    .ok_or_else(unsupported)?;
+                let (elt, len) = inner.split_once(';').ok_or_else(unsupported)?;
+                let len = len.trim().parse().map_err(|_| unsupported())?;
+                match elt.trim() {
+                    "u8" => Self::U8Array(len),
+                    "?" => Self::FormatArray(len),
+                    _ => return Err(unsupported()),
+                }
+            }
+        })
+    }
+}
+
+/// A piece of a format string.
+#[derive(Clone, Debug)]
+enum Piece<'f> {
+    Literal(String),
+    Param {
+        position: usize,
+        ty: ParamType,
+        /// Display hint, the part after `:`.
+        hint: Option<&'f str>,
+    },
+}
+
+fn parse_format(format: &str) -> Result<Vec<Piece<'_>>, DefmtError> {
+    let bad = || DefmtError::BadFormat(format.to_string());
+    let mut pieces = vec![];
+    let mut literal = String::new();
+    let mut next_position = 0;
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if let Some(r) = rest.strip_prefix("{{") {
+            literal.push('{');
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("}}") {
+            literal.push('}');
+            rest = r;
+        } else if c == '{' {
+            let end = rest.find('}').ok_or_else(bad)?;
+            let param = &rest[1..end];
+            rest = &rest[end + 1..];
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+
+            // `{POSITION=TYPE:HINT}`, where every part is optional.
+            let (param, hint) = match param.split_once(':') {
+                Some((p, h)) => (p, Some(h)),
+                None => (param, None),
+            };
+            let (position, ty) = match param.split_once('=') {
+                Some((p, t)) => (p, t),
+                None => (param, ""),
+            };
+            let position = if position.is_empty() {
+                next_position
+            } else {
+                position.parse().map_err(|_| bad())?
+            };
+            next_position = position + 1;
+            pieces.push(Piece::Param { position, ty: ParamType::parse(ty)?, hint });
+        } else {
+            literal.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Picks the part of an enum's format for variant `n`. Enum formats list the
+/// formats of each variant, separated by `|`.
+fn variant_format(format: &str, n: u32) -> Result<&str, DefmtError> {
+    format.split('|').nth(n as usize)
+        .ok_or_else(|| DefmtError::BadVariant(n, format.to_string()))
+}
+
+struct Decoder<'t, 'b> {
+    table: &'t Table,
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'t> Decoder<'t, '_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], DefmtError> {
+        let end = self.pos.checked_add(n).ok_or(DefmtError::Truncated)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or(DefmtError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, n: usize) -> Result<u128, DefmtError> {
+        let mut buf = [0; 16];
+        buf[..n].copy_from_slice(self.take(n)?);
+        Ok(u128::from_le_bytes(buf))
+    }
+
+    fn u16(&mut self) -> Result<u16, DefmtError> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32, DefmtError> {
+        Ok(self.uint(4)? as u32)
+    }
+
+    /// Decodes the arguments for `format`. Each position is sent once, in
+    /// order, no matter how many times it appears in the format.
+    fn args(&mut self, format: &str) -> Result<Args<'t>, DefmtError> {
+        let mut types = BTreeMap::new();
+        for piece in parse_format(format)? {
+            if let Piece::Param { position, ty, .. } = piece {
+                types.entry(position).or_insert(ty);
+            }
+        }
+        let mut args = Args::default();
+        for (position, ty) in types {
+            let arg = self.arg(&ty)?;
+            args.values.insert(position, arg);
+        }
+        Ok(args)
+    }
+
+    fn arg(&mut self, ty: &ParamType) -> Result<Arg<'t>, DefmtError> {
+        Ok(match *ty {
+            ParamType::Bool => Arg::Bool(self.uint(1)? != 0),
+            ParamType::Uint(n) => Arg::Uint(self.uint(n)?),
+            ParamType::Int(n) => {
+                // Sign-extend from the top bit of the value.
+                let shift = 128 - n * 8;
+                Arg::Int(((self.uint(n)? << shift) as i128) >> shift)
+            }
+            ParamType::F32 => Arg::F32(f32::from_bits(self.u32()?)),
+            ParamType::F64 => Arg::F64(f64::from_bits(self.uint(8)? as u64)),
+            ParamType::Char => {
+                let c = self.u32()?;
+                Arg::Char(char::from_u32(c).ok_or(DefmtError::BadChar(c))?)
+            }
+            ParamType::Str => {
+                let len = self.u32()? as usize;
+                let s = std::str::from_utf8(self.take(len)?).map_err(|_| DefmtError::Utf8)?;
+                Arg::Str(s.to_string())
+            }
+            ParamType::IStr => {
+                let index = self.u16()?;
+                Arg::IStr(&self.entry(index)?.format)
+            }
+            ParamType::U8Slice => {
+                let len = self.u32()? as usize;
+                Arg::Bytes(self.take(len)?.to_vec())
+            }
+            ParamType::U8Array(len) => Arg::Bytes(self.take(len)?.to_vec()),
+            ParamType::Format => {
+                let entry = self.entry_from_frame()?;
+                Arg::Format(self.formatted(entry)?)
+            }
+            ParamType::FormatSlice => {
+                let len = self.u32()? as usize;
+                Arg::FormatSlice(self.formatted_slice(len)?)
+            }
+            ParamType::FormatArray(len) => Arg::FormatSlice(self.formatted_slice(len)?),
+        })
+    }
+
+    fn entry(&self, index: u16) -> Result<&'t Entry, DefmtError> {
+        self.table.get(index).ok_or(DefmtError::UnknownIndex(index))
+    }
+
+    fn entry_from_frame(&mut self) -> Result<&'t Entry, DefmtError> {
+        let index = self.u16()?;
+        self.entry(index)
+    }
+
+    /// Decodes a value formatted with `entry`, starting with the variant
+    /// number if it's an enum with several variants.
+    fn formatted(&mut self, entry: &'t Entry) -> Result<Formatted<'t>, DefmtError> {
+        let format = if entry.format.contains('|') {
+            let variants = entry.format.split('|').count();
+            // The variant number is as small as it can be.
+            let n = if variants <= 0xff {
+                self.uint(1)? as u32
+            } else if variants <= 0xffff {
+                self.u16()?.into()
+            } else {
+                self.u32()?
+            };
+            variant_format(&entry.format, n)?
+        } else {
+            &entry.format
+        };
+        let args = self.args(format)?;
+        Ok(Formatted { entry, format, args })
+    }
+
+    /// Decodes `len` values of one type, whose format is only sent once.
+    fn formatted_slice(&mut self, len: usize) -> Result<Vec<Formatted<'t>>, DefmtError> {
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        let entry = self.entry_from_frame()?;
+        (0..len).map(|_| self.formatted(entry)).collect()
+    }
+}
+
+fn render(
+    out: &mut impl Write,
+    format: &str,
+    args: &Args<'_>,
+    db: Option<&DebugDb>,
+) -> fmt::Result {
+    let Ok(pieces) = parse_format(format) else {
+        return out.write_str(format);
+    };
+    for piece in pieces {
+        match piece {
+            Piece::Literal(s) => out.write_str(&s)?,
+            Piece::Param { position, hint, .. } => match args.values.get(&position) {
+                Some(arg) => render_arg(out, arg, hint, db)?,
+                None => out.write_str("?")?,
+            },
+        }
+    }
+    Ok(())
+}
+
+fn render_arg(
+    out: &mut impl Write,
+    arg: &Arg<'_>,
+    hint: Option<&str>,
+    db: Option<&DebugDb>,
+) -> fmt::Result {
+    match arg {
+        Arg::Bool(b) => write!(out, "{b}"),
+        Arg::Uint(n) => render_int(out, *n, hint, || n.to_string()),
+        Arg::Int(n) => render_int(out, *n as u128, hint, || n.to_string()),
+        Arg::F32(x) => write!(out, "{x}"),
+        Arg::F64(x) => write!(out, "{x}"),
+        Arg::Char(c) if hint == Some("?") => write!(out, "{c:?}"),
+        Arg::Char(c) => write!(out, "{c}"),
+        Arg::Str(s) if hint == Some("?") => write!(out, "{s:?}"),
+        Arg::Str(s) => out.write_str(s),
+        Arg::IStr(s) => out.write_str(s),
+        Arg::Bytes(b) if hint == Some("a") => {
+            write!(out, "b\"")?;
+            for c in b.iter().flat_map(|&c| std::ascii::escape_default(c)) {
+                out.write_char(c as char)?;
+            }
+            write!(out, "\"")
+        }
+        Arg::Bytes(b) => {
+            out.write_char('[')?;
+            for (i, &byte) in b.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                render_int(out, byte.into(), hint, || byte.to_string())?;
+            }
+            out.write_char(']')
+        }
+        Arg::Format(f) => render_formatted(out, f, db),
+        Arg::FormatSlice(fs) => {
+            out.write_char('[')?;
+            for (i, f) in fs.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                render_formatted(out, f, db)?;
+            }
+            out.write_char(']')
+        }
+    }
+}
+
+fn render_int(
+    out: &mut impl Write,
+    bits: u128,
+    hint: Option<&str>,
+    decimal: impl FnOnce() -> String,
+) -> fmt::Result {
+    match hint {
+        Some("x") => write!(out, "{bits:x}"),
+        Some("#x") => write!(out, "{bits:#x}"),
+        Some("X") => write!(out, "{bits:X}"),
+        Some("#X") => write!(out, "{bits:#X}"),
+        Some("b") => write!(out, "{bits:b}"),
+        Some("#b") => write!(out, "{bits:#b}"),
+        Some("o") => write!(out, "{bits:o}"),
+        Some("#o") => write!(out, "{bits:#o}"),
+        _ => out.write_str(&decimal()),
+    }
+}
+
+fn render_formatted(out: &mut impl Write, f: &Formatted<'_>, db: Option<&DebugDb>) -> fmt::Result {
+    // Label the value with the full name of its type: derived formats start
+    // with the short name of a struct, or the name of an enum's variant.
+    let ty = db.filter(|_| f.entry.tag == Tag::Derived)
+        .and_then(|db| match &db.defmt_types(f.entry)[..] {
+            &[id] => Some((db.type_by_id(id)?, db.type_name(id)?)),
+            _ => None,
+        });
+    match ty {
+        Some((Type::Enum(_) | Type::CEnum(_), name)) => {
+            write!(out, "{name}::")?;
+            render(out, f.format, &f.args, db)
+        }
+        Some((_, name)) => {
+            out.write_str(&name)?;
+            render(out, &f.format[derived_name(f.format).len()..], &f.args, db)
+        }
+        None => render(out, f.format, &f.args, db),
+    }
+}
+
+/// Gets the name at the start of a derived format, which is that of the type
+/// for structs and of the variant for enums.
+fn derived_name(format: &str) -> &str {
+    let end = format.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(format.len());
+    &format[..end]
+}
+
+/// Gets the field names of a struct or variant from its derived format, with
+/// tuple fields numbered as the compiler names them (`__0`, `__1`, ...).
+fn derived_fields(format: &str) -> Vec<String> {
+    let body = &format[derived_name(format).len()..];
+    let Ok(pieces) = parse_format(body) else { return vec![] };
+    if body.starts_with(" {{") {
+        // `Name {{ a: {=u8}, b: {=?} }}`: each field name is at the end of
+        // the literal before its parameter.
+        pieces.iter()
+            .filter_map(|p| match p {
+                Piece::Literal(s) => s.strip_suffix(": ")
+                    .map(|s| s.rsplit([' ', ',']).next().unwrap_or(s)),
+                _ => None,
+            })
+            .map(str::to_string)
+            .collect()
+    } else {
+        pieces.iter()
+            .filter(|p| matches!(p, Piece::Param { .. }))
+            .enumerate()
+            .map(|(i, _)| format!("__{i}"))
+            .collect()
+    }
+}
+
+impl DebugDb {
+    /// Finds the types that a derived `Format` impl could belong to, by
+    /// matching the names in its format with those of the type and its
+    /// fields (or variants, for enums).
+    ///
+    /// The format doesn't say what module the type is in, so several types
+    /// can match, such as a generic type's instances.
+    pub fn defmt_types(&self, entry: &Entry) -> Vec<TypeId> {
+        if entry.tag != Tag::Derived {
+            return vec![];
+        }
+        let format = &entry.format;
+        if format.contains('|') {
+            // Enum formats don't include the enum's name, only its variants'.
+            let variants = format.split('|').map(derived_name).collect::<Vec<_>>();
+            self.canonical_types()
+                .filter(|(_, ty)| match ty {
+                    Type::Enum(e) => e.shape.variants()
+                        .map(|v| v.member.name.as_deref().unwrap_or(""))
+                        .eq(variants.iter().copied()),
+                    Type::CEnum(e) => e.enumerators.values()
+                        .map(|e| &*e.name)
+                        .eq(variants.iter().copied()),
+                    _ => false,
+                })
+                .map(|(id, _)| id)
+                .collect()
+        } else {
+            let name = derived_name(format);
+            let fields = derived_fields(format);
+            let pattern = regex::Regex::new(&format!("(^|::){}(<|$)", regex::escape(name)))
+                .unwrap();
+            self.types_matching(&pattern)
+                .filter(|(_, ty)| match ty {
+                    Type::Struct(s) => s.members.iter()
+                        .filter(|m| !m.artificial)
+                        .map(|m| m.name.as_deref().unwrap_or(""))
+                        .eq(fields.iter().map(String::as_str)),
+                    // A single-variant enum has no `|` in its format.
+                    Type::Enum(e) => e.shape.variants()
+                        .map(|v| v.member.name.as_deref().unwrap_or(""))
+                        .eq([name]),
+                    _ => false,
+                })
+                .map(|(id, _)| id)
+                .collect()
+        }
+    }
+}
+
+/// Parses a JSON object whose values are all strings, as in the names of
+/// `defmt` symbols.
+fn parse_json_object(s: &str) -> Option<Vec<(String, String)>> {
+    let mut chars = s.trim().chars().peekable();
+    let mut fields = vec![];
+    let skip_ws = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    let string = |chars: &mut std::iter::Peekable<std::str::Chars>| -> Option<String> {
+        if chars.next()? != '"' {
+            return None;
+        }
+        let mut out = String::new();
+        loop {
+            match chars.next()? {
+                '"' => return Some(out),
+                '\\' => out.push(match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex = chars.by_ref().take(4).collect::<String>();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    c => c,
+                }),
+                c => out.push(c),
+            }
+        }
+    };
+
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_ws(&mut chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Some(fields);
+    }
+    loop {
+        skip_ws(&mut chars);
+        let key = string(&mut chars)?;
+        skip_ws(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_ws(&mut chars);
+        let value = string(&mut chars)?;
+        fields.push((key, value));
+        skip_ws(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => return Some(fields),
+            _ => return None,
+        }
+    }
+}
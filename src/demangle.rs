@@ -0,0 +1,282 @@
+//! Demangling of symbol names.
+//!
+//! C++ names, which follow the Itanium C++ ABI used by GCC and Clang on
+//! everything but Windows, are demangled with the `cpp_demangle` crate. Output
+//! is mostly formatted the way `c++filt` formats it, so that names can be
+//! copied between the two; the exceptions are listed in the tests.
+//!
+//! Rust names, in both the legacy and v0 schemes, are demangled with the
+//! `rustc-demangle` crate.
+
+/// Limit on how deeply the parser will recurse, to keep hostile input from
+/// overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Demangles a symbol name in any of the schemes we know: Rust v0, Rust
+/// legacy, or Itanium C++. Returns `None` if it isn't mangled in any of them.
+pub fn demangle_symbol(mangled: &str) -> Option<String> {
+    demangle_rust(mangled).or_else(|| demangle(mangled))
+}
+
+/// Demangles a Rust symbol name, returning `None` if `mangled` isn't one.
+/// Hashes and crate disambiguators are left out, so names look the way they do
+/// in the debug info.
+pub fn demangle_rust(mangled: &str) -> Option<String> {
+    // Legacy names are also valid C++ names, and `rustc-demangle` accepts
+    // both, so insist on the hash. macOS adds an extra leading underscore.
+    let unprefixed = mangled.strip_prefix('_').filter(|m| m.starts_with("_Z")).unwrap_or(mangled);
+    if unprefixed.starts_with("_Z") && !is_rust_legacy(unprefixed) {
+        return None;
+    }
+    let demangled = rustc_demangle::try_demangle(mangled).ok()?;
+    Some(format!("{demangled:#}"))
+}
+
+/// Demangles an Itanium ABI C++ symbol name, returning `None` if `mangled`
+/// isn't one, or uses an encoding we don't support.
+///
+/// Rust's legacy symbol names use the same syntax, but with Rust escapes and a
+/// trailing hash; those are not treated as C++ names.
+pub fn demangle(mangled: &str) -> Option<String> {
+    if !mangled.starts_with("_Z") || is_rust_legacy(mangled) {
+        return None;
+    }
+    if let Some(name) = special_name(mangled) {
+        return Some(name);
+    }
+    let options = cpp_demangle::ParseOptions::default().recursion_limit(MAX_DEPTH as u32);
+    let symbol = cpp_demangle::Symbol::new_with_options(mangled, &options).ok()?;
+    let options = cpp_demangle::DemangleOptions::default().recursion_limit(MAX_DEPTH as u32);
+    symbol.demangle_with_options(&options).ok()
+}
+
+/// Demangles the vtables, VTTs and thunks that `cpp_demangle` prints in its
+/// own notation, like `{vtable(Foo)}`, the way `c++filt` does.
+fn special_name(mangled: &str) -> Option<String> {
+    let rest = mangled.strip_prefix("_ZT")?;
+    let (kind, rest) = rest.split_at(1);
+    match kind {
+        // The type is demangled as the name of its typeinfo, which the crate
+        // does spell out.
+        "V" | "T" => {
+            let ty = demangle(&format!("_ZTI{rest}"))?;
+            let ty = ty.strip_prefix("typeinfo for ")?;
+            Some(match kind {
+                "V" => format!("vtable for {ty}"),
+                _ => format!("VTT for {ty}"),
+            })
+        }
+        "h" | "v" => {
+            let target = skip_call_offset(mangled.get(3..)?)?;
+            let to = if kind == "h" { "non-virtual" } else { "virtual" };
+            Some(format!("{to} thunk to {}", demangle(&format!("_Z{target}"))?))
+        }
+        "c" => {
+            let target = skip_call_offset(skip_call_offset(rest)?)?;
+            Some(format!("covariant return thunk to {}", demangle(&format!("_Z{target}"))?))
+        }
+        _ => None,
+    }
+}
+
+/// Skips a `<call-offset>`: `h <number> _` for a non-virtual offset, or
+/// `v <number> _ <number> _` for a virtual one.
+fn skip_call_offset(s: &str) -> Option<&str> {
+    let numbers = match s.as_bytes().first()? {
+        b'h' => 1,
+        b'v' => 2,
+        _ => return None,
+    };
+    let mut rest = &s[1..];
+    for _ in 0..numbers {
+        let n = rest.strip_prefix('n').unwrap_or(rest);
+        let digits = n.find(|c: char| !c.is_ascii_digit())?;
+        rest = n[digits..].strip_prefix('_').filter(|_| digits > 0)?;
+    }
+    Some(rest)
+}
+
+/// Checks for a Rust legacy symbol, whose last path component is a hash of
+/// the form `h0123456789abcdef`.
+fn is_rust_legacy(mangled: &str) -> bool {
+    // Any suffix, like `.llvm.1234`, comes after the closing `E`, but the
+    // name itself can contain dots too, e.g. `..` for `::`.
+    std::iter::once(mangled)
+        .chain(mangled.match_indices("E.").map(|(i, _)| &mangled[..=i]))
+        .any(has_rust_hash)
+}
+
+fn has_rust_hash(base: &str) -> bool {
+    let Some(body) = base.strip_suffix('E') else { return false };
+    let Some(hash) = body.len().checked_sub(17).and_then(|i| body.get(i..)) else {
+        return false;
+    };
+    body.len() >= 19
+        && body[..body.len() - 17].ends_with("17")
+        && hash.starts_with('h')
+        && hash[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Symbols and what `c++filt` (GNU binutils 2.40) makes of them.
+    const CXXFILT: &[(&str, &str)] = &[
+        ("_Z1fILi3EEvv", "void f<3>()"),
+        ("_Z1fILb1EEvv", "void f<true>()"),
+        ("_Z1fILc65EEvv", "void f<(char)65>()"),
+        ("_ZGVZ4mainE1x", "guard variable for main::x"),
+        ("_ZGVN3foo3barE", "guard variable for foo::bar"),
+        ("_ZGTtNKSt11logic_error4whatEv", "transaction clone for std::logic_error::what() const"),
+        ("_ZThn8_N3Foo3barEv", "non-virtual thunk to Foo::bar()"),
+        ("_ZTv0_n24_N3Foo3barEv", "virtual thunk to Foo::bar()"),
+        ("_ZTcv0_n24_h8_N3Foo5cloneEv", "covariant return thunk to Foo::clone()"),
+        ("_ZTv0_n12_NK3Foo3bazEv", "virtual thunk to Foo::baz() const"),
+        ("_ZThn16_NK3Foo3bazEi", "non-virtual thunk to Foo::baz(int) const"),
+        ("_ZN3FooC2Ev", "Foo::Foo()"),
+        ("_ZN3FooD0Ev", "Foo::~Foo()"),
+        ("_ZNSt6vectorIiSaIiEE9push_backERKi", "std::vector<int, std::allocator<int> >::push_back(int const&)"),
+        ("_ZNKSt6vectorIiSaIiEE4sizeEv", "std::vector<int, std::allocator<int> >::size() const"),
+        ("_ZN9__gnu_cxx13new_allocatorIcE8allocateEmPKv", "__gnu_cxx::new_allocator<char>::allocate(unsigned long, void const*)"),
+        ("_ZSt4endlIcSt11char_traitsIcEERSt13basic_ostreamIT_T0_ES6_", "std::basic_ostream<char, std::char_traits<char> >& std::endl<char, std::char_traits<char> >(std::basic_ostream<char, std::char_traits<char> >&)"),
+        ("_ZNSt8ios_base4InitC1Ev", "std::ios_base::Init::Init()"),
+        ("_Z3fooPFviE", "foo(void (*)(int))"),
+        ("_Z3fooRA10_i", "foo(int (&) [10])"),
+        ("_ZN12_GLOBAL__N_13fooEv", "(anonymous namespace)::foo()"),
+        ("_ZZ4mainENKUlvE_clEv", "main::{lambda()#1}::operator()() const"),
+        ("_ZTV3Foo", "vtable for Foo"),
+        ("_ZTI3Foo", "typeinfo for Foo"),
+        ("_ZTS3Foo", "typeinfo name for Foo"),
+        ("_ZTT3Foo", "VTT for Foo"),
+        ("_Z3fooIJidEEvDpT_", "void foo<int, double>(int, double)"),
+        ("_ZN3foo3barEv.cold", "foo::bar() [clone .cold]"),
+        ("_ZN3foo3barEv.constprop.0", "foo::bar() [clone .constprop.0]"),
+        ("_ZdlPvm", "operator delete(void*, unsigned long)"),
+        ("_Znwm", "operator new(unsigned long)"),
+        ("_ZNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEED1Ev", "std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >::~basic_string()"),
+        ("_ZTHN3foo1xE", "TLS init function for foo::x"),
+        ("_ZTWN3foo1xE", "TLS wrapper function for foo::x"),
+        ("_ZNK3Foo3barEv", "Foo::bar() const"),
+        ("_Z1fM3FooFviE", "f(void (Foo::*)(int))"),
+        ("_ZN3FooaSERKS_", "Foo::operator=(Foo const&)"),
+        ("_ZN3FoocvbEv", "Foo::operator bool()"),
+        ("_Z1fIiEvT_", "void f<int>(int)"),
+        ("_ZL3barv", "bar()"),
+        ("_ZN1D1gEv", "D::g()"),
+        ("_ZN1DD0Ev", "D::~D()"),
+        ("_ZN1DD1Ev", "D::~D()"),
+        ("_ZN1DD2Ev", "D::~D()"),
+        ("_ZN2VBD0Ev", "VB::~VB()"),
+        ("_ZN2VBD1Ev", "VB::~VB()"),
+        ("_ZNK1D1fEv", "D::f() const"),
+        ("_ZNK2VB1fEv", "VB::f() const"),
+        ("_ZNSt10unique_ptrI1BSt14default_deleteIS0_EED1Ev", "std::unique_ptr<B, std::default_delete<B> >::~unique_ptr()"),
+        ("_ZNSt10unique_ptrI1BSt14default_deleteIS0_EED2Ev", "std::unique_ptr<B, std::default_delete<B> >::~unique_ptr()"),
+        ("_ZNSt14_Function_baseD1Ev", "std::_Function_base::~_Function_base()"),
+        ("_ZNSt14_Function_baseD2Ev", "std::_Function_base::~_Function_base()"),
+        ("_ZNSt16_Sp_counted_baseILN9__gnu_cxx12_Lock_policyE2EE10_M_releaseEv", "std::_Sp_counted_base<(__gnu_cxx::_Lock_policy)2>::_M_release()"),
+        ("_ZNSt16_Sp_counted_baseILN9__gnu_cxx12_Lock_policyE2EE24_M_release_last_use_coldEv", "std::_Sp_counted_base<(__gnu_cxx::_Lock_policy)2>::_M_release_last_use_cold()"),
+        ("_ZNSt17_Function_handlerIFiiEZ4mainEUliE_E10_M_managerERSt9_Any_dataRKS3_St18_Manager_operation", "std::_Function_handler<int (int), main::{lambda(int)#1}>::_M_manager(std::_Any_data&, std::_Any_data const&, std::_Manager_operation)"),
+        ("_ZNSt17_Function_handlerIFiiEZ4mainEUliE_E9_M_invokeERKSt9_Any_dataOi", "std::_Function_handler<int (int), main::{lambda(int)#1}>::_M_invoke(std::_Any_data const&, int&&)"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE10_M_destroyEv", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::_M_destroy()"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE10_M_disposeEv", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::_M_dispose()"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE14_M_get_deleterERKSt9type_info", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::_M_get_deleter(std::type_info const&)"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EED0Ev", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::~_Sp_counted_ptr_inplace()"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EED1Ev", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::~_Sp_counted_ptr_inplace()"),
+        ("_ZNSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EED2Ev", "std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>::~_Sp_counted_ptr_inplace()"),
+        ("_ZNSt3mapIiNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEESt4lessIiESaISt4pairIKiS5_EEEixEOi", "std::map<int, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >, std::less<int>, std::allocator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > >::operator[](int&&)"),
+        ("_ZNSt6vectorINSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEESaIS5_EED1Ev", "std::vector<std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >, std::allocator<std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >::~vector()"),
+        ("_ZNSt6vectorINSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEESaIS5_EED2Ev", "std::vector<std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >, std::allocator<std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >::~vector()"),
+        ("_ZNSt8_Rb_treeIiSt4pairIKiNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEEESt10_Select1stIS8_ESt4lessIiESaIS8_EE24_M_get_insert_unique_posERS1_", "std::_Rb_tree<int, std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > >, std::_Select1st<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, std::less<int>, std::allocator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > >::_M_get_insert_unique_pos(int const&)"),
+        ("_ZNSt8_Rb_treeIiSt4pairIKiNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEEESt10_Select1stIS8_ESt4lessIiESaIS8_EE29_M_get_insert_hint_unique_posESt23_Rb_tree_const_iteratorIS8_ERS1_", "std::_Rb_tree<int, std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > >, std::_Select1st<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, std::less<int>, std::allocator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > >::_M_get_insert_hint_unique_pos(std::_Rb_tree_const_iterator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, int const&)"),
+        ("_ZNSt8_Rb_treeIiSt4pairIKiNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEEESt10_Select1stIS8_ESt4lessIiESaIS8_EE8_M_eraseEPSt13_Rb_tree_nodeIS8_E", "std::_Rb_tree<int, std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > >, std::_Select1st<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, std::less<int>, std::allocator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > >::_M_erase(std::_Rb_tree_node<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >*)"),
+        ("_ZSt19piecewise_construct", "std::piecewise_construct"),
+        ("_ZStL8__ioinit", "std::__ioinit"),
+        ("_ZTI1B", "typeinfo for B"),
+        ("_ZTI1D", "typeinfo for D"),
+        ("_ZTI2B2", "typeinfo for B2"),
+        ("_ZTI2VB", "typeinfo for VB"),
+        ("_ZTISt11_Mutex_baseILN9__gnu_cxx12_Lock_policyE2EE", "typeinfo for std::_Mutex_base<(__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTISt16_Sp_counted_baseILN9__gnu_cxx12_Lock_policyE2EE", "typeinfo for std::_Sp_counted_base<(__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTISt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE", "typeinfo for std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTIZ4mainEUliE_", "typeinfo for main::{lambda(int)#1}"),
+        ("_ZTS1B", "typeinfo name for B"),
+        ("_ZTS1D", "typeinfo name for D"),
+        ("_ZTS2B2", "typeinfo name for B2"),
+        ("_ZTS2VB", "typeinfo name for VB"),
+        ("_ZTSSt11_Mutex_baseILN9__gnu_cxx12_Lock_policyE2EE", "typeinfo name for std::_Mutex_base<(__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTSSt16_Sp_counted_baseILN9__gnu_cxx12_Lock_policyE2EE", "typeinfo name for std::_Sp_counted_base<(__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTSSt19_Sp_make_shared_tag", "typeinfo name for std::_Sp_make_shared_tag"),
+        ("_ZTSSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE", "typeinfo name for std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>"),
+        ("_ZTSZ4mainEUliE_", "typeinfo name for main::{lambda(int)#1}"),
+        ("_ZTT2VB", "VTT for VB"),
+        ("_ZTV1D", "vtable for D"),
+        ("_ZTV2VB", "vtable for VB"),
+        ("_ZTVSt23_Sp_counted_ptr_inplaceI1DSaIvELN9__gnu_cxx12_Lock_policyE2EE", "vtable for std::_Sp_counted_ptr_inplace<D, std::allocator<void>, (__gnu_cxx::_Lock_policy)2>"),
+        ("_ZThn8_N1D1gEv", "non-virtual thunk to D::g()"),
+        ("_ZThn8_N1DD0Ev", "non-virtual thunk to D::~D()"),
+        ("_ZThn8_N1DD1Ev", "non-virtual thunk to D::~D()"),
+        ("_ZTv0_n24_N2VBD0Ev", "virtual thunk to VB::~VB()"),
+        ("_ZTv0_n24_N2VBD1Ev", "virtual thunk to VB::~VB()"),
+        ("_ZTv0_n32_NK2VB1fEv", "virtual thunk to VB::f() const"),
+        ("_ZZNSt19_Sp_make_shared_tag5_S_tiEvE5__tag", "std::_Sp_make_shared_tag::_S_ti()::__tag"),
+        ("_ZSt10__invoke_rIiRZ4mainEUliE_JiEENSt9enable_ifIX16is_invocable_r_vIT_T0_DpT1_EES3_E4typeEOS4_DpOS5_", "std::enable_if<is_invocable_r_v<int, main::{lambda(int)#1}&, int>, int>::type std::__invoke_r<int, main::{lambda(int)#1}&, int>(main::{lambda(int)#1}&, int&&)"),
+    ];
+
+    /// Symbols that `cpp_demangle` formats differently from `c++filt`, and
+    /// what it makes of them.
+    const DIFFERENCES: &[(&str, &str)] = &[
+        // Integer literals in template arguments are written as casts, where
+        // `c++filt` uses suffixes like `4ul`.
+        ("_ZNSt5arrayIiLm4EE4sizeEv", "std::array<int, (unsigned long)4>::size()"),
+        ("_Z1fILln5EEvv", "void f<(long)-5>()"),
+        ("_Z1fILj7EEvv", "void f<(unsigned int)7>()"),
+        // The enclosing function of a local name is given its return type.
+        ("_ZZ3litILi3EEivE1x", "int lit<3>()::x"),
+        ("_ZZ3litILin2EEivE1x", "int lit<-2>()::x"),
+        // References to function parameter packs lose their `&&`.
+        ("_ZNSt8_Rb_treeIiSt4pairIKiNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEEESt10_Select1stIS8_ESt4lessIiESaIS8_EE22_M_emplace_hint_uniqueIJRKSt21piecewise_construct_tSt5tupleIJOiEESJ_IJEEEEESt17_Rb_tree_iteratorIS8_ESt23_Rb_tree_const_iteratorIS8_EDpOT_", "std::_Rb_tree_iterator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > std::_Rb_tree<int, std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > >, std::_Select1st<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, std::less<int>, std::allocator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > > >::_M_emplace_hint_unique<std::piecewise_construct_t const&, std::tuple<int&&>, std::tuple<> >(std::_Rb_tree_const_iterator<std::pair<int const, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> > > >, std::piecewise_construct_t const&, std::tuple<int&&>, std::tuple<>)"),
+    ];
+
+    #[test]
+    fn matches_cxxfilt() {
+        for &(mangled, expected) in CXXFILT.iter().chain(DIFFERENCES) {
+            assert_eq!(demangle(mangled).as_deref(), Some(expected), "demangling {mangled}");
+        }
+    }
+
+    #[test]
+    fn demangles_rust() {
+        let cases = [
+            ("_ZN4core3fmt5write17h0123456789abcdefE", "core::fmt::write"),
+            ("__ZN4core3fmt5write17h0123456789abcdefE", "core::fmt::write"),
+            (
+                "_ZN71_$LT$Test$u20$$u2b$$u20$$u27$static$u20$as$u20$foo..Bar$LT$Test$GT$$GT$3bar17h930b740aa94f1d3aE",
+                "<Test + 'static as foo::Bar<Test>>::bar",
+            ),
+            ("_ZN4core3ptr13drop_in_place17h0123456789abcdefE.llvm.12345678", "core::ptr::drop_in_place"),
+            ("_RNvCs15kBYyAo9fc_7mycrate7example", "mycrate::example"),
+            (
+                "_RNvMsr_NtCs3ssYzQotkvD_3std4pathNtB5_7PathBuf3newCs15kBYyAo9fc_7mycrate",
+                "<std::path::PathBuf>::new",
+            ),
+            ("_RINvCs7qp2U7fqm6G_7mycrate7exampleAtj8_EB2_", "mycrate::example::<[u16; 8]>"),
+        ];
+        for (mangled, expected) in cases {
+            assert_eq!(demangle_rust(mangled).as_deref(), Some(expected), "demangling {mangled}");
+            assert_eq!(demangle_symbol(mangled).as_deref(), Some(expected), "demangling {mangled}");
+        }
+        // C++ names are left for `demangle`.
+        assert_eq!(demangle_rust("_ZN3foo3barE"), None);
+        assert_eq!(demangle_symbol("_ZN3foo3barEv").as_deref(), Some("foo::bar()"));
+        assert_eq!(demangle_rust("main"), None);
+    }
+
+    #[test]
+    fn rejects_non_cxx_names() {
+        assert_eq!(demangle("main"), None);
+        assert_eq!(demangle("_ZN3std2io5stdio6_print17h0123456789abcdefE"), None);
+        assert_eq!(demangle("_ZN3foo"), None);
+    }
+}
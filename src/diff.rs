@@ -0,0 +1,201 @@
+//! Structural comparison of two types, e.g. two versions of a protocol struct
+//! or two monomorphizations of a generic type.
+//!
+//! The comparison is shallow: members are matched by name and their types are
+//! compared by name, without looking inside them. The exception is the
+//! variants of an enum, whose members are compared individually, since the
+//! variant types of two different enums always have different names.
+
+use crate::{DebugDb, Member, Type, TypeId, Variant, VariantShape};
+use std::ops::RangeInclusive;
+
+/// One way in which two types differ, as found by `DebugDb::diff_types`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeDifference {
+    /// Where the difference is: empty for the types themselves, a member name
+    /// like `len`, or a variant and member name like `Some.__0`.
+    pub path: String,
+    pub what: Difference,
+}
+
+/// The particular way a `TypeDifference` differs. Values from the first type
+/// come first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Difference {
+    /// The types are of different kinds, given by `Type::kind_name`. Nothing
+    /// more is compared.
+    Kind(&'static str, &'static str),
+    Size(Option<u64>, Option<u64>),
+    Alignment(Option<u64>, Option<u64>),
+    /// The member or variant is at a different offset.
+    Offset(u64, u64),
+    /// The member has types with different names.
+    MemberType(TypeId, TypeId),
+    /// The member, variant or enumerator is only in the first type.
+    OnlyInFirst,
+    /// The member, variant or enumerator is only in the second type.
+    OnlyInSecond,
+    /// The variant is selected by different discriminator values.
+    Discriminants(Vec<RangeInclusive<u128>>, Vec<RangeInclusive<u128>>),
+    /// The C-style enumerator has a different value.
+    Value(u64, u64),
+}
+
+impl DebugDb {
+    /// Compares types `a` and `b`, returning the ways in which they differ.
+    /// Typedefs and qualifiers are looked through first. An empty result
+    /// means the types have the same layout.
+    ///
+    /// Returns `None` if either type can't be found.
+    pub fn diff_types(&self, a: TypeId, b: TypeId) -> Option<Vec<TypeDifference>> {
+        let a = self.resolve_alias(a);
+        let b = self.resolve_alias(b);
+        let (ta, tb) = (self.type_by_id(a)?, self.type_by_id(b)?);
+
+        let mut out = vec![];
+        let mut diff = |path: &str, what| out.push(TypeDifference {
+            path: path.to_string(),
+            what,
+        });
+
+        if ta.kind_name() != tb.kind_name() {
+            diff("", Difference::Kind(ta.kind_name(), tb.kind_name()));
+            return Some(out);
+        }
+        let (za, zb) = (ta.byte_size(self), tb.byte_size(self));
+        if za != zb {
+            diff("", Difference::Size(za, zb));
+        }
+        let (aa, ab) = (ta.alignment(self), tb.alignment(self));
+        if aa != ab {
+            diff("", Difference::Alignment(aa, ab));
+        }
+
+        match (ta, tb) {
+            (Type::Struct(sa), Type::Struct(sb)) => {
+                self.diff_members("", &sa.members, &sb.members, &mut diff);
+            }
+            (Type::Union(ua), Type::Union(ub)) => {
+                self.diff_members("", &ua.members, &ub.members, &mut diff);
+            }
+            (Type::Enum(ea), Type::Enum(eb)) => {
+                let (ra, rb) = (ea.discriminant_ranges(self), eb.discriminant_ranges(self));
+                let discriminants = |ranges: &[(RangeInclusive<u128>, &Variant)], name| {
+                    ranges.iter()
+                        .filter(|(_, v)| v.member.name.as_deref() == name)
+                        .map(|(r, _)| r.clone())
+                        .collect::<Vec<_>>()
+                };
+                if let (VariantShape::Many { member: da, .. }, VariantShape::Many { member: db, .. })
+                    = (&ea.shape, &eb.shape)
+                {
+                    self.diff_member("(discriminator)", da, db, &mut diff);
+                }
+
+                for va in ea.shape.variants() {
+                    let name = va.member.name.as_deref();
+                    let path = name.unwrap_or("_");
+                    let Some(vb) = eb.shape.variants()
+                        .find(|vb| vb.member.name.as_deref() == name)
+                    else {
+                        diff(path, Difference::OnlyInFirst);
+                        continue;
+                    };
+                    if va.member.location != vb.member.location {
+                        diff(path, Difference::Offset(va.member.location, vb.member.location));
+                    }
+                    let (da, db) = (discriminants(&ra, name), discriminants(&rb, name));
+                    if da != db {
+                        diff(path, Difference::Discriminants(da, db));
+                    }
+                    let members = |v: &Variant| match self.type_by_id(v.member.type_id) {
+                        Some(Type::Struct(s)) => &s.members[..],
+                        _ => &[],
+                    };
+                    self.diff_members(&format!("{path}."), members(va), members(vb), &mut diff);
+                }
+                for vb in eb.shape.variants() {
+                    let name = vb.member.name.as_deref();
+                    if !ea.shape.variants().any(|va| va.member.name.as_deref() == name) {
+                        diff(name.unwrap_or("_"), Difference::OnlyInSecond);
+                    }
+                }
+            }
+            (Type::CEnum(ca), Type::CEnum(cb)) => {
+                for ea in ca.enumerators.values() {
+                    match cb.enumerators.values().find(|eb| eb.name == ea.name) {
+                        None => diff(&ea.name, Difference::OnlyInFirst),
+                        Some(eb) if eb.const_value != ea.const_value => {
+                            diff(&ea.name, Difference::Value(ea.const_value, eb.const_value));
+                        }
+                        Some(_) => (),
+                    }
+                }
+                for eb in cb.enumerators.values() {
+                    if !ca.enumerators.values().any(|ea| ea.name == eb.name) {
+                        diff(&eb.name, Difference::OnlyInSecond);
+                    }
+                }
+            }
+            (Type::Array(xa), Type::Array(xb))
+                if self.type_name(xa.element_type_id) != self.type_name(xb.element_type_id) =>
+            {
+                diff("[]", Difference::MemberType(xa.element_type_id, xb.element_type_id));
+            }
+            (Type::Pointer(pa), Type::Pointer(pb))
+                if self.type_name(pa.type_id) != self.type_name(pb.type_id) =>
+            {
+                diff("*", Difference::MemberType(pa.type_id, pb.type_id));
+            }
+            _ => (),
+        }
+        Some(out)
+    }
+
+    /// Compares two lists of members, matching them up by name. Unnamed
+    /// members are matched up by position among the unnamed members.
+    fn diff_members(
+        &self,
+        prefix: &str,
+        a: &[Member],
+        b: &[Member],
+        diff: &mut impl FnMut(&str, Difference),
+    ) {
+        let label = |ms: &[Member], i: usize| match &ms[i].name {
+            Some(n) => format!("{prefix}{n}"),
+            None => {
+                let nth = ms[..i].iter().filter(|m| m.name.is_none()).count();
+                format!("{prefix}<unnamed #{nth}>")
+            }
+        };
+        let labels_a = (0..a.len()).map(|i| label(a, i)).collect::<Vec<_>>();
+        let labels_b = (0..b.len()).map(|i| label(b, i)).collect::<Vec<_>>();
+
+        for (ma, la) in a.iter().zip(&labels_a) {
+            match labels_b.iter().position(|lb| lb == la) {
+                None => diff(la, Difference::OnlyInFirst),
+                Some(j) => self.diff_member(la, ma, &b[j], diff),
+            }
+        }
+        for lb in &labels_b {
+            if !labels_a.contains(lb) {
+                diff(lb, Difference::OnlyInSecond);
+            }
+        }
+    }
+
+    fn diff_member(
+        &self,
+        path: &str,
+        a: &Member,
+        b: &Member,
+        diff: &mut impl FnMut(&str, Difference),
+    ) {
+        if a.location != b.location {
+            diff(path, Difference::Offset(a.location, b.location));
+        }
+        if self.type_name(a.type_id) != self.type_name(b.type_id) {
+            diff(path, Difference::MemberType(a.type_id, b.type_id));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Export of the graph of references between types in Graphviz DOT format,
+//! for visualizing what pulls in what.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use crate::{DebugDb, Dependency, TypeId};
+
+impl DebugDb {
+    /// Writes the types in `types`, and the references between them, as a
+    /// DOT digraph. Each node is labeled with its type's name, kind and size.
+    /// References to types outside the set are left out.
+    ///
+    /// Edges are styled by how one type refers to another: solid for
+    /// containment, dashed for pointers, dotted for typedefs and qualifiers,
+    /// and grey for signatures and template parameters.
+    ///
+    /// To graph everything reachable from one type, collect
+    /// `walk_dependencies` into the set.
+    pub fn write_dot(
+        &self,
+        out: &mut impl Write,
+        types: &BTreeSet<TypeId>,
+    ) -> fmt::Result {
+        writeln!(out, "digraph types {{")?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+        for &id in types {
+            let Some(ty) = self.type_by_id(id) else { continue };
+            let name = self.type_name(id);
+            let size = match ty.byte_size(self) {
+                Some(z) => format!("{z} bytes"),
+                None => "unsized".to_string(),
+            };
+            writeln!(
+                out,
+                "    {} [label={}];",
+                node_id(id),
+                dot_string(&format!(
+                    "{}\n{} {}",
+                    name.as_deref().unwrap_or("(anonymous)"),
+                    ty.kind_name(),
+                    size,
+                )),
+            )?;
+        }
+        for &id in types {
+            let Some(ty) = self.type_by_id(id) else { continue };
+            // A struct with several members of one type gets one edge.
+            let deps = ty.dependencies().into_iter()
+                .filter(|(_, to)| types.contains(to))
+                .map(|(dep, to)| (to, dep))
+                .collect::<BTreeSet<_>>();
+            for (to, dep) in deps {
+                let style = match dep {
+                    Dependency::Contains => "solid",
+                    Dependency::PointsTo => "dashed",
+                    Dependency::Aliases => "dotted",
+                    Dependency::Signature | Dependency::Parameter => "solid, color=grey",
+                };
+                writeln!(out, "    {} -> {} [style={style}];", node_id(id), node_id(to))?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+/// Names the node for a type, after where it is in the debug info.
+fn node_id(id: TypeId) -> String {
+    match id.0 {
+        gimli::UnitSectionOffset::DebugInfoOffset(o) => format!("info_{:x}", o.0),
+        gimli::UnitSectionOffset::DebugTypesOffset(o) => format!("types_{:x}", o.0),
+    }
+}
+
+/// Quotes `s` as a DOT string.
+fn dot_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -3,12 +3,12 @@
 //! This consumes DWARF debug info sections by recursive descent, building up
 //! our data model.
 
-use crate::{DebugDbBuilder, Encoding, Base, Struct, Enum, Variant, VariantShape, TemplateTypeParameter, Member, TypeId, CEnum, Union, Enumerator, Array, Pointer, RtArcReader, Subroutine, DeclCoord, Subprogram, SubParameter, InlinedSubroutine, StaticVariable};
+use crate::{DebugDbBuilder, Encoding, Base, Struct, Enum, Variant, VariantShape, TemplateTypeParameter, Member, TypeId, CEnum, Union, Enumerator, Array, Pointer, RtArcReader, Subroutine, Typedef, Qualified, Qualifier, DeclCoord, FrameBase, ProgramId, ParamId, Subprogram, SubParameter, InlinedSubroutine, StaticVariable};
 use indexmap::IndexMap;
 use std::{num::NonZeroU64, convert::Infallible};
 use thiserror::Error;
 
-use gimli::{constants as gim_con, UnitSectionOffset};
+use gimli::{constants as gim_con, Endianity, Reader, UnitSectionOffset};
 
 #[derive(Clone, Debug, Error)]
 pub enum ParseError {
@@ -18,8 +18,6 @@ pub enum ParseError {
     AttrNotString(gim_con::DwAt),
     #[error("path entry was not a string")]
     PathNotString,
-    #[error("inlined subroutine w/o abstract origin at {0:x?}")]
-    UnboundSubroutine(UnitSectionOffset<usize>),
 }
 
 impl From<Infallible> for ParseError {
@@ -28,6 +26,37 @@ impl From<Infallible> for ParseError {
     }
 }
 
+/// Names given to structs, unions and enums that have none in the debug
+/// info, as C's often don't, so that they can still be recorded and typedefs
+/// of them resolved. Braced like the names rustc makes up for closures, so
+/// that they can't be mistaken for a real name.
+pub(crate) const ANONYMOUS_STRUCT: &str = "{anonymous struct}";
+pub(crate) const ANONYMOUS_UNION: &str = "{anonymous union}";
+pub(crate) const ANONYMOUS_ENUM: &str = "{anonymous enum}";
+
+thread_local! {
+    /// Amount added to the offsets of units being parsed, when they've been
+    /// moved by `rebase_unit_header`. References within a unit are relative
+    /// to it and follow it automatically, but references between units are
+    /// to offsets in the original section, and need this adding.
+    static INFO_BASE: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Runs `body` with references between units adjusted for units that have
+/// been moved by `base` bytes.
+pub(crate) fn with_info_base<T>(base: usize, body: impl FnOnce() -> T) -> T {
+    let old = INFO_BASE.with(|b| b.replace(base));
+    let result = body();
+    INFO_BASE.with(|b| b.set(old));
+    result
+}
+
+/// Converts a reference to another unit into an offset in the (possibly
+/// combined) debug info.
+fn info_ref(o: gimli::DebugInfoOffset) -> UnitSectionOffset {
+    gimli::DebugInfoOffset(o.0 + INFO_BASE.with(|b| b.get())).into()
+}
+
 pub fn parse_entry(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
@@ -67,7 +96,7 @@ fn handle_nested_types(
             gim_con::DW_TAG_base_type => {
                 parse_base_type(dwarf, unit, cursor, builder)?;
             }
-            gim_con::DW_TAG_structure_type => {
+            gim_con::DW_TAG_structure_type | gim_con::DW_TAG_class_type => {
                 parse_structure_type(dwarf, unit, cursor, builder)?;
             }
             gim_con::DW_TAG_enumeration_type => {
@@ -95,7 +124,32 @@ fn handle_nested_types(
                 parse_static_variable(dwarf, unit, cursor, builder)?;
             }
 
-            gim_con::DW_TAG_typedef | gim_con::DW_TAG_const_type | gim_con::DW_TAG_restrict_type => {
+            gim_con::DW_TAG_typedef => {
+                parse_typedef(dwarf, unit, cursor, builder)?;
+            }
+
+            gim_con::DW_TAG_const_type
+                | gim_con::DW_TAG_volatile_type
+                | gim_con::DW_TAG_restrict_type => {
+                parse_qualified_type(dwarf, unit, cursor, builder)?;
+            }
+            // C++ `using` declarations and directives, template parameters
+            // of functions, base classes, and DWARF procedures (expressions
+            // shared between locations) don't declare any types.
+            gim_con::DW_TAG_imported_declaration
+                | gim_con::DW_TAG_imported_module
+                | gim_con::DW_TAG_template_value_parameter
+                | gim_con::DW_TAG_GNU_template_parameter_pack
+                | gim_con::DW_TAG_inheritance
+                | gim_con::DW_TAG_dwarf_procedure => {
+                skip_entry(cursor)?;
+            }
+            // C++ types that the model can't represent yet.
+            gim_con::DW_TAG_reference_type
+                | gim_con::DW_TAG_rvalue_reference_type
+                | gim_con::DW_TAG_ptr_to_member_type
+                | gim_con::DW_TAG_unspecified_type => {
+                warn!(builder, "unsupported type {} at {:x?}", child.tag(), TypeId(child.offset().to_unit_section_offset(unit)));
                 skip_entry(cursor)?;
             }
             _ => {
@@ -122,16 +176,18 @@ fn parse_namespace(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             _ => (),
         }
     }
 
-    let name = name.unwrap();
+    // C++ anonymous namespaces have no name; this is what `c++filt` calls
+    // them.
+    let name = name.unwrap_or_else(|| "(anonymous namespace)".to_string());
 
     if entry.has_children() {
-        builder.path_component(name, |builder| {
+        builder.namespace(name, |builder| {
             while let Some(()) = cursor.next_entry()? {
                 if let Some(_) = cursor.current() {
                     handle_nested_types(dwarf, unit, cursor, builder)?;
@@ -165,7 +221,7 @@ fn parse_base_type(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_byte_size => {
                 byte_size = Some(attr.value().udata_value().unwrap());
@@ -185,7 +241,7 @@ fn parse_base_type(
                         gim_con::DW_ATE_complex_float => Encoding::ComplexFloat,
                         gim_con::DW_ATE_UTF => Encoding::UtfChar,
                         _ => {
-                            eprintln!("WARN: base type {name:?} will be ignored; unsupported encoding {e:?}");
+                            warn!(builder, "base type {name:?} will be ignored; unsupported encoding {e:?}");
                             return skip_entry(cursor); // TODO
                         }
                     });
@@ -202,7 +258,7 @@ fn parse_base_type(
     let encoding = encoding.unwrap();
 
     builder.record_type(Base {
-        name,
+        name: name.into(),
         offset,
         encoding,
         byte_size,
@@ -218,7 +274,7 @@ fn parse_structure_type(
     builder: &mut DebugDbBuilder,
 ) -> Result<(), ParseError> {
     let entry = cursor.current().unwrap();
-    assert!(entry.tag() == gim_con::DW_TAG_structure_type);
+    assert!(matches!(entry.tag(), gim_con::DW_TAG_structure_type | gim_con::DW_TAG_class_type));
 
     let offset = entry.offset().to_unit_section_offset(unit);
     let mut name = None;
@@ -231,7 +287,7 @@ fn parse_structure_type(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_byte_size => {
                 byte_size = Some(attr.value().udata_value().unwrap());
@@ -246,24 +302,24 @@ fn parse_structure_type(
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected decl_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected decl_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -280,10 +336,7 @@ fn parse_structure_type(
     let mut members = vec![];
     let mut variant_parts = vec![];
 
-    let Some(name) = name else {
-        eprintln!("WARN: unnamed struct type at {:x?}", TypeId(offset));
-        return skip_entry(cursor);
-    };
+    let name = name.unwrap_or_else(|| ANONYMOUS_STRUCT.to_string());
 
     if decl {
         builder.record_decl(&name, TypeId(offset));
@@ -298,17 +351,17 @@ fn parse_structure_type(
                         gim_con::DW_TAG_template_type_parameter => {
                             template_type_parameters.push(
                                 parse_template_type_parameter(
-                                    dwarf, unit, cursor,
+                                    dwarf, unit, cursor, builder,
                                 )?,
                             );
                         }
                         gim_con::DW_TAG_member => {
-                            let m = parse_member(dwarf, unit, cursor)?;
+                            let m = parse_member(dwarf, unit, cursor, builder)?;
                             members.push(m);
                         }
                         gim_con::DW_TAG_variant_part => {
                             variant_parts
-                                .push(parse_variant_part(dwarf, unit, cursor)?);
+                                .push(parse_variant_part(dwarf, unit, cursor, builder)?);
                         }
                         _ => {
                             handle_nested_types(dwarf, unit, cursor, builder)?;
@@ -337,7 +390,7 @@ fn parse_structure_type(
             false
         });
         builder.record_type(Struct {
-            name,
+            name: name.into(),
             byte_size,
             alignment,
             template_type_parameters,
@@ -354,7 +407,7 @@ fn parse_structure_type(
         );
         let shape = variant_parts.into_iter().next().unwrap();
         builder.record_type(Enum {
-            name,
+            name: name.into(),
             byte_size,
             alignment,
             template_type_parameters,
@@ -374,6 +427,7 @@ fn parse_template_type_parameter(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<TemplateTypeParameter, ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_template_type_parameter);
@@ -385,7 +439,7 @@ fn parse_template_type_parameter(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_type => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
@@ -393,7 +447,7 @@ fn parse_template_type_parameter(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
@@ -402,16 +456,22 @@ fn parse_template_type_parameter(
         }
     }
 
-    let (name, type_id) = (name.unwrap(), TypeId(type_id.unwrap()));
-    let name = name.to_string();
+    // The elements of a C++ template parameter pack have no names, and
+    // `void` arguments have no type.
+    let name = name.unwrap_or_default();
+    let type_id = match type_id {
+        Some(t) => TypeId(t),
+        None => record_void(unit, builder),
+    };
 
-    Ok(TemplateTypeParameter { name, type_id })
+    Ok(TemplateTypeParameter { name: name.into(), type_id })
 }
 
 fn parse_member(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<Member, ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_member);
@@ -427,7 +487,7 @@ fn parse_member(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_artificial => match attr.value() {
                 gimli::AttributeValue::Flag(f) => {
@@ -441,7 +501,7 @@ fn parse_member(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
@@ -450,30 +510,36 @@ fn parse_member(
                 alignment = Some(attr.value().udata_value().unwrap());
             }
             gim_con::DW_AT_data_member_location => {
-                location = Some(attr.value().udata_value().unwrap());
+                // When given as an expression, this expects the address of
+                // the enclosing struct on the stack; using zero produces the
+                // member's offset.
+                location = get_attr_udata(dwarf, unit, &attr, Some(0))?;
+                if location.is_none() {
+                    warn!(builder, "member location is not constant: {:?}", attr.value());
+                }
             }
             gim_con::DW_AT_decl_file => {
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected decl_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected decl_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -493,7 +559,7 @@ fn parse_member(
     let name = name.map(|s| s.to_string());
 
     Ok(Member {
-        name,
+        name: name.map(Into::into),
         artificial,
         type_id,
         alignment,
@@ -507,26 +573,11 @@ fn parse_variant_part(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<VariantShape, ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_variant_part);
 
-    let mut discr = None;
-
-    let mut attrs = entry.attrs();
-    while let Some(attr) = attrs.next()? {
-        match attr.name() {
-            gim_con::DW_AT_discr => {
-                if let gimli::AttributeValue::UnitRef(o) = attr.value() {
-                    discr = Some(o.to_unit_section_offset(&unit));
-                } else {
-                    panic!("unexpected discr type: {:?}", attr.value());
-                }
-            }
-            _ => (),
-        }
-    }
-
     let mut members = vec![];
     let mut variants = IndexMap::default();
     if entry.has_children() {
@@ -534,11 +585,11 @@ fn parse_variant_part(
             if let Some(child) = cursor.current() {
                 match child.tag() {
                     gim_con::DW_TAG_member => {
-                        members.push(parse_member(dwarf, unit, cursor)?);
+                        members.push(parse_member(dwarf, unit, cursor, builder)?);
                     }
                     gim_con::DW_TAG_variant => {
                         let (discr_value, v) =
-                            parse_variant(dwarf, unit, cursor)?;
+                            parse_variant(dwarf, unit, cursor, builder)?;
                         variants.insert(discr_value, v);
                     }
                     _ => {
@@ -562,7 +613,6 @@ fn parse_variant_part(
             // The single variant has a defined discriminator; use the Many
             // shape.
             VariantShape::Many {
-                discr: discr.unwrap(),
                 member: members.into_iter().next().unwrap(),
                 variants,
             }
@@ -573,7 +623,6 @@ fn parse_variant_part(
         }
     } else {
         VariantShape::Many {
-            discr: discr.unwrap(),
             member: members.into_iter().next().unwrap(),
             variants,
         }
@@ -585,6 +634,7 @@ fn parse_variant(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<(Option<u64>, Variant), ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_variant);
@@ -597,30 +647,37 @@ fn parse_variant(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_discr_value => {
-                discr_value = Some(attr.value().udata_value().unwrap());
+                discr_value = get_attr_discr_value(&attr)?;
+                if discr_value.is_none() {
+                    warn!(
+                        builder,
+                        "unsupported discr_value: {:?}",
+                        attr.value(),
+                    );
+                }
             }
             gim_con::DW_AT_decl_file => {
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected decl_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected decl_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -639,7 +696,7 @@ fn parse_variant(
             if let Some(child) = cursor.current() {
                 match child.tag() {
                     gim_con::DW_TAG_member => {
-                        members.push(parse_member(dwarf, unit, cursor)?);
+                        members.push(parse_member(dwarf, unit, cursor, builder)?);
                     }
                     _ => {
                         skip_entry(cursor)?;
@@ -681,7 +738,7 @@ fn parse_enumeration_type(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_byte_size => {
                 byte_size = Some(attr.value().udata_value().unwrap());
@@ -698,7 +755,7 @@ fn parse_enumeration_type(
 
     let mut enumerators = IndexMap::default();
 
-    let name = name.unwrap();
+    let name = name.unwrap_or_else(|| ANONYMOUS_ENUM.to_string());
     if entry.has_children() {
         builder.path_component(name.clone(), |_| {
             while let Some(()) = cursor.next_entry()? {
@@ -724,7 +781,7 @@ fn parse_enumeration_type(
     let name = builder.format_path(name);
 
     builder.record_type(CEnum {
-        name,
+        name: name.into(),
         offset,
         enum_class,
         byte_size,
@@ -749,7 +806,7 @@ fn parse_enumerator(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_const_value => {
                 const_value = Some(
@@ -769,7 +826,7 @@ fn parse_enumerator(
     let const_value = const_value.unwrap();
 
     Ok(Enumerator {
-        name,
+        name: name.into(),
         const_value,
         offset: entry.offset().to_unit_section_offset(unit),
     })
@@ -796,7 +853,7 @@ fn parse_array_type(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    element_type_id = Some(o.into());
+                    element_type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
@@ -807,14 +864,15 @@ fn parse_array_type(
 
     let element_type_id = TypeId(element_type_id.unwrap());
 
-    let mut subrange = None;
+    // One subrange per dimension, outermost first.
+    let mut subranges = vec![];
     if entry.has_children() {
         while let Some(()) = cursor.next_entry()? {
             if let Some(child) = cursor.current() {
                 match child.tag() {
                     gim_con::DW_TAG_subrange_type => {
-                        subrange =
-                            Some(parse_subrange_type(dwarf, unit, cursor)?);
+                        let offset = child.offset().to_unit_section_offset(unit);
+                        subranges.push((offset, parse_subrange_type(dwarf, unit, cursor)?));
                     }
                     _ => {
                         skip_entry(cursor)?;
@@ -825,20 +883,30 @@ fn parse_array_type(
             }
         }
     }
-    let (index_type_id, lower_bound, count) = subrange.unwrap();
-
-    builder.record_type(Array {
-        element_type_id,
-        index_type_id,
-        lower_bound,
-        count,
-        offset,
-    });
+    assert!(!subranges.is_empty(), "array type without subranges at {offset:x?}");
+
+    // A multi-dimensional array like C's `int x[4][8]` is an array of arrays.
+    // The inner arrays don't have entries of their own, so they're identified
+    // by their subranges.
+    let mut element_type_id = element_type_id;
+    for (i, &(subrange_offset, (index_type_id, lower_bound, count))) in
+        subranges.iter().enumerate().rev()
+    {
+        let offset = if i == 0 { offset } else { subrange_offset };
+        builder.record_type(Array {
+            element_type_id,
+            index_type_id,
+            lower_bound,
+            count,
+            offset,
+        });
+        element_type_id = TypeId(offset);
+    }
     Ok(())
 }
 
 fn parse_subrange_type(
-    _dwarf: &gimli::Dwarf<RtArcReader>,
+    dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
 ) -> Result<
@@ -850,6 +918,7 @@ fn parse_subrange_type(
 
     let mut type_id = None;
     let mut lower_bound = None;
+    let mut upper_bound = None;
     let mut count = None;
 
     let mut attrs = entry.attrs();
@@ -861,16 +930,22 @@ fn parse_subrange_type(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_lower_bound => {
-                lower_bound = Some(attr.value().udata_value().unwrap());
+                lower_bound = get_attr_udata(dwarf, unit, &attr, None)?;
             }
             gim_con::DW_AT_count => {
-                count = Some(attr.value().udata_value().unwrap());
+                // This may be an expression that can't be computed
+                // statically, for variable-length arrays, in which case we
+                // treat the array as unbounded.
+                count = get_attr_udata(dwarf, unit, &attr, None)?;
+            }
+            gim_con::DW_AT_upper_bound => {
+                upper_bound = get_attr_udata(dwarf, unit, &attr, None)?;
             }
             _ => (),
         }
@@ -878,6 +953,11 @@ fn parse_subrange_type(
 
     let type_id = TypeId(type_id.unwrap());
     let lower_bound = lower_bound.unwrap_or(0);
+    // C compilers give the last index rather than a count. An upper bound of
+    // all ones, i.e. -1, is how GCC describes a zero-length array.
+    let count = count.or_else(|| {
+        Some(upper_bound?.wrapping_add(1).saturating_sub(lower_bound))
+    });
 
     if entry.has_children() {
         while let Some(()) = cursor.next_entry()? {
@@ -907,12 +987,14 @@ fn parse_pointer_type(
     let offset = entry.offset().to_unit_section_offset(unit);
     let mut name = None;
     let mut type_id = None;
+    let mut byte_size = None;
+    let mut address_class = None;
 
     let mut attrs = entry.attrs();
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_type => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
@@ -920,11 +1002,20 @@ fn parse_pointer_type(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
             }
+            gim_con::DW_AT_byte_size => {
+                byte_size = attr.value().udata_value();
+            }
+            gim_con::DW_AT_address_class => {
+                address_class = match attr.value() {
+                    gimli::AttributeValue::AddressClass(c) => Some(c.0),
+                    v => v.udata_value(),
+                };
+            }
             gim_con::DW_AT_declaration => {
                 // TODO handle as declaration
                 return skip_entry(cursor);
@@ -933,12 +1024,11 @@ fn parse_pointer_type(
         }
     }
 
-    if type_id.is_none() {
-        eprintln!("WARN: pointer type missing pointee typeid at: {:x?}", offset);
-        return skip_entry(cursor);
-    }
-
-    let type_id = TypeId(type_id.unwrap());
+    // Pointers to `void` have no type.
+    let type_id = match type_id {
+        Some(t) => TypeId(t),
+        None => record_void(unit, builder),
+    };
 
     if entry.has_children() {
         while let Some(()) = cursor.next_entry()? {
@@ -956,12 +1046,132 @@ fn parse_pointer_type(
 
     builder.record_type(Pointer {
         type_id,
-        name,
+        name: name.map(Into::into),
+        byte_size,
+        address_class,
         offset,
     });
     Ok(())
 }
 
+fn parse_typedef(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
+) -> Result<(), ParseError> {
+    let entry = cursor.current().unwrap();
+    assert!(entry.tag() == gim_con::DW_TAG_typedef);
+
+    let offset = entry.offset().to_unit_section_offset(unit);
+    let mut name = None;
+    let mut type_id = None;
+
+    let mut attrs = entry.attrs();
+    while let Some(attr) = attrs.next()? {
+        match attr.name() {
+            gim_con::DW_AT_name => {
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
+            }
+            gim_con::DW_AT_type => {
+                if let gimli::AttributeValue::UnitRef(o) = attr.value() {
+                    type_id = Some(o.to_unit_section_offset(unit));
+                } else if let gimli::AttributeValue::DebugInfoRef(o) =
+                    attr.value()
+                {
+                    type_id = Some(info_ref(o));
+                } else {
+                    panic!("unexpected type type: {:?}", attr.value());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Typedefs of `void` have no type, and there's nothing useful we can do
+    // with them.
+    let (Some(name), Some(type_id)) = (name, type_id) else {
+        warn!(builder, "typedef missing name or type at: {:x?}", offset);
+        return skip_entry(cursor);
+    };
+
+    skip_entry(cursor)?;
+
+    builder.record_type(Typedef {
+        name: builder.format_path(name).into(),
+        type_id: TypeId(type_id),
+        offset,
+    });
+    Ok(())
+}
+
+fn parse_qualified_type(
+    _dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
+) -> Result<(), ParseError> {
+    let entry = cursor.current().unwrap();
+    let qualifier = match entry.tag() {
+        gim_con::DW_TAG_const_type => Qualifier::Const,
+        gim_con::DW_TAG_volatile_type => Qualifier::Volatile,
+        gim_con::DW_TAG_restrict_type => Qualifier::Restrict,
+        t => panic!("not a qualified type: {}", t),
+    };
+
+    let offset = entry.offset().to_unit_section_offset(unit);
+    let mut type_id = None;
+
+    let mut attrs = entry.attrs();
+    while let Some(attr) = attrs.next()? {
+        if attr.name() == gim_con::DW_AT_type {
+            if let gimli::AttributeValue::UnitRef(o) = attr.value() {
+                type_id = Some(o.to_unit_section_offset(unit));
+            } else if let gimli::AttributeValue::DebugInfoRef(o) =
+                attr.value()
+            {
+                type_id = Some(info_ref(o));
+            } else {
+                panic!("unexpected type type: {:?}", attr.value());
+            }
+        }
+    }
+
+    // Qualified `void` (as in `const void *`) has no type.
+    let type_id = match type_id {
+        Some(t) => TypeId(t),
+        None => record_void(unit, builder),
+    };
+
+    skip_entry(cursor)?;
+
+    builder.record_type(Qualified {
+        qualifier,
+        type_id,
+        offset,
+    });
+    Ok(())
+}
+
+/// Records a `void` type for `unit`, for the types that refer to it by
+/// leaving out their type, and returns its ID. DWARF has no entry for `void`,
+/// so it's made up, in the same shape rustc gives `()`: a zero-sized base
+/// type. It's keyed by the offset of the unit header, which no entry has.
+fn record_void(
+    unit: &gimli::Unit<RtArcReader>,
+    builder: &mut DebugDbBuilder,
+) -> TypeId {
+    let offset = unit.header.offset();
+    builder.record_type(Base {
+        name: "void".into(),
+        offset,
+        encoding: Encoding::Unsigned,
+        byte_size: 0,
+        alignment: None,
+    });
+    TypeId(offset)
+}
+
 fn parse_union_type(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
@@ -975,12 +1185,13 @@ fn parse_union_type(
     let mut name = None;
     let mut byte_size = None;
     let mut alignment = None;
+    let mut decl = false;
 
     let mut attrs = entry.attrs();
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_byte_size => {
                 byte_size = Some(attr.value().udata_value().unwrap());
@@ -989,8 +1200,7 @@ fn parse_union_type(
                 alignment = Some(attr.value().udata_value().unwrap());
             }
             gim_con::DW_AT_declaration => {
-                // TODO handle as declaration
-                return skip_entry(cursor);
+                decl = true;
             }
             _ => (),
         }
@@ -999,24 +1209,26 @@ fn parse_union_type(
     let mut template_type_parameters = vec![];
     let mut members = vec![];
 
-    let Some(name) = name else {
-        eprintln!("WARN: skipping nameless union at: {:x?}", offset);
+    let name = name.unwrap_or_else(|| ANONYMOUS_UNION.to_string());
+
+    if decl {
+        builder.record_decl(&name, TypeId(offset));
         return skip_entry(cursor);
-    };
+    }
     if entry.has_children() {
-        builder.path_component(name.clone(), |_| {
+        builder.path_component(name.clone(), |builder| {
             while let Some(()) = cursor.next_entry()? {
                 if let Some(child) = cursor.current() {
                     match child.tag() {
                         gim_con::DW_TAG_template_type_parameter => {
                             template_type_parameters.push(
                                 parse_template_type_parameter(
-                                    dwarf, unit, cursor,
+                                    dwarf, unit, cursor, builder,
                                 )?,
                             );
                         }
                         gim_con::DW_TAG_member => {
-                            members.push(parse_member(dwarf, unit, cursor)?);
+                            members.push(parse_member(dwarf, unit, cursor, builder)?);
                         }
                         _ => {
                             skip_entry(cursor)?;
@@ -1030,10 +1242,10 @@ fn parse_union_type(
         })?;
     }
 
-    let (byte_size, alignment) = (byte_size.unwrap(), alignment.unwrap());
+    let byte_size = byte_size.unwrap();
     let name = builder.format_path(name);
     builder.record_type(Union {
-        name,
+        name: name.into(),
         byte_size,
         alignment,
         template_type_parameters,
@@ -1054,6 +1266,8 @@ fn parse_subroutine_type(
 
     let offset = entry.offset().to_unit_section_offset(unit);
     let mut return_type_id = None;
+    let mut prototyped = false;
+    let mut calling_convention = None;
 
     let mut attrs = entry.attrs();
     while let Some(attr) = attrs.next()? {
@@ -1064,24 +1278,40 @@ fn parse_subroutine_type(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    return_type_id = Some(TypeId(o.into()));
+                    return_type_id = Some(TypeId(info_ref(o)));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
             }
+            gim_con::DW_AT_prototyped => {
+                prototyped = attr.value() == gimli::AttributeValue::Flag(true);
+            }
+            gim_con::DW_AT_calling_convention => {
+                if let gimli::AttributeValue::CallingConvention(cc) = attr.value() {
+                    calling_convention = Some(cc);
+                }
+            }
             _ => (),
         }
     }
 
     let mut formal_parameters = vec![];
+    let mut parameter_names = vec![];
+    let mut variadic = false;
 
     if entry.has_children() {
         while let Some(()) = cursor.next_entry()? {
             if let Some(child) = cursor.current() {
                 match child.tag() {
                     gim_con::DW_TAG_formal_parameter => {
-                        formal_parameters
-                            .push(parse_formal_parameter(dwarf, unit, cursor)?);
+                        let (name, type_id) =
+                            parse_formal_parameter(dwarf, unit, cursor)?;
+                        parameter_names.push(name.map(Into::into));
+                        formal_parameters.push(type_id);
+                    }
+                    gim_con::DW_TAG_unspecified_parameters => {
+                        variadic = true;
+                        skip_entry(cursor)?;
                     }
                     _ => {
                         skip_entry(cursor)?;
@@ -1096,31 +1326,39 @@ fn parse_subroutine_type(
     builder.record_type(Subroutine {
         return_type_id,
         formal_parameters,
+        parameter_names,
+        variadic,
+        prototyped,
+        calling_convention,
         offset,
     });
     Ok(())
 }
 
 fn parse_formal_parameter(
-    _dwarf: &gimli::Dwarf<RtArcReader>,
+    dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
-) -> Result<TypeId, ParseError> {
+) -> Result<(Option<String>, TypeId), ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_formal_parameter);
 
+    let mut name = None;
     let mut type_id = None;
 
     let mut attrs = entry.attrs();
     while let Some(attr) = attrs.next()? {
         match attr.name() {
+            gim_con::DW_AT_name => {
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
+            }
             gim_con::DW_AT_type => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
                     type_id = Some(o.to_unit_section_offset(&unit));
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
@@ -1131,14 +1369,22 @@ fn parse_formal_parameter(
 
     let type_id = TypeId(type_id.unwrap());
 
-    Ok(type_id)
+    Ok((name, type_id))
 }
 
 fn get_attr_string<'a>(
     dwarf: &'a gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
     attr: &gimli::Attribute<RtArcReader>,
 ) -> Result<String, ParseError> {
     match attr.value() {
+        v @ gimli::AttributeValue::DebugStrOffsetsIndex(index) => {
+            if let Ok(s) = dwarf.attr_string(unit, v) {
+                Ok(String::from_utf8_lossy(s.bytes()).into_owned())
+            } else {
+                Ok(format!("<.debug_str_offsets[{}]>", index.0))
+            }
+        }
         gimli::AttributeValue::DebugStrRef(offset) => {
             if let Ok(s) = dwarf.debug_str.get_str(offset) {
                 Ok(String::from_utf8_lossy(s.bytes()).into_owned())
@@ -1162,9 +1408,18 @@ fn get_attr_string<'a>(
 
 fn get_path<'a>(
     dwarf: &'a gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
     attrval: gimli::AttributeValue<RtArcReader>,
 ) -> Result<String, ParseError> {
     match attrval {
+        // DWARF 5 line tables may refer to strings indirectly.
+        v @ gimli::AttributeValue::DebugStrOffsetsIndex(index) => {
+            if let Ok(s) = dwarf.attr_string(unit, v) {
+                Ok(String::from_utf8_lossy(s.bytes()).into_owned())
+            } else {
+                Ok(format!("<.debug_str_offsets[{}]>", index.0))
+            }
+        }
         gimli::AttributeValue::DebugLineStrRef(offset) => {
             if let Ok(s) = dwarf.debug_line_str.get_str(offset) {
                 Ok(String::from_utf8_lossy(s.bytes()).into_owned())
@@ -1194,6 +1449,121 @@ fn get_path<'a>(
     }
 }
 
+/// Works out the address given by a location list (in `.debug_loc` or, for
+/// DWARF 5, `.debug_loclists`), for a variable that's at a fixed address but
+/// is described by a list anyway, as optimizing compilers sometimes do. The
+/// result is `None` unless every entry gives the same address.
+fn list_location(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    mut list: gimli::LocListIter<RtArcReader>,
+) -> Result<Option<u64>, ParseError> {
+    let mut found = None;
+    while let Some(entry) = list.next()? {
+        match evaluate_expr(dwarf, unit, entry.data, None)? {
+            Some(addr) if found.is_none_or(|f| f == addr) => found = Some(addr),
+            _ => return Ok(None),
+        }
+    }
+    Ok(found)
+}
+
+/// Evaluates a DWARF expression (`DW_OP_*`) that can be computed without access
+/// to a running program, such as the address of a static variable.
+///
+/// If `initial` is provided, it's pushed on the stack before evaluation starts.
+///
+/// Returns `None` if the expression needs information that's only available at
+/// runtime, such as register or memory contents, if it doesn't produce a
+/// single address or value, or if it uses operations that gimli doesn't
+/// support.
+fn evaluate_expr(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    expr: gimli::Expression<RtArcReader>,
+    initial: Option<u64>,
+) -> Result<Option<u64>, ParseError> {
+    let mut eval = expr.evaluation(unit.encoding());
+    if let Some(v) = initial {
+        eval.set_initial_value(v);
+    }
+    let mut result = eval.evaluate();
+    loop {
+        match result {
+            Ok(gimli::EvaluationResult::Complete) => break,
+            Ok(gimli::EvaluationResult::RequiresRelocatedAddress(a)) => {
+                result = eval.resume_with_relocated_address(a);
+            }
+            Ok(gimli::EvaluationResult::RequiresIndexedAddress { index, .. }) => {
+                let a = dwarf.address(unit, index)?;
+                result = eval.resume_with_indexed_address(a);
+            }
+            // Errors here are about the expression itself, which only matter
+            // to the attribute it's in; callers warn about those.
+            Ok(_) | Err(_) => return Ok(None),
+        }
+    }
+
+    let pieces = eval.result();
+    let [piece] = &pieces[..] else { return Ok(None) };
+    match &piece.location {
+        gimli::Location::Address { address } => Ok(Some(*address)),
+        gimli::Location::Value { value } => {
+            let addr_mask = !0 >> (64 - 8 * u32::from(unit.encoding().address_size));
+            Ok(value.to_u64(addr_mask).ok())
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Gets the value of an attribute that may be given either as a constant, or
+/// as an expression that computes one. See `evaluate_expr` for the meaning of
+/// `initial`.
+fn get_attr_udata(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    attr: &gimli::Attribute<RtArcReader>,
+    initial: Option<u64>,
+) -> Result<Option<u64>, ParseError> {
+    match attr.exprloc_value() {
+        Some(e) => evaluate_expr(dwarf, unit, e, initial),
+        None => Ok(attr.value().udata_value()),
+    }
+}
+
+/// Gets the value of a `DW_AT_discr_value` attribute.
+///
+/// rustc uses whichever constant form fits the value, which for niche-encoded
+/// enums with 128-bit discriminators means a 16-byte block. Values are
+/// returned as `u64`, so such a block is only accepted if its value fits.
+/// Signed values are sign-extended, which is fine, since only the bits that
+/// fit the discriminator are compared.
+fn get_attr_discr_value(
+    attr: &gimli::Attribute<RtArcReader>,
+) -> Result<Option<u64>, ParseError> {
+    let value = attr.value();
+    if let Some(x) = value.udata_value() {
+        return Ok(Some(x));
+    }
+    if let Some(x) = value.sdata_value() {
+        return Ok(Some(x as u64));
+    }
+    let gimli::AttributeValue::Block(r) = value else { return Ok(None) };
+    let bytes = r.to_slice()?;
+    if bytes.len() > 16 {
+        return Ok(None);
+    }
+    let mut buffer = [0; 16];
+    let x = if r.endian().is_big_endian() {
+        buffer[16 - bytes.len()..].copy_from_slice(&bytes);
+        u128::from_be_bytes(buffer)
+    } else {
+        buffer[..bytes.len()].copy_from_slice(&bytes);
+        u128::from_le_bytes(buffer)
+    };
+    Ok(u64::try_from(x).ok())
+}
+
 fn skip_entry(
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
 ) -> Result<(), ParseError> {
@@ -1202,15 +1572,15 @@ fn skip_entry(
     /*
     match entry.tag() {
         gim_con::DW_TAG_structure_type => {
-            eprintln!("WARN: structure skipped: offset={:x?}",
+            warn!("structure skipped: offset={:x?}",
                 entry.offset());
         }
         gim_con::DW_TAG_enumeration_type => {
-            eprintln!("WARN: enumeration skipped: offset={:x?}",
+            warn!("enumeration skipped: offset={:x?}",
                 entry.offset());
         }
         gim_con::DW_TAG_base_type => {
-            eprintln!("WARN: base type skipped: offset={:x?}",
+            warn!("base type skipped: offset={:x?}",
                 entry.offset());
         }
         _ => (),
@@ -1230,6 +1600,15 @@ fn skip_entry(
     Ok(())
 }
 
+/// Interpretations of the `DW_AT_high_pc` attribute, which depend on its form.
+#[derive(Copy, Clone, Debug)]
+enum HighPc {
+    /// Offset from `DW_AT_low_pc`.
+    Offset(u64),
+    /// Address just past the end of the range.
+    Absolute(u64),
+}
+
 fn parse_subprogram(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
@@ -1242,19 +1621,22 @@ fn parse_subprogram(
     let mut linkage_name = None;
     let mut lo_pc = None;
     let mut hi_pc = None;
+    let mut pc_ranges = vec![];
     let mut return_type_id = None;
     let mut decl_coord = DeclCoord::default();
     let mut abstract_origin = None;
     let mut noreturn = false;
+    let mut frame_base = None;
+    let mut specification = None;
 
     let mut attrs = entry.attrs();
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_linkage_name => {
-                linkage_name = Some(get_attr_string(dwarf, &attr)?);
+                linkage_name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_noreturn => match attr.value() {
                 gimli::AttributeValue::Flag(f) => {
@@ -1266,24 +1648,24 @@ fn parse_subprogram(
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected decl_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected decl_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -1293,14 +1675,32 @@ fn parse_subprogram(
                 decl_coord.column = NonZeroU64::new(attr.value().udata_value().unwrap());
             }
             gim_con::DW_AT_low_pc => {
-                if let gimli::AttributeValue::Addr(a) = attr.value() {
+                if let Some(a) = dwarf.attr_address(unit, attr.value())? {
                     lo_pc = Some(a);
                 } else {
-                    eprintln!("WARN: unexpected low_pc type: {:?}", attr.value());
+                    warn!(builder, "unexpected low_pc type: {:?}", attr.value());
+                }
+            }
+            gim_con::DW_AT_ranges => {
+                if let Some(roff) = dwarf.attr_ranges_offset(unit, attr.value())? {
+                    let mut riter = dwarf.ranges(unit, roff)?;
+                    while let Some(range) = riter.next()? {
+                        pc_ranges.push(range.begin..range.end);
+                    }
+                } else {
+                    warn!(builder, "unexpected ranges type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_high_pc => {
-                hi_pc = Some(attr.value().udata_value().unwrap());
+                // high_pc is an offset from low_pc if given as a constant, but
+                // an absolute address if given as an address.
+                hi_pc = Some(match attr.value() {
+                    v @ (gimli::AttributeValue::Addr(_)
+                        | gimli::AttributeValue::DebugAddrIndex(_)) => {
+                        HighPc::Absolute(dwarf.attr_address(unit, v)?.unwrap())
+                    }
+                    v => HighPc::Offset(v.udata_value().unwrap()),
+                });
             }
             gim_con::DW_AT_type => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
@@ -1308,37 +1708,65 @@ fn parse_subprogram(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    return_type_id = Some(TypeId(o.into()));
+                    return_type_id = Some(TypeId(info_ref(o)));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_abstract_origin => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
-                    abstract_origin = Some(o.to_unit_section_offset(&unit));
+                    abstract_origin = Some(ProgramId(o.to_unit_section_offset(&unit)));
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    abstract_origin = Some(o.into());
+                    abstract_origin = Some(ProgramId(info_ref(o)));
                 } else {
                     panic!("unexpected abstract_origin type: {:?}", attr.value());
                 }
             }
+            gim_con::DW_AT_specification => {
+                // Out-of-line definitions of C++ functions declared in a
+                // namespace or class point back to the declaration.
+                if let gimli::AttributeValue::UnitRef(o) = attr.value() {
+                    specification = Some(o);
+                } else {
+                    warn!(builder, "unexpected specification type: {:?}", attr.value());
+                }
+            }
+            gim_con::DW_AT_frame_base => {
+                frame_base = Some(match attr.value() {
+                    gimli::AttributeValue::Exprloc(e) => parse_frame_base(unit, e)?,
+                    _ => FrameBase::Other,
+                });
+            }
             // sibling
             // inline
             // prototyped
             // external
-            // frame_base
             _ => {
                 //println!("skipping subprogram attr: {:x?}", attr.name());
             }
         }
     }
 
-    let pc_range = if let (Some(lo), Some(hi)) = (lo_pc, hi_pc) {
-        Some(lo..lo + hi)
+    if let Some(o) = specification {
+        let spec = parse_specification(dwarf, unit, o)?;
+        name = name.or(spec.name);
+        linkage_name = linkage_name.or(spec.linkage_name);
+        return_type_id = return_type_id.or(spec.type_id.map(TypeId));
+    }
+
+    let pc_range = match (lo_pc, hi_pc) {
+        (Some(lo), Some(HighPc::Offset(hi))) => Some(lo..lo + hi),
+        (Some(lo), Some(HighPc::Absolute(hi))) => Some(lo..hi),
+        _ => None,
+    };
+    let pc_range = if let Some(r) = pc_range {
+        pc_ranges.push(r.clone());
+        Some(r)
     } else {
-        None
+        pc_ranges.sort_by_key(|r| r.start);
+        pc_ranges.first().cloned()
     };
 
     let offset = entry.offset().to_unit_section_offset(unit);
@@ -1352,18 +1780,18 @@ fn parse_subprogram(
                 match child.tag() {
                     gim_con::DW_TAG_formal_parameter => {
                         formal_parameters
-                            .push(parse_sub_parameter(dwarf, unit, cursor)?);
+                            .push(parse_sub_parameter(dwarf, unit, cursor, builder)?);
                     }
                     gim_con::DW_TAG_template_type_parameter => {
                         template_type_parameters.push(
                             parse_template_type_parameter(
-                                dwarf, unit, cursor,
+                                dwarf, unit, cursor, builder,
                             )?,
                         );
                     }
                     gim_con::DW_TAG_inlined_subroutine => {
                         inlines
-                            .push(parse_inlined_subroutine(dwarf, unit, cursor)?);
+                            .push(parse_inlined_subroutine(dwarf, unit, cursor, builder)?);
                     }
                     // variable
                     // lexical_block
@@ -1384,6 +1812,7 @@ fn parse_subprogram(
         offset,
         name,
         pc_range,
+        pc_ranges,
         decl_coord,
         return_type_id,
         formal_parameters,
@@ -1392,15 +1821,78 @@ fn parse_subprogram(
         abstract_origin,
         linkage_name,
         noreturn,
+        frame_base,
     });
 
     Ok(())
 }
 
+/// Recognizes the common forms of `DW_AT_frame_base` expression, which are a
+/// single operation.
+fn parse_frame_base(
+    unit: &gimli::Unit<RtArcReader>,
+    expr: gimli::Expression<RtArcReader>,
+) -> Result<FrameBase, gimli::Error> {
+    let mut ops = expr.operations(unit.encoding());
+    let base = match ops.next()? {
+        Some(gimli::Operation::CallFrameCFA) => FrameBase::Cfa,
+        Some(gimli::Operation::Register { register }) => FrameBase::Register(register),
+        Some(gimli::Operation::RegisterOffset { register, offset, .. }) => {
+            FrameBase::RegisterOffset(register, offset)
+        }
+        _ => return Ok(FrameBase::Other),
+    };
+    Ok(if ops.next()?.is_some() { FrameBase::Other } else { base })
+}
+
+/// Attributes that a definition can inherit from the declaration named by its
+/// `DW_AT_specification`.
+struct Specification {
+    name: Option<String>,
+    linkage_name: Option<String>,
+    type_id: Option<gimli::UnitSectionOffset>,
+}
+
+fn parse_specification(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    offset: gimli::UnitOffset,
+) -> Result<Specification, ParseError> {
+    let entry = unit.entry(offset)?;
+    let mut spec = Specification {
+        name: None,
+        linkage_name: None,
+        type_id: None,
+    };
+    let mut attrs = entry.attrs();
+    while let Some(attr) = attrs.next()? {
+        match attr.name() {
+            gim_con::DW_AT_name => {
+                spec.name = Some(get_attr_string(dwarf, unit, &attr)?);
+            }
+            gim_con::DW_AT_linkage_name => {
+                spec.linkage_name = Some(get_attr_string(dwarf, unit, &attr)?);
+            }
+            gim_con::DW_AT_type => match attr.value() {
+                gimli::AttributeValue::UnitRef(o) => {
+                    spec.type_id = Some(o.to_unit_section_offset(unit));
+                }
+                gimli::AttributeValue::DebugInfoRef(o) => {
+                    spec.type_id = Some(info_ref(o));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    Ok(spec)
+}
+
 fn parse_sub_parameter(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<SubParameter, ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_formal_parameter);
@@ -1415,7 +1907,7 @@ fn parse_sub_parameter(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_type => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
@@ -1423,18 +1915,18 @@ fn parse_sub_parameter(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(TypeId(o.into()));
+                    type_id = Some(TypeId(info_ref(o)));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_abstract_origin => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
-                    abstract_origin = Some(o.to_unit_section_offset(&unit));
+                    abstract_origin = Some(ParamId(o.to_unit_section_offset(&unit)));
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    abstract_origin = Some(o.into());
+                    abstract_origin = Some(ParamId(info_ref(o)));
                 } else {
                     panic!("unexpected abstract_origin type: {:?}", attr.value());
                 }
@@ -1443,24 +1935,24 @@ fn parse_sub_parameter(
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected decl_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected decl_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -1495,6 +1987,7 @@ fn parse_inlined_subroutine(
     dwarf: &gimli::Dwarf<RtArcReader>,
     unit: &gimli::Unit<RtArcReader>,
     cursor: &mut gimli::EntriesCursor<'_, '_, RtArcReader>,
+    builder: &mut DebugDbBuilder,
 ) -> Result<InlinedSubroutine, ParseError> {
     let entry = cursor.current().unwrap();
     assert!(entry.tag() == gim_con::DW_TAG_inlined_subroutine);
@@ -1509,38 +2002,37 @@ fn parse_inlined_subroutine(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_ranges => {
-                if let gimli::AttributeValue::RangeListsRef(roff) = attr.value() {
-                    let roff = dwarf.ranges_offset_from_raw(unit, roff);
+                if let Some(roff) = dwarf.attr_ranges_offset(unit, attr.value())? {
                     let mut riter = dwarf.ranges(unit, roff)?;
                     while let Some(range) = riter.next()? {
-                        pc_ranges.push(range);
+                        pc_ranges.push(range.begin..range.end);
                     }
                 } else {
-                    eprintln!("WARN: unexpected ranges type: {:?}", attr.value());
+                    warn!(builder, "unexpected ranges type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_call_file => {
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 call_coord.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 call_coord.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected call_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected call_file type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_call_line => {
@@ -1550,22 +2042,28 @@ fn parse_inlined_subroutine(
                 call_coord.column = NonZeroU64::new(attr.value().udata_value().unwrap());
             }
             gim_con::DW_AT_low_pc => {
-                if let gimli::AttributeValue::Addr(a) = attr.value() {
+                if let Some(a) = dwarf.attr_address(unit, attr.value())? {
                     lo_pc = Some(a);
                 } else {
-                    eprintln!("WARN: unexpected low_pc type: {:?}", attr.value());
+                    warn!(builder, "unexpected low_pc type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_high_pc => {
-                hi_pc = Some(attr.value().udata_value().unwrap());
+                hi_pc = Some(match attr.value() {
+                    v @ (gimli::AttributeValue::Addr(_)
+                        | gimli::AttributeValue::DebugAddrIndex(_)) => {
+                        HighPc::Absolute(dwarf.attr_address(unit, v)?.unwrap())
+                    }
+                    v => HighPc::Offset(v.udata_value().unwrap()),
+                });
             }
             gim_con::DW_AT_abstract_origin => {
                 if let gimli::AttributeValue::UnitRef(o) = attr.value() {
-                    abstract_origin = Some(o.to_unit_section_offset(&unit));
+                    abstract_origin = Some(ProgramId(o.to_unit_section_offset(&unit)));
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    abstract_origin = Some(o.into());
+                    abstract_origin = Some(ProgramId(info_ref(o)));
                 } else {
                     panic!("unexpected abstract_origin type: {:?}", attr.value());
                 }
@@ -1576,11 +2074,14 @@ fn parse_inlined_subroutine(
         }
     }
 
-    if let (Some(begin), Some(off)) = (lo_pc, hi_pc) {
-        pc_ranges.push(gimli::Range {
-            begin,
-            end: begin + off,
-        });
+    match (lo_pc, hi_pc) {
+        (Some(begin), Some(HighPc::Offset(off))) => {
+            pc_ranges.push(begin..begin + off);
+        }
+        (Some(begin), Some(HighPc::Absolute(end))) => {
+            pc_ranges.push(begin..end);
+        }
+        _ => (),
     }
 
     let offset = entry.offset().to_unit_section_offset(unit);
@@ -1593,11 +2094,11 @@ fn parse_inlined_subroutine(
                 match child.tag() {
                     gim_con::DW_TAG_inlined_subroutine => {
                         inlines
-                            .push(parse_inlined_subroutine(dwarf, unit, cursor)?);
+                            .push(parse_inlined_subroutine(dwarf, unit, cursor, builder)?);
                     }
                     gim_con::DW_TAG_formal_parameter => {
                         formal_parameters
-                            .push(parse_sub_parameter(dwarf, unit, cursor)?);
+                            .push(parse_sub_parameter(dwarf, unit, cursor, builder)?);
                     }
                     // lexical_block
                     _ => {
@@ -1635,6 +2136,7 @@ fn parse_static_variable(
     let mut type_id = None;
     let mut decl = DeclCoord::default();
     let mut location = None;
+    let mut specification = None;
 
     let offset = entry.offset().to_unit_section_offset(unit);
 
@@ -1642,42 +2144,26 @@ fn parse_static_variable(
     while let Some(attr) = attrs.next()? {
         match attr.name() {
             gim_con::DW_AT_name => {
-                name = Some(get_attr_string(dwarf, &attr)?);
+                name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_linkage_name => {
-                linkage_name = Some(get_attr_string(dwarf, &attr)?);
+                linkage_name = Some(get_attr_string(dwarf, unit, &attr)?);
             }
             gim_con::DW_AT_location => {
-                let e = attr.exprloc_value().unwrap();
-                let mut eval = e.evaluation(unit.encoding());
-                let mut result = eval.evaluate()?;
-                loop {
-                    match result {
-                        gimli::EvaluationResult::Complete => {
-                            let r = eval.result();
-                            if r.len() == 1 {
-                                match &r[0].location {
-                                    gimli::Location::Address { address } => {
-                                        location = Some(*address);
-                                        break;
-                                    }
-                                    x => {
-                                        panic!("unexpected static location: {:?}", x);
-                                    }
-                                }
-                            } else {
-                                panic!("unexpected eval results: {:?}", r);
-                            }
-                        }
-                        gimli::EvaluationResult::RequiresRelocatedAddress(a) => {
-                            result = eval.resume_with_relocated_address(a)?;
-
-                        }
-                        x => {
-                            println!("unhandled location expression at {:x?}: {:?}", offset, x);
+                location = match attr.exprloc_value() {
+                    Some(e) => evaluate_expr(dwarf, unit, e, None)?,
+                    None => match dwarf.attr_locations(unit, attr.value())? {
+                        Some(list) => list_location(dwarf, unit, list)?,
+                        None => {
+                            warn!(builder, "static location is not an expression at {:x?}: {:?}", offset, attr.value());
                             return skip_entry(cursor);
                         }
-                    } 
+                    },
+                };
+                if location.is_none() {
+                    // Thread-locals and the like don't have a fixed address.
+                    warn!(builder, "static location not fixed at {:x?}", offset);
+                    return skip_entry(cursor);
                 }
             }
             gim_con::DW_AT_type => {
@@ -1686,7 +2172,7 @@ fn parse_static_variable(
                 } else if let gimli::AttributeValue::DebugInfoRef(o) =
                     attr.value()
                 {
-                    type_id = Some(o.into());
+                    type_id = Some(info_ref(o));
                 } else {
                     panic!("unexpected type type: {:?}", attr.value());
                 }
@@ -1695,24 +2181,34 @@ fn parse_static_variable(
                 if let gimli::AttributeValue::FileIndex(f) = attr.value() {
                     if let Some(lp) = &unit.line_program {
                         if let Some(fent) = lp.header().file(f) {
-                            let file = get_path(dwarf, fent.path_name())?;
+                            let file = get_path(dwarf, unit, fent.path_name())?;
                             if let Some(dv) = fent.directory(lp.header()) {
                                 decl.file = Some(format!(
                                     "{}/{}",
-                                    get_path(dwarf, dv)?,
+                                    get_path(dwarf, unit, dv)?,
                                     file,
                                 ));
                             } else {
                                 decl.file = Some(file);
                             }
                         } else {
-                            eprintln!("WARN: invalid file index");
+                            warn!(builder, "invalid file index");
                         }
                     } else {
-                        eprintln!("WARN: missing line program");
+                        warn!(builder, "missing line program");
                     }
                 } else {
-                    eprintln!("WARN: unexpected call_file type: {:?}", attr.value());
+                    warn!(builder, "unexpected call_file type: {:?}", attr.value());
+                }
+            }
+            gim_con::DW_AT_specification => {
+                // C++ compilers describe namespace-scope variables with a
+                // declaration inside the namespace, and a definition (with
+                // the location) outside it that points back here.
+                if let gimli::AttributeValue::UnitRef(o) = attr.value() {
+                    specification = Some(o);
+                } else {
+                    warn!(builder, "unexpected specification type: {:?}", attr.value());
                 }
             }
             gim_con::DW_AT_decl_line => {
@@ -1732,20 +2228,33 @@ fn parse_static_variable(
         return Ok(());
     }
 
-    let type_id = TypeId(type_id.unwrap());
+    if let Some(o) = specification {
+        let spec = parse_specification(dwarf, unit, o)?;
+        name = name.or(spec.name);
+        linkage_name = linkage_name.or(spec.linkage_name);
+        type_id = type_id.or(spec.type_id);
+    }
+
+    let (Some(name), Some(type_id)) = (name, type_id) else {
+        warn!(builder, "static variable at {:x?} has no name or type", offset);
+        return Ok(());
+    };
+
+    let type_id = TypeId(type_id);
     let location = location.unwrap();
 
     let name = if linkage_name.is_none() {
         // This is a heuristic for detecting #[no_mangle] Rust variables.
-        name.unwrap()
+        name
     } else {
-        builder.format_path(name.unwrap())
+        builder.format_path(name)
     };
 
 
     builder.record_variable(StaticVariable {
         offset,
         name,
+        linkage_name,
         type_id,
         decl,
         location,
@@ -1753,3 +2262,188 @@ fn parse_static_variable(
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::TestTypes;
+    use crate::{DebugDb, NameMatch, Type};
+    use gimli::write::{Address, AttributeValue, DwarfUnit, EndianVec, Expression, Sections};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    /// An opcode gimli doesn't know.
+    const BAD_OP: u8 = 0xff;
+
+    const ADDRESSES: [u32; 2] = [0x2000_0000, 0x2000_0100];
+
+    /// Contents of `.debug_addr`, with a header and then `ADDRESSES`.
+    fn debug_addr() -> Vec<u8> {
+        let mut out = vec![];
+        out.extend_from_slice(&(4 + 4 * ADDRESSES.len() as u32).to_le_bytes());
+        out.extend_from_slice(&5u16.to_le_bytes());
+        out.extend_from_slice(&[4, 0]);
+        for a in ADDRESSES {
+            out.extend_from_slice(&a.to_le_bytes());
+        }
+        out
+    }
+
+    /// Writes out a 32-bit DWARF 5 unit made by `fill`, and parses it.
+    fn parse(fill: impl FnOnce(&mut DwarfUnit)) -> DebugDb {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 5,
+            address_size: 4,
+        };
+        let mut unit = DwarfUnit::new(encoding);
+        fill(&mut unit);
+        let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+        unit.write(&mut sections).unwrap();
+
+        let mut data = HashMap::new();
+        sections.for_each(|id, s| -> gimli::Result<()> {
+            data.insert(id, s.slice().to_vec());
+            Ok(())
+        }).unwrap();
+        data.insert(gimli::SectionId::DebugAddr, debug_addr());
+        let dwarf = gimli::Dwarf::load(|id| -> gimli::Result<_> {
+            let bytes = data.get(&id).cloned().unwrap_or_default();
+            Ok(gimli::EndianReader::new(Arc::from(bytes), gimli::RunTimeEndian::Little))
+        }).unwrap();
+        let header = dwarf.units().next().unwrap().unwrap();
+        let mut unit = dwarf.unit(header).unwrap();
+        // Past the header.
+        unit.addr_base = gimli::DebugAddrBase(8);
+
+        let mut builder = TestTypes::little().builder;
+        crate::parse_unit(&dwarf, &unit, &mut builder).unwrap();
+        builder.build().unwrap()
+    }
+
+    fn named(unit: &mut DwarfUnit, tag: gimli::DwTag, name: &str) -> gimli::write::UnitEntryId {
+        let root = unit.unit.root();
+        let id = unit.unit.add(root, tag);
+        unit.unit.get_mut(id).set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+        id
+    }
+
+    fn base(
+        unit: &mut DwarfUnit,
+        name: &str,
+        byte_size: u8,
+        encoding: gimli::DwAte,
+    ) -> gimli::write::UnitEntryId {
+        let id = named(unit, gimli::DW_TAG_base_type, name);
+        let entry = unit.unit.get_mut(id);
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Data1(byte_size));
+        entry.set(gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+        id
+    }
+
+    fn int(unit: &mut DwarfUnit) -> gimli::write::UnitEntryId {
+        base(unit, "int", 4, gimli::DW_ATE_signed)
+    }
+
+    #[test]
+    fn member_locations() {
+        let db = parse(|unit| {
+            let int = int(unit);
+            let s = named(unit, gimli::DW_TAG_structure_type, "S");
+            unit.unit.get_mut(s).set(gimli::DW_AT_byte_size, AttributeValue::Data1(16));
+            let mut plus_uconst = Expression::new();
+            plus_uconst.op_plus_uconst(4);
+            let mut constu = Expression::new();
+            constu.op_constu(8);
+            for (name, location) in [
+                ("a", AttributeValue::Data1(0)),
+                ("b", AttributeValue::Exprloc(plus_uconst)),
+                ("c", AttributeValue::Exprloc(constu)),
+                ("d", AttributeValue::Exprloc(Expression::raw(vec![BAD_OP]))),
+            ] {
+                let m = unit.unit.add(s, gimli::DW_TAG_member);
+                let entry = unit.unit.get_mut(m);
+                entry.set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(int));
+                entry.set(gimli::DW_AT_data_member_location, location);
+            }
+        });
+
+        let (_, Type::Struct(s)) = db.types_by_name("S", NameMatch::EXACT).next().unwrap() else {
+            panic!("S isn't a struct");
+        };
+        let locations: Vec<_> = s.members.iter()
+            .map(|m| (m.name.as_deref().unwrap(), m.location))
+            .collect();
+        // Members whose location can't be worked out are put at the start.
+        assert_eq!(locations, [("a", 0), ("b", 4), ("c", 8), ("d", 0)]);
+        assert_eq!(db.warnings().len(), 1);
+        assert!(db.warnings()[0].contains("member location is not constant"));
+    }
+
+    #[test]
+    fn variable_locations() {
+        let db = parse(|unit| {
+            let int = int(unit);
+            let mut addr = Expression::new();
+            addr.op_addr(Address::Constant(0x2000_0040));
+            for (name, location) in [
+                ("by_addr", addr),
+                ("by_index", Expression::raw(vec![gimli::DW_OP_addrx.0, 1])),
+                ("unsupported", Expression::raw(vec![BAD_OP])),
+            ] {
+                let v = named(unit, gimli::DW_TAG_variable, name);
+                let entry = unit.unit.get_mut(v);
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(int));
+                entry.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+            }
+        });
+
+        let address = |name| db.unique_static_variable_by_name(name).map(|(_, v)| v.location);
+        assert_eq!(address("by_addr"), Some(0x2000_0040));
+        assert_eq!(address("by_index"), Some(u64::from(ADDRESSES[1])));
+        assert_eq!(address("unsupported"), None);
+        assert_eq!(db.warnings().len(), 1);
+        assert!(db.warnings()[0].contains("static location not fixed"));
+    }
+
+    #[test]
+    fn multidimensional_arrays() {
+        // struct Grid { int cells[4][8]; };
+        let db = parse(|unit| {
+            let int = int(unit);
+            let index = base(unit, "sizetype", 8, gimli::DW_ATE_unsigned);
+            let root = unit.unit.root();
+            let array = unit.unit.add(root, gimli::DW_TAG_array_type);
+            unit.unit.get_mut(array).set(gimli::DW_AT_type, AttributeValue::UnitRef(int));
+            // Bounds given as a count and, as C compilers do, the last index.
+            for bound in [(gimli::DW_AT_count, 4), (gimli::DW_AT_upper_bound, 7)] {
+                let subrange = unit.unit.add(array, gimli::DW_TAG_subrange_type);
+                let entry = unit.unit.get_mut(subrange);
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(index));
+                entry.set(bound.0, AttributeValue::Data1(bound.1));
+            }
+            let grid = named(unit, gimli::DW_TAG_structure_type, "Grid");
+            unit.unit.get_mut(grid).set(gimli::DW_AT_byte_size, AttributeValue::Data1(128));
+            let cells = unit.unit.add(grid, gimli::DW_TAG_member);
+            let entry = unit.unit.get_mut(cells);
+            entry.set(gimli::DW_AT_name, AttributeValue::String("cells".into()));
+            entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(array));
+            entry.set(gimli::DW_AT_data_member_location, AttributeValue::Data1(0));
+        });
+
+        let (_, Type::Struct(grid)) = db.types_by_name("Grid", NameMatch::EXACT).next().unwrap() else {
+            panic!("Grid isn't a struct");
+        };
+        let cells = db.type_by_id(grid.members[0].type_id).unwrap();
+        let Type::Array(outer) = cells else { panic!("cells isn't an array") };
+        assert_eq!(outer.count, Some(4));
+        let Some(Type::Array(inner)) = db.type_by_id(outer.element_type_id) else {
+            panic!("cells isn't an array of arrays");
+        };
+        assert_eq!(inner.count, Some(8));
+        assert_eq!(db.type_name(inner.element_type_id).as_deref(), Some("int"));
+        assert_eq!(cells.byte_size(&db), Some(128));
+        assert_eq!(db.type_name(grid.members[0].type_id).as_deref(), Some("[[int; 8]; 4]"));
+    }
+}
+
@@ -0,0 +1,247 @@
+//! Filter expressions for picking out types by their properties, e.g.
+//! `kind=struct size>256 align=4 name~Buffer`.
+//!
+//! A filter is a whitespace-separated list of conditions, all of which must
+//! hold. Each condition compares a field of the type to a value:
+//!
+//! - `kind`, with `=` or `!=`, against `TypeKind::name`.
+//! - `name`, with `=` or `!=` for the whole name, or `~` or `!~` for a regex
+//!   search within it.
+//! - `size` and `align`, in bytes, with any of `=`, `!=`, `<`, `<=`, `>` and
+//!   `>=`. Types without a size or alignment fail these conditions.
+
+use crate::{DebugDb, Type, TypeId, TypeKind};
+use regex::Regex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FilterError {
+    #[error("expected a condition like `field=value`, found `{0}`")]
+    BadTerm(String),
+    #[error("unknown field `{0}`; try kind, name, size or align")]
+    UnknownField(String),
+    #[error("unknown kind `{0}`; try struct, enum, union, ptr, base or alias")]
+    UnknownKind(String),
+    #[error("operator `{op}` can't be used with `{field}`")]
+    BadOperator { field: String, op: String },
+    #[error("bad number `{0}`")]
+    BadNumber(String),
+    #[error("bad pattern")]
+    BadPattern(#[from] regex::Error),
+}
+
+/// A parsed filter expression; see the module docs for the syntax.
+#[derive(Clone, Debug)]
+pub struct TypeFilter {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Clone, Debug)]
+enum Condition {
+    Kind { negate: bool, kind: TypeKind },
+    Name { negate: bool, name: String },
+    NameMatches { negate: bool, pattern: Regex },
+    Size(Comparison, u64),
+    Align(Comparison, u64),
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn from_op(op: &str) -> Option<Self> {
+        Some(match op {
+            "=" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            _ => return None,
+        })
+    }
+
+    fn holds(self, lhs: u64, rhs: u64) -> bool {
+        match self {
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+        }
+    }
+}
+
+const OPERATOR_CHARS: &[char] = &['=', '!', '<', '>', '~'];
+
+impl TypeFilter {
+    /// Parses a filter expression. An empty expression matches everything.
+    pub fn parse(s: &str) -> Result<Self, FilterError> {
+        let conditions = s.split_whitespace()
+            .map(Condition::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { conditions })
+    }
+
+    /// Checks whether the type `id` passes the filter.
+    pub fn matches(&self, world: &DebugDb, id: TypeId, ty: &Type) -> bool {
+        self.conditions.iter().all(|c| c.matches(world, id, ty))
+    }
+}
+
+impl Condition {
+    fn parse(term: &str) -> Result<Self, FilterError> {
+        let bad_term = || FilterError::BadTerm(term.to_string());
+        let op_start = term.find(OPERATOR_CHARS).ok_or_else(bad_term)?;
+        let (field, rest) = term.split_at(op_start);
+        let op_end = rest.find(|c| !OPERATOR_CHARS.contains(&c))
+            .ok_or_else(bad_term)?;
+        let (op, value) = rest.split_at(op_end);
+        if field.is_empty() {
+            return Err(bad_term());
+        }
+        let bad_op = || FilterError::BadOperator {
+            field: field.to_string(),
+            op: op.to_string(),
+        };
+        let number = || parse_int::parse::<u64>(value)
+            .map_err(|_| FilterError::BadNumber(value.to_string()));
+
+        Ok(match field {
+            "kind" => match op {
+                "=" | "!=" => Self::Kind {
+                    negate: op == "!=",
+                    kind: TypeKind::from_name(value)
+                        .ok_or_else(|| FilterError::UnknownKind(value.to_string()))?,
+                },
+                _ => return Err(bad_op()),
+            },
+            "name" => match op {
+                "=" | "!=" => Self::Name {
+                    negate: op == "!=",
+                    name: value.to_string(),
+                },
+                "~" | "!~" => Self::NameMatches {
+                    negate: op == "!~",
+                    pattern: Regex::new(value)?,
+                },
+                _ => return Err(bad_op()),
+            },
+            "size" => {
+                Self::Size(Comparison::from_op(op).ok_or_else(bad_op)?, number()?)
+            }
+            "align" => {
+                Self::Align(Comparison::from_op(op).ok_or_else(bad_op)?, number()?)
+            }
+            _ => return Err(FilterError::UnknownField(field.to_string())),
+        })
+    }
+
+    fn matches(&self, world: &DebugDb, id: TypeId, ty: &Type) -> bool {
+        match self {
+            Self::Kind { negate, kind } => (ty.kind() == *kind) != *negate,
+            Self::Name { negate, name } => {
+                let n = world.type_name(id);
+                (n.as_deref() == Some(name.as_str())) != *negate
+            }
+            Self::NameMatches { negate, pattern } => {
+                let n = world.type_name(id);
+                n.is_some_and(|n| pattern.is_match(&n)) != *negate
+            }
+            Self::Size(cmp, n) => {
+                ty.byte_size(world).is_some_and(|z| cmp.holds(z, *n))
+            }
+            Self::Align(cmp, n) => {
+                ty.alignment(world).is_some_and(|a| cmp.holds(a, *n))
+            }
+        }
+    }
+}
+
+impl DebugDb {
+    /// Returns an iterator over the canonical types that pass `filter`.
+    pub fn types_filtered<'d>(
+        &'d self,
+        filter: &'d TypeFilter,
+    ) -> impl Iterator<Item = (TypeId, &'d Type)> + 'd {
+        self.canonical_types()
+            .filter(move |&(id, ty)| filter.matches(self, id, ty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+
+    /// Builds a database with `u8`, `u32`, and two structs, and returns the
+    /// names of the types each filter picks out.
+    fn filtered(filter: &str) -> Vec<String> {
+        let mut types = TestTypes::little();
+        let u8_id = types.base("u8", 1);
+        let u32_id = types.base("u32", 4);
+        types.structure("Small", 2, &[("a", u8_id, 0), ("b", u8_id, 1)]);
+        types.structure("BigBuffer", 512, &[("len", u32_id, 0)]);
+        let world = types.build();
+
+        let filter = TypeFilter::parse(filter).unwrap();
+        let mut names: Vec<_> = world.types_filtered(&filter)
+            .map(|(id, _)| world.type_name(id).unwrap().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(filtered(""), ["BigBuffer", "Small", "u32", "u8"]);
+        assert_eq!(filtered("kind=struct"), ["BigBuffer", "Small"]);
+        assert_eq!(filtered("kind!=struct"), ["u32", "u8"]);
+        assert_eq!(filtered("name=u8"), ["u8"]);
+        assert_eq!(filtered("name!=u8"), ["BigBuffer", "Small", "u32"]);
+        assert_eq!(filtered("name~^[A-Z]"), ["BigBuffer", "Small"]);
+        assert_eq!(filtered("name!~Buf"), ["Small", "u32", "u8"]);
+        assert_eq!(filtered("size>256"), ["BigBuffer"]);
+        assert_eq!(filtered("size<=2"), ["Small", "u8"]);
+        assert_eq!(filtered("size=0x200"), ["BigBuffer"]);
+        // Struct alignment is inferred from the members.
+        assert_eq!(filtered("align=4"), ["BigBuffer", "u32"]);
+        assert_eq!(filtered("align<4"), ["Small", "u8"]);
+    }
+
+    #[test]
+    fn conditions_all_hold() {
+        assert_eq!(filtered("kind=struct align=1"), ["Small"]);
+        assert_eq!(filtered("kind=base size>1 name~u"), ["u32"]);
+        assert!(filtered("kind=struct kind=base").is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |s| TypeFilter::parse(s).unwrap_err();
+        assert!(matches!(err("size"), FilterError::BadTerm(t) if t == "size"));
+        assert!(matches!(err("=4"), FilterError::BadTerm(_)));
+        assert!(matches!(err("size>"), FilterError::BadTerm(_)));
+        assert!(matches!(err("colour=red"), FilterError::UnknownField(f) if f == "colour"));
+        assert!(matches!(err("kind=blob"), FilterError::UnknownKind(k) if k == "blob"));
+        assert!(matches!(
+            err("kind<struct"),
+            FilterError::BadOperator { field, op } if field == "kind" && op == "<"
+        ));
+        assert!(matches!(err("size~4"), FilterError::BadOperator { .. }));
+        assert!(matches!(err("size=big"), FilterError::BadNumber(n) if n == "big"));
+        assert!(matches!(err("name~("), FilterError::BadPattern(_)));
+        assert_eq!(
+            err("align=>4").to_string(),
+            "operator `=>` can't be used with `align`",
+        );
+    }
+}
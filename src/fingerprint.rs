@@ -0,0 +1,251 @@
+//! Content hashes of types, which stay the same from one build of a program
+//! to the next as long as the types themselves don't change.
+//!
+//! A type's ID is its offset in the debug info, which moves whenever anything
+//! before it changes. A `Fingerprint` instead covers what the type is: its
+//! kind, name and size, and its members' names, offsets and types. Source
+//! locations aren't included, so moving a type around a file (or building in a
+//! different directory) doesn't change it.
+//!
+//! Types contained in a type (members, array elements, the targets of typedefs
+//! and qualifiers) contribute their whole contents. Types that are only
+//! referred to (pointees, parameters and template arguments) contribute just
+//! their kind and name, so a change to a struct doesn't ripple out to
+//! everything holding a pointer to it, and recursive types have finite
+//! fingerprints.
+
+use crate::{DebugDb, Member, TemplateTypeParameter, Type, TypeId, Variant, VariantShape};
+
+/// A 64-bit content hash of a type; see the module docs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Fingerprint(pub u64);
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// 64-bit FNV-1a. The standard library's hashers aren't guaranteed to give the
+/// same results across Rust versions, which fingerprints need.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn u64(&mut self, n: u64) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn opt_u64(&mut self, n: Option<u64>) {
+        match n {
+            Some(n) => {
+                self.bytes(&[1]);
+                self.u64(n);
+            }
+            None => self.bytes(&[0]),
+        }
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.bytes(&[b as u8]);
+    }
+
+    /// Hashes a string along with its length, so that consecutive strings
+    /// can't run into each other.
+    fn str(&mut self, s: &str) {
+        self.u64(s.len() as u64);
+        self.bytes(s.as_bytes());
+    }
+
+    fn opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.bytes(&[1]);
+                self.str(s);
+            }
+            None => self.bytes(&[0]),
+        }
+    }
+}
+
+impl DebugDb {
+    /// Computes the fingerprint of type `id`, or returns `None` if there's no
+    /// such type.
+    ///
+    /// Types that are unified with each other have the same fingerprint, and
+    /// so do matching types in different builds or different programs.
+    pub fn fingerprint(&self, id: TypeId) -> Option<Fingerprint> {
+        self.type_by_id(id)?;
+        let mut h = Fnv::new();
+        self.hash_contained(id, &mut vec![], &mut h);
+        Some(Fingerprint(h.0))
+    }
+
+    /// Hashes the whole contents of type `id`. `stack` holds the types being
+    /// hashed further out, so that a type reached again (which can only happen
+    /// through anonymous types) is hashed as a reference back to it.
+    fn hash_contained(&self, id: TypeId, stack: &mut Vec<TypeId>, h: &mut Fnv) {
+        if let Some(depth) = stack.iter().position(|&s| s == id) {
+            h.str("back");
+            h.u64(depth as u64);
+            return;
+        }
+        let Some(t) = self.type_by_id(id) else {
+            h.str("missing");
+            return;
+        };
+        stack.push(id);
+        h.str(t.kind_name());
+        match t {
+            Type::Base(b) => {
+                h.str(&b.name);
+                h.str(&format!("{:?}", b.encoding));
+                h.u64(b.byte_size);
+                h.opt_u64(b.alignment);
+            }
+            Type::Struct(s) => {
+                h.str(&s.name);
+                h.opt_u64(s.byte_size);
+                h.opt_u64(s.alignment);
+                h.bool(s.tuple_like);
+                self.hash_params(&s.template_type_parameters, stack, h);
+                self.hash_members(&s.members, stack, h);
+            }
+            Type::Union(u) => {
+                h.str(&u.name);
+                h.u64(u.byte_size);
+                h.opt_u64(u.alignment);
+                self.hash_params(&u.template_type_parameters, stack, h);
+                self.hash_members(&u.members, stack, h);
+            }
+            Type::Enum(e) => {
+                h.str(&e.name);
+                h.opt_u64(e.byte_size);
+                h.opt_u64(e.alignment);
+                self.hash_params(&e.template_type_parameters, stack, h);
+                match &e.shape {
+                    VariantShape::Zero => h.str("zero"),
+                    VariantShape::One(v) => {
+                        h.str("one");
+                        self.hash_variant(v, stack, h);
+                    }
+                    VariantShape::Many { member, variants, .. } => {
+                        h.str("many");
+                        self.hash_members(std::slice::from_ref(member), stack, h);
+                        h.u64(variants.len() as u64);
+                        for (value, v) in variants {
+                            h.opt_u64(*value);
+                            self.hash_variant(v, stack, h);
+                        }
+                    }
+                }
+            }
+            Type::CEnum(e) => {
+                h.str(&e.name);
+                h.bool(e.enum_class);
+                h.u64(e.byte_size);
+                h.opt_u64(e.alignment);
+                h.u64(e.enumerators.len() as u64);
+                for (value, en) in &e.enumerators {
+                    h.u64(*value);
+                    h.str(&en.name);
+                }
+            }
+            Type::Array(a) => {
+                h.u64(a.lower_bound);
+                h.opt_u64(a.count);
+                self.hash_contained(a.element_type_id, stack, h);
+            }
+            Type::Pointer(p) => {
+                h.opt_str(p.name.as_deref());
+                h.opt_u64(p.byte_size);
+                h.opt_u64(p.address_class);
+                self.hash_referenced(p.type_id, stack, h);
+            }
+            Type::Subroutine(s) => {
+                h.bool(s.variadic);
+                h.bool(s.prototyped);
+                h.opt_u64(s.calling_convention.map(|cc| u64::from(cc.0)));
+                match s.return_type_id {
+                    Some(rt) => {
+                        h.bool(true);
+                        self.hash_referenced(rt, stack, h);
+                    }
+                    None => h.bool(false),
+                }
+                h.u64(s.formal_parameters.len() as u64);
+                for &p in &s.formal_parameters {
+                    self.hash_referenced(p, stack, h);
+                }
+            }
+            Type::Typedef(t) => {
+                h.str(&t.name);
+                self.hash_contained(t.type_id, stack, h);
+            }
+            Type::Qualified(q) => {
+                h.str(&q.qualifier.to_string());
+                self.hash_contained(q.type_id, stack, h);
+            }
+            Type::Unresolved(_) => (),
+        }
+        stack.pop();
+    }
+
+    /// Hashes a type that's referred to rather than contained: named types by
+    /// just their kind and name, and anything else in full.
+    fn hash_referenced(&self, id: TypeId, stack: &mut Vec<TypeId>, h: &mut Fnv) {
+        let name = match self.type_by_id(id) {
+            Some(Type::Struct(s)) => &s.name,
+            Some(Type::Union(u)) => &u.name,
+            Some(Type::Enum(e)) => &e.name,
+            Some(Type::CEnum(e)) => &e.name,
+            Some(Type::Base(b)) => &b.name,
+            Some(Type::Typedef(t)) => &t.name,
+            _ => return self.hash_contained(id, stack, h),
+        };
+        if name.is_empty() {
+            return self.hash_contained(id, stack, h);
+        }
+        h.str("ref");
+        h.str(self.type_by_id(id).unwrap().kind_name());
+        h.str(name);
+    }
+
+    fn hash_members(&self, members: &[Member], stack: &mut Vec<TypeId>, h: &mut Fnv) {
+        h.u64(members.len() as u64);
+        for m in members {
+            h.opt_str(m.name.as_deref());
+            h.bool(m.artificial);
+            h.u64(m.location);
+            h.opt_u64(m.alignment);
+            self.hash_contained(m.type_id, stack, h);
+        }
+    }
+
+    fn hash_variant(&self, v: &Variant, stack: &mut Vec<TypeId>, h: &mut Fnv) {
+        self.hash_members(std::slice::from_ref(&v.member), stack, h);
+    }
+
+    fn hash_params(
+        &self,
+        params: &[TemplateTypeParameter],
+        stack: &mut Vec<TypeId>,
+        h: &mut Fnv,
+    ) {
+        h.u64(params.len() as u64);
+        for p in params {
+            h.str(&p.name);
+            self.hash_referenced(p.type_id, stack, h);
+        }
+    }
+}
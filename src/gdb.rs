@@ -0,0 +1,311 @@
+//! A client for the GDB remote serial protocol, implementing enough of it to
+//! read memory and registers from a live target through a debug server like
+//! OpenOCD, QEMU's gdbstub or `gdbserver`.
+//!
+//! `GdbClient` implements `Machine`, so values can be loaded from a running
+//! target in the same way as from a memory image.
+
+use std::cell::RefCell;
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::load::Machine;
+
+/// Packet size assumed if the server doesn't report one.
+const DEFAULT_PACKET_SIZE: usize = 256;
+
+/// Times a packet is resent when the server reports a bad checksum.
+const RETRIES: usize = 3;
+
+/// How long to wait for the server to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum GdbError {
+    #[error("can't talk to the target: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed reply from the target: {0}")]
+    Protocol(String),
+    #[error("target reported error {0:#04x}")]
+    Target(u8),
+    #[error("request not supported by the target")]
+    Unsupported,
+}
+
+/// A connection to a GDB server.
+///
+/// Requests are made through `&self`, so that the client can be used as a
+/// `Machine`; the connection is kept in a `RefCell`, making the client
+/// `!Sync`.
+pub struct GdbClient {
+    conn: RefCell<Connection>,
+    /// Largest packet the server accepts, in bytes.
+    packet_size: usize,
+}
+
+impl GdbClient {
+    /// Connects to a GDB server listening at `addr`, such as
+    /// `localhost:3333` for OpenOCD, and negotiates the protocol features
+    /// used by the client.
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, GdbError> {
+        // Try each address the name resolves to in turn, as
+        // `TcpStream::connect` does, but without waiting forever on any.
+        let mut stream = Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "address resolved to nothing",
+        ));
+        for a in addr.to_socket_addrs()? {
+            stream = TcpStream::connect_timeout(&a, CONNECT_TIMEOUT);
+            if stream.is_ok() {
+                break;
+            }
+        }
+        let stream = stream?;
+        stream.set_nodelay(true)?;
+        let mut conn = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            ack: true,
+        };
+
+        let reply = conn.request(b"qSupported")?;
+        let mut packet_size = DEFAULT_PACKET_SIZE;
+        let mut no_ack = false;
+        for feature in reply.split(|&b| b == b';') {
+            if let Some(size) = feature.strip_prefix(b"PacketSize=") {
+                packet_size = std::str::from_utf8(size).ok()
+                    .and_then(|s| usize::from_str_radix(s, 16).ok())
+                    .ok_or_else(|| GdbError::Protocol("bad PacketSize".into()))?;
+            } else if feature == b"QStartNoAckMode+" {
+                no_ack = true;
+            }
+        }
+        // Acknowledgements only matter over unreliable links, and cost a
+        // round trip per packet.
+        if no_ack && conn.request(b"QStartNoAckMode")? == b"OK" {
+            conn.ack = false;
+        }
+        // Servers expect to be asked why the target stopped before anything
+        // else; the answer isn't needed here.
+        conn.request(b"?")?;
+
+        Ok(Self {
+            conn: RefCell::new(conn),
+            packet_size,
+        })
+    }
+
+    /// Bytes of memory that fit in one reply, given that each is sent as two
+    /// hex digits.
+    fn chunk_size(&self) -> usize {
+        (self.packet_size.saturating_sub(8) / 2).max(1)
+    }
+
+    /// Writes `data` to the target's memory at `address`.
+    pub fn write_memory(&self, address: u64, data: &[u8]) -> Result<(), GdbError> {
+        let mut conn = self.conn.borrow_mut();
+        // Each byte takes two hex digits, plus room for the header.
+        let chunk_size = (self.packet_size.saturating_sub(32) / 2).max(1);
+        for (i, chunk) in data.chunks(chunk_size).enumerate() {
+            let addr = address + (i * chunk_size) as u64;
+            let mut packet = format!("M{addr:x},{:x}:", chunk.len()).into_bytes();
+            packet.extend(encode_hex(chunk));
+            expect_ok(&conn.request(&packet)?)?;
+        }
+        Ok(())
+    }
+
+    /// Reads all general registers, in the target-specific order and byte
+    /// order used by the server. Bytes the server reports as unavailable
+    /// are zero.
+    pub fn read_registers(&self) -> Result<Vec<u8>, GdbError> {
+        let reply = self.conn.borrow_mut().request(b"g")?;
+        check_error(&reply)?;
+        decode_hex(&reply)
+    }
+
+    /// Reads register `n`, numbered as in the server's target description
+    /// (which isn't necessarily the DWARF numbering), in target byte order.
+    pub fn read_register(&self, n: usize) -> Result<Vec<u8>, GdbError> {
+        let reply = self.conn.borrow_mut().request(format!("p{n:x}").as_bytes())?;
+        if reply.is_empty() {
+            return Err(GdbError::Unsupported);
+        }
+        check_error(&reply)?;
+        decode_hex(&reply)
+    }
+
+    /// Detaches from the target, letting it run freely, and closes the
+    /// connection.
+    pub fn detach(self) -> Result<(), GdbError> {
+        let reply = self.conn.into_inner().request(b"D")?;
+        expect_ok(&reply)
+    }
+}
+
+impl Machine for GdbClient {
+    type Error = GdbError;
+
+    fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut conn = self.conn.borrow_mut();
+        let mut done = 0;
+        for chunk in dest.chunks_mut(self.chunk_size()) {
+            let addr = address.wrapping_add(done as u64);
+            let reply = conn.request(format!("m{addr:x},{:x}", chunk.len()).as_bytes())?;
+            if reply.is_empty() {
+                return Err(GdbError::Unsupported);
+            }
+            // An error here means the memory can't be accessed, which is a
+            // hole in the address space rather than a failure.
+            if check_error(&reply).is_err() {
+                break;
+            }
+            let bytes = decode_hex(&reply)?;
+            let n = usize::min(bytes.len(), chunk.len());
+            chunk[..n].copy_from_slice(&bytes[..n]);
+            done += n;
+            if n < chunk.len() {
+                break;
+            }
+        }
+        Ok(done)
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Whether packets are still being acknowledged with `+`.
+    ack: bool,
+}
+
+impl Connection {
+    fn request(&mut self, packet: &[u8]) -> Result<Vec<u8>, GdbError> {
+        self.send(packet)?;
+        self.receive()
+    }
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), GdbError> {
+        let mut framed = Vec::with_capacity(packet.len() + 4);
+        framed.push(b'$');
+        framed.extend_from_slice(packet);
+        framed.push(b'#');
+        framed.extend(encode_hex(&[checksum(packet)]));
+
+        for _ in 0..RETRIES {
+            self.writer.write_all(&framed)?;
+            self.writer.flush()?;
+            if !self.ack {
+                return Ok(());
+            }
+            match self.read_byte()? {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                b => return Err(GdbError::Protocol(format!("expected ack, got {:?}", b as char))),
+            }
+        }
+        Err(GdbError::Protocol("packet rejected repeatedly".into()))
+    }
+
+    /// Receives a packet, undoing escaping and run-length encoding.
+    fn receive(&mut self) -> Result<Vec<u8>, GdbError> {
+        loop {
+            // Skip anything before the start of the packet, such as stray
+            // acknowledgements.
+            while self.read_byte()? != b'$' {}
+
+            let mut sum = 0u8;
+            let mut data = vec![];
+            loop {
+                let b = self.read_byte()?;
+                if b == b'#' {
+                    break;
+                }
+                sum = sum.wrapping_add(b);
+                match b {
+                    b'}' => {
+                        let e = self.read_byte()?;
+                        sum = sum.wrapping_add(e);
+                        data.push(e ^ 0x20);
+                    }
+                    b'*' => {
+                        let n = self.read_byte()?;
+                        sum = sum.wrapping_add(n);
+                        let &last = data.last()
+                            .ok_or_else(|| GdbError::Protocol("repeat at start of packet".into()))?;
+                        let count = usize::from(n).checked_sub(29)
+                            .ok_or_else(|| GdbError::Protocol("bad repeat count".into()))?;
+                        data.extend(std::iter::repeat_n(last, count));
+                    }
+                    _ => data.push(b),
+                }
+            }
+            let mut cs = [0; 2];
+            self.reader.read_exact(&mut cs)?;
+
+            if !self.ack {
+                return Ok(data);
+            }
+            if decode_hex(&cs)? == [sum] {
+                self.writer.write_all(b"+")?;
+                return Ok(data);
+            }
+            self.writer.write_all(b"-")?;
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8, GdbError> {
+        let mut b = [0];
+        self.reader.read_exact(&mut b)?;
+        Ok(b[0])
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Turns an `Exx` reply into an error.
+fn check_error(reply: &[u8]) -> Result<(), GdbError> {
+    match reply {
+        [b'E', code @ ..] if code.len() == 2 => {
+            Err(GdbError::Target(decode_hex(code)?[0]))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn expect_ok(reply: &[u8]) -> Result<(), GdbError> {
+    check_error(reply)?;
+    match reply {
+        b"OK" => Ok(()),
+        b"" => Err(GdbError::Unsupported),
+        _ => Err(GdbError::Protocol(String::from_utf8_lossy(reply).into_owned())),
+    }
+}
+
+fn encode_hex(data: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    data.iter().flat_map(|&b| [DIGITS[usize::from(b >> 4)], DIGITS[usize::from(b & 0xf)]])
+}
+
+/// Decodes pairs of hex digits. `xx`, which servers send for bytes they
+/// can't provide, decodes as zero.
+fn decode_hex(hex: &[u8]) -> Result<Vec<u8>, GdbError> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(GdbError::Protocol("odd number of hex digits".into()));
+    }
+    hex.chunks(2)
+        .map(|pair| match pair {
+            b"xx" => Ok(0),
+            _ => std::str::from_utf8(pair).ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    GdbError::Protocol(format!("bad hex {:?}", String::from_utf8_lossy(pair)))
+                }),
+        })
+        .collect()
+}
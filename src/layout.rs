@@ -0,0 +1,711 @@
+//! Analysis of how structs, unions and enums are laid out in memory, and in
+//! particular where their padding is, in the manner of `pahole`.
+//!
+//! `DebugDb::layouts` describes each member of a type by its offset and size,
+//! along with the unused bytes before it. Since the results are plain data,
+//! they can be sorted, e.g. by `Layout::padding`, to find the types that waste
+//! the most space.
+//!
+//! `DebugDb::size_breakdown` goes further, following members into the types
+//! they contain to attribute every byte of a type to some member (or padding)
+//! at the deepest level.
+//!
+//! `DebugDb::layout_of` also follows members all the way down, but keeps the
+//! shape of each type -- struct members, union alternatives, enum variants and
+//! their discriminator, array elements -- with offsets from the start of the
+//! outermost type, for tools that want to draw or search layouts themselves.
+
+use crate::{DebugDb, Member, Name, Type, TypeId, VariantShape};
+use std::ops::Range;
+
+/// Arrangement of the members of a struct or union, or of one variant of an
+/// enum.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    /// The type being described.
+    pub type_id: TypeId,
+    /// For an enum, the name of the variant being described. `None` for
+    /// structs and unions.
+    pub variant: Option<Name>,
+    /// Size of the whole type, in bytes.
+    pub byte_size: u64,
+    /// Members in order of increasing offset. For an enum variant, this is the
+    /// discriminator (if there is one) followed by the fields of the variant.
+    pub members: Vec<MemberLayout>,
+    /// Number of unused bytes after the end of the last member.
+    pub trailing_padding: u64,
+}
+
+/// Position of a single member within a `Layout`.
+#[derive(Clone, Debug)]
+pub struct MemberLayout {
+    /// Name of the member, if it has one.
+    pub name: Option<Name>,
+    /// Type of the member.
+    pub type_id: TypeId,
+    /// Offset of the member from the start of the enclosing type, in bytes.
+    pub offset: u64,
+    /// Size of the member in bytes, or `None` if its type has no size. Such
+    /// members are treated as zero-sized.
+    pub byte_size: Option<u64>,
+    /// Number of unused bytes between the end of the previous member (or the
+    /// start of the type) and this one. Members that overlap the previous
+    /// member have no padding before them.
+    pub padding_before: u64,
+}
+
+impl MemberLayout {
+    /// Gets the offset just past the end of the member.
+    pub fn end(&self) -> u64 {
+        self.offset + self.byte_size.unwrap_or(0)
+    }
+}
+
+impl Layout {
+    /// Total number of padding bytes between members.
+    pub fn internal_padding(&self) -> u64 {
+        self.members.iter().map(|m| m.padding_before).sum()
+    }
+
+    /// Total number of padding bytes, between members and at the end.
+    pub fn padding(&self) -> u64 {
+        self.internal_padding() + self.trailing_padding
+    }
+
+    /// Lists the ranges of bytes not covered by any member, in order,
+    /// including any trailing padding.
+    pub fn holes(&self) -> Vec<Range<u64>> {
+        let mut holes: Vec<_> = self.members.iter()
+            .filter(|m| m.padding_before != 0)
+            .map(|m| m.offset - m.padding_before..m.offset)
+            .collect();
+        if self.trailing_padding != 0 {
+            holes.push(self.byte_size - self.trailing_padding..self.byte_size);
+        }
+        holes
+    }
+
+    /// Builds a layout from members in any order.
+    fn new(
+        type_id: TypeId,
+        variant: Option<Name>,
+        byte_size: u64,
+        mut members: Vec<MemberLayout>,
+    ) -> Self {
+        members.sort_by_key(|m| (m.offset, m.byte_size));
+        let mut end = 0;
+        for m in &mut members {
+            m.padding_before = m.offset.saturating_sub(end);
+            end = u64::max(end, m.end());
+        }
+        Self {
+            type_id,
+            variant,
+            byte_size,
+            members,
+            trailing_padding: byte_size.saturating_sub(end),
+        }
+    }
+}
+
+/// One part of a type's size, in the tree produced by
+/// `DebugDb::size_breakdown`.
+#[derive(Clone, Debug)]
+pub struct SizePart {
+    /// What this part is.
+    pub kind: PartKind,
+    /// Type of the part, or `None` for padding.
+    pub type_id: Option<TypeId>,
+    /// Offset of the part from the start of the part containing it (or of one
+    /// repetition of it; see `repeat`), in bytes.
+    pub offset: u64,
+    /// Number of bytes the part accounts for in total.
+    pub byte_size: u64,
+    /// How many times `parts` are repeated, which is the element count for
+    /// an array, and otherwise 1. `parts` describe a single repetition.
+    pub repeat: u64,
+    /// True if `parts` overlap one another, as the members of a union or the
+    /// variants of an enum do, rather than adding up to `byte_size`.
+    pub overlapping: bool,
+    /// The parts that make up this one, in order of offset. Empty for types
+    /// that can't be broken down further.
+    pub parts: Vec<SizePart>,
+}
+
+/// What a `SizePart` is.
+#[derive(Clone, Debug)]
+pub enum PartKind {
+    /// The whole type, at the root of the tree.
+    Whole,
+    /// A member of a struct or union, or a field of an enum variant.
+    Member(Option<Name>),
+    /// A variant of an enum, covering its fields and the discriminator.
+    Variant(Option<Name>),
+    /// Unused bytes. Padding between members is there to align the member
+    /// that follows it; padding at the end is there to align the type as a
+    /// whole.
+    Padding,
+}
+
+impl SizePart {
+    /// Total number of padding bytes in this part, at any depth. Overlapping
+    /// parts only count their padding that isn't covered by another part, so
+    /// the padding of enum variants and union members isn't included.
+    pub fn padding(&self) -> u64 {
+        match self.kind {
+            PartKind::Padding => self.byte_size,
+            _ if self.overlapping => self.repeat * self.parts.iter()
+                .filter(|p| matches!(p.kind, PartKind::Padding))
+                .map(|p| p.byte_size)
+                .sum::<u64>(),
+            _ => self.repeat * self.parts.iter().map(SizePart::padding).sum::<u64>(),
+        }
+    }
+}
+
+/// The complete layout of a type, and of everything inside it, as produced by
+/// `DebugDb::layout_of`.
+#[derive(Clone, Debug)]
+pub struct TypeLayout {
+    /// The type being described. This is the type as it was referred to, so
+    /// may be a typedef; `contents` describes the type it stands for.
+    pub type_id: TypeId,
+    /// Offset from the start of the outermost type, in bytes.
+    pub offset: u64,
+    /// Size in bytes, or `None` if the type has no size.
+    pub byte_size: Option<u64>,
+    /// What's inside the type.
+    pub contents: LayoutContents,
+}
+
+/// What's inside a `TypeLayout`.
+#[derive(Clone, Debug)]
+pub enum LayoutContents {
+    /// Nothing to break down: base types, pointers, C-like enums, and types
+    /// without any debug info.
+    Scalar,
+    /// The members of a struct, in order of offset.
+    Struct {
+        members: Vec<FieldLayout>,
+        /// Byte ranges not covered by any member, including trailing padding.
+        padding: Vec<Range<u64>>,
+    },
+    /// The members of a union, which all start at its beginning.
+    Union {
+        members: Vec<FieldLayout>,
+        /// Byte ranges not covered by any member.
+        padding: Vec<Range<u64>>,
+    },
+    /// The variants of a Rust-style enum.
+    Enum {
+        /// The member holding the discriminator, if there is one.
+        discriminator: Option<Box<FieldLayout>>,
+        variants: Vec<VariantLayout>,
+    },
+    /// An array, of which only the first element is laid out; the others are
+    /// the same, `stride` bytes apart.
+    Array {
+        count: Option<u64>,
+        stride: Option<u64>,
+        /// The first element, or `None` for empty arrays.
+        element: Option<Box<TypeLayout>>,
+    },
+    /// The type contains itself, which can only happen in broken debug info,
+    /// so isn't laid out a second time.
+    Recursive,
+}
+
+/// A member of a struct, union or enum within a `TypeLayout`.
+#[derive(Clone, Debug)]
+pub struct FieldLayout {
+    /// Name of the member, if it has one.
+    pub name: Option<Name>,
+    /// The member itself.
+    pub layout: TypeLayout,
+}
+
+/// One variant of an enum within a `TypeLayout`.
+#[derive(Clone, Debug)]
+pub struct VariantLayout {
+    /// Name of the variant, if it has one.
+    pub name: Option<Name>,
+    /// Value of the discriminator that selects this variant. `None` for the
+    /// default variant, which is selected by any value not listed, and for
+    /// the only variant of an enum without a discriminator.
+    pub discriminant: Option<u64>,
+    /// The struct holding the variant's fields.
+    pub payload: TypeLayout,
+    /// Byte ranges of the enum used by neither the discriminator nor this
+    /// variant's fields.
+    pub padding: Vec<Range<u64>>,
+}
+
+impl TypeLayout {
+    /// Gets the offset just past the end of the type.
+    pub fn end(&self) -> u64 {
+        self.offset + self.byte_size.unwrap_or(0)
+    }
+}
+
+/// A struct with padding in it, as found by `DebugDb::padded_structs`.
+#[derive(Clone, Debug)]
+pub struct PaddedStruct {
+    pub type_id: TypeId,
+    /// Size of the struct in bytes.
+    pub byte_size: u64,
+    /// Padding bytes, between members and at the end.
+    pub padding: u64,
+    /// Number of separate runs of padding between members. Padding at the
+    /// end isn't counted, as `pahole` doesn't count it.
+    pub holes: usize,
+    /// Padding bytes after the last member, which `pahole` reports apart
+    /// from the holes.
+    pub trailing_padding: u64,
+}
+
+impl PaddedStruct {
+    /// Fraction of the struct that is padding, from 0 to 1.
+    pub fn ratio(&self) -> f64 {
+        self.padding as f64 / self.byte_size as f64
+    }
+}
+
+/// How `DebugDb::padded_structs` orders its results.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PaddingRank {
+    /// Most padding bytes first.
+    Bytes,
+    /// Highest proportion of padding first.
+    Ratio,
+}
+
+impl DebugDb {
+    /// Finds the distinct structs that have any padding, worst first by `rank`.
+    /// Ties are broken by the other measure, then by type ID.
+    ///
+    /// The structs holding the fields of enum variants are left out: they're
+    /// as big as the whole enum, and the space their fields don't use holds
+    /// the discriminator or is needed by other variants, so isn't padding.
+    pub fn padded_structs(&self, rank: PaddingRank) -> Vec<PaddedStruct> {
+        let variants = self.variant_payloads();
+        let mut found = self.canonical_types()
+            .filter(|(id, t)| matches!(t, Type::Struct(_)) && !variants.contains(id))
+            .flat_map(|(id, _)| self.layouts(id))
+            .filter(|l| l.padding() != 0 && l.byte_size != 0)
+            .map(|l| PaddedStruct {
+                type_id: l.type_id,
+                byte_size: l.byte_size,
+                padding: l.padding(),
+                holes: l.members.iter().filter(|m| m.padding_before != 0).count(),
+                trailing_padding: l.trailing_padding,
+            })
+            .collect::<Vec<_>>();
+        // Ratios are compared as fractions, to stay exact.
+        let by_ratio = |a: &PaddedStruct, b: &PaddedStruct| {
+            (u128::from(b.padding) * u128::from(a.byte_size))
+                .cmp(&(u128::from(a.padding) * u128::from(b.byte_size)))
+        };
+        found.sort_by(|a, b| {
+            let by_bytes = b.padding.cmp(&a.padding);
+            match rank {
+                PaddingRank::Bytes => by_bytes.then_with(|| by_ratio(a, b)),
+                PaddingRank::Ratio => by_ratio(a, b).then(by_bytes),
+            }
+            .then(a.type_id.cmp(&b.type_id))
+        });
+        found
+    }
+
+    /// Finds the canonical structs that hold the fields of Rust enum
+    /// variants. These are named after the enum (e.g. `Option<u8>::Some`),
+    /// and aren't interesting apart from it.
+    pub(crate) fn variant_payloads(&self) -> std::collections::BTreeSet<TypeId> {
+        self.canonical_types()
+            .filter_map(|(_, t)| match t {
+                Type::Enum(e) => Some(e),
+                _ => None,
+            })
+            .flat_map(|e| match &e.shape {
+                VariantShape::Zero => vec![],
+                VariantShape::One(v) => vec![v],
+                VariantShape::Many { variants, .. } => variants.values().collect(),
+            })
+            .map(|v| {
+                let id = self.resolve_alias(v.member.type_id);
+                self.type_canon.get(&id).copied().unwrap_or(id)
+            })
+            .collect()
+    }
+
+    /// Attributes the size of type `id` to its members, recursively: each
+    /// member that is itself a struct, union, enum or array is broken down in
+    /// turn. Holes between members and trailing padding appear as parts of
+    /// their own, so the parts of a struct always add up to its size.
+    ///
+    /// Typedefs and qualifiers are looked through. Returns `None` for types
+    /// without a known size.
+    pub fn size_breakdown(&self, id: TypeId) -> Option<SizePart> {
+        self.type_by_id(self.resolve_alias(id))?.byte_size(self)?;
+        Some(self.size_part(PartKind::Whole, id, 0))
+    }
+
+    /// Breaks down a part of type `id` at `offset`.
+    fn size_part(&self, kind: PartKind, id: TypeId, offset: u64) -> SizePart {
+        let resolved = self.resolve_alias(id);
+        let ty = self.type_by_id(resolved);
+        let byte_size = ty.and_then(|t| t.byte_size(self)).unwrap_or(0);
+        let mut part = SizePart {
+            kind,
+            type_id: Some(id),
+            offset,
+            byte_size,
+            repeat: 1,
+            overlapping: false,
+            parts: vec![],
+        };
+        match ty {
+            Some(Type::Struct(_)) => {
+                if let [layout] = &self.layouts(resolved)[..] {
+                    part.parts = self.layout_parts(layout);
+                }
+            }
+            Some(Type::Union(_)) => {
+                part.overlapping = true;
+                if let [layout] = &self.layouts(resolved)[..] {
+                    part.parts = self.layout_parts(layout);
+                }
+            }
+            Some(Type::Enum(_)) => {
+                part.overlapping = true;
+                let layouts = self.layouts(resolved);
+                let mut end = 0;
+                for layout in &layouts {
+                    let used = layout.byte_size - layout.trailing_padding;
+                    end = end.max(used);
+                    let mut parts = self.layout_parts(layout);
+                    parts.retain(|p| p.offset < used);
+                    part.parts.push(SizePart {
+                        kind: PartKind::Variant(layout.variant.clone()),
+                        type_id: None,
+                        offset: 0,
+                        byte_size: used,
+                        repeat: 1,
+                        overlapping: false,
+                        parts,
+                    });
+                }
+                if byte_size > end && !layouts.is_empty() {
+                    part.parts.push(padding_part(end..byte_size));
+                }
+            }
+            Some(Type::Array(a)) => {
+                if let Some(count) = a.count.filter(|&n| n > 0) {
+                    let element = self.size_part(PartKind::Whole, a.element_type_id, 0);
+                    part.repeat = count;
+                    part.overlapping = element.overlapping;
+                    part.parts = if element.parts.is_empty() {
+                        vec![SizePart { kind: PartKind::Member(None), ..element }]
+                    } else {
+                        element.parts
+                    };
+                }
+            }
+            _ => (),
+        }
+        part
+    }
+
+    /// Turns a layout into parts: its members, broken down, and its holes.
+    fn layout_parts(&self, layout: &Layout) -> Vec<SizePart> {
+        let mut parts = vec![];
+        for m in &layout.members {
+            if m.padding_before != 0 {
+                parts.push(padding_part(m.offset - m.padding_before..m.offset));
+            }
+            parts.push(self.size_part(PartKind::Member(m.name.clone()), m.type_id, m.offset));
+        }
+        if layout.trailing_padding != 0 {
+            parts.push(padding_part(layout.byte_size - layout.trailing_padding..layout.byte_size));
+        }
+        parts
+    }
+
+    /// Works out the layout of the type `id`, and in particular, where it has
+    /// padding. Typedefs and qualifiers are looked through.
+    ///
+    /// Structs and unions produce a single `Layout`. Enums produce one for each
+    /// variant, since each variant arranges its fields differently; the
+    /// discriminator is included in each of them. Other types, and types
+    /// without a known size, produce nothing.
+    pub fn layouts(&self, id: TypeId) -> Vec<Layout> {
+        let id = self.resolve_alias(id);
+        let Some(ty) = self.type_by_id(id) else { return vec![] };
+        let Some(byte_size) = ty.byte_size(self) else { return vec![] };
+
+        match ty {
+            Type::Struct(s) => {
+                let members = s.members.iter()
+                    .map(|m| self.member_layout(m, 0))
+                    .collect();
+                vec![Layout::new(id, None, byte_size, members)]
+            }
+            Type::Union(u) => {
+                let members = u.members.iter()
+                    .map(|m| self.member_layout(m, 0))
+                    .collect();
+                vec![Layout::new(id, None, byte_size, members)]
+            }
+            Type::Enum(e) => {
+                let (discr, variants) = match &e.shape {
+                    VariantShape::Zero => (None, vec![]),
+                    VariantShape::One(v) => (None, vec![v]),
+                    VariantShape::Many { member, variants, .. } => {
+                        (Some(member), variants.values().collect())
+                    }
+                };
+                variants.into_iter()
+                    .map(|v| {
+                        let mut members = vec![];
+                        if let Some(discr) = discr {
+                            members.push(self.member_layout(discr, 0));
+                        }
+                        let base = v.member.location;
+                        let payload = self.type_by_id(
+                            self.resolve_alias(v.member.type_id),
+                        );
+                        match payload {
+                            Some(Type::Struct(s)) => members.extend(
+                                s.members.iter()
+                                    .map(|m| self.member_layout(m, base)),
+                            ),
+                            _ => members.push(self.member_layout(&v.member, 0)),
+                        }
+                        Layout::new(
+                            id,
+                            v.member.name.clone(),
+                            byte_size,
+                            members,
+                        )
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Works out the layout of type `id` and everything inside it, with each
+    /// part's offset from the start of `id`. Unlike `layouts`, this describes
+    /// every kind of type, with those that can't be broken down appearing as
+    /// `LayoutContents::Scalar`. Returns `None` if there's no such type.
+    pub fn layout_of(&self, id: TypeId) -> Option<TypeLayout> {
+        self.type_by_id(id)?;
+        Some(self.type_layout(id, 0, &mut vec![]))
+    }
+
+    /// Lays out type `id` at `offset`. `path` holds the types being laid out
+    /// around it, to spot types that contain themselves.
+    fn type_layout(&self, id: TypeId, offset: u64, path: &mut Vec<TypeId>) -> TypeLayout {
+        let resolved = self.resolve_alias(id);
+        let ty = self.type_by_id(resolved);
+        let byte_size = ty.and_then(|t| t.byte_size(self));
+        let mut layout = TypeLayout {
+            type_id: id,
+            offset,
+            byte_size,
+            contents: LayoutContents::Scalar,
+        };
+        if path.contains(&resolved) {
+            layout.contents = LayoutContents::Recursive;
+            return layout;
+        }
+        path.push(resolved);
+
+        // Member offsets are relative to the type; holes are shifted to be
+        // relative to the outermost type, like everything else.
+        let field = |m: &Member, base: u64, path: &mut Vec<TypeId>| FieldLayout {
+            name: m.name.clone(),
+            layout: self.type_layout(m.type_id, base + m.location, path),
+        };
+        let holes = |l: &Layout| {
+            l.holes().into_iter()
+                .map(|r| offset + r.start..offset + r.end)
+                .collect::<Vec<_>>()
+        };
+        layout.contents = match ty {
+            Some(Type::Struct(s)) => {
+                let mut members = s.members.iter()
+                    .map(|m| field(m, offset, path))
+                    .collect::<Vec<_>>();
+                members.sort_by_key(|m| (m.layout.offset, m.layout.byte_size));
+                let padding = self.layouts(resolved).first().map(holes).unwrap_or_default();
+                LayoutContents::Struct { members, padding }
+            }
+            Some(Type::Union(u)) => {
+                let members = u.members.iter()
+                    .map(|m| field(m, offset, path))
+                    .collect();
+                let padding = self.layouts(resolved).first().map(holes).unwrap_or_default();
+                LayoutContents::Union { members, padding }
+            }
+            Some(Type::Enum(e)) => {
+                let (discriminator, variants) = match &e.shape {
+                    VariantShape::Zero => (None, vec![]),
+                    VariantShape::One(v) => (None, vec![(None, v)]),
+                    VariantShape::Many { member, variants, .. } => (
+                        Some(Box::new(field(member, offset, path))),
+                        variants.iter().map(|(&d, v)| (d, v)).collect(),
+                    ),
+                };
+                let layouts = self.layouts(resolved);
+                let variants = variants.into_iter().enumerate()
+                    .map(|(i, (discriminant, v))| VariantLayout {
+                        name: v.member.name.clone(),
+                        discriminant,
+                        payload: self.type_layout(v.member.type_id, offset + v.member.location, path),
+                        padding: layouts.get(i).map(holes).unwrap_or_default(),
+                    })
+                    .collect();
+                LayoutContents::Enum { discriminator, variants }
+            }
+            Some(Type::Array(a)) => {
+                let stride = self.type_by_id(a.element_type_id)
+                    .and_then(|t| t.byte_size(self));
+                let element = (a.count != Some(0))
+                    .then(|| Box::new(self.type_layout(a.element_type_id, offset, path)));
+                LayoutContents::Array { count: a.count, stride, element }
+            }
+            _ => LayoutContents::Scalar,
+        };
+        path.pop();
+        layout
+    }
+
+    /// Describes member `m` of a type that starts at `base`. The padding is
+    /// filled in later by `Layout::new`.
+    fn member_layout(&self, m: &Member, base: u64) -> MemberLayout {
+        MemberLayout {
+            name: m.name.clone(),
+            type_id: m.type_id,
+            offset: base + m.location,
+            byte_size: self.type_by_id(m.type_id)
+                .and_then(|t| t.byte_size(self)),
+            padding_before: 0,
+        }
+    }
+}
+
+fn padding_part(range: Range<u64>) -> SizePart {
+    SizePart {
+        kind: PartKind::Padding,
+        type_id: None,
+        offset: range.start,
+        byte_size: range.end - range.start,
+        repeat: 1,
+        overlapping: false,
+        parts: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+
+    /// Records `struct S { a: u8, b: u32, c: u16 }` laid out as C would, with
+    /// three bytes of padding after `a` and two at the end.
+    fn padded_struct(types: &mut TestTypes) -> TypeId {
+        let u8_id = types.base("u8", 1);
+        let u16_id = types.base("u16", 2);
+        let u32_id = types.base("u32", 4);
+        types.structure("S", 12, &[("a", u8_id, 0), ("b", u32_id, 4), ("c", u16_id, 8)])
+    }
+
+    #[test]
+    fn struct_holes() {
+        let mut types = TestTypes::little();
+        let id = padded_struct(&mut types);
+        let world = types.build();
+
+        let [layout] = &world.layouts(id)[..] else { panic!("expected one layout") };
+        assert_eq!(layout.byte_size, 12);
+        let padding: Vec<_> = layout.members.iter().map(|m| m.padding_before).collect();
+        assert_eq!(padding, [0, 3, 0]);
+        assert_eq!(layout.internal_padding(), 3);
+        assert_eq!(layout.trailing_padding, 2);
+        assert_eq!(layout.padding(), 5);
+        assert_eq!(layout.holes(), [1..4, 10..12]);
+    }
+
+    #[test]
+    fn union_members_overlap() {
+        let mut types = TestTypes::little();
+        let u8_id = types.base("u8", 1);
+        let u32_id = types.base("u32", 4);
+        let id = types.union("U", 4, &[("a", u32_id), ("b", u8_id)]);
+        let world = types.build();
+
+        let [layout] = &world.layouts(id)[..] else { panic!("expected one layout") };
+        assert_eq!(layout.padding(), 0);
+        assert!(layout.holes().is_empty());
+    }
+
+    #[test]
+    fn padded_structs_ranking() {
+        let mut types = TestTypes::little();
+        // 5 bytes of padding in 12.
+        let big = padded_struct(&mut types);
+        // 3 bytes of padding in 4.
+        let u8_id = types.base("u8", 1);
+        let small = types.structure("T", 4, &[("a", u8_id, 0)]);
+        // No padding at all, so not listed.
+        types.structure("Packed", 2, &[("a", u8_id, 0), ("b", u8_id, 1)]);
+        let world = types.build();
+
+        let ids = |rank| -> Vec<_> {
+            world.padded_structs(rank).iter().map(|p| p.type_id).collect()
+        };
+        assert_eq!(ids(PaddingRank::Bytes), [big, small]);
+        assert_eq!(ids(PaddingRank::Ratio), [small, big]);
+
+        let found = world.padded_structs(PaddingRank::Bytes);
+        assert_eq!((found[0].padding, found[0].holes, found[0].trailing_padding), (5, 1, 2));
+        assert_eq!(found[1].ratio(), 0.75);
+    }
+
+    #[test]
+    fn size_breakdown_covers_every_byte() {
+        let mut types = TestTypes::little();
+        let id = padded_struct(&mut types);
+        let world = types.build();
+
+        let whole = world.size_breakdown(id).unwrap();
+        assert_eq!(whole.byte_size, 12);
+        assert_eq!(whole.padding(), 5);
+        assert_eq!(whole.parts.iter().map(|p| p.byte_size).sum::<u64>(), 12);
+        let kinds: Vec<_> = whole.parts.iter()
+            .map(|p| (p.offset, matches!(p.kind, PartKind::Padding)))
+            .collect();
+        assert_eq!(kinds, [(0, false), (1, true), (4, false), (8, false), (10, true)]);
+    }
+
+    #[test]
+    fn nested_padding_is_relative_to_outermost_type() {
+        let mut types = TestTypes::little();
+        let inner = padded_struct(&mut types);
+        let u8_id = types.base("u8", 1);
+        let outer = types.structure("Outer", 16, &[("x", u8_id, 0), ("inner", inner, 4)]);
+        let world = types.build();
+
+        let layout = world.layout_of(outer).unwrap();
+        let LayoutContents::Struct { members, padding } = &layout.contents else {
+            panic!("expected a struct, got {:?}", layout.contents);
+        };
+        assert_eq!(padding, &vec![Range { start: 1, end: 4 }]);
+        let inner = &members[1].layout;
+        assert_eq!((inner.offset, inner.end()), (4, 16));
+        let LayoutContents::Struct { padding, .. } = &inner.contents else {
+            panic!("expected a struct, got {:?}", inner.contents);
+        };
+        assert_eq!(padding, &[5..8, 14..16]);
+    }
+}
@@ -1,23 +1,65 @@
 //! Collects debug information from a program into a queryable, cross-referenced
 //! form.
 
+/// Records a warning about debug info that couldn't be fully understood in
+/// `$to`, a `DebugDbBuilder` or anything else with a `warnings` list, to end up
+/// in `DebugDb::warnings`.
+macro_rules! warn {
+    ($to:expr, $($arg:tt)*) => {
+        $to.warnings.push(format!($($arg)*))
+    };
+}
+
+pub mod addr2line;
+pub mod archive;
+pub mod cache;
+pub mod closure;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod codegen;
+pub mod coroutine;
+pub mod defmt;
+pub mod demangle;
+pub mod diff;
+pub mod dot;
+pub mod filter;
+pub mod fingerprint;
+#[cfg(feature = "gdb")]
+pub mod gdb;
+pub mod intern;
+pub mod layout;
 pub mod load;
+#[cfg(feature = "pdb")]
+pub mod pdb;
+#[cfg(feature = "probe-rs")]
+pub mod probe;
+pub mod store;
+pub mod svd;
+pub mod typename;
 pub mod value;
 pub mod model;
+pub mod stats;
 pub mod unify;
+pub mod unwind;
+pub mod wasm;
 
+mod compressed;
 mod dwarf_parser;
+#[cfg(test)]
+mod test_util;
 
 use crate::unify::Unify;
 use crate::dwarf_parser::ParseError;
 
+pub use self::intern::Name;
 pub use self::model::*;
 
 use object::{Object, ObjectSection, ObjectSymbol};
 use thiserror::Error;
 use std::borrow::Cow;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::convert::Infallible;
+use std::ops::Range;
 use std::sync::Arc;
 
 // Internal type abbreviations
@@ -27,14 +69,15 @@ type RtArcReader = gimli::EndianReader<gimli::RunTimeEndian, Arc<[u8]>>;
 /// A database of information extracted from the debug info of a program.
 ///
 /// This is primarily focused on correctly representing Rust programs, but it
-/// can represent a large subset of C types as a side effect -- currently only
-/// unnamed types present a problem. This could be fixed.
+/// can represent a large subset of C types as a side effect. Unnamed structs,
+/// unions and enums are given placeholder names like `{anonymous struct}`.
 #[derive(Clone, Debug)]
 pub struct DebugDb {
     /// Endianness of the target system.
     endian: gimli::RunTimeEndian,
-    /// Pointer width of the target system. Currently only 32 and 64 are
-    /// supported here.
+    /// Pointer width of the target system according to the object file.
+    /// Currently only 32 and 64 are supported here; the address sizes of the
+    /// units are more precise, where there are any.
     is_64: bool,
 
     /// All types in the program, indexed by location in the debug section(s).
@@ -63,7 +106,34 @@ pub struct DebugDb {
     ///
     /// Invariant: all UnitSectionOffset values have corresponding entries in
     /// `types`.
-    type_name_index: BTreeIndex<TypeId, String>,
+    type_name_index: BTreeIndex<TypeId, Name>,
+
+    /// Module (namespace) path of each type defined inside a namespace, such
+    /// as `"core::option"`. Types at the top level of a unit are absent.
+    type_modules: BTreeMap<TypeId, String>,
+
+    /// Index: module path to the types defined directly in that module.
+    types_by_module: BTreeIndex<TypeId, String>,
+
+    /// Index: abbreviated name (see `typename::abbreviate`) to the canonical
+    /// types with that abbreviation.
+    types_by_short_name: BTreeIndex<TypeId, String>,
+
+    /// Module (namespace) path of each subprogram defined inside a namespace,
+    /// like `type_modules`.
+    program_modules: BTreeMap<ProgramId, String>,
+
+    /// Index: module path to the subprograms defined directly in that module.
+    subprograms_by_module: BTreeIndex<ProgramId, String>,
+
+    /// Compilation units, by the location of their header in the debug info
+    /// section(s). Everything between one unit's location and the next belongs
+    /// to the first.
+    units: BTreeMap<gimli::UnitSectionOffset, CompUnit>,
+
+    /// Index: canonical type to the canonical types that refer to it directly,
+    /// and how. This is the reverse of `Type::dependencies`.
+    type_users: BTreeMap<TypeId, BTreeSet<(TypeId, Dependency)>>,
 
     /// Index: array element type and size to location(s) in `types`. Since
     /// arrays do not have names in DWARF, they can't be looked up in the
@@ -85,23 +155,52 @@ pub struct DebugDb {
     /// All subprograms, indexed by location in the debug section(s).
     subprograms: BTreeMap<ProgramId, Subprogram>,
 
+    /// Index: subprograms by name.
+    subprograms_by_name: BTreeIndex<ProgramId, String>,
+
+    /// Index: subprograms by linkage (symbol) name, both mangled and
+    /// demangled.
+    subprograms_by_linkage_name: BTreeIndex<ProgramId, String>,
+
     /// Mapping of text address to line number information.
     line_table: BTreeMap<u64, Vec<LineNumberRow>>,
 
+    /// Number of line number table rows for each source file, by compilation
+    /// unit.
+    line_files: BTreeMap<gimli::UnitSectionOffset, BTreeMap<String, usize>>,
+
     /// All static variables, indexed by ID.
     variables: BTreeMap<VarId, StaticVariable>,
 
     /// Index: static variables by name.
     variables_by_name: BTreeIndex<VarId, String>,
 
+    /// Index: static variables by linkage (symbol) name, both mangled and
+    /// demangled.
+    variables_by_linkage_name: BTreeIndex<VarId, String>,
+
     /// All entities with fixed addresses, indexed by base address.
     entities_by_address: BTreeMap<u64, Vec<AddressRange>>,
 
-    // TODO
-    pub debug_frame: gimli::DebugFrame<gimli::EndianReader<gimli::RunTimeEndian, Arc<[u8]>>>,
+    /// Contents of `.debug_frame`, or an empty section if there isn't one.
+    debug_frame: gimli::DebugFrame<RtArcReader>,
+
+    /// Contents of `.eh_frame`, if the program has one, along with the section
+    /// addresses needed to decode pointers in it.
+    eh_frame: Option<(gimli::EhFrame<RtArcReader>, gimli::BaseAddresses)>,
 
     raw_symbols_by_address: BTreeMap<u64, BTreeSet<String>>,
     raw_symbols_by_name: BTreeMap<String, BTreeSet<u64>>,
+
+    /// Allocated sections of the program, indexed by start address.
+    sections_by_address: BTreeMap<u64, (Range<u64>, String)>,
+
+    /// Programs combined into this database by `parse_images`, in order.
+    /// Empty for a database parsed from a single program.
+    images: Vec<Image>,
+
+    /// Warnings about the debug info, from parsing it.
+    warnings: Vec<String>,
 }
 
 impl DebugDb {
@@ -110,15 +209,36 @@ impl DebugDb {
         self.endian
     }
 
-    /// Gets the size of a pointer in the program, in bytes.
+    /// Gets the program's `.debug_frame`, for reading call frame information
+    /// with `gimli` directly. It's empty if the program has none. Unwinding
+    /// is easier with `unwind_frame` and `backtrace`.
+    pub fn debug_frame(&self) -> &gimli::DebugFrame<RtArcReader> {
+        &self.debug_frame
+    }
+
+    /// Gets the size of a pointer in the program, in bytes. This is the
+    /// address size of the first compilation unit, or if there are none, the
+    /// pointer size of the object file format.
+    ///
+    /// Pointer types can be a different size; use `Type::byte_size` or
+    /// `pointer_byte_size` for those.
     pub fn pointer_size(&self) -> usize {
-        if self.is_64 {
-            8
-        } else {
-            4
+        match self.units.values().next() {
+            Some(unit) => usize::from(unit.address_size),
+            None if self.is_64 => 8,
+            None => 4,
         }
     }
 
+    /// Gets the size of pointer type `p` in bytes: its own size if the debug
+    /// info gives one, or else the address size of the unit it's in.
+    pub fn pointer_byte_size(&self, p: &Pointer) -> u64 {
+        p.byte_size.unwrap_or_else(|| {
+            self.unit_containing(TypeId(p.offset))
+                .map_or(self.pointer_size() as u64, |(_, u)| u64::from(u.address_size))
+        })
+    }
+
     /// Returns the number of types in the debug info.
     pub fn type_count(&self) -> usize {
         self.types.len()
@@ -126,6 +246,11 @@ impl DebugDb {
 
     /// Produces an iterator over all types defined in the debug info, together
     /// with their IDs.
+    ///
+    /// Types come in order of `TypeId`, which is the order they appear in the
+    /// debug info. This is the same every time a given program is loaded, but
+    /// changes from one build of a program to the next; for output that's
+    /// meant to be compared between builds, see `types_sorted_by_name`.
     pub fn types(
         &self,
     ) -> impl Iterator<Item = (TypeId, &Type)> + '_ {
@@ -133,7 +258,7 @@ impl DebugDb {
     }
 
     /// Produces an iterator over all canonical types defined in the debug info,
-    /// together with their IDs.
+    /// together with their IDs, in the same order as `types`.
     pub fn canonical_types(
         &self,
     ) -> impl Iterator<Item = (TypeId, &Type)> + '_ {
@@ -141,6 +266,39 @@ impl DebugDb {
             .filter(move |(tid, _t)| !self.type_canon.contains_key(tid))
     }
 
+    /// Lists all types defined in the debug info in order of name, so that the
+    /// order doesn't depend on where they happen to be in the debug info.
+    ///
+    /// Types with the same name are ordered by kind and then by fingerprint
+    /// (see `fingerprint`), so that the order only changes between builds when
+    /// the types do. Only types that are identical in every way are left in
+    /// order of `TypeId`.
+    pub fn types_sorted_by_name(&self) -> Vec<(TypeId, &Type)> {
+        self.sorted_by_name(self.types())
+    }
+
+    /// Lists the canonical types in the debug info in order of name, as
+    /// `types_sorted_by_name` does.
+    pub fn canonical_types_sorted_by_name(&self) -> Vec<(TypeId, &Type)> {
+        self.sorted_by_name(self.canonical_types())
+    }
+
+    fn sorted_by_name<'a>(
+        &'a self,
+        types: impl Iterator<Item = (TypeId, &'a Type)>,
+    ) -> Vec<(TypeId, &'a Type)> {
+        let mut types = types
+            .map(|(id, ty)| (self.type_name(id), ty.kind_name(), id, ty))
+            .collect::<Vec<_>>();
+        // Fingerprints take some working out, so they're only found for ties.
+        types.sort_by(|a, b| {
+            (&a.0, a.1).cmp(&(&b.0, b.1))
+                .then_with(|| self.fingerprint(a.2).cmp(&self.fingerprint(b.2)))
+                .then(a.2.cmp(&b.2))
+        });
+        types.into_iter().map(|(_, _, id, ty)| (id, ty)).collect()
+    }
+
     pub fn aliases_of_type(&self, id: TypeId) -> Option<&BTreeSet<TypeId>> {
         self.type_rcanon.get(&id)
     }
@@ -157,6 +315,124 @@ impl DebugDb {
         self.types.get(&id)
     }
 
+    /// Checks whether types `a` and `b` are structurally the same -- that is,
+    /// whether they have the same names, sizes, members, member offsets,
+    /// variants, and so on, all the way down -- regardless of whether they
+    /// are the same entry in the debug info.
+    pub fn same_layout(
+        &self,
+        a: TypeId,
+        b: TypeId,
+    ) -> bool {
+        self.same_layout_as(a, self, b)
+    }
+
+    /// Like `same_layout`, but compares type `a` from this database against
+    /// type `b` from `other`, which may describe a different program.
+    pub fn same_layout_as(
+        &self,
+        a: TypeId,
+        other: &DebugDb,
+        b: TypeId,
+    ) -> bool {
+        if self.pointer_size() != other.pointer_size() {
+            return false;
+        }
+
+        // Pairs we've already checked, or are in the process of checking.
+        // Recursive types will revisit pairs; assuming they match is what
+        // lets the comparison terminate, and any mismatch will be found
+        // elsewhere.
+        let mut assumed = BTreeSet::new();
+        let mut work = vec![(a, b)];
+        while let Some((a, b)) = work.pop() {
+            if !assumed.insert((a, b)) {
+                continue;
+            }
+            let (Some(ta), Some(tb)) = (self.type_by_id(a), other.type_by_id(b)) else {
+                return false;
+            };
+            match ta.equiv(tb) {
+                Some(conditions) => work.extend(conditions),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Walks the types reachable from `id` -- through members, variants, array
+    /// elements, pointers, typedefs, qualifiers, subroutine signatures and
+    /// template parameters -- producing each exactly once, in breadth-first
+    /// order, starting with `id` itself. Cycles (such as a linked list node
+    /// pointing to itself) are handled by never revisiting a type.
+    ///
+    /// Use `Dependencies::following` to only cross some kinds of reference,
+    /// e.g. to find just the types stored inline.
+    ///
+    /// References to types that aren't in the database are skipped.
+    pub fn walk_dependencies(&self, id: TypeId) -> Dependencies<'_> {
+        Dependencies {
+            db: self,
+            queue: [id].into_iter().collect(),
+            seen: [id].into_iter().collect(),
+            follow: |_| true,
+        }
+    }
+
+    /// Returns an iterator over the types that refer to `id` directly, along
+    /// with how they refer to it -- e.g. the structs and arrays that contain
+    /// it (`Dependency::Contains`), or the pointers to it
+    /// (`Dependency::PointsTo`). This is the reverse of `Type::dependencies`.
+    ///
+    /// Both `id` and the results are canonical types, so references to any
+    /// type unified with `id` are included. Each type is produced once per
+    /// kind of reference, however many members (say) have type `id`.
+    ///
+    /// To find everything that transitively contains `id`, apply this
+    /// repeatedly.
+    pub fn types_referencing(
+        &self,
+        id: TypeId,
+    ) -> impl Iterator<Item = (TypeId, Dependency, &Type)> + '_ {
+        let id = self.type_canon.get(&id).copied().unwrap_or(id);
+        self.type_users.get(&id)
+            .into_iter()
+            .flatten()
+            .map(move |&(user, dep)| (user, dep, &self.types[&user]))
+    }
+
+    /// Finds the type that `id` is an alias for, by following any chain of
+    /// typedefs and qualifiers. If `id` is neither, it's returned unchanged.
+    pub fn resolve_alias(
+        &self,
+        mut id: TypeId,
+    ) -> TypeId {
+        // Cycles shouldn't occur, but bound the walk in case they do.
+        for _ in 0..self.types.len() {
+            match self.types.get(&id) {
+                Some(Type::Typedef(t)) => id = t.type_id,
+                Some(Type::Qualified(q)) => id = q.type_id,
+                _ => break,
+            }
+        }
+        id
+    }
+
+    /// Removes any qualifiers (`const`, `volatile`, etc.) from the outside of
+    /// the type `id`, leaving typedefs in place.
+    pub fn strip_qualifiers(
+        &self,
+        mut id: TypeId,
+    ) -> TypeId {
+        for _ in 0..self.types.len() {
+            match self.types.get(&id) {
+                Some(Type::Qualified(q)) => id = q.type_id,
+                _ => break,
+            }
+        }
+        id
+    }
+
     /// Shorthand for looking up the name of a type.
     ///
     /// Note that not all types have names, so this may return `None` even if
@@ -172,15 +448,188 @@ impl DebugDb {
     /// given name.
     ///
     /// Names are matched in their entirety, e.g. the name `"Option"` does not
-    /// match a type `"core::option::Option<u16>"`.
+    /// match a type `"core::option::Option<u16>"`. With `NameMatch::EXACT`
+    /// they must match exactly; otherwise `how` says which differences to
+    /// ignore, which is handy for names typed in by people. Looser matches
+    /// have to check every name, so are slower.
     ///
     /// Not all types are in the type name index. In particular, array types and
     /// subroutine types.
     pub fn types_by_name(
         &self,
         name: &str,
+        how: NameMatch,
+    ) -> impl Iterator<Item = (TypeId, &Type)> + '_ {
+        let exact = (how == NameMatch::EXACT)
+            .then(|| self.consult_index(&self.type_name_index, name));
+        let key = how.normalize(name).into_owned();
+        let loose = (how != NameMatch::EXACT).then(|| {
+            self.type_name_index.iter()
+                .filter(move |(n, _)| how.normalize(n) == key)
+                .flat_map(move |(_, ids)| ids.iter().map(move |&id| (id, &self.types[&id])))
+        });
+        exact.into_iter().flatten().chain(loose.into_iter().flatten())
+    }
+
+    /// Returns an iterator over the canonical types whose names abbreviate to
+    /// the same thing as `name`, as `typename::abbreviate` does. This lets
+    /// `Vec<u8>` find `alloc::vec::Vec<u8, alloc::alloc::Global>`.
+    ///
+    /// Abbreviations can be ambiguous -- `Error` is likely to find several
+    /// unrelated types -- so callers should check whether the results have
+    /// more than one full name.
+    pub fn types_by_short_name(
+        &self,
+        name: &str,
+    ) -> impl Iterator<Item = (TypeId, &Type)> + '_ {
+        self.consult_index(&self.types_by_short_name, typename::abbreviate(name).as_str())
+    }
+
+    /// Returns an iterator over types whose names match `pattern`, in order of
+    /// name.
+    ///
+    /// Unlike `types_by_name`, this is a search: the pattern may match any
+    /// part of the name unless it's anchored, so `^core::option::` finds
+    /// types from that module, and `::Option<` finds `Option`s from anywhere.
+    ///
+    /// This consults the same index as `types_by_name`, so the same types are
+    /// missing (arrays, subroutines).
+    pub fn types_matching<'d>(
+        &'d self,
+        pattern: &'d regex::Regex,
+    ) -> impl Iterator<Item = (TypeId, &'d Type)> + 'd {
+        self.type_name_index.iter()
+            .filter(move |(name, _)| pattern.is_match(name))
+            .flat_map(move |(_, ids)| ids.iter().map(move |&id| (id, &self.types[&id])))
+    }
+
+    /// Returns an iterator over canonical types whose size in bytes is within
+    /// `range`, along with their sizes. Types without a size never match.
+    ///
+    /// For example, `db.types_with_size(4096..)` finds everything at least
+    /// 4 KiB in size.
+    pub fn types_with_size<R>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = (TypeId, &Type, u64)> + '_
+    where
+        R: std::ops::RangeBounds<u64> + 'static,
+    {
+        self.canonical_types()
+            .filter_map(move |(id, ty)| {
+                let size = ty.byte_size(self)?;
+                range.contains(&size).then_some((id, ty, size))
+            })
+    }
+
+    /// Returns an iterator over the distinct names in the type-name index, in
+    /// order. Each can be passed to `types_by_name` to find the types.
+    ///
+    /// This is meant for things like completing a type name as it's typed.
+    pub fn type_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.type_name_index.keys().map(|name| &**name)
+    }
+
+    /// Gets the module path of the namespace that a type was defined in, e.g.
+    /// `"core::option"` for `core::option::Option<u8>`. Returns `None` for
+    /// types defined outside of any namespace.
+    ///
+    /// Types nested inside other types (rather than directly inside a
+    /// namespace) report the module of the outermost type.
+    pub fn type_module(&self, id: TypeId) -> Option<&str> {
+        self.type_modules.get(&id).map(String::as_str)
+    }
+
+    /// Gets the name of a type without its module path, e.g. `"Option<u8>"`
+    /// for `core::option::Option<u8>`. For types outside any namespace this is
+    /// the same as `type_name`.
+    pub fn type_short_name(
+        &self,
+        id: TypeId,
+    ) -> Option<Cow<'_, str>> {
+        let name = self.type_name(id)?;
+        let Some(module) = self.type_module(id) else { return Some(name) };
+        let short = name.strip_prefix(module)
+            .and_then(|rest| rest.strip_prefix("::"));
+        match short {
+            Some(short) => Some(Cow::Owned(short.to_string())),
+            None => Some(name),
+        }
+    }
+
+    /// Returns an iterator over the types defined directly in the module with
+    /// path `module`, such as `"my_crate::net"`. Types in submodules are not
+    /// included; see `modules` to find those.
+    pub fn types_in_module(
+        &self,
+        module: &str,
     ) -> impl Iterator<Item = (TypeId, &Type)> + '_ {
-        self.consult_index(&self.type_name_index, name)
+        self.consult_index(&self.types_by_module, module)
+    }
+
+    /// Gets the name of the compilation unit that type `id` was defined in --
+    /// usually the path of the source file that was compiled. Returns `None`
+    /// if the unit has no name.
+    pub fn type_unit(&self, id: TypeId) -> Option<&str> {
+        self.unit_containing(id)
+            .and_then(|(_, unit)| unit.name.as_deref())
+    }
+
+    /// Returns an iterator over all compilation units in the program, with
+    /// their IDs, in order of their locations in the debug info.
+    pub fn units(&self) -> impl Iterator<Item = (UnitId, &CompUnit)> + '_ {
+        self.units.iter().map(|(&offset, unit)| (UnitId(offset), unit))
+    }
+
+    /// Finds the compilation unit that defined `id`, which may be the ID of a
+    /// type, subprogram, variable or unit, giving the unit's ID and
+    /// description.
+    pub fn unit_containing(
+        &self,
+        id: impl EntryId,
+    ) -> Option<(UnitId, &CompUnit)> {
+        self.units.range(..=id.offset())
+            .next_back()
+            .map(|(&o, unit)| (UnitId(o), unit))
+    }
+
+    /// Checks whether type `id`, or any type that was unified with it, was
+    /// defined in compilation unit `unit`. Types that are defined identically
+    /// in several units are only stored once, so this is how to find all the
+    /// types a unit uses.
+    pub fn type_defined_in_unit(&self, id: TypeId, unit: UnitId) -> bool {
+        let in_unit = |id: TypeId| {
+            self.unit_containing(id).is_some_and(|(o, _)| o == unit)
+        };
+        in_unit(id) || self.aliases_of_type(id)
+            .is_some_and(|ids| ids.iter().copied().any(in_unit))
+    }
+
+    /// Gets the module path of the namespace that a subprogram was defined
+    /// in, e.g. `"core::fmt"` for `core::fmt::write`. Returns `None` for
+    /// subprograms defined outside of any namespace, which includes all of
+    /// them in C.
+    pub fn subprogram_module(&self, id: ProgramId) -> Option<&str> {
+        self.program_modules.get(&id).map(String::as_str)
+    }
+
+    /// Returns an iterator over the subprograms defined directly in the module
+    /// with path `module`. Subprograms in submodules are not included.
+    pub fn subprograms_in_module(
+        &self,
+        module: &str,
+    ) -> impl Iterator<Item = (ProgramId, &Subprogram)> + '_ {
+        self.consult_index_generic(&self.subprograms_by_module, module, &self.subprograms)
+    }
+
+    /// Returns an iterator over the paths of all modules that directly contain
+    /// at least one type or subprogram, in sorted order.
+    pub fn modules(&self) -> impl Iterator<Item = &str> + '_ {
+        self.types_by_module.keys()
+            .chain(self.subprograms_by_module.keys())
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
     }
 
     /// Consults the array index and returns an iterator over array types with a
@@ -227,6 +676,45 @@ impl DebugDb {
         self.subprograms.get(&pid)
     }
 
+    /// Returns an iterator over subprograms with a given name.
+    ///
+    /// Like `types_by_name`, names are matched in their entirety, including
+    /// any namespace path, e.g. `"app::main"`.
+    pub fn subprograms_by_name(
+        &self,
+        name: &str,
+    ) -> impl Iterator<Item = (ProgramId, &Subprogram)> + '_ {
+        self.consult_index_generic(&self.subprograms_by_name, name, &self.subprograms)
+    }
+
+    /// Returns an iterator over subprograms with a given linkage name, i.e. the
+    /// name of the symbol used to refer to them.
+    ///
+    /// C++ symbols can be given in either mangled or demangled form, e.g.
+    /// `_ZN3foo3barEv` or `foo::bar()`.
+    pub fn subprograms_by_linkage_name(
+        &self,
+        name: &str,
+    ) -> impl Iterator<Item = (ProgramId, &Subprogram)> + '_ {
+        self.consult_index_generic(&self.subprograms_by_linkage_name, name, &self.subprograms)
+    }
+
+    /// Finds the (non-inlined) subprogram whose generated code contains `pc`.
+    ///
+    /// To find out about any inlined subroutines at `pc`, see
+    /// `static_stack_for_pc`.
+    pub fn subprogram_for_pc(
+        &self,
+        pc: u64,
+    ) -> Option<(ProgramId, &Subprogram)> {
+        self.entities_by_address(pc)
+            .find_map(|ar| match ar.entity {
+                EntityId::Prog(pid) => Some(pid),
+                _ => None,
+            })
+            .map(|pid| (pid, &self.subprograms[&pid]))
+    }
+
     /// Returns an iterator over _all_ rows in the computed line number table.
     ///
     /// You probably don't want to do this.
@@ -236,6 +724,38 @@ impl DebugDb {
         self.line_table.iter().map(|(&a, row)| (a, &**row))
     }
 
+    /// Returns an iterator over the line number table entries for code starting
+    /// within `range`, in address order.
+    pub fn line_rows_in(
+        &self,
+        range: Range<u64>,
+    ) -> impl Iterator<Item = &LineNumberRow> + '_ {
+        self.line_table.range(range).flat_map(|(_, rows)| rows)
+    }
+
+    /// Lists the source files referenced by the line number tables, with the
+    /// number of rows (pieces of code) attributed to each, in order of path.
+    pub fn source_files(&self) -> BTreeMap<&str, usize> {
+        let mut files = BTreeMap::new();
+        for (file, &n) in self.line_files.values().flatten() {
+            *files.entry(file.as_str()).or_default() += n;
+        }
+        files
+    }
+
+    /// Lists the source files referenced by the line number table of the
+    /// compilation unit `unit`, with the number of rows attributed to each, in
+    /// order of path.
+    pub fn source_files_in_unit(
+        &self,
+        unit: UnitId,
+    ) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.line_files.get(&unit.0)
+            .into_iter()
+            .flatten()
+            .map(|(file, &n)| (file.as_str(), n))
+    }
+
     /// Looks up the line number table entry associated with `pc`.
     pub fn lookup_line_row(
         &self,
@@ -248,6 +768,25 @@ impl DebugDb {
             .find(move |row| row.pc_range.contains(&pc))
     }
 
+    /// Finds the line number table entries for a source location, giving the
+    /// ranges of code generated for it.
+    ///
+    /// `file` may be a complete path as recorded in the debug info, or any
+    /// trailing part of one (such as just the filename), matched at path
+    /// component boundaries. A single line often produces several disjoint
+    /// ranges of code, particularly in optimized builds; all of them are
+    /// returned, in address order.
+    pub fn lookup_addrs_for_line<'a>(
+        &'a self,
+        file: &'a str,
+        line: u64,
+    ) -> impl Iterator<Item = &'a LineNumberRow> + 'a {
+        self.line_table.values()
+            .flatten()
+            .filter(move |row| row.line.map(|l| l.get()) == Some(line))
+            .filter(move |row| row.file.as_deref().is_some_and(|f| path_ends_with(f, file)))
+    }
+
     /// Computes the static stack slice implied by a PC value.
     ///
     /// For simple cases of subroutines without inlined code, the stack slice
@@ -262,45 +801,23 @@ impl DebugDb {
         &self,
         pc: u64,
     ) -> Result<Option<Vec<PcInfo>>, ParseError> {
-        // Find subprogram containing PC.
-        let Some((pid, subp)) = self.subprograms()
-            .find(|(_, subp)| subp.pc_range
-                .as_ref()
-                .map(|r| r.contains(&pc))
-                .unwrap_or(false))
+        let Some((pid, _, calls)) = self.inlined_calls_for_pc(pc)
             else { return Ok(None); };
 
+        // Record the call info at each step down the inlined calls. Call
+        // sites may be missing, but every inlined call still gets a frame, so
+        // that the innermost one is attributed to the right subprogram.
         let mut frag = vec![];
-
-        // Follow inlined subroutine tree to the tip, recording call info at
-        // each step.
         let mut enclosing_prog = pid;
-        let mut inlines = Some(&subp.inlines);
-        'inline_loop:
-            while let Some(inl) = inlines.take() {
-                for inlsub in inl {
-                    for pcr in &inlsub.pc_ranges {
-                        if pcr.begin <= pc && pc < pcr.end {
-                            // We're in this one.
-                            if let Some(file) = &inlsub.call_coord.file {
-                                frag.push(PcInfo {
-                                    subprogram: enclosing_prog,
-                                    file: file.clone(),
-                                    line: inlsub.call_coord.line,
-                                    column: inlsub.call_coord.column,
-                                });
-
-                                enclosing_prog = ProgramId(
-                                    inlsub.abstract_origin
-                                    .expect("inlined sub w/o abstract_origin")
-                                );
-                                inlines = Some(&inlsub.inlines);
-                                continue 'inline_loop;
-                            }
-                        }
-                    }
-                }
-            }
+        for (inlsub, origin) in calls {
+            frag.push(PcInfo {
+                subprogram: enclosing_prog,
+                file: inlsub.call_coord.file.clone(),
+                line: inlsub.call_coord.line,
+                column: inlsub.call_coord.column,
+            });
+            enclosing_prog = origin;
+        }
 
         // Finally, find the innermost record from the line number info.
         if let Some(row) = self.lookup_line_row(pc) {
@@ -315,6 +832,97 @@ impl DebugDb {
         Ok(Some(frag))
     }
 
+    /// Finds the subprogram containing a PC value, and the inlined calls
+    /// within it that contain the PC, outermost first, each with the
+    /// subprogram it's an instance of.
+    pub(crate) fn inlined_calls_for_pc(
+        &self,
+        pc: u64,
+    ) -> Option<(ProgramId, &Subprogram, Vec<InlinedCall<'_>>)> {
+        let (pid, subp) = self.subprogram_for_pc(pc)?;
+
+        let mut calls = vec![];
+        let mut inlines = &subp.inlines[..];
+        'inline_loop:
+            loop {
+                for inlsub in inlines {
+                    for pcr in &inlsub.pc_ranges {
+                        if pcr.start != 0 && pcr.start <= pc && pc < pcr.end {
+                            // We're in this one, but without knowing what was
+                            // inlined, there's nothing to attribute its code
+                            // to. Stop at the last subprogram we do know.
+                            let Some(origin) = inlsub.abstract_origin
+                                else { break 'inline_loop; };
+
+                            calls.push((inlsub, origin));
+                            inlines = &inlsub.inlines;
+                            continue 'inline_loop;
+                        }
+                    }
+                }
+                break;
+            }
+
+        Some((pid, subp, calls))
+    }
+
+    /// Symbolizes a backtrace, given as a list of return addresses,
+    /// describing the function, inlined calls, and source location at each.
+    ///
+    /// Return addresses usually point just past a call instruction, which may
+    /// be past the end of the calling function or on a different line, so
+    /// each is looked up as `address - 1`. To symbolize the address of an
+    /// instruction that's being executed -- such as the PC of the innermost
+    /// frame -- use `symbolize_pc` instead.
+    pub fn symbolize(
+        &self,
+        return_addresses: &[u64],
+    ) -> Vec<SymbolizedFrame> {
+        return_addresses.iter()
+            .map(|&address| SymbolizedFrame {
+                address,
+                functions: self.symbolize_pc(address.saturating_sub(1)).functions,
+            })
+            .collect()
+    }
+
+    /// Symbolizes a single PC value. See `symbolize`.
+    pub fn symbolize_pc(&self, pc: u64) -> SymbolizedFrame {
+        let stack = self.static_stack_for_pc(pc).ok().flatten().unwrap_or_default();
+        let mut functions: Vec<_> = stack.into_iter()
+            .rev()
+            .map(|info| SymbolizedFunction {
+                name: self.subprograms.get(&info.subprogram)
+                    .and_then(|subp| subp.name.clone()),
+                subprogram: info.subprogram,
+                file: info.file,
+                line: info.line,
+                column: info.column,
+            })
+            .collect();
+
+        if functions.is_empty() {
+            if let Some((pid, subp)) = self.subprogram_for_pc(pc) {
+                // Code with no line number info.
+                functions.push(SymbolizedFunction {
+                    name: subp.name.clone(),
+                    subprogram: pid,
+                    file: None,
+                    line: None,
+                    column: None,
+                });
+            }
+        }
+
+        SymbolizedFrame {
+            address: pc,
+            functions,
+        }
+    }
+
+    /// Finds the address of the only symbol called `name`, which may be
+    /// mangled or demangled. Returns `None` if there are no such symbols, or
+    /// more than one.
     pub fn unique_raw_symbol_by_name(
         &self,
         name: &str,
@@ -338,6 +946,71 @@ impl DebugDb {
             .flat_map(|set| set.iter().map(String::as_str))
     }
 
+    /// Finds the closest raw symbol at or below `address`, returning its
+    /// address and one of its names. This is a last resort for addresses that
+    /// the debug info doesn't describe, e.g. in hand-written assembly.
+    pub fn raw_symbol_before(&self, address: u64) -> Option<(u64, &str)> {
+        let (&addr, names) = self.raw_symbols_by_address
+            .range(..=address)
+            .rfind(|(&addr, names)| addr != 0 && !names.is_empty())?;
+        Some((addr, names.iter().next()?))
+    }
+
+    /// Gets the warnings from parsing the debug info, about things that
+    /// weren't understood and were skipped or approximated, in the order they
+    /// were found.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Gets the number of warnings from parsing the debug info; see
+    /// `warnings`.
+    pub fn parse_warnings(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// Finds the name of the allocated section containing `address`, e.g.
+    /// `.bss` or `.rodata`.
+    pub fn section_for_address(&self, address: u64) -> Option<&str> {
+        let (_, (range, name)) = self.sections_by_address
+            .range(..=address)
+            .next_back()?;
+        if range.contains(&address) {
+            Some(name)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the allocated sections of the program, with
+    /// their address ranges, in address order.
+    pub fn sections(&self) -> impl Iterator<Item = (&str, Range<u64>)> {
+        self.sections_by_address.values()
+            .map(|(range, name)| (name.as_str(), range.clone()))
+    }
+
+    /// Returns the programs combined into this database by `parse_images`, in
+    /// the order they were given. This is empty if the database was parsed
+    /// from a single program.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Finds the image whose debug info defined `id`, which may be the ID of a
+    /// type, subprogram, variable or unit.
+    pub fn image_containing(&self, id: impl EntryId) -> Option<&Image> {
+        let gimli::UnitSectionOffset::DebugInfoOffset(o) = id.offset() else {
+            return None;
+        };
+        self.images.iter().find(|i| i.debug_info.contains(&o.0))
+    }
+
+    /// Finds the image occupying `address`.
+    pub fn image_at(&self, address: u64) -> Option<&Image> {
+        self.images.iter()
+            .find(|i| i.address_ranges.iter().any(|r| r.contains(&address)))
+    }
+
     /// Returns an iterator over all static variables defined in this program.
     pub fn static_variables(
         &self,
@@ -359,6 +1032,17 @@ impl DebugDb {
         self.consult_index_generic(&self.variables_by_name, name, &self.variables)
     }
 
+    /// Returns an iterator over static variables with a given linkage name,
+    /// i.e. the name of the symbol used to refer to them.
+    ///
+    /// C++ symbols can be given in either mangled or demangled form.
+    pub fn static_variables_by_linkage_name(
+        &self,
+        name: &str,
+    ) -> impl Iterator<Item = (VarId, &StaticVariable)> + '_ {
+        self.consult_index_generic(&self.variables_by_linkage_name, name, &self.variables)
+    }
+
     pub fn unique_static_variable_by_name(
         &self,
         name: &str,
@@ -372,6 +1056,8 @@ impl DebugDb {
         }
     }
 
+    /// Returns an iterator over all entities (static variables and
+    /// subprograms) whose address range contains `address`.
     pub fn entities_by_address(
         &self,
         address: u64,
@@ -417,6 +1103,79 @@ impl DebugDb {
     }
 }
 
+/// Which differences `DebugDb::types_by_name` ignores when comparing names.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct NameMatch {
+    /// Ignore case, so that `option<U8>` finds `Option<u8>`.
+    pub ignore_case: bool,
+    /// Ignore how the name is laid out -- whitespace, apart from what's
+    /// needed to keep words apart, and `::` before generic arguments -- so
+    /// that `Option::< u8 >` finds `Option<u8>` and `(u8,u16)` finds
+    /// `(u8, u16)`, but `*constu8` doesn't find `*const u8`. See
+    /// `typename::canonicalize`.
+    pub ignore_formatting: bool,
+}
+
+impl NameMatch {
+    /// Names must match exactly.
+    pub const EXACT: Self = Self { ignore_case: false, ignore_formatting: false };
+    /// Differences in case and formatting are ignored.
+    pub const LOOSE: Self = Self { ignore_case: true, ignore_formatting: true };
+
+    /// Puts `name` in a form where the differences to be ignored are gone, so
+    /// names that match have the same normal form.
+    fn normalize(self, name: &str) -> Cow<'_, str> {
+        let name = if self.ignore_formatting {
+            Cow::Owned(typename::canonicalize(name))
+        } else {
+            Cow::Borrowed(name)
+        };
+        if self.ignore_case {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            name
+        }
+    }
+}
+
+/// Iterator over the types reachable from a starting type; see
+/// `DebugDb::walk_dependencies`.
+#[derive(Clone)]
+pub struct Dependencies<'a> {
+    db: &'a DebugDb,
+    queue: VecDeque<TypeId>,
+    seen: BTreeSet<TypeId>,
+    follow: fn(Dependency) -> bool,
+}
+
+impl<'a> Dependencies<'a> {
+    /// Restricts the walk to references for which `follow` returns `true`.
+    /// For example, `following(|d| d == Dependency::Contains)` finds
+    /// everything stored inline in the starting type, but nothing behind a
+    /// pointer.
+    pub fn following(mut self, follow: fn(Dependency) -> bool) -> Self {
+        self.follow = follow;
+        self
+    }
+}
+
+impl<'a> Iterator for Dependencies<'a> {
+    type Item = (TypeId, &'a Type);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let id = self.queue.pop_front()?;
+            let Some(ty) = self.db.type_by_id(id) else { continue };
+            for (dep, next) in ty.dependencies() {
+                if (self.follow)(dep) && self.seen.insert(next) {
+                    self.queue.push_back(next);
+                }
+            }
+            return Some((id, ty));
+        }
+    }
+}
+
 /// Builder that accumulates the type information from a program and produces a
 /// `DebugDb` database.
 ///
@@ -426,17 +1185,29 @@ impl DebugDb {
 #[derive(Clone, Debug)]
 pub struct DebugDbBuilder {
     path: Vec<String>,
+    namespaces: Vec<String>,
     endian: gimli::RunTimeEndian,
     is_64: bool,
     types: BTreeMap<TypeId, Type>,
     decls: BTreeMap<String, BTreeSet<TypeId>>,
+    type_modules: BTreeMap<TypeId, String>,
+    program_modules: BTreeMap<ProgramId, String>,
+    units: BTreeMap<gimli::UnitSectionOffset, CompUnit>,
+    names: intern::Interner,
     debug_frame: gimli::DebugFrame<gimli::EndianReader<gimli::RunTimeEndian, Arc<[u8]>>>,
+    eh_frame: Option<(gimli::EhFrame<RtArcReader>, gimli::BaseAddresses)>,
 
     subprograms: BTreeMap<ProgramId, Subprogram>,
     line_table: BTreeMap<u64, Vec<LineNumberRow>>,
+    line_files: BTreeMap<gimli::UnitSectionOffset, BTreeMap<String, usize>>,
     variables: BTreeMap<VarId, StaticVariable>,
 
     raw_symbols: Vec<(String, u64)>,
+    sections: Vec<(String, Range<u64>)>,
+    images: Vec<Image>,
+
+    /// Warnings about the debug info, for `DebugDb::warnings`.
+    warnings: Vec<String>,
 }
 
 impl DebugDbBuilder {
@@ -450,18 +1221,28 @@ impl DebugDbBuilder {
         Self {
             endian,
             path: vec![],
+            namespaces: vec![],
             is_64,
             debug_frame,
+            eh_frame: None,
             types: BTreeMap::new(),
             decls: BTreeMap::new(),
+            type_modules: BTreeMap::new(),
+            program_modules: BTreeMap::new(),
+            units: BTreeMap::new(),
+            names: intern::Interner::new(),
             subprograms: BTreeMap::new(),
             line_table: BTreeMap::new(),
+            line_files: BTreeMap::new(),
             variables: BTreeMap::new(),
             raw_symbols: vec![],
+            sections: vec![],
+            images: vec![],
+            warnings: vec![],
         }
     }
 
-    pub fn build(self) -> Result<DebugDb, ParseError> {
+    pub fn build(mut self) -> Result<DebugDb, ParseError> {
         let mut types = self.types;
 
         // Build type name index.
@@ -472,6 +1253,7 @@ impl DebugDbBuilder {
             Type::CEnum(s) => Some(s.name.clone()),
             Type::Union(s) => Some(s.name.clone()),
             Type::Pointer(s) => s.name.clone(),
+            Type::Typedef(s) => Some(s.name.clone()),
             _ => None,
         });
 
@@ -495,13 +1277,10 @@ impl DebugDbBuilder {
         // Attempt to resolve decls.
         let mut ambiguous_decl_count = 0;
         for (name, decl_ids) in &self.decls {
-            if let Some(tids) = type_name_index.get(name) {
+            if let Some(tids) = type_name_index.get(name.as_str()) {
                 if tids.len() != 1 {
                     // The name is still ambiguous after unification.
-                    eprintln!("WARN: decl ambiguous; {name} could be:");
-                    for tid in tids {
-                        eprintln!("- {tid:x?}");
-                    }
+                    warn!(self, "decl ambiguous; {name} could be any of {tids:x?}");
                     ambiguous_decl_count += 1;
                 }
                 // Assume it's the first one.
@@ -510,14 +1289,11 @@ impl DebugDbBuilder {
                     u.equate(alias, tid);
                 }
             } else {
-                eprintln!("WARN: unresolved declaration {name}:");
-                for id in decl_ids {
-                    eprintln!(" - {id:x?}");
-                }
+                warn!(self, "unresolved declaration {name} at {decl_ids:x?}");
             }
         }
         if ambiguous_decl_count > 0 {
-            eprintln!("WARN: {ambiguous_decl_count} ambiguous declarations found");
+            warn!(self, "{ambiguous_decl_count} ambiguous declarations found");
         }
 
         let mut unresolved_types = BTreeMap::new();
@@ -584,6 +1360,12 @@ impl DebugDbBuilder {
                 Type::Pointer(s) => {
                     check(s.type_id)?;
                 }
+                Type::Typedef(s) => {
+                    check(s.type_id)?;
+                }
+                Type::Qualified(s) => {
+                    check(s.type_id)?;
+                }
                 Type::Subroutine(s) => {
                     if let Some(t) = s.return_type_id {
                         check(t)?;
@@ -598,125 +1380,97 @@ impl DebugDbBuilder {
         let type_canon = u.finish();
         types.extend(unresolved_types);
 
-        // Build array index.
-        let array_index = index_by_key(&types, |_, t| match t {
-            Type::Array(a) => Some((a.element_type_id, a.count)),
-            _ => None,
-        });
-        // Build subroutine index. This is more complex in shape than the other
-        // indices.
-        let subroutine_index = {
-            let mut ind = BTreeMap::<_, BTreeIndex<_, _>>::new();
-            for (k, v) in &types {
-                if let Type::Subroutine(s) = v {
-                    ind.entry(s.formal_parameters.clone())
-                        .or_default()
-                        .entry(s.return_type_id)
-                        .or_default()
-                        .insert(*k);
-                }
-            }
-            ind
-        };
-
-        let variables_by_name = index_by_key(&self.variables, |_, v| Some(v.name.clone()));
-
-        // Build address map.
-        let mut entities_by_address: BTreeMap<_, Vec<_>> = BTreeMap::new();
-        for (&vid, v) in &self.variables {
-            let Some(t) = types.get(&v.type_id) else {
-                eprintln!("WARN: type of variable {} not found: {:x?}",
-                    v.name, v.type_id);
-                continue;
-            };
-            let sz = t.byte_size_early(
-                if self.is_64 { 8 } else { 4 },
-                |t| types.get(&t),
-            );
-            if let Some(sz) = sz {
-                entities_by_address.entry(v.location)
-                    .or_default()
-                    .push(AddressRange {
-                        range: v.location..v.location + sz,
-                        entity: EntityId::Var(vid),
-                    });
-            }
-        }
-        for (&pid, p) in &self.subprograms {
-            if let Some(pc_range) = p.pc_range.clone() {
-                entities_by_address.entry(pc_range.start)
-                    .or_default()
-                    .push(AddressRange {
-                        range: pc_range,
-                        entity: EntityId::Prog(pid),
-                    });
-            }
-        }
-
-        fn check_inl(inl: &InlinedSubroutine) -> Result<(), ParseError> {
+        // Inlined code that doesn't say what was inlined can still be loaded;
+        // symbolizing just stops at the last call that does.
+        fn check_inl(inl: &InlinedSubroutine, warnings: &mut Vec<String>) {
             if inl.abstract_origin.is_none() {
-                return Err(ParseError::UnboundSubroutine(inl.offset));
+                warnings.push(format!(
+                    "inlined subroutine w/o abstract origin at {:x?}",
+                    inl.offset,
+                ));
             }
             for inner in &inl.inlines {
-                check_inl(inner)?;
+                check_inl(inner, warnings);
             }
-            Ok(())
         }
 
-        // Check that inlined subroutines match our expectations.
         for subprogram in self.subprograms.values() {
             for inl in &subprogram.inlines {
-                check_inl(inl)?;
+                check_inl(inl, &mut self.warnings);
             }
         }
 
-        let type_rcanon = invert(&type_canon);
-
-        let raw_symbols_by_name = index_by_key(
-            self.raw_symbols.iter().map(|(k, v)| (v, k)),
-            |_, name| Some(name.to_string()),
-        );
-
-        let raw_symbols_by_address = index_by_key(
-            self.raw_symbols.iter().map(|(k, v)| (k, v)),
-            |_, addr| Some(*addr),
-        );
-
-
-        Ok(DebugDb {
+        Ok(Tables {
             endian: self.endian,
+            is_64: self.is_64,
             types,
             type_canon,
-            type_rcanon,
-            is_64: self.is_64,
+            type_name_index,
+            type_modules: self.type_modules,
+            program_modules: self.program_modules,
+            units: self.units,
             subprograms: self.subprograms,
             line_table: self.line_table,
+            line_files: self.line_files,
             variables: self.variables,
             debug_frame: self.debug_frame,
-            type_name_index,
-            array_index,
-            subroutine_index,
-            variables_by_name,
-            entities_by_address,
-            raw_symbols_by_name,
-            raw_symbols_by_address,
-        })
+            eh_frame: self.eh_frame,
+            raw_symbols: self.raw_symbols,
+            sections: self.sections,
+            images: self.images,
+            warnings: self.warnings,
+        }.index())
+    }
+
+    /// Records the contents of `.eh_frame`, which is used for unwinding when
+    /// `.debug_frame` doesn't cover an address. `bases` gives the section
+    /// addresses that pointers in it may be relative to.
+    pub fn record_eh_frame(
+        &mut self,
+        eh_frame: gimli::EhFrame<RtArcReader>,
+        bases: gimli::BaseAddresses,
+    ) {
+        self.eh_frame = Some((eh_frame, bases));
+    }
+
+    /// Records the start of a compilation unit, at `offset` in the debug info
+    /// section(s).
+    pub fn record_unit(&mut self, id: UnitId, unit: CompUnit) {
+        self.units.insert(id.0, unit);
     }
 
     pub fn record_raw_symbol(&mut self, addr: u64, name: String) {
         self.raw_symbols.push((name, addr));
     }
 
+    /// Records an allocated section of the program occupying `range`.
+    pub fn record_section(&mut self, range: Range<u64>, name: String) {
+        self.sections.push((name, range));
+    }
+
+    /// Records that the debug info and sections recorded since the last image
+    /// belong to `image`.
+    pub fn record_image(&mut self, image: Image) {
+        self.images.push(image);
+    }
+
     /// Adds a type to the database.
     ///
     /// It's unusual to call this from outside the library, but it might be
     /// useful if you have additional type information from some outside source.
     pub fn record_type(&mut self, t: impl Into<Type>) {
-        let t = t.into();
-        self.types.insert(TypeId(t.offset()), t);
+        let mut t = t.into();
+        t.for_each_name_mut(&mut |n| *n = self.names.intern(n));
+        if !self.namespaces.is_empty() {
+            self.type_modules.insert(t.id(), self.namespaces.join("::"));
+        }
+        self.types.insert(t.id(), t);
     }
 
     pub fn record_subprogram(&mut self, t: Subprogram) {
+        if !self.namespaces.is_empty() {
+            self.program_modules.insert(ProgramId(t.offset), self.namespaces.join("::"));
+        }
         self.subprograms.insert(ProgramId(t.offset), t);
     }
 
@@ -730,6 +1484,17 @@ impl DebugDbBuilder {
             .push(r)
     }
 
+    /// Counts a line number table row for `file` in compilation unit `unit`,
+    /// for `DebugDb::source_files`.
+    pub fn record_line_file(&mut self, unit: UnitId, file: &str) {
+        let files = self.line_files.entry(unit.0).or_default();
+        if let Some(n) = files.get_mut(file) {
+            *n += 1;
+        } else {
+            files.insert(file.to_string(), 1);
+        }
+    }
+
     pub fn record_decl(&mut self, name: impl std::fmt::Display, id: TypeId) {
         self.decls.entry(self.format_path(name))
             .or_default()
@@ -756,6 +1521,20 @@ impl DebugDbBuilder {
         self.path.pop();
         result
     }
+
+    /// Like `path_component`, but for a namespace, which also becomes part of
+    /// the module path of any types recorded in `body`.
+    fn namespace<T>(
+        &mut self,
+        c: impl Into<String>,
+        body: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let c = c.into();
+        self.namespaces.push(c.clone());
+        let result = self.path_component(c, body);
+        self.namespaces.pop();
+        result
+    }
 }
 
 /// Utility for indexing entries in a key-value table by some projection.
@@ -785,6 +1564,224 @@ where
     index
 }
 
+/// The primary contents of a `DebugDb`, from which all of its indices can be
+/// derived. This is what `DebugDbBuilder::build` produces once types have
+/// been unified, and what the `cache` module saves.
+struct Tables {
+    endian: gimli::RunTimeEndian,
+    is_64: bool,
+    types: BTreeMap<TypeId, Type>,
+    type_canon: BTreeMap<TypeId, TypeId>,
+    type_name_index: BTreeIndex<TypeId, Name>,
+    type_modules: BTreeMap<TypeId, String>,
+    program_modules: BTreeMap<ProgramId, String>,
+    units: BTreeMap<gimli::UnitSectionOffset, CompUnit>,
+    subprograms: BTreeMap<ProgramId, Subprogram>,
+    line_table: BTreeMap<u64, Vec<LineNumberRow>>,
+    line_files: BTreeMap<gimli::UnitSectionOffset, BTreeMap<String, usize>>,
+    variables: BTreeMap<VarId, StaticVariable>,
+    debug_frame: gimli::DebugFrame<RtArcReader>,
+    eh_frame: Option<(gimli::EhFrame<RtArcReader>, gimli::BaseAddresses)>,
+    raw_symbols: Vec<(String, u64)>,
+    sections: Vec<(String, Range<u64>)>,
+    images: Vec<Image>,
+    warnings: Vec<String>,
+}
+
+impl Tables {
+    /// Generates the remaining indices and produces the database.
+    fn index(self) -> DebugDb {
+        let Tables {
+            endian,
+            is_64,
+            types,
+            type_canon,
+            type_name_index,
+            type_modules,
+            program_modules,
+            units,
+            subprograms,
+            line_table,
+            line_files,
+            variables,
+            debug_frame,
+            eh_frame,
+            raw_symbols,
+            sections,
+            images,
+            mut warnings,
+        } = self;
+
+
+        // Build array index.
+        let array_index = index_by_key(&types, |_, t| match t {
+            Type::Array(a) => Some((a.element_type_id, a.count)),
+            _ => None,
+        });
+        // Build subroutine index. This is more complex in shape than the other
+        // indices.
+        let subroutine_index = {
+            let mut ind = BTreeMap::<_, BTreeIndex<_, _>>::new();
+            for (k, v) in &types {
+                if let Type::Subroutine(s) = v {
+                    ind.entry(s.formal_parameters.clone())
+                        .or_default()
+                        .entry(s.return_type_id)
+                        .or_default()
+                        .insert(*k);
+                }
+            }
+            ind
+        };
+
+        // Build module index, leaving out types that were unified with others.
+        let types_by_module = index_by_key(&type_modules, |id, module| {
+            if type_canon.contains_key(id) {
+                None
+            } else {
+                Some(module.clone())
+            }
+        });
+
+        let mut types_by_short_name: BTreeIndex<_, _> = BTreeMap::new();
+        for (name, ids) in &type_name_index {
+            let ids = ids.iter().filter(|id| !type_canon.contains_key(id));
+            types_by_short_name.entry(typename::abbreviate(name))
+                .or_default()
+                .extend(ids);
+        }
+        types_by_short_name.retain(|_, ids| !ids.is_empty());
+
+        let subprograms_by_module = index_by_key(&program_modules, |_, module| {
+            Some(module.clone())
+        });
+
+        let variables_by_name = index_by_key(&variables, |_, v| Some(v.name.clone()));
+        let mut variables_by_linkage_name = index_by_key(
+            &variables,
+            |_, v| v.linkage_name.clone(),
+        );
+        index_demangled(&mut variables_by_linkage_name);
+        let subprograms_by_name = index_by_key(&subprograms, |_, p| p.name.clone());
+        let mut subprograms_by_linkage_name = index_by_key(
+            &subprograms,
+            |_, p| p.linkage_name.clone(),
+        );
+        index_demangled(&mut subprograms_by_linkage_name);
+
+        // Build address map.
+        let mut entities_by_address: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (&vid, v) in &variables {
+            let Some(t) = types.get(&v.type_id) else {
+                warnings.push(format!("type of variable {} not found: {:x?}",
+                    v.name, v.type_id));
+                continue;
+            };
+            let sz = t.byte_size_early(
+                |p| {
+                    let unit = units.range(..=p.offset).next_back();
+                    unit.map_or(if is_64 { 8 } else { 4 }, |(_, u)| u64::from(u.address_size))
+                },
+                |t| types.get(&t),
+            );
+            if let Some(sz) = sz {
+                entities_by_address.entry(v.location)
+                    .or_default()
+                    .push(AddressRange {
+                        range: v.location..v.location + sz,
+                        entity: EntityId::Var(vid),
+                    });
+            }
+        }
+        for (&pid, p) in &subprograms {
+            // Code the linker discarded can be left with ranges at zero.
+            for r in p.pc_ranges.iter().filter(|r| r.start != 0) {
+                entities_by_address.entry(r.start)
+                    .or_default()
+                    .push(AddressRange {
+                        range: r.clone(),
+                        entity: EntityId::Prog(pid),
+                    });
+            }
+        }
+
+        // Build reverse reference index, between canonical types only.
+        let mut type_users: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+        for (&id, t) in &types {
+            if type_canon.contains_key(&id) {
+                continue;
+            }
+            for (dep, used) in t.dependencies() {
+                let used = type_canon.get(&used).copied().unwrap_or(used);
+                type_users.entry(used).or_default().insert((id, dep));
+            }
+        }
+
+        let type_rcanon = invert(&type_canon);
+
+        let mut raw_symbols_by_name = index_by_key(
+            raw_symbols.iter().map(|(k, v)| (v, k)),
+            |_, name| Some(name.to_string()),
+        );
+        index_demangled(&mut raw_symbols_by_name);
+
+        let raw_symbols_by_address = index_by_key(
+            raw_symbols.iter().map(|(k, v)| (k, v)),
+            |_, addr| Some(*addr),
+        );
+
+        let sections_by_address = sections.into_iter()
+            .map(|(name, range)| (range.start, (range, name)))
+            .collect();
+
+
+        DebugDb {
+            endian,
+            types,
+            type_canon,
+            type_rcanon,
+            is_64,
+            subprograms,
+            subprograms_by_name,
+            subprograms_by_linkage_name,
+            line_table,
+            line_files,
+            variables,
+            debug_frame,
+            eh_frame,
+            type_name_index,
+            type_modules,
+            types_by_module,
+            types_by_short_name,
+            program_modules,
+            subprograms_by_module,
+            units,
+            type_users,
+            array_index,
+            subroutine_index,
+            variables_by_name,
+            variables_by_linkage_name,
+            entities_by_address,
+            raw_symbols_by_name,
+            raw_symbols_by_address,
+            sections_by_address,
+            images,
+            warnings,
+        }
+    }
+}
+
+/// Extends an index keyed by symbol name so that each C++ symbol can also be
+/// found by its demangled name.
+fn index_demangled<K: Ord + Clone>(index: &mut BTreeIndex<K, String>) {
+    let demangled = index.iter()
+        .filter_map(|(name, keys)| Some((demangle::demangle_symbol(name)?, keys.clone())))
+        .collect::<Vec<_>>();
+    for (name, keys) in demangled {
+        index.entry(name).or_default().extend(keys);
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum FileError {
     #[error("DWARF data structures could not be understood")]
@@ -793,101 +1790,580 @@ pub enum FileError {
     Obj(#[from] object::Error),
     #[error("DWARF failed to parse")]
     Dwarf(#[from] gimli::Error),
+    #[error("WebAssembly module could not be read")]
+    Wasm(#[from] wasm::WasmError),
+    #[error("firmware archive could not be read")]
+    Archive(#[from] archive::ArchiveError),
+    #[cfg(feature = "pdb")]
+    #[error("PDB could not be read")]
+    Pdb(#[source] Arc<::pdb::Error>),
+    #[error("debug info file does not match the program (UUIDs differ)")]
+    UuidMismatch,
+    #[error("section {section} could not be decompressed: {reason}")]
+    Decompress {
+        section: &'static str,
+        reason: String,
+    },
+    #[error("no programs were given")]
+    NoImages,
+    #[error("program {0} has a different endianness or address size from the first")]
+    ImageMismatch(String),
 }
 
 /// Parses type information from an `object::File`.
+///
+/// Every type is parsed up front, rather than when it's first asked for:
+/// unifying types defined in several units, resolving declarations, and
+/// indexing the users of each type all need the whole set. To avoid paying
+/// for that each time a program is loaded, save the database with `cache`.
 pub fn parse_file<'a>(
     object: &'a object::File,
 ) -> Result<DebugDb, FileError> {
-    let endian = if object.is_little_endian() {
-        gimli::RunTimeEndian::Little
+    parse_object(object, object, SplitDwarf::Ignore)
+}
+
+/// Parses type information from the contents of a program file, which may be
+/// any object format `object` understands, a WebAssembly module, a firmware
+/// archive of several programs (see `archive::Archive::images`), or, with the
+/// `pdb` feature, a Windows PDB.
+///
+/// This is for callers that get the file's bytes some other way than reading
+/// it from disk, such as from a browser.
+pub fn parse_bytes(data: &[u8]) -> Result<DebugDb, FileError> {
+    #[cfg(feature = "pdb")]
+    if pdb::is_pdb(data) {
+        return parse_pdb(data);
+    }
+    if wasm::WasmModule::is_wasm(data) {
+        parse_wasm(&wasm::WasmModule::parse(data)?)
+    } else if archive::Archive::is_archive(data) {
+        let archive = archive::Archive::parse(data)?;
+        let objects = archive.images().into_iter()
+            .map(|(name, data)| Ok((name, object::File::parse(data)?)))
+            .collect::<Result<Vec<_>, FileError>>()?;
+        let images = objects.iter()
+            .map(|(name, object)| (*name, object))
+            .collect::<Vec<_>>();
+        parse_images(&images)
     } else {
-        gimli::RunTimeEndian::Big
+        parse_file(&object::File::parse(data)?)
+    }
+}
+
+/// Parses type information from an `object::File` built with split DWARF
+/// (such as by `-gsplit-dwarf`), where the file itself only contains skeleton
+/// units and the bulk of the debug info lives elsewhere.
+///
+/// If `dwp` is provided, it's used as the DWARF package (`.dwp`) containing the
+/// split units. Otherwise, each skeleton unit's `.dwo` file is located using
+/// its `DW_AT_dwo_name` and `DW_AT_comp_dir` attributes and read from the
+/// filesystem, which targets like wasm32-unknown-unknown don't have. Split
+/// units that can't be found are skipped with a warning.
+///
+/// The split units are merged into a single database, as if the debug info had
+/// been linked into the file as usual.
+pub fn parse_split_file<'a>(
+    object: &'a object::File,
+    dwp: Option<&'a object::File>,
+) -> Result<DebugDb, FileError> {
+    let split = match dwp {
+        Some(dwp) => {
+            use gimli::Section;
+
+            let endian = object_endian(dwp);
+            let load_section = section_loader(dwp, endian, true);
+            let empty = gimli::EndianReader::new(Arc::from(&[][..]), endian);
+            // This is `DwarfPackage::load`, except that the type unit index is
+            // left empty. We don't look up type units by signature, and some
+            // tools write an empty index that gimli refuses to parse.
+            let package = gimli::DwarfPackage {
+                cu_index: gimli::DebugCuIndex::load(&load_section)?.index()?,
+                tu_index: gimli::DebugTuIndex::from(empty.clone()).index()?,
+                debug_abbrev: Section::load(&load_section)?,
+                debug_info: Section::load(&load_section)?,
+                debug_line: Section::load(&load_section)?,
+                debug_str: Section::load(&load_section)?,
+                debug_str_offsets: Section::load(&load_section)?,
+                debug_loc: Section::load(&load_section)?,
+                debug_loclists: Section::load(&load_section)?,
+                debug_rnglists: Section::load(&load_section)?,
+                debug_types: Section::load(&load_section)?,
+                empty,
+            };
+            SplitDwarf::Package(Box::new(package))
+        }
+        None => SplitDwarf::DwoFiles,
     };
+    parse_object(object, object, split)
+}
 
-    let load_section =
-        |id: gimli::SectionId| -> Result<RtArcReader, FileError> {
-            let cow = object.section_by_name(id.name())
-                .map(|sect| sect.uncompressed_data())
-                .transpose()?
-                .unwrap_or_else(Default::default);
-            Ok(gimli::EndianReader::new(Arc::from(cow), endian))
-        };
+/// Parses type information for a Mach-O binary whose debug info has been moved
+/// into a `.dSYM` bundle by `dsymutil`.
+///
+/// `dsym` is the DWARF file from inside the bundle (see `dsym_path`). Debug
+/// info is read from `dsym`, and symbols from `object`. If both files record
+/// a UUID, they must match.
+pub fn parse_file_with_dsym<'a>(
+    object: &'a object::File,
+    dsym: &'a object::File,
+) -> Result<DebugDb, FileError> {
+    if let (Some(a), Some(b)) = (object.mach_uuid()?, dsym.mach_uuid()?) {
+        if a != b {
+            return Err(FileError::UuidMismatch);
+        }
+    }
+    parse_object(object, dsym, SplitDwarf::Ignore)
+}
 
-    let dwarf = gimli::Dwarf::load(&load_section)?;
+/// Finds the DWARF file in the `.dSYM` bundle for the binary at `path`, if
+/// there is one.
+///
+/// This checks `<path>.dSYM/Contents/Resources/DWARF/<name>`, which is where
+/// `dsymutil` puts it by default.
+pub fn dsym_path(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    let name = path.file_name()?;
+    let mut bundle = path.as_os_str().to_owned();
+    bundle.push(".dSYM");
+    let dwarf = std::path::Path::new(&bundle)
+        .join("Contents/Resources/DWARF")
+        .join(name);
+    if dwarf.is_file() {
+        Some(dwarf)
+    } else {
+        None
+    }
+}
 
+/// Parses type information from the contents of a Windows PDB file, mapping
+/// its CodeView type records onto the same kinds of `Type` as DWARF would
+/// produce.
+///
+/// Only types, public symbols and sections are read, not functions or
+/// variables. Addresses in the resulting database are relative to the image
+/// base; see the `pdb` module for details.
+#[cfg(feature = "pdb")]
+pub fn parse_pdb(data: &[u8]) -> Result<DebugDb, FileError> {
+    Ok(pdb::parse(data)?.build()?)
+}
+
+/// Where to find the split units that go with any skeleton units.
+enum SplitDwarf {
+    /// Only parse what's in the main file.
+    Ignore,
+    /// Load a separate `.dwo` file for each skeleton unit.
+    DwoFiles,
+    /// Find split units in a DWARF package.
+    Package(Box<gimli::DwarfPackage<RtArcReader>>),
+}
+
+/// Does the work of `parse_file` and friends. Debug info is read from `debug`,
+/// which is usually the same as `object`, but may be a separate file holding
+/// the program's DWARF. Symbols are always read from `object`.
+fn parse_object(
+    object: &object::File,
+    debug: &object::File,
+    split: SplitDwarf,
+) -> Result<DebugDb, FileError> {
+    let endian = object_endian(debug);
+    let load_section = section_loader(debug, endian, false);
+    let mut builder = parse_dwarf(load_section, endian, debug.is_64(), split)?;
+
+    record_symbols_and_sections(object, &mut builder);
+    // .eh_frame is loaded code, so it's in the program even when the rest of
+    // the debug info has been split out.
+    record_eh_frame(object, endian, &mut builder)?;
+
+    Ok(builder.build()?)
+}
+
+/// Parses type information from several programs into a single database, such
+/// as the tasks of a firmware image that each run in their own part of the
+/// address space. Each entry of `images` gives a name for the program and the
+/// program itself.
+///
+/// Each program's debug info is placed after the previous program's, so the
+/// IDs of its types, subprograms and variables are distinct, and the program
+/// they came from can be found with `DebugDb::image_containing`. Types that
+/// are the same in several programs are unified as usual. Code and data
+/// addresses are taken as-is, so programs must not overlap in the address
+/// space.
+///
+/// The programs must share an endianness and address size. Call frame
+/// information is only taken from the first program.
+pub fn parse_images(
+    images: &[(&str, &object::File)],
+) -> Result<DebugDb, FileError> {
+    use gimli::{Reader, Section};
+
+    let Some((_, first)) = images.first() else {
+        return Err(FileError::NoImages);
+    };
+    let endian = object_endian(first);
+    let debug_frame = gimli::DebugFrame::load(section_loader(first, endian, false))?;
+    let mut builder = DebugDbBuilder::new(endian, first.is_64(), debug_frame);
+    record_eh_frame(first, endian, &mut builder)?;
+
+    let mut base = 0;
+    for &(name, object) in images {
+        if object_endian(object) != endian || object.is_64() != first.is_64() {
+            return Err(FileError::ImageMismatch(name.to_string()));
+        }
+        let dwarf = gimli::Dwarf::load(section_loader(object, endian, false))?;
+        let mut iter = dwarf.units();
+        while let Some(header) = iter.next()? {
+            let unit = dwarf.unit(rebase_unit_header(header, base)?)?;
+            dwarf_parser::with_info_base(base, || {
+                record_line_table(&dwarf, &unit, &mut builder)?;
+                parse_unit(&dwarf, &unit, &mut builder)
+            })?;
+        }
+        record_symbols_and_sections(object, &mut builder);
+
+        // Sections tend to be packed together, so merge the ones that touch.
+        let mut sections = object.sections()
+            .filter(|s| s.address() != 0 && s.size() != 0)
+            .map(|s| s.address()..s.address() + s.size())
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|r| r.start);
+        let mut address_ranges: Vec<Range<u64>> = vec![];
+        for r in sections {
+            match address_ranges.last_mut() {
+                Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+                _ => address_ranges.push(r),
+            }
+        }
+
+        let len = dwarf.debug_info.reader().len();
+        builder.record_image(Image {
+            name: name.to_string(),
+            debug_info: base..base + len,
+            address_ranges,
+        });
+        base += len;
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Records the symbols and allocated sections of `object`.
+fn record_symbols_and_sections(
+    object: &object::File,
+    builder: &mut DebugDbBuilder,
+) {
+    for sym in object.symbols() {
+        let Ok(name) = sym.name() else { continue; };
+        let addr = sym.address();
+        builder.record_raw_symbol(addr, name.to_string());
+    }
+
+    for section in object.sections() {
+        let (addr, size) = (section.address(), section.size());
+        if addr == 0 || size == 0 {
+            continue;
+        }
+        let Ok(name) = section.name() else { continue; };
+        builder.record_section(addr..addr + size, name.to_string());
+    }
+}
+
+/// Records the `.eh_frame` section of `object`, if it has one.
+fn record_eh_frame(
+    object: &object::File,
+    endian: gimli::RunTimeEndian,
+    builder: &mut DebugDbBuilder,
+) -> Result<(), FileError> {
+    let Some(section) = object.section_by_name(".eh_frame") else {
+        return Ok(());
+    };
     use gimli::Section;
-    let debug_frame = gimli::DebugFrame::load(load_section)?;
+    let mut eh_frame = gimli::EhFrame::load(section_loader(object, endian, false))?;
+    eh_frame.set_address_size(if object.is_64() { 8 } else { 4 });
+    let mut bases = gimli::BaseAddresses::default()
+        .set_eh_frame(section.address());
+    if let Some(text) = object.section_by_name(".text") {
+        bases = bases.set_text(text.address());
+    }
+    if let Some(got) = object.section_by_name(".got") {
+        bases = bases.set_got(got.address());
+    }
+    builder.record_eh_frame(eh_frame, bases);
+    Ok(())
+}
+
+/// Parses type information from the DWARF in a WebAssembly module.
+///
+/// Code addresses in the resulting database are relative to the start of the
+/// module's code section; see `wasm::WasmModule::code_address`.
+pub fn parse_wasm(
+    module: &wasm::WasmModule,
+) -> Result<DebugDb, FileError> {
+    // Wasm is always little-endian, and we only support wasm32 for now.
+    let endian = gimli::RunTimeEndian::Little;
+    let load_section = |id: gimli::SectionId| -> Result<RtArcReader, FileError> {
+        let data = module.custom_section(id.name()).unwrap_or_default();
+        Ok(gimli::EndianReader::new(Arc::from(data), endian))
+    };
+    let builder = parse_dwarf(load_section, endian, false, SplitDwarf::Ignore)?;
+    Ok(builder.build()?)
+}
 
-    let mut builder = DebugDbBuilder::new(endian, object.is_64(), debug_frame);
+/// Collects everything from the DWARF sections produced by `load_section`,
+/// plus any split units they refer to.
+fn parse_dwarf(
+    load_section: impl Fn(gimli::SectionId) -> Result<RtArcReader, FileError>,
+    endian: gimli::RunTimeEndian,
+    is_64: bool,
+    split: SplitDwarf,
+) -> Result<DebugDbBuilder, FileError> {
+    let dwarf = gimli::Dwarf::load(&load_section)?;
+
+    use gimli::{Reader, Section};
+    let mut debug_frame = gimli::DebugFrame::load(&load_section)?;
+    debug_frame.set_address_size(if is_64 { 8 } else { 4 });
+
+    let mut builder = DebugDbBuilder::new(endian, is_64, debug_frame);
+
+    // Split units have their own .debug_info offsets, starting from zero. To
+    // keep them from colliding with each other and with the main file, each
+    // one gets moved to just past the end of the previous.
+    let mut next_split_base = dwarf.debug_info.reader().len();
 
     let mut iter = dwarf.units();
     while let Some(header) = iter.next()? {
         let unit = dwarf.unit(header)?;
 
-        if let Some(lp) = &unit.line_program {
-            let lp = lp.clone();
-            let mut rows = lp.rows();
-
-            let mut last_row: Option<LineNumberRow> = None;
-            while let Some((header, row)) = rows.next_row()? {
-                let file = if let Some(file) = row.file(header) {
-                    if let Some(directory) = file.directory(header) {
-                        format!(
-                            "{}/{}",
-                            String::from_utf8_lossy(dwarf.attr_string(&unit, directory)?.bytes()),
-                            String::from_utf8_lossy(
-                            dwarf
-                            .attr_string(&unit, file.path_name())?
-                            .bytes())
-                        ).into()
-                    } else {
+        record_line_table(&dwarf, &unit, &mut builder)?;
+        parse_unit(&dwarf, &unit, &mut builder)?;
+
+        let Some(dwo_id) = unit.dwo_id else { continue };
+        let split_dwarf = match &split {
+            SplitDwarf::Ignore => continue,
+            SplitDwarf::DwoFiles => load_dwo_file(&dwarf, &unit, endian, &mut builder)?,
+            SplitDwarf::Package(package) => package.find_cu(dwo_id, &dwarf)?,
+        };
+        let Some(split_dwarf) = split_dwarf else {
+            warn!(builder, "split unit {:x?} not found", dwo_id);
+            continue;
+        };
+
+        let mut split_iter = split_dwarf.units();
+        while let Some(header) = split_iter.next()? {
+            let mut split_unit = split_dwarf.unit(rebase_unit_header(header, next_split_base)?)?;
+            if split_unit.dwo_id == Some(dwo_id) {
+                split_unit.copy_relocated_attributes(&unit);
+            }
+            if split_unit.line_program.is_none() {
+                // Split units don't point at a line program, but may have one
+                // at the start of .debug_line.dwo that gives their file names.
+                split_unit.line_program = split_dwarf.debug_line.program(
+                    gimli::DebugLineOffset(0),
+                    split_unit.header.address_size(),
+                    unit.comp_dir.clone(),
+                    split_unit.name.clone(),
+                ).ok();
+            }
+            dwarf_parser::with_info_base(next_split_base, || {
+                parse_unit(&split_dwarf, &split_unit, &mut builder)
+            })?;
+        }
+        next_split_base += split_dwarf.debug_info.reader().len();
+    }
+
+    Ok(builder)
+}
+
+fn object_endian(object: &object::File) -> gimli::RunTimeEndian {
+    if object.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    }
+}
+
+/// Produces a function for loading DWARF sections out of `object`. If `dwo` is
+/// true, the split DWARF (`.dwo`) variants of the sections are loaded instead.
+///
+/// Compressed sections, both `SHF_COMPRESSED` and GNU-style `.zdebug_*`, are
+/// decompressed as they're loaded; see the `compressed` module.
+fn section_loader<'a>(
+    object: &'a object::File,
+    endian: gimli::RunTimeEndian,
+    dwo: bool,
+) -> impl Fn(gimli::SectionId) -> Result<RtArcReader, FileError> + 'a {
+    move |id| {
+        let name = if dwo { id.dwo_name() } else { Some(id.name()) };
+        let Some(name) = name else {
+            return Ok(gimli::EndianReader::new(Arc::from(&[][..]), endian));
+        };
+        let cow = object.section_by_name(name)
+            .map(|sect| compressed::section_data(object, &sect))
+            .transpose()
+            .map_err(|reason| FileError::Decompress { section: name, reason })?
+            .unwrap_or_else(Default::default);
+        Ok(gimli::EndianReader::new(Arc::from(cow), endian))
+    }
+}
+
+/// Loads the `.dwo` file named by a skeleton unit, returning `None` if it
+/// doesn't name one or it can't be read.
+fn load_dwo_file(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    endian: gimli::RunTimeEndian,
+    builder: &mut DebugDbBuilder,
+) -> Result<Option<gimli::Dwarf<RtArcReader>>, FileError> {
+    use gimli::Reader;
+
+    let mut entries = unit.entries();
+    let Some((_, root)) = entries.next_dfs()? else { return Ok(None) };
+    let name = match root.attr_value(gimli::DW_AT_dwo_name)? {
+        Some(name) => Some(name),
+        None => root.attr_value(gimli::DW_AT_GNU_dwo_name)?,
+    };
+    let Some(name) = name else { return Ok(None) };
+
+    let mut path = std::path::PathBuf::new();
+    if let Some(comp_dir) = &unit.comp_dir {
+        path.push(&*comp_dir.to_string_lossy()?);
+    }
+    path.push(&*dwarf.attr_string(unit, name)?.to_string_lossy()?);
+
+    let buffer = match std::fs::read(&path) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            warn!(builder, "unable to read {}: {}", path.display(), e);
+            return Ok(None);
+        }
+    };
+    let dwo = object::File::parse(&*buffer)?;
+    let mut dwo_dwarf = gimli::Dwarf::load(section_loader(&dwo, endian, true))?;
+    // Addresses and (pre-DWARF 5) range lists are left in the main file.
+    dwo_dwarf.file_type = gimli::DwarfFileType::Dwo;
+    dwo_dwarf.debug_addr = dwarf.debug_addr.clone();
+    dwo_dwarf.ranges = gimli::RangeLists::new(
+        dwarf.ranges.debug_ranges().clone(),
+        dwo_dwarf.ranges.debug_rnglists().clone(),
+    );
+    Ok(Some(dwo_dwarf))
+}
+
+/// Makes a copy of a unit header that claims to be located `base` bytes
+/// further into `.debug_info`.
+fn rebase_unit_header(
+    header: gimli::UnitHeader<RtArcReader>,
+    base: usize,
+) -> Result<gimli::UnitHeader<RtArcReader>, FileError> {
+    let gimli::UnitSectionOffset::DebugInfoOffset(offset) = header.offset() else {
+        return Ok(header);
+    };
+    let entries = header.range_from(gimli::UnitOffset(header.header_size())..)?;
+    Ok(gimli::UnitHeader::new(
+        header.encoding(),
+        header.unit_length(),
+        header.type_(),
+        header.debug_abbrev_offset(),
+        gimli::DebugInfoOffset(offset.0 + base).into(),
+        entries,
+    ))
+}
+
+/// Records the rows of a unit's line number program.
+fn record_line_table(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    builder: &mut DebugDbBuilder,
+) -> Result<(), FileError> {
+    if let Some(lp) = &unit.line_program {
+        let lp = lp.clone();
+        let mut rows = lp.rows();
+
+        let mut last_row: Option<LineNumberRow> = None;
+        while let Some((header, row)) = rows.next_row()? {
+            let file = if let Some(file) = row.file(header) {
+                Some(if let Some(directory) = file.directory(header) {
+                    format!(
+                        "{}/{}",
+                        String::from_utf8_lossy(dwarf.attr_string(unit, directory)?.bytes()),
                         String::from_utf8_lossy(
                         dwarf
-                            .attr_string(&unit, file.path_name())?
-                            .bytes())
-                            .into_owned()
-                    }
+                        .attr_string(unit, file.path_name())?
+                        .bytes())
+                    )
                 } else {
-                    "???".into()
-                };
-                if let Some(mut pending) = last_row.take() {
-                    pending.pc_range.end = row.address();
+                    String::from_utf8_lossy(
+                    dwarf
+                        .attr_string(unit, file.path_name())?
+                        .bytes())
+                        .into_owned()
+                })
+            } else {
+                None
+            };
+            if let Some(mut pending) = last_row.take() {
+                pending.pc_range.end = row.address();
+                // Rows that cover no code (e.g. several rows for a single
+                // address) would only get in the way of lookups.
+                if !pending.pc_range.is_empty() {
+                    if let Some(file) = &pending.file {
+                        builder.record_line_file(UnitId(unit.header.offset()), file);
+                    }
                     builder.record_line_table_row(pending.pc_range.start, pending);
                 }
-
-                if !row.end_sequence() {
-                    last_row = Some(LineNumberRow {
-                        pc_range: row.address()..0,
-                        file,
-                        line: row.line(),
-                        column: match row.column() {
-                            gimli::ColumnType::Column(c) => Some(c),
-                            gimli::ColumnType::LeftEdge => None,
-                        },
-                    });
-                }
             }
-            if last_row.is_some() {
-                eprintln!("WARN: line number program not terminated by end sequence");
+
+            if !row.end_sequence() {
+                last_row = Some(LineNumberRow {
+                    pc_range: row.address()..0,
+                    file,
+                    line: row.line(),
+                    column: match row.column() {
+                        gimli::ColumnType::Column(c) => Some(c),
+                        gimli::ColumnType::LeftEdge => None,
+                    },
+                });
             }
         }
-        let mut entries = unit.entries();
-        while let Some(()) = entries.next_entry()? {
-            if entries.current().is_none() {
-                break;
-            }
-            dwarf_parser::parse_entry(&dwarf, &unit, &mut entries, &mut builder)?;
+        if last_row.is_some() {
+            warn!(builder, "line number program not terminated by end sequence");
         }
     }
+    Ok(())
+}
 
-    for sym in object.symbols() {
-        let Ok(name) = sym.name() else { continue; };
-        let addr = sym.address();
-        builder.record_raw_symbol(addr, name.to_string());
+/// Parses the debugging information entries of a unit.
+fn parse_unit(
+    dwarf: &gimli::Dwarf<RtArcReader>,
+    unit: &gimli::Unit<RtArcReader>,
+    builder: &mut DebugDbBuilder,
+) -> Result<(), FileError> {
+    use gimli::Reader;
+
+    let string = |r: &RtArcReader| r.to_string_lossy().map(Cow::into_owned);
+    let mut tree = unit.entries_tree(None)?;
+    let root = tree.root()?;
+    let producer = root.entry().attr_value(gimli::DW_AT_producer)?
+        .map(|v| dwarf.attr_string(unit, v).and_then(|s| string(&s)))
+        .transpose()?;
+    let language = match root.entry().attr_value(gimli::DW_AT_language)? {
+        Some(gimli::AttributeValue::Language(lang)) => Some(lang),
+        _ => None,
+    };
+    builder.record_unit(UnitId(unit.header.offset()), CompUnit {
+        name: unit.name.as_ref().map(string).transpose()?,
+        comp_dir: unit.comp_dir.as_ref().map(string).transpose()?,
+        producer,
+        language,
+        address_size: unit.header.address_size(),
+    });
+
+    let mut entries = unit.entries();
+    while let Some(()) = entries.next_entry()? {
+        if entries.current().is_none() {
+            break;
+        }
+        dwarf_parser::parse_entry(dwarf, unit, &mut entries, builder)?;
     }
-
-    Ok(builder.build()?)
+    Ok(())
 }
 
 #[derive(Clone, Debug)]
@@ -912,3 +2388,11 @@ fn invert<K, V>(map: &BTreeMap<K, V>) -> BTreeMap<V, BTreeSet<K>>
     }
     result
 }
+
+/// Checks whether `path` ends with the path components in `suffix`.
+fn path_ends_with(path: &str, suffix: &str) -> bool {
+    match path.strip_suffix(suffix) {
+        Some(rest) => rest.is_empty() || rest.ends_with('/') || suffix.starts_with('/'),
+        None => false,
+    }
+}
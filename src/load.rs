@@ -8,6 +8,8 @@ use rangemap::RangeInclusiveMap;
 use thiserror::Error;
 use std::convert::{TryFrom, Infallible};
 
+/// Types that can be read out of a `Machine`'s memory, given a description of
+/// the type the program used for the data.
 pub trait Load: Sized {
     fn from_state<M: Machine>(
         machine: &M,
@@ -17,6 +19,11 @@ pub trait Load: Sized {
     ) -> Result<Self, LoadError<M::Error>>;
 }
 
+/// Source of the memory contents of a program: a memory dump, a core file, a
+/// debug probe attached to a live target, etc.
+///
+/// Memory is read on demand, so a `Machine` doesn't need to hold a complete
+/// image of the address space.
 pub trait Machine {
     /// Error type that indicates that we had a failure to access machine state.
     type Error;
@@ -43,6 +50,14 @@ pub trait Machine {
     fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error>;
 }
 
+impl<M: Machine + ?Sized> Machine for &M {
+    type Error = M::Error;
+
+    fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error> {
+        (**self).read_memory(address, dest)
+    }
+}
+
 #[derive(Clone)]
 pub struct ImgMachine {
     img: Vec<u8>,
@@ -71,6 +86,19 @@ impl Machine for ImgMachine {
     }
 }
 
+/// A byte slice acts as a memory image starting at address zero.
+impl Machine for [u8] {
+    type Error = Infallible;
+
+    fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error> {
+        let Ok(address) = usize::try_from(address) else { return Ok(0) };
+        let Some(chunk) = self.get(address..) else { return Ok(0) };
+        let n = usize::min(chunk.len(), dest.len());
+        dest[..n].copy_from_slice(&chunk[..n]);
+        Ok(n)
+    }
+}
+
 impl Machine for RangeInclusiveMap<u64, Vec<u8>> {
     type Error = Infallible;
 
@@ -89,6 +117,59 @@ impl Machine for RangeInclusiveMap<u64, Vec<u8>> {
     }
 }
 
+/// Adds `data`, located at `base`, to a segment map, replacing any data
+/// already present at the same addresses.
+///
+/// Existing segments that only partially overlap are trimmed, rather than
+/// split, so that each remaining range still starts at the first byte of its
+/// data.
+pub fn overlay_segment(
+    segments: &mut RangeInclusiveMap<u64, Vec<u8>>,
+    base: u64,
+    data: Vec<u8>,
+) {
+    if data.is_empty() {
+        return;
+    }
+    let end = base.saturating_add(data.len() as u64 - 1);
+    let overlapping = segments.overlapping(&(base..=end))
+        .map(|(r, d)| (r.clone(), d.clone()))
+        .collect::<Vec<_>>();
+    for (range, old) in overlapping {
+        segments.remove(range.clone());
+        if *range.start() < base {
+            let n = usize::try_from(base - range.start()).unwrap();
+            segments.insert(*range.start()..=base - 1, old[..n.min(old.len())].to_vec());
+        }
+        if *range.end() > end {
+            let n = usize::try_from(end + 1 - range.start()).unwrap();
+            segments.insert(end + 1..=*range.end(), old[n.min(old.len())..].to_vec());
+        }
+    }
+    segments.insert(base..=end, data);
+}
+
+/// Adds the loadable segments of `object` to a segment map, replacing any data
+/// already present at the same addresses.
+///
+/// For an executable this produces its initial memory image. For an ELF core
+/// file, the `PT_LOAD` segments hold the contents of memory at the time of the
+/// dump, so overlaying a core file over its executable gives the program's
+/// state when it crashed. Segments with no data in the file (such as `.bss`,
+/// or regions the kernel didn't dump) are skipped.
+pub fn load_segments<'data>(
+    segments: &mut RangeInclusiveMap<u64, Vec<u8>>,
+    object: &object::File<'data>,
+) -> Result<(), object::Error> {
+    use object::{Object, ObjectSegment};
+
+    for seg in object.segments() {
+        let data = seg.data()?;
+        overlay_segment(segments, seg.address(), data.to_vec());
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum LoadError<E> {
     #[error("tuple type missing member {0}")]
@@ -97,6 +178,8 @@ pub enum LoadError<E> {
     NotATuple,
     #[error("not a struct")]
     NotAStruct,
+    #[error("not a union")]
+    NotAUnion,
     #[error("expected encoding {expected:?}, type had encoding {got:?}")]
     WrongEncoding { expected: Encoding, got: Encoding },
     #[error("expected type with size {expected}, but type had size {got}")]
@@ -109,6 +192,10 @@ pub enum LoadError<E> {
     NotACEnum,
     #[error("pointer type required")]
     NotAPointer,
+    #[error("slice or str fat pointer required")]
+    NotASlice,
+    #[error("str contents are not valid UTF-8")]
+    BadUtf8,
     #[error("array type is not finite and can't be loaded")]
     InfiniteArray,
     #[error("arrays with non-zero lower bounds ({0}) are not supported")]
@@ -133,7 +220,7 @@ pub enum LoadError<E> {
     #[error("can't load an uninhabited (empty) enum")]
     Uninhabited,
     #[error("discriminator value {0} not valid for type")]
-    BadDiscriminator(u64),
+    BadDiscriminator(u128),
     #[error("unsupported type (TODO)")]
     UnsupportedType,
     #[error("expected member `{0}` not found")]
@@ -142,6 +229,10 @@ pub enum LoadError<E> {
     WrongTypeName { expected: String, got: String},
     #[error("some of the bytes required to load this type are not present in the machine")]
     DataUnavailable,
+    #[error("pointer refers to a type that is not in the debug info")]
+    UnresolvedPointee,
+    #[error("null pointer")]
+    NullPointer,
 
     #[error("an error occurred accessing the underlying machine state")]
     Machine(#[from] E),
@@ -295,7 +386,7 @@ impl Load for core::sync::atomic::AtomicU32 {
         if ty.name != "core::sync::atomic::AtomicU32" {
             return Err(LoadError::WrongTypeName {
                 expected: "core::sync::atomic::AtomicU32".to_string(),
-                got: ty.name.clone(),
+                got: ty.name.to_string(),
             });
         }
         let Some(m_v) = ty.unique_member("v") else {
@@ -308,7 +399,7 @@ impl Load for core::sync::atomic::AtomicU32 {
         if unsafecell.name != "core::cell::UnsafeCell<u32>" {
             return Err(LoadError::WrongTypeName {
                 expected: "core::cell::UnsafeCell<u32>".to_string(),
-                got: unsafecell.name.clone(),
+                got: unsafecell.name.to_string(),
             });
         }
         let Some(m_value) = unsafecell.unique_member("value") else {
@@ -427,36 +518,82 @@ impl<T: Load> Load for Option<T> {
 }
 */
 
-pub(crate) fn choose_variant<'e, M: Machine>(
+impl Enum {
+    /// Works out which variant of this enum is stored in `bytes`, which hold a
+    /// value of the enum type, by reading and checking the discriminator.
+    ///
+    /// A variant with no discriminator value of its own is the default, chosen
+    /// when the discriminator matches no other variant. Rust uses this for
+    /// layouts where the discriminator is stored in a niche of another
+    /// variant's data.
+    pub fn determine_variant<'e>(
+        &'e self,
+        bytes: &[u8],
+        world: &DebugDb,
+    ) -> Result<&'e Variant, LoadError<Infallible>> {
+        choose_variant(bytes, 0, world, self)
+    }
+}
+
+/// Determines which variant of `e` is stored at `addr`. See
+/// `Enum::determine_variant`.
+pub(crate) fn choose_variant<'e, M: Machine + ?Sized>(
     machine: &M,
     addr: u64,
-    world: &'e DebugDb,
+    world: &DebugDb,
     e: &'e Enum,
 ) -> Result<&'e Variant, LoadError<M::Error>> {
     match &e.shape {
         VariantShape::Zero => {
-            return Err(LoadError::Uninhabited);
+            Err(LoadError::Uninhabited)
         }
         VariantShape::One(v) => Ok(v),
-        VariantShape::Many {
-            member, variants, ..
-        } => {
-            let dtype_id = member.type_id;
-            let dty = world.type_by_id(dtype_id).unwrap();
+        VariantShape::Many { member, .. } => {
+            let ranges = e.discriminant_ranges(world);
+            let dsize = world.type_by_id(member.type_id)
+                .and_then(|t| t.byte_size(world))
+                .ok_or(LoadError::UnsupportedType)?;
+            if ranges.is_empty() || !matches!(dsize, 1 | 2 | 4 | 8 | 16) {
+                return Err(LoadError::UnsupportedType);
+            }
             let da = addr + member.location;
-            let dsize = usize::try_from(dty.byte_size(world).unwrap()).unwrap();
-            let d = load_unsigned(world.endian(), machine, da, dsize)?
+            let d = load_discriminant(world.endian(), machine, da, dsize as usize)?
                 .ok_or(LoadError::DataUnavailable)?;
-            let v = variants
-                .get(&Some(d))
-                .or_else(|| variants.get(&None))
+            let v = ranges.into_iter()
+                .find(|(r, _)| r.contains(&d))
+                .map(|(_, v)| v)
                 .ok_or(LoadError::BadDiscriminator(d))?;
             Ok(v)
         }
     }
 }
 
-pub(crate) fn load_unsigned<M: Machine>(
+/// Loads an enum discriminator of `size` bytes, which may be up to 16 bytes
+/// for niches in 128-bit types. Returns `None` if the data isn't available.
+fn load_discriminant<M: Machine + ?Sized>(
+    endian: gimli::RunTimeEndian,
+    machine: &M,
+    addr: u64,
+    size: usize,
+) -> Result<Option<u128>, M::Error> {
+    if size != 16 {
+        return load_unsigned(endian, machine, addr, size)
+            .map(|d| d.map(u128::from));
+    }
+    let mut buffer = [0; 16];
+    let n = machine.read_memory(addr, &mut buffer)?;
+    Ok(if n < 16 {
+        None
+    } else {
+        Some(if endian.is_big_endian() {
+            u128::from_be_bytes(buffer)
+        } else {
+            u128::from_le_bytes(buffer)
+        })
+    })
+}
+
+pub(crate) fn load_unsigned<M: Machine + ?Sized>(
     endian: gimli::RunTimeEndian,
     machine: &M,
     addr: u64,
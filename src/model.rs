@@ -3,42 +3,206 @@
 //! This is our abstract description of types and routines in a program.
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::hash::Hash;
 use std::num::NonZeroU64;
+use std::ops::RangeInclusive;
 use crate::DebugDb;
+use crate::intern::Name;
 use indexmap::IndexMap;
 
-/// Identifies a specific type within a program, using its offset within the
-/// debug section(s).
+/// Identifies a specific type within a program.
+///
+/// This is an opaque handle: how types are numbered is up to the database,
+/// and may change. For now it's the type's offset within the debug
+/// section(s), which can be had with `offset` for use with `gimli` directly.
+/// The same goes for the other IDs below.
 ///
 /// Sometimes types appear more than once in debug info. In that case, each type
 /// will have a distinct `TypeId`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
-pub struct TypeId(pub gimli::UnitSectionOffset);
+pub struct TypeId(pub(crate) gimli::UnitSectionOffset);
 
-impl From<gimli::UnitSectionOffset> for TypeId {
-    fn from(x: gimli::UnitSectionOffset) -> Self {
-        Self(x)
-    }
+/// Identifies a subprogram within a program -- a function or subroutine.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ProgramId(pub(crate) gimli::UnitSectionOffset);
+
+/// Identifies a static variable.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct VarId(pub(crate) gimli::UnitSectionOffset);
+
+/// Identifies a parameter of a subprogram, or of an inlined copy of one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ParamId(pub(crate) gimli::UnitSectionOffset);
+
+/// Identifies a compilation unit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct UnitId(pub(crate) gimli::UnitSectionOffset);
+
+/// Any of the IDs of things defined in the debug info, for finding where they
+/// were defined with `DebugDb::unit_containing` and
+/// `DebugDb::image_containing`.
+pub trait EntryId: Copy {
+    /// Gets the location of the definition in the debug info.
+    fn offset(self) -> gimli::UnitSectionOffset;
 }
 
-/// Identifies a subprogram within a program -- a function or subroutine.
+macro_rules! entry_id {
+    ($($id:ident),*) => {$(
+        impl $id {
+            /// Gets the location of the definition in the debug info.
+            pub fn offset(self) -> gimli::UnitSectionOffset {
+                self.0
+            }
+        }
+
+        impl EntryId for $id {
+            fn offset(self) -> gimli::UnitSectionOffset {
+                self.0
+            }
+        }
+
+        impl From<gimli::UnitSectionOffset> for $id {
+            fn from(x: gimli::UnitSectionOffset) -> Self {
+                Self(x)
+            }
+        }
+
+        impl From<$id> for gimli::UnitSectionOffset {
+            fn from(x: $id) -> Self {
+                x.0
+            }
+        }
+    )*};
+}
+
+entry_id!(TypeId, ProgramId, VarId, ParamId, UnitId);
+
+/// Gives each of the structs recorded in the model an `id` accessor.
+macro_rules! entry_ids {
+    ($($id:ident for $($t:ident),*;)*) => {$($(
+        impl $t {
+            /// Gets the ID of this entry.
+            pub fn id(&self) -> $id {
+                $id(self.offset)
+            }
+        }
+    )*)*};
+}
+
+entry_ids! {
+    TypeId for Base, Struct, Enum, CEnum, Array, Pointer, Union, Subroutine,
+        Typedef, Qualified, Unresolved;
+    ProgramId for Subprogram;
+    ParamId for SubParameter;
+    VarId for StaticVariable;
+}
+
+/// The kinds of `Type`, without their contents; see `Type::kind`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
-pub struct ProgramId(pub gimli::UnitSectionOffset);
+pub enum TypeKind {
+    Base,
+    Struct,
+    Enum,
+    CEnum,
+    Array,
+    Pointer,
+    Union,
+    Subroutine,
+    Typedef,
+    Qualified,
+    Unresolved,
+}
 
-impl From<gimli::UnitSectionOffset> for ProgramId {
-    fn from(x: gimli::UnitSectionOffset) -> Self {
-        Self(x)
+impl TypeKind {
+    /// Every kind, in the order of their declaration.
+    pub const ALL: [Self; 11] = [
+        Self::Base,
+        Self::Struct,
+        Self::Enum,
+        Self::CEnum,
+        Self::Array,
+        Self::Pointer,
+        Self::Union,
+        Self::Subroutine,
+        Self::Typedef,
+        Self::Qualified,
+        Self::Unresolved,
+    ];
+
+    /// Gets the short name of the kind, such as `"struct"` or `"ptr"`, as
+    /// used in tysh's listings and filters.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Base => "base",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::CEnum => "c-enum",
+            Self::Array => "array",
+            Self::Pointer => "ptr",
+            Self::Union => "union",
+            Self::Subroutine => "subr",
+            Self::Typedef => "alias",
+            Self::Qualified => "qual",
+            Self::Unresolved => "missing",
+        }
+    }
+
+    /// Finds the kind with the short name `name`, as given by `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
     }
 }
 
-/// Identifies a static variable.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
-pub struct VarId(pub gimli::UnitSectionOffset);
+impl std::fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Formats a type's one-line description; see `Type::display`.
+pub struct TypeDisplay<'a> {
+    ty: &'a Type,
+    world: &'a DebugDb,
+}
+
+impl std::fmt::Display for TypeDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let count = |f: &mut std::fmt::Formatter, n: u64, what: &str| {
+            write!(f, ", {n} {what}{}", if n == 1 { "" } else { "s" })
+        };
 
-impl From<gimli::UnitSectionOffset> for VarId {
-    fn from(x: gimli::UnitSectionOffset) -> Self {
-        Self(x)
+        let ty = self.ty;
+        f.write_str(ty.kind_name())?;
+        if let Some(size) = ty.byte_size(self.world) {
+            count(f, size, "byte")?;
+        }
+        match ty {
+            Type::Struct(s) => count(f, s.members.len() as u64, "member"),
+            Type::Union(s) => count(f, s.members.len() as u64, "member"),
+            Type::Enum(s) => count(f, match &s.shape {
+                VariantShape::Zero => 0,
+                VariantShape::One(_) => 1,
+                VariantShape::Many { variants, .. } => variants.len() as u64,
+            }, "variant"),
+            Type::CEnum(s) => count(f, s.enumerators.len() as u64, "value"),
+            Type::Array(a) => match a.count {
+                Some(n) => count(f, n, "element"),
+                None => f.write_str(", unknown length"),
+            },
+            Type::Subroutine(s) => {
+                count(f, s.formal_parameters.len() as u64, "parameter")?;
+                if s.variadic {
+                    f.write_str(" and more")?;
+                }
+                Ok(())
+            }
+            Type::Base(_)
+                | Type::Pointer(_)
+                | Type::Typedef(_)
+                | Type::Qualified(_)
+                | Type::Unresolved(_) => Ok(()),
+        }
     }
 }
 
@@ -55,42 +219,219 @@ pub enum Type {
     Pointer(Pointer),
     Union(Union),
     Subroutine(Subroutine),
+    Typedef(Typedef),
+    Qualified(Qualified),
     Unresolved(Unresolved),
 }
 
 impl Type {
-    /// Returns the location of the type's definition within the debug info
-    /// section(s).
-    pub fn offset(&self) -> gimli::UnitSectionOffset {
-        // TODO so this field should clearly get factored out....
+    /// Calls `f` on each name in this type: its own, and those of its
+    /// members, variants, enumerators, and template parameters. Used for
+    /// interning.
+    pub(crate) fn for_each_name_mut(&mut self, f: &mut impl FnMut(&mut Name)) {
+        fn members(ms: &mut [Member], f: &mut impl FnMut(&mut Name)) {
+            for n in ms.iter_mut().filter_map(|m| m.name.as_mut()) {
+                f(n);
+            }
+        }
+        fn params(ps: &mut [TemplateTypeParameter], f: &mut impl FnMut(&mut Name)) {
+            for p in ps {
+                f(&mut p.name);
+            }
+        }
+        match self {
+            Type::Struct(s) => {
+                f(&mut s.name);
+                params(&mut s.template_type_parameters, f);
+                members(&mut s.members, f);
+            }
+            Type::Enum(s) => {
+                f(&mut s.name);
+                params(&mut s.template_type_parameters, f);
+                match &mut s.shape {
+                    VariantShape::Zero => (),
+                    VariantShape::One(v) => members(std::slice::from_mut(&mut v.member), f),
+                    VariantShape::Many { member, variants, .. } => {
+                        members(std::slice::from_mut(member), f);
+                        for v in variants.values_mut() {
+                            members(std::slice::from_mut(&mut v.member), f);
+                        }
+                    }
+                }
+            }
+            Type::Base(s) => f(&mut s.name),
+            Type::CEnum(s) => {
+                f(&mut s.name);
+                for e in s.enumerators.values_mut() {
+                    f(&mut e.name);
+                }
+            }
+            Type::Union(s) => {
+                f(&mut s.name);
+                params(&mut s.template_type_parameters, f);
+                members(&mut s.members, f);
+            }
+            Type::Pointer(s) => {
+                if let Some(n) = &mut s.name {
+                    f(n);
+                }
+            }
+            Type::Typedef(s) => f(&mut s.name),
+            Type::Subroutine(s) => {
+                s.parameter_names.iter_mut().flatten().for_each(f);
+            }
+            Type::Array(_)
+                | Type::Qualified(_)
+                | Type::Unresolved(_) => (),
+        }
+    }
+
+    /// Gets the kind of type this is, without any of the details.
+    pub fn kind(&self) -> TypeKind {
+        match self {
+            Self::Base(_) => TypeKind::Base,
+            Self::Struct(_) => TypeKind::Struct,
+            Self::Enum(_) => TypeKind::Enum,
+            Self::CEnum(_) => TypeKind::CEnum,
+            Self::Array(_) => TypeKind::Array,
+            Self::Pointer(_) => TypeKind::Pointer,
+            Self::Union(_) => TypeKind::Union,
+            Self::Subroutine(_) => TypeKind::Subroutine,
+            Self::Typedef(_) => TypeKind::Typedef,
+            Self::Qualified(_) => TypeKind::Qualified,
+            Self::Unresolved(_) => TypeKind::Unresolved,
+        }
+    }
+
+    /// Gets a short name for the kind of type this is, such as `"struct"` or
+    /// `"ptr"`. This is `self.kind().name()`.
+    pub fn kind_name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    /// Describes the type in one line, without its name: its kind, size, and
+    /// how many members, variants or whatever else it has, such as
+    /// `"struct, 24 bytes, 3 members"` or `"subr, 2 parameters"`.
+    ///
+    /// This is `self.display(world).to_string()`.
+    pub fn summary(&self, world: &DebugDb) -> String {
+        self.display(world).to_string()
+    }
+
+    /// Gets an adapter that formats the type's one-line description, as given
+    /// by `summary`, without building a string first.
+    pub fn display<'a>(&'a self, world: &'a DebugDb) -> TypeDisplay<'a> {
+        TypeDisplay { ty: self, world }
+    }
+
+    /// Lists the types this type refers to directly, and how.
+    ///
+    /// Array index types are left out, since they're synthetic. Template
+    /// parameters are included as `Dependency::Parameter`, even though they
+    /// don't affect the type's representation, so that generic types can be
+    /// found from their parameters.
+    pub fn dependencies(&self) -> Vec<(Dependency, TypeId)> {
+        use Dependency::*;
+
+        let mut deps = vec![];
         match self {
-            Self::Struct(s) => s.offset,
-            Self::Enum(s) => s.offset,
-            Self::Base(s) => s.offset,
-            Self::CEnum(s) => s.offset,
-            Self::Array(s) => s.offset,
-            Self::Pointer(s) => s.offset,
-            Self::Union(s) => s.offset,
-            Self::Subroutine(s) => s.offset,
-            Self::Unresolved(s) => s.offset,
+            Self::Struct(s) => {
+                deps.extend(s.members.iter().map(|m| (Contains, m.type_id)));
+                deps.extend(
+                    s.template_type_parameters.iter()
+                        .map(|p| (Parameter, p.type_id)),
+                );
+            }
+            Self::Union(s) => {
+                deps.extend(s.members.iter().map(|m| (Contains, m.type_id)));
+                deps.extend(
+                    s.template_type_parameters.iter()
+                        .map(|p| (Parameter, p.type_id)),
+                );
+            }
+            Self::Enum(s) => {
+                deps.extend(
+                    s.template_type_parameters.iter()
+                        .map(|p| (Parameter, p.type_id)),
+                );
+                match &s.shape {
+                    VariantShape::Zero => (),
+                    VariantShape::One(v) => {
+                        deps.push((Contains, v.member.type_id));
+                    }
+                    VariantShape::Many { member, variants, .. } => {
+                        deps.push((Contains, member.type_id));
+                        deps.extend(
+                            variants.values()
+                                .map(|v| (Contains, v.member.type_id)),
+                        );
+                    }
+                }
+            }
+            Self::Array(a) => deps.push((Contains, a.element_type_id)),
+            Self::Pointer(p) => deps.push((PointsTo, p.type_id)),
+            Self::Typedef(t) => deps.push((Aliases, t.type_id)),
+            Self::Qualified(q) => deps.push((Aliases, q.type_id)),
+            Self::Subroutine(s) => {
+                deps.extend(s.return_type_id.map(|t| (Signature, t)));
+                deps.extend(
+                    s.formal_parameters.iter().map(|&t| (Signature, t)),
+                );
+            }
+            Self::Base(_) | Self::CEnum(_) | Self::Unresolved(_) => (),
+        }
+        deps
+    }
+
+    /// Gets the type's ID.
+    pub fn id(&self) -> TypeId {
+        match self {
+            Self::Struct(s) => s.id(),
+            Self::Enum(s) => s.id(),
+            Self::Base(s) => s.id(),
+            Self::CEnum(s) => s.id(),
+            Self::Array(s) => s.id(),
+            Self::Pointer(s) => s.id(),
+            Self::Union(s) => s.id(),
+            Self::Subroutine(s) => s.id(),
+            Self::Typedef(s) => s.id(),
+            Self::Qualified(s) => s.id(),
+            Self::Unresolved(s) => s.id(),
         }
     }
 
     /// Determines the alignment of the type, in bytes.
     ///
-    /// Not all types have alignment.
+    /// Compilers often leave the alignment out of the debug info; C compilers
+    /// usually do. Base types and C enums are then taken to be naturally
+    /// aligned, and structs and unions to be aligned like their most aligned
+    /// member. Not all types have alignment.
     pub fn alignment(&self, world: &DebugDb) -> Option<u64> {
+        fn natural(byte_size: u64) -> u64 {
+            // The largest power of two dividing the size, e.g. 16 for a
+            // 16-byte float, but 2 for a 10-byte x87 float. Zero-sized types
+            // like `()` are aligned to 1.
+            if byte_size == 0 { 1 } else { 1 << byte_size.trailing_zeros() }
+        }
+        fn widest_member(members: &[Member], world: &DebugDb) -> Option<u64> {
+            members.iter()
+                .map(|m| m.alignment.or_else(|| world.type_by_id(m.type_id)?.alignment(world)))
+                .try_fold(1, |a, m| Some(a.max(m?)))
+        }
+
         match self {
-            Self::Struct(s) => s.alignment,
+            Self::Struct(s) => s.alignment.or_else(|| widest_member(&s.members, world)),
             Self::Enum(s) => s.alignment,
-            Self::Base(s) => s.alignment,
-            Self::CEnum(s) => s.alignment,
-            Self::Union(s) => Some(s.alignment),
+            Self::Base(s) => s.alignment.or(Some(natural(s.byte_size))),
+            Self::CEnum(s) => s.alignment.or(Some(natural(s.byte_size))),
+            Self::Union(s) => s.alignment.or_else(|| widest_member(&s.members, world)),
             Self::Array(a) => {
                 let eltty = world.type_by_id(a.element_type_id)?;
                 eltty.alignment(world)
             }
-            Self::Pointer(_) => Some(world.pointer_size() as u64),
+            Self::Pointer(p) => Some(world.pointer_byte_size(p)),
+            Self::Typedef(t) => world.type_by_id(t.type_id)?.alignment(world),
+            Self::Qualified(q) => world.type_by_id(q.type_id)?.alignment(world),
 
             _ => None,
         }
@@ -109,6 +450,7 @@ impl Type {
             Self::Base(s) => Some(s.byte_size),
             Self::CEnum(s) => Some(s.byte_size),
             Self::Union(s) => Some(s.byte_size),
+            Self::Pointer(s) => s.byte_size,
 
             _ => None,
         }
@@ -116,7 +458,7 @@ impl Type {
 
     pub(crate) fn byte_size_early<'a>(
         &'a self,
-        pointer_size: usize,
+        pointer_size: impl Fn(&Pointer) -> u64,
         lookup_type: impl Fn(TypeId) -> Option<&'a Type>,
     ) -> Option<u64> {
         let mut factor = 1;
@@ -129,8 +471,14 @@ impl Type {
                         factor *= a.count?;
                         t = lookup_type(a.element_type_id)?;
                     }
-                    Self::Pointer(_) => break Some(factor * pointer_size as u64),
-                    Self::Subroutine(_) => break None,
+                    Self::Pointer(p) => break Some(factor * pointer_size(p)),
+                    Self::Subroutine(_) | Self::Unresolved(_) => break None,
+                    Self::Typedef(td) => {
+                        t = lookup_type(td.type_id)?;
+                    }
+                    Self::Qualified(q) => {
+                        t = lookup_type(q.type_id)?;
+                    }
 
                     _ => panic!("inconsistency btw byte_size_early and inherent_byte_size"),
                 },
@@ -143,7 +491,7 @@ impl Type {
     /// Not all types have sizes.
     pub fn byte_size(&self, world: &DebugDb) -> Option<u64> {
         self.byte_size_early(
-            world.pointer_size(),
+            |p| world.pointer_byte_size(p),
             |t| world.type_by_id(t),
         )
     }
@@ -179,7 +527,32 @@ impl Type {
                     format!("[{}; ???]", eltname).into()
                 }
             }
-            Self::Subroutine(_) => "subroutine".into(), // TODO
+            Self::Subroutine(s) => {
+                let type_name = |id| world
+                    .type_by_id(id)
+                    .map(|t| t.name(world))
+                    .unwrap_or("???".into());
+                let mut params = s.formal_parameters.iter()
+                    .map(|&p| type_name(p))
+                    .collect::<Vec<_>>();
+                if s.variadic {
+                    params.push("...".into());
+                }
+                let mut name = format!("fn({})", params.join(", "));
+                if let Some(rt) = s.return_type_id {
+                    name += " -> ";
+                    name += &type_name(rt);
+                }
+                name.into()
+            }
+            Self::Typedef(s) => (&s.name).into(),
+            Self::Qualified(q) => {
+                let inner = world
+                    .type_by_id(q.type_id)
+                    .map(|t| t.name(world))
+                    .unwrap_or("???".into());
+                format!("{} {inner}", q.qualifier).into()
+            }
             Self::Unresolved(_) => "<UNRESOLVED>".into(),
         }
     }
@@ -233,6 +606,18 @@ impl From<Subroutine> for Type {
     }
 }
 
+impl From<Typedef> for Type {
+    fn from(x: Typedef) -> Self {
+        Self::Typedef(x)
+    }
+}
+
+impl From<Qualified> for Type {
+    fn from(x: Qualified) -> Self {
+        Self::Qualified(x)
+    }
+}
+
 /// A "base type," also known as a "primitive type," is not constructed by
 /// aggregating other types. Examples in Rust include `u32` and `bool`.
 ///
@@ -242,7 +627,7 @@ impl From<Subroutine> for Type {
 #[derive(Clone, Debug)]
 pub struct Base {
     /// Name of the type.
-    pub name: String,
+    pub name: Name,
     /// How to interpret the type's bits.
     pub encoding: Encoding,
     /// Number of bytes in a value of the type.
@@ -250,7 +635,7 @@ pub struct Base {
     /// Explicit alignment, if given.
     pub alignment: Option<u64>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// A "struct type" describes a record containing members, each of which has its
@@ -263,7 +648,7 @@ pub struct Base {
 #[derive(Debug, Clone)]
 pub struct Struct {
     /// Name of the struct type.
-    pub name: String,
+    pub name: Name,
     /// Size of a value of this struct in bytes.
     pub byte_size: Option<u64>,
     /// Alignment required for values of this struct.
@@ -282,7 +667,7 @@ pub struct Struct {
     /// order of position in the struct in memory.
     pub members: Vec<Member>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
     /// Location of the declaration of this subprogram in the source.
     pub decl_coord: DeclCoord,
 }
@@ -290,7 +675,7 @@ pub struct Struct {
 impl Struct {
     pub fn unique_member(&self, name: &str) -> Option<&Member> {
         let mut matches = self.members.iter()
-            .filter(|m| m.name.as_ref().map(String::as_str) == Some(name));
+            .filter(|m| m.name.as_deref() == Some(name));
         let first = matches.next()?;
         if matches.next().is_some() {
             // There is no _unique_ member by this name.
@@ -299,6 +684,41 @@ impl Struct {
             Some(first)
         }
     }
+
+    /// If this struct is the fat pointer rustc produces for a reference to a
+    /// slice or `str` -- `&[T]`, `&str`, `*const [T]` and so on -- gets its
+    /// `data_ptr` and `length` members, in that order.
+    pub fn slice_members(&self) -> Option<(&Member, &Member)> {
+        if PointerKind::from_name(&self.name) != PointerKind::Slice {
+            return None;
+        }
+        let data_ptr = self.unique_member("data_ptr")?;
+        let length = self.unique_member("length")?;
+        Some((data_ptr, length))
+    }
+
+    /// Checks whether this struct is a Rust slice or `str` fat pointer. See
+    /// `slice_members` for details.
+    pub fn is_slice(&self) -> bool {
+        self.slice_members().is_some()
+    }
+
+    /// Checks whether this struct is a Rust `str` fat pointer, like `&str`.
+    /// Such structs are also slices, as far as `is_slice` is concerned.
+    pub fn is_str(&self) -> bool {
+        self.is_slice()
+            && pointee_name(&self.name).map(str::trim) == Some("str")
+    }
+
+    /// For a slice or `str` fat pointer, finds the element type of the slice,
+    /// i.e. the type `data_ptr` points to. For a `str`, this is `u8`.
+    pub fn slice_element_type(&self, world: &DebugDb) -> Option<TypeId> {
+        let (data_ptr, _) = self.slice_members()?;
+        match world.type_by_id(world.resolve_alias(data_ptr.type_id))? {
+            Type::Pointer(p) => Some(p.type_id),
+            _ => None,
+        }
+    }
 }
 
 /// An "enum type," in the Rust sense of the term, is a tagged union (or
@@ -313,7 +733,7 @@ impl Struct {
 #[derive(Debug, Clone)]
 pub struct Enum {
     /// Name of the enum type.
-    pub name: String,
+    pub name: Name,
     /// Size of a value of the enum type, in bytes.
     pub byte_size: Option<u64>,
     /// Alignment required for values of this enum.
@@ -324,7 +744,62 @@ pub struct Enum {
     /// Description of the variants in this enum.
     pub shape: VariantShape,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+impl Enum {
+    /// Works out which discriminator values select which variant, as a list of
+    /// non-overlapping ranges in increasing order.
+    ///
+    /// For a niche-encoded enum, like `Option<&T>`, the debug info only gives
+    /// values for the variants stored in the niche; the remaining variant is
+    /// the default, and gets all the values in between. The ranges cover every
+    /// value the discriminator can hold, unless there's no default variant.
+    ///
+    /// Values are given zero-extended from the discriminator's size. The list
+    /// is empty if the enum has no discriminator, or if it doesn't have a size
+    /// of at most 16 bytes.
+    pub fn discriminant_ranges(
+        &self,
+        world: &DebugDb,
+    ) -> Vec<(RangeInclusive<u128>, &Variant)> {
+        let VariantShape::Many { member, variants, .. } = &self.shape else {
+            return vec![];
+        };
+        let dsize = world.type_by_id(member.type_id)
+            .and_then(|t| t.byte_size(world));
+        let max = match dsize {
+            Some(n @ 1..=16) => u128::MAX >> (128 - 8 * n),
+            _ => return vec![],
+        };
+
+        // Values may have been recorded sign-extended to 64 bits, so only
+        // keep the bits that fit.
+        let mut explicit = variants.iter()
+            .filter_map(|(k, v)| Some((u128::from((*k)?) & max, v)))
+            .collect::<Vec<_>>();
+        explicit.sort_by_key(|&(k, _)| k);
+        explicit.dedup_by_key(|&mut (k, _)| k);
+
+        let default = variants.get(&None);
+        let mut ranges = vec![];
+        // Start of the values not yet covered, or `None` once we've covered
+        // `max`.
+        let mut next = Some(0);
+        for (k, v) in explicit {
+            if let (Some(d), Some(n)) = (default, next) {
+                if n < k {
+                    ranges.push((n..=k - 1, d));
+                }
+            }
+            ranges.push((k..=k, v));
+            next = k.checked_add(1).filter(|&n| n <= max);
+        }
+        if let (Some(d), Some(n)) = (default, next) {
+            ranges.push((n..=max, d));
+        }
+        ranges
+    }
 }
 
 /// A "C-style enum" type -- a type with several value variants, each of which
@@ -332,7 +807,7 @@ pub struct Enum {
 #[derive(Debug, Clone)]
 pub struct CEnum {
     /// Name of the enum type.
-    pub name: String,
+    pub name: Name,
     /// Flag indicating that this enum is a distinct type, rather than
     /// evaluating as values of some base type. This is set for all enums in
     /// Rust, some enums in C++, and no enums in C.
@@ -344,7 +819,43 @@ pub struct CEnum {
     /// Variants ("enumerators") of this type.
     pub enumerators: IndexMap<u64, Enumerator>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+impl CEnum {
+    /// Finds the enumerator with the value `value`, if there is one.
+    ///
+    /// Only the low `byte_size` bytes of `value` are significant, so a raw
+    /// value read from memory or a register matches an enumerator with a
+    /// negative value, e.g. `0xffff` and `-1` are the same for a 2-byte enum.
+    pub fn enumerator_for_value(&self, value: i128) -> Option<&Enumerator> {
+        let mask = self.value_mask();
+        // Negative values are recorded sign-extended to 64 bits, so try that
+        // first; otherwise look for the same bits.
+        self.enumerators.get(&(value as u64)).or_else(|| {
+            self.enumerators.values()
+                .find(|e| e.const_value & mask == value as u64 & mask)
+        })
+    }
+
+    /// Produces a map from the value of each enumerator, as it would appear
+    /// in memory (i.e. truncated to `byte_size` and zero-extended), to its
+    /// name.
+    pub fn names_by_value(&self) -> BTreeMap<u64, &Name> {
+        let mask = self.value_mask();
+        self.enumerators.values()
+            .map(|e| (e.const_value & mask, &e.name))
+            .collect()
+    }
+
+    /// Gets a mask covering the bits of a value of this type.
+    fn value_mask(&self) -> u64 {
+        match self.byte_size {
+            0 => 0,
+            n if n >= 8 => u64::MAX,
+            n => (1 << (n * 8)) - 1,
+        }
+    }
 }
 
 /// An array type.
@@ -366,25 +877,122 @@ pub struct Array {
     /// Number of elements in the array, if specified.
     pub count: Option<u64>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// A pointer type.
 ///
 /// There are many flavors of pointers -- `const`, not-`const`, Rust references,
 /// C raw pointers, etc. This models them all. The differences between them are
-/// not present in DWARF -- though they can be inferred from the `name`.
+/// mostly not present in DWARF, but `is_mut` and `kind` infer them from the
+/// `name` and the pointee.
 ///
-/// Pointer size is implicit and fixed for the whole program; it can be queried
-/// from the `DebugDb` instance.
+/// Pointer size is usually implicit and fixed for the whole program; it can be
+/// queried from the `DebugDb` instance.
 #[derive(Debug, Clone)]
 pub struct Pointer {
     /// Type of data this points _to_.
     pub type_id: TypeId,
     /// Name of the pointer type. Compilers don't name all pointer types.
-    pub name: Option<String>,
+    pub name: Option<Name>,
+    /// Size of the pointer in bytes, if it's given. Otherwise it's the
+    /// address size of its unit; see `DebugDb::pointer_byte_size`.
+    pub byte_size: Option<u64>,
+    /// Target-specific address class (`DW_AT_address_class`), for targets
+    /// with more than one kind of pointer, such as near and far pointers.
+    pub address_class: Option<u64>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+impl Pointer {
+    /// Works out whether the pointee can be modified through this pointer.
+    ///
+    /// Rust pointers say so in their names (`&mut T`, `*const T`). Otherwise
+    /// the pointer is taken to be mutable unless the pointee is `const`
+    /// qualified, as in C.
+    pub fn is_mut(&self, world: &DebugDb) -> bool {
+        if let Some(name) = &self.name {
+            if name.starts_with("&mut ") || name.starts_with("*mut ") {
+                return true;
+            }
+            if name.starts_with('&') || name.starts_with("*const ") {
+                return false;
+            }
+        }
+        let mut id = self.type_id;
+        // Qualifiers can be nested (`const volatile`), but not infinitely.
+        for _ in 0..4 {
+            match world.type_by_id(id) {
+                Some(Type::Qualified(q)) if q.qualifier == Qualifier::Const => {
+                    return false;
+                }
+                Some(Type::Qualified(q)) => id = q.type_id,
+                _ => break,
+            }
+        }
+        true
+    }
+
+    /// Classifies the pointer as thin, or as a fat pointer to a slice or
+    /// trait object, going by the names of the pointer and its pointee.
+    pub fn kind(&self, world: &DebugDb) -> PointerKind {
+        if let Some(name) = &self.name {
+            let kind = PointerKind::from_name(name);
+            if kind != PointerKind::Thin {
+                return kind;
+            }
+        }
+        match world.type_name(self.type_id) {
+            Some(pointee) => PointerKind::from_pointee_name(&pointee),
+            None => PointerKind::Thin,
+        }
+    }
+}
+
+/// Shapes of pointer, as determined by `Pointer::kind`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PointerKind {
+    /// An ordinary pointer, which is just an address.
+    Thin,
+    /// A pointer to a slice or `str`, which is an address and a length.
+    Slice,
+    /// A pointer to a trait object, which is an address and a vtable pointer.
+    TraitObject,
+}
+
+impl PointerKind {
+    /// Classifies a pointer type by its Rust name, e.g. `&mut [u8]`.
+    pub fn from_name(name: &str) -> Self {
+        match pointee_name(name) {
+            Some(pointee) => Self::from_pointee_name(pointee),
+            None => Self::Thin,
+        }
+    }
+
+    /// Classifies a pointer type by the Rust name of what it points to, e.g.
+    /// `[u8]`.
+    pub fn from_pointee_name(pointee: &str) -> Self {
+        let pointee = pointee.trim();
+        if pointee == "str"
+            || (pointee.starts_with('[')
+                && pointee.ends_with(']')
+                && !pointee.contains(';'))
+        {
+            Self::Slice
+        } else if pointee.starts_with("dyn ") || pointee.starts_with("(dyn ") {
+            Self::TraitObject
+        } else {
+            Self::Thin
+        }
+    }
+}
+
+/// Strips the `&`, `&mut`, `*const` or `*mut` off the Rust name of a pointer
+/// type, giving the name of what it points to.
+pub(crate) fn pointee_name(name: &str) -> Option<&str> {
+    ["&mut ", "&", "*const ", "*mut "].iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
 }
 
 /// A C-style non-tagged union.
@@ -395,18 +1003,19 @@ pub struct Pointer {
 #[derive(Debug, Clone)]
 pub struct Union {
     /// Name of this union type.
-    pub name: String,
+    pub name: Name,
     /// Size of a value of this union type, in bytes.
     pub byte_size: u64,
-    /// Alignment required for a value of this union type, in bytes.
-    pub alignment: u64,
+    /// Alignment required for a value of this union type, in bytes. C
+    /// compilers generally don't record this.
+    pub alignment: Option<u64>,
     /// If this union is generic, this contains an array of template type
     /// parameters. If it is not generic, this is empty.
     pub template_type_parameters: Vec<TemplateTypeParameter>,
     /// Members of the union in declaration order.
     pub members: Vec<Member>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// A subroutine type. Note that this is different from a `Subprogram` -- this
@@ -419,8 +1028,88 @@ pub struct Subroutine {
     pub return_type_id: Option<TypeId>,
     /// Types of parameters to a routine of this type.
     pub formal_parameters: Vec<TypeId>,
+    /// Names of the parameters, in the same order as `formal_parameters`.
+    /// Compilers usually leave these out of subroutine types, so most are
+    /// `None`.
+    pub parameter_names: Vec<Option<Name>>,
+    /// If `true`, the routine takes more arguments after the
+    /// `formal_parameters`, as in C's `printf(const char *, ...)`.
+    pub variadic: bool,
+    /// If `true`, this type came from a C function prototype, so the
+    /// parameters are exactly as listed. C functions declared without one
+    /// (`int f()`) may be called with anything.
+    pub prototyped: bool,
+    /// Calling convention, if it was given. Normally it isn't, meaning the
+    /// normal convention for the platform.
+    pub calling_convention: Option<gimli::DwCc>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+/// An alternate name for another type, such as one introduced by a C
+/// `typedef`.
+///
+/// A typedef has the same size, alignment, and representation as the type it
+/// names. Use `DebugDb::resolve_alias` to find the underlying type.
+#[derive(Debug, Clone)]
+pub struct Typedef {
+    /// Name introduced by the typedef.
+    pub name: Name,
+    /// Type being given a name.
+    pub type_id: TypeId,
+    /// Location in debug info.
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+/// A type with a qualifier, such as `const`, applied.
+///
+/// Qualifiers don't change the representation of a type, so a qualified type
+/// has the same size and alignment as the type it qualifies. Each qualifier is
+/// a separate layer, so `const volatile T` is two nested `Qualified` types. Use
+/// `DebugDb::strip_qualifiers` to get at the type underneath.
+#[derive(Debug, Clone)]
+pub struct Qualified {
+    /// Which qualifier is applied.
+    pub qualifier: Qualifier,
+    /// Type being qualified.
+    pub type_id: TypeId,
+    /// Location in debug info.
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+/// How one type refers to another, as reported by `Type::dependencies`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Dependency {
+    /// The other type is stored inline: as a member of a struct or union, a
+    /// variant or discriminator of an enum, or the element of an array.
+    Contains,
+    /// The other type is the target of a pointer.
+    PointsTo,
+    /// This is a typedef or qualified version of the other type.
+    Aliases,
+    /// The other type is the return type or a parameter of a subroutine.
+    Signature,
+    /// The other type is a template type parameter of a generic type, like
+    /// the `u8` in `Option<u8>`.
+    Parameter,
+}
+
+/// Type qualifiers, as used by `Qualified`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Qualifier {
+    Const,
+    Volatile,
+    Restrict,
+}
+
+impl std::fmt::Display for Qualifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Const => "const",
+            Self::Volatile => "volatile",
+            Self::Restrict => "restrict",
+        })
+    }
 }
 
 /// A type that was not found in the debug info.
@@ -430,7 +1119,7 @@ pub struct Subroutine {
 #[derive(Debug, Clone)]
 pub struct Unresolved {
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// Possible encodings for a `Base` type.
@@ -472,7 +1161,7 @@ pub enum Encoding {
 #[derive(Debug, Clone)]
 pub struct TemplateTypeParameter {
     /// Name of parameter.
-    pub name: String,
+    pub name: Name,
     /// Type the parameter is bound to.
     pub type_id: TypeId,
 }
@@ -482,7 +1171,7 @@ pub struct TemplateTypeParameter {
 pub struct Member {
     /// Name of the member. Not all members have names, though in Rust they all
     /// do.
-    pub name: Option<String>,
+    pub name: Option<Name>,
     /// If `true`, this member is compiler-generated and will not make very much
     /// sense to the user.
     pub artificial: bool,
@@ -494,7 +1183,7 @@ pub struct Member {
     /// Offset of this member within the enclosing type.
     pub location: u64,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
     pub decl_coord: DeclCoord,
 }
 
@@ -512,8 +1201,6 @@ pub enum VariantShape {
     /// The enum contains a discriminator. This generally implies that there are
     /// two or more variants, though nothing in the spec requires this.
     Many {
-        /// Location of the definition of the discriminator in debug info.
-        discr: gimli::UnitSectionOffset,
         /// Member describing the discriminator. Note that this member will
         /// typically be nameless.
         member: Member,
@@ -525,6 +1212,19 @@ pub enum VariantShape {
     },
 }
 
+impl VariantShape {
+    /// Returns an iterator over the enum's variants, in the order they appear
+    /// in the debug info.
+    pub fn variants(&self) -> impl Iterator<Item = &Variant> {
+        let (one, many) = match self {
+            Self::Zero => (None, None),
+            Self::One(v) => (Some(v), None),
+            Self::Many { variants, .. } => (None, Some(variants.values())),
+        };
+        one.into_iter().chain(many.into_iter().flatten())
+    }
+}
+
 /// A variant of a Rust-style enum.
 #[derive(Debug, Clone)]
 pub struct Variant {
@@ -534,7 +1234,7 @@ pub struct Variant {
     /// struct.
     pub member: Member,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
     pub decl_coord: DeclCoord,
 }
 
@@ -542,11 +1242,11 @@ pub struct Variant {
 #[derive(Debug, Clone)]
 pub struct Enumerator {
     /// Name of this variant.
-    pub name: String,
+    pub name: Name,
     /// Numeric value associated with this invariant.
     pub const_value: u64,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// A function or subroutine in a program.
@@ -563,7 +1263,15 @@ pub struct Subprogram {
     ///
     /// Subprograms that are completely inlined will often have nonsense
     /// `pc_range` values starting at address 0.
+    ///
+    /// If the code is split into several pieces (see `pc_ranges`), this is the
+    /// piece at the lowest address, which is normally the entry point.
     pub pc_range: Option<std::ops::Range<u64>>,
+    /// All ranges of PC values in the code generated for this subprogram. This
+    /// usually has one element, the same as `pc_range`, but compilers may split
+    /// a function into discontiguous pieces, e.g. to move rarely-executed code
+    /// out of the way.
+    pub pc_ranges: Vec<std::ops::Range<u64>>,
     /// Location of the declaration of this subprogram in the source.
     pub decl_coord: DeclCoord,
     /// If this subprogram is an instance of a generic subprogram, this provides
@@ -579,7 +1287,7 @@ pub struct Subprogram {
     /// If this subprogram represents a specialization of another, this provides
     /// a link to the prototype. The prototype may have information that this
     /// record does not, such as a valid name.
-    pub abstract_origin: Option<gimli::UnitSectionOffset>,
+    pub abstract_origin: Option<ProgramId>,
     /// Actual symbol name used to refer to this subprogram, if it is different
     /// from `name` -- which it tends to be in languages with hierarchical
     /// namespaces.
@@ -589,8 +1297,33 @@ pub struct Subprogram {
     ///
     /// In Rust, `noreturn` functions tend to have `!` as their return type.
     pub noreturn: bool,
+    /// Where the subprogram's local variables are found relative to, if its
+    /// code was generated here and the debug info says.
+    pub frame_base: Option<FrameBase>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+/// The frame base of a subprogram (`DW_AT_frame_base`), which the locations
+/// of its local variables are given relative to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameBase {
+    /// The canonical frame address from the CFI, as GCC uses.
+    Cfa,
+    /// The value of a register: the stack pointer, or a frame pointer if the
+    /// function keeps one.
+    Register(gimli::Register),
+    /// A register plus an offset.
+    RegisterOffset(gimli::Register, i64),
+    /// Something more complicated, which isn't interpreted.
+    Other,
+}
+
+impl Subprogram {
+    /// Demangles `linkage_name`, if there is one and it's a C++ symbol.
+    pub fn demangled_name(&self) -> Option<String> {
+        self.linkage_name.as_deref().and_then(crate::demangle::demangle_symbol)
+    }
 }
 
 /// Parameter to a subprogram.
@@ -610,7 +1343,7 @@ pub struct SubParameter {
     /// Type of the parameter, if available.
     pub type_id: Option<TypeId>,
     /// Reference to a different `SubParameter` that this specializes.
-    pub abstract_origin: Option<gimli::UnitSectionOffset>,
+    pub abstract_origin: Option<ParamId>,
     /// Fixed value for this parameter. This can happen in cases where a
     /// specialized `Subprogram` fixes one or more parameter values to
     /// constants.
@@ -618,7 +1351,7 @@ pub struct SubParameter {
     /// TODO: type probably needs to be more general.
     pub const_value: Option<u64>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// File "coordinates" -- path, line number, column number.
@@ -645,9 +1378,9 @@ impl DeclCoord {
 #[derive(Clone, Debug)]
 pub struct InlinedSubroutine {
     /// Location of the subprogram abstract root that defines this.
-    pub abstract_origin: Option<gimli::UnitSectionOffset>,
+    pub abstract_origin: Option<ProgramId>,
     /// Ranges of PC values that are included in this inlined subroutine.
-    pub pc_ranges: Vec<gimli::Range>,
+    pub pc_ranges: Vec<std::ops::Range<u64>>,
     /// Location of the callsite that was inlined.
     pub call_coord: DeclCoord,
     /// Further inlined subroutines within this one.
@@ -655,7 +1388,7 @@ pub struct InlinedSubroutine {
     /// Definition of the formal parameters to this inlined subroutine.
     pub formal_parameters: Vec<SubParameter>,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
 }
 
 /// A row of the computed line number table.
@@ -663,8 +1396,8 @@ pub struct InlinedSubroutine {
 pub struct LineNumberRow {
     /// Range of PC values that should use this entry.
     pub pc_range: std::ops::Range<u64>,
-    /// Filename.
-    pub file: String,
+    /// Filename, if the line table gives one.
+    pub file: Option<String>,
     /// Line number, if available.
     pub line: Option<NonZeroU64>,
     /// Column number, if available.
@@ -675,22 +1408,100 @@ pub struct LineNumberRow {
 ///
 /// TODO: the name of this type should become more meaningful as we learn how it
 /// is used.
+#[derive(Clone, Debug)]
 pub struct PcInfo {
     /// Subprogram being run.
     pub subprogram: ProgramId,
-    /// File containing code being run.
-    pub file: String,
+    /// File containing code being run, if available.
+    pub file: Option<String>,
     /// Line number of code being run, if available.
     pub line: Option<NonZeroU64>,
     /// Column number of code being run, if available.
     pub column: Option<NonZeroU64>,
 }
 
+/// An inlined call, with the subprogram it's an instance of, as found by
+/// `DebugDb::inlined_calls_for_pc`.
+pub(crate) type InlinedCall<'a> = (&'a InlinedSubroutine, ProgramId);
+
+/// Description of one address from a backtrace, as produced by
+/// `DebugDb::symbolize`.
+#[derive(Clone, Debug)]
+pub struct SymbolizedFrame {
+    /// Address that was symbolized, as given.
+    pub address: u64,
+    /// Functions executing at the address, innermost (most recently inlined)
+    /// first, ending with the function that was actually called. Empty if
+    /// nothing is known about the address.
+    pub functions: Vec<SymbolizedFunction>,
+}
+
+/// One function in a `SymbolizedFrame`.
+#[derive(Clone, Debug)]
+pub struct SymbolizedFunction {
+    /// Name of the function, if known.
+    pub name: Option<String>,
+    /// Subprogram for the function.
+    pub subprogram: ProgramId,
+    /// File containing the code being run, if known.
+    pub file: Option<String>,
+    /// Line number of the code being run, if known.
+    pub line: Option<NonZeroU64>,
+    /// Column number of the code being run, if known.
+    pub column: Option<NonZeroU64>,
+}
+
+/// A compilation unit: the debug info from one run of the compiler, covering
+/// one source file in C, or one crate (or codegen unit of a crate) in Rust.
+#[derive(Clone, Debug, Default)]
+pub struct CompUnit {
+    /// Name of the unit, usually the path of the main source file.
+    pub name: Option<String>,
+    /// Directory the compiler was run in. Relative paths in the unit are
+    /// relative to this.
+    pub comp_dir: Option<String>,
+    /// Description of the compiler that produced the unit, usually its name
+    /// and version, and sometimes its options.
+    pub producer: Option<String>,
+    /// Source language of the unit.
+    pub language: Option<gimli::DwLang>,
+    /// Size of a target address in bytes, from the unit's header. Pointers in
+    /// the unit that don't give their own size are this big.
+    pub address_size: u8,
+}
+
+impl CompUnit {
+    /// Gives the name of the unit's language, like `Rust` or `C99`.
+    pub fn language_name(&self) -> Option<Cow<'static, str>> {
+        let lang = self.language?;
+        Some(match lang.static_string() {
+            Some(s) => s.trim_start_matches("DW_LANG_").into(),
+            None => format!("language {:#x}", lang.0).into(),
+        })
+    }
+}
+
+/// One of several programs whose debug info has been combined into a single
+/// database by `parse_images`, such as a task in a firmware archive.
+#[derive(Clone, Debug)]
+pub struct Image {
+    /// Name of the image, such as the name of the task.
+    pub name: String,
+    /// Where the image's units were placed in `.debug_info`. The IDs of the
+    /// image's types, subprograms and variables fall in this range.
+    pub debug_info: std::ops::Range<usize>,
+    /// Addresses occupied by the image's allocated sections.
+    pub address_ranges: Vec<std::ops::Range<u64>>,
+}
+
 /// A static variable with a fixed address.
 #[derive(Clone, Debug)]
 pub struct StaticVariable {
     /// Name of variable.
     pub name: String,
+    /// Actual symbol name used to refer to this variable, if it is different
+    /// from `name`.
+    pub linkage_name: Option<String>,
     /// Type contained in variable.
     pub type_id: TypeId,
     /// Location of variable declaration.
@@ -698,7 +1509,14 @@ pub struct StaticVariable {
     /// Address in memory.
     pub location: u64,
     /// Location in debug info.
-    pub offset: gimli::UnitSectionOffset,
+    pub(crate) offset: gimli::UnitSectionOffset,
+}
+
+impl StaticVariable {
+    /// Demangles `linkage_name`, if there is one and it's a C++ symbol.
+    pub fn demangled_name(&self) -> Option<String> {
+        self.linkage_name.as_deref().and_then(crate::demangle::demangle_symbol)
+    }
 }
 
 pub trait Equiv {
@@ -784,6 +1602,7 @@ impl<T> Equiv for Option<T>
     fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
         match (self, other) {
             (Some(a), Some(b)) => a.equiv(b),
+            (None, None) => Some(vec![]),
             _ => None
         }
     }
@@ -856,7 +1675,9 @@ impl Equiv for Enum {
 
 impl Equiv for Pointer {
     fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
-        if self.name != other.name {
+        let self_easy = (&self.name, self.byte_size, self.address_class);
+        let other_easy = (&other.name, other.byte_size, other.address_class);
+        if self_easy != other_easy {
             // TODO: should this allow for one unnamed type?
             return None;
         }
@@ -913,6 +1734,12 @@ impl Equiv for CEnum {
 
 impl Equiv for Subroutine {
     fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
+        let self_easy = (self.variadic, self.prototyped, self.calling_convention);
+        let other_easy = (other.variadic, other.prototyped, other.calling_convention);
+        if self_easy != other_easy {
+            return None;
+        }
+
         let mut conditions = vec![];
         conditions.extend(self.return_type_id.equiv(&other.return_type_id)?);
         conditions.extend(self.formal_parameters.equiv(&other.formal_parameters)?);
@@ -920,6 +1747,26 @@ impl Equiv for Subroutine {
     }
 }
 
+impl Equiv for Typedef {
+    fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
+        if self.name != other.name {
+            return None;
+        }
+
+        Some(vec![(self.type_id, other.type_id)])
+    }
+}
+
+impl Equiv for Qualified {
+    fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
+        if self.qualifier != other.qualifier {
+            return None;
+        }
+
+        Some(vec![(self.type_id, other.type_id)])
+    }
+}
+
 impl Equiv for Type {
     fn equiv(&self, other: &Self) -> Option<Vec<(TypeId, TypeId)>> {
         match (self, other) {
@@ -931,7 +1778,146 @@ impl Equiv for Type {
             (Self::CEnum(a), Self::CEnum(b)) => a.equiv(b),
             (Self::Union(a), Self::Union(b)) => a.equiv(b),
             (Self::Subroutine(a), Self::Subroutine(b)) => a.equiv(b),
+            (Self::Typedef(a), Self::Typedef(b)) => a.equiv(b),
+            (Self::Qualified(a), Self::Qualified(b)) => a.equiv(b),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+    use crate::value::{self, Value};
+
+    /// Records an enum named `E` whose discriminator is a `disc_size`-byte
+    /// integer at offset 0, with an empty variant for each of `variants`.
+    fn enumeration(
+        types: &mut TestTypes,
+        disc_size: u64,
+        variants: &[(Option<u64>, &str)],
+    ) -> TypeId {
+        let disc_id = types.base("disc", disc_size);
+        let member = types.member("", disc_id, 0);
+        let variants = variants.iter()
+            .map(|&(value, name)| {
+                let payload = types.structure(name, disc_size, &[]);
+                let variant = Variant {
+                    member: types.member(name, payload, 0),
+                    offset: types.offset(),
+                    decl_coord: Default::default(),
+                };
+                (value, variant)
+            })
+            .collect();
+        types.record(|offset| Enum {
+            name: "E".into(),
+            byte_size: Some(disc_size),
+            alignment: None,
+            template_type_parameters: vec![],
+            shape: VariantShape::Many { member: Member { name: None, ..member }, variants },
+            offset,
+        })
+    }
+
+    fn ranges(world: &DebugDb, id: TypeId) -> Vec<(RangeInclusive<u128>, String)> {
+        let Some(Type::Enum(e)) = world.type_by_id(id) else { panic!("not an enum") };
+        e.discriminant_ranges(world).into_iter()
+            .map(|(r, v)| (r, v.member.name.as_deref().unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn display_summarizes() {
+        let mut types = TestTypes::little();
+        let u8_id = types.base("u8", 1);
+        let pair = types.structure("Pair", 2, &[("a", u8_id, 0), ("b", u8_id, 1)]);
+        let e = enumeration(&mut types, 1, &[(Some(0), "A")]);
+        let world = types.build();
+
+        let show = |id| world.type_by_id(id).unwrap().display(&world).to_string();
+        assert_eq!(show(u8_id), "base, 1 byte");
+        assert_eq!(show(pair), "struct, 2 bytes, 2 members");
+        assert_eq!(show(e), "enum, 1 byte, 1 variant");
+        assert_eq!(world.type_by_id(pair).unwrap().summary(&world), show(pair));
+    }
+
+    #[test]
+    fn explicit_discriminants() {
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 1, &[(Some(1), "B"), (Some(0), "A")]);
+        let world = types.build();
+        assert_eq!(ranges(&world, id), [(0..=0, "A".to_string()), (1..=1, "B".to_string())]);
+    }
+
+    #[test]
+    fn niche_fills_the_gaps() {
+        let mut types = TestTypes::little();
+        let id = enumeration(
+            &mut types,
+            1,
+            &[(Some(2), "A"), (None, "Dataful"), (Some(3), "B")],
+        );
+        let world = types.build();
+        let expected = [
+            (0..=1, "Dataful"),
+            (2..=2, "A"),
+            (3..=3, "B"),
+            (4..=255, "Dataful"),
+        ];
+        assert_eq!(ranges(&world, id), expected.map(|(r, n)| (r, n.to_string())));
+    }
+
+    #[test]
+    fn niche_at_the_ends() {
+        // Like `Option<&T>`, where null is `None` and everything else `Some`.
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 4, &[(Some(0), "None"), (None, "Some")]);
+        let world = types.build();
+        assert_eq!(
+            ranges(&world, id),
+            [(0..=0, "None".to_string()), (1..=0xFFFF_FFFF, "Some".to_string())],
+        );
+
+        // A niche at the top of the range, recorded as -1 sign-extended to 64
+        // bits.
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 1, &[(Some(u64::MAX), "None"), (None, "Some")]);
+        let world = types.build();
+        assert_eq!(
+            ranges(&world, id),
+            [(0..=254, "Some".to_string()), (255..=255, "None".to_string())],
+        );
+    }
+
+    #[test]
+    fn discriminator_sizes() {
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 16, &[(Some(0), "A")]);
+        let world = types.build();
+        assert_eq!(ranges(&world, id), [(0..=0, "A".to_string())]);
+
+        // Too big to describe.
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 32, &[(Some(0), "A")]);
+        let world = types.build();
+        assert!(ranges(&world, id).is_empty());
+    }
+
+    #[test]
+    fn decode_niche() {
+        let mut types = TestTypes::little();
+        let id = enumeration(&mut types, 4, &[(Some(0), "None"), (None, "Some")]);
+        let world = types.build();
+        let ty = world.type_by_id(id).unwrap();
+
+        let variant = |bytes: &[u8]| match Value::from_bytes(bytes, &world, ty).unwrap() {
+            Value::Enum(value::Enum { disc, .. }) => disc,
+            v => panic!("expected an enum, got {v:?}"),
+        };
+        assert_eq!(variant(&[0, 0, 0, 0]), "None");
+        assert_eq!(variant(&[0x34, 0x12, 0, 0]), "Some");
+        assert_eq!(variant(&[0xFF, 0xFF, 0xFF, 0xFF]), "Some");
+    }
+}
@@ -0,0 +1,613 @@
+//! Support for reading type information from Windows PDB files, as an
+//! alternative to DWARF.
+//!
+//! PDBs describe types with CodeView type records, which are numbered rather
+//! than located by offset. Each record becomes the type whose `TypeId` is its
+//! index, given as a `.debug_info` offset, so the rest of the library can treat
+//! it like any other type. Primitive types like `int` are numbered but have no
+//! records, and are made up as they're referred to.
+//!
+//! Some types in the model have no record of their own in CodeView: qualifiers
+//! on a pointer are attributes of the pointer's record, and a multidimensional
+//! array is one record. These get IDs in the `.debug_types` offset space,
+//! which a database read from a PDB otherwise doesn't use.
+//!
+//! Only types, public symbols and sections are read. Addresses are relative to
+//! the image base (RVAs), since a PDB doesn't say where the image is loaded.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+use ::pdb::{FallibleIterator, Indirection, PrimitiveKind, PrimitiveType, TypeData, TypeIndex};
+use indexmap::IndexMap;
+
+use crate::dwarf_parser::{ANONYMOUS_ENUM, ANONYMOUS_STRUCT, ANONYMOUS_UNION};
+use crate::{
+    Array, Base, CEnum, DebugDbBuilder, DeclCoord, Encoding, Enumerator, FileError, Member,
+    Pointer, Qualified, Qualifier, Struct, Subroutine, TypeId, Union,
+};
+
+/// Start of an MSF 7.0 file, the container format of PDBs written by any
+/// toolchain from this century.
+const MAGIC: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0";
+
+/// Type indices below this are primitive types, described by the index
+/// itself rather than by a record.
+const FIRST_RECORD: u32 = 0x1000;
+
+impl From<::pdb::Error> for FileError {
+    fn from(e: ::pdb::Error) -> Self {
+        FileError::Pdb(Arc::new(e))
+    }
+}
+
+/// Checks whether `data` starts like a PDB file.
+pub fn is_pdb(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Reads the types, public symbols and sections of the PDB file `data` into a
+/// builder. See `parse_pdb`.
+pub(crate) fn parse(data: &[u8]) -> Result<DebugDbBuilder, FileError> {
+    let mut pdb = ::pdb::PDB::open(std::io::Cursor::new(data))?;
+
+    use ::pdb::MachineType;
+    let is_64 = matches!(
+        pdb.debug_information()?.machine_type()?,
+        MachineType::Amd64 | MachineType::Arm64 | MachineType::Ia64,
+    );
+    // Windows only runs on little-endian machines, and PDBs have no
+    // `.debug_frame` to speak of.
+    let endian = gimli::RunTimeEndian::Little;
+    let debug_frame = gimli::DebugFrame::from(
+        gimli::EndianReader::new(Arc::from(&[][..]), endian),
+    );
+    let mut builder = DebugDbBuilder::new(endian, is_64, debug_frame);
+
+    let tpi = pdb.type_information()?;
+    let mut records = BTreeMap::new();
+    let mut iter = tpi.iter();
+    while let Some(ty) = iter.next()? {
+        match ty.parse() {
+            Ok(data) => {
+                records.insert(ty.index(), data);
+            }
+            Err(e) => warn!(builder, "skipping type record {:#x}: {e}", ty.index().0),
+        }
+    }
+
+    // Classes, unions and enums are mostly referred to through forward
+    // references, which only give their names. Sizes are needed to count
+    // array elements, so collect them from the definitions.
+    let mut sizes_by_name = HashMap::new();
+    for data in records.values() {
+        let (properties, name, unique_name, size) = match data {
+            TypeData::Class(c) => (c.properties, c.name, c.unique_name, c.size),
+            TypeData::Union(u) => (u.properties, u.name, u.unique_name, u.size),
+            _ => continue,
+        };
+        if !properties.forward_reference() {
+            sizes_by_name.insert(unique_name.unwrap_or(name).to_string().into_owned(), size);
+        }
+    }
+
+    let mut converter = Converter {
+        records: &records,
+        finder: tpi.finder(),
+        sizes_by_name,
+        primitives: BTreeSet::new(),
+        next_synthetic: 0,
+        builder,
+    };
+    for (&index, data) in &records {
+        converter.record(index, data);
+    }
+    let mut builder = converter.builder;
+
+    let address_map = pdb.address_map()?;
+    let symbols = pdb.global_symbols()?;
+    let mut iter = symbols.iter();
+    while let Some(symbol) = iter.next()? {
+        let Ok(::pdb::SymbolData::Public(public)) = symbol.parse() else { continue };
+        if let Some(rva) = public.offset.to_rva(&address_map) {
+            builder.record_raw_symbol(u64::from(rva.0), public.name.to_string().into_owned());
+        }
+    }
+
+    for section in pdb.sections()?.unwrap_or_default() {
+        let start = u64::from(section.virtual_address);
+        let end = start + u64::from(section.virtual_size);
+        if start != 0 && end != start {
+            builder.record_section(start..end, section.name().to_string());
+        }
+    }
+
+    Ok(builder)
+}
+
+/// Turns CodeView type records into types in a `DebugDbBuilder`.
+struct Converter<'a, 't> {
+    /// All type records, by index.
+    records: &'a BTreeMap<TypeIndex, TypeData<'t>>,
+    /// Used to decode primitive type indices.
+    finder: ::pdb::TypeFinder<'t>,
+    /// Sizes of the classes and unions that are defined, by unique name if
+    /// they have one, or else by name.
+    sizes_by_name: HashMap<String, u64>,
+    /// Primitive types recorded so far.
+    primitives: BTreeSet<TypeIndex>,
+    /// Next offset to use in the `.debug_types` space for types with no
+    /// record of their own.
+    next_synthetic: usize,
+    builder: DebugDbBuilder,
+}
+
+impl<'a, 't> Converter<'a, 't> {
+    /// Records the type described by the record `data`, at `index`.
+    fn record(&mut self, index: TypeIndex, data: &TypeData<'t>) {
+        let offset = offset(index);
+        match data {
+            TypeData::Class(c) => {
+                let name = type_name(c.name, ANONYMOUS_STRUCT);
+                if c.properties.forward_reference() {
+                    self.builder.record_decl(&name, TypeId(offset));
+                    return;
+                }
+                let members = match c.fields {
+                    Some(fields) => self.members(fields),
+                    None => vec![],
+                };
+                self.builder.record_type(Struct {
+                    name: name.into(),
+                    byte_size: Some(c.size),
+                    alignment: None,
+                    template_type_parameters: vec![],
+                    tuple_like: false,
+                    members,
+                    offset,
+                    decl_coord: DeclCoord::default(),
+                });
+            }
+            TypeData::Union(u) => {
+                let name = type_name(u.name, ANONYMOUS_UNION);
+                if u.properties.forward_reference() {
+                    self.builder.record_decl(&name, TypeId(offset));
+                    return;
+                }
+                let members = self.members(u.fields);
+                self.builder.record_type(Union {
+                    name: name.into(),
+                    byte_size: u.size,
+                    alignment: None,
+                    template_type_parameters: vec![],
+                    members,
+                    offset,
+                });
+            }
+            TypeData::Enumeration(e) => {
+                let name = type_name(e.name, ANONYMOUS_ENUM);
+                if e.properties.forward_reference() {
+                    self.builder.record_decl(&name, TypeId(offset));
+                    return;
+                }
+                let Some(byte_size) = self.size_of(e.underlying_type) else {
+                    warn!(self.builder, "enum {name} has an underlying type of unknown size");
+                    return;
+                };
+                let enumerators = self.field_list(e.fields)
+                    .into_iter()
+                    .filter_map(|f| match f {
+                        TypeData::Enumerate(v) => {
+                            let const_value = variant_value(v.value);
+                            Some((const_value, Enumerator {
+                                name: (&*v.name.to_string()).into(),
+                                const_value,
+                                offset: self::offset(e.fields),
+                            }))
+                        }
+                        _ => None,
+                    })
+                    .collect::<IndexMap<_, _>>();
+                self.builder.record_type(CEnum {
+                    name: name.into(),
+                    enum_class: false,
+                    byte_size,
+                    alignment: None,
+                    enumerators,
+                    offset,
+                });
+            }
+            TypeData::Pointer(p) => {
+                let attrs = p.attributes;
+                let qualifiers = [
+                    (attrs.is_const(), Qualifier::Const),
+                    (attrs.is_volatile(), Qualifier::Volatile),
+                    (attrs.is_restrict(), Qualifier::Restrict),
+                ].into_iter()
+                    .filter_map(|(present, q)| present.then_some(q))
+                    .collect::<Vec<_>>();
+                // Qualifiers on the pointer itself wrap it, so the pointer
+                // needs an ID of its own.
+                let pointer_offset = if qualifiers.is_empty() {
+                    offset
+                } else {
+                    self.synthetic()
+                };
+                let type_id = self.type_id(p.underlying_type);
+                self.builder.record_type(Pointer {
+                    type_id,
+                    name: None,
+                    byte_size: Some(u64::from(attrs.size())).filter(|&s| s != 0),
+                    address_class: None,
+                    offset: pointer_offset,
+                });
+                self.record_qualifiers(offset, &qualifiers, TypeId(pointer_offset));
+            }
+            TypeData::Modifier(m) => {
+                // Modifiers that are neither of these (`__unaligned`) are
+                // seen through by `type_id`.
+                let qualifiers = [
+                    (m.constant, Qualifier::Const),
+                    (m.volatile, Qualifier::Volatile),
+                ].into_iter()
+                    .filter_map(|(present, q)| present.then_some(q))
+                    .collect::<Vec<_>>();
+                let inner = self.type_id(m.underlying_type);
+                self.record_qualifiers(offset, &qualifiers, inner);
+            }
+            TypeData::Array(a) => {
+                if a.dimensions.is_empty() {
+                    warn!(self.builder, "array {index:?} has no dimensions");
+                    return;
+                }
+                let mut element_type_id = self.type_id(a.element_type);
+                let index_type_id = self.type_id(a.indexing_type);
+                // Dimensions are the sizes in bytes of each level of the
+                // array, innermost first; the record itself is the outermost.
+                let mut element_size = self.size_of(a.element_type);
+                for (i, &dim) in a.dimensions.iter().enumerate() {
+                    let dim = u64::from(dim);
+                    let this = if i + 1 == a.dimensions.len() {
+                        offset
+                    } else {
+                        self.synthetic()
+                    };
+                    self.builder.record_type(Array {
+                        element_type_id,
+                        index_type_id,
+                        lower_bound: 0,
+                        count: element_size.filter(|&s| s != 0).map(|s| dim / s),
+                        offset: this,
+                    });
+                    element_type_id = TypeId(this);
+                    element_size = Some(dim);
+                }
+            }
+            TypeData::Procedure(p) => {
+                let return_type_id = p.return_type.and_then(|t| self.return_type(t));
+                self.record_subroutine(offset, return_type_id, None, p.argument_list);
+            }
+            TypeData::MemberFunction(f) => {
+                let return_type_id = self.return_type(f.return_type);
+                self.record_subroutine(
+                    offset,
+                    return_type_id,
+                    f.this_pointer_type,
+                    f.argument_list,
+                );
+            }
+            // Members, field lists, argument lists and bitfields are only
+            // ever parts of other types, and are converted with them. The
+            // rest describe C++ methods, which the model doesn't include.
+            _ => (),
+        }
+    }
+
+    /// Gets the ID of the type at `index`, seeing through records that don't
+    /// become types of their own, and recording primitive types as they're
+    /// needed.
+    fn type_id(&mut self, mut index: TypeIndex) -> TypeId {
+        // Records only refer to earlier ones, so this can't loop, but bound
+        // it in case of a malformed file.
+        for _ in 0..8 {
+            match self.records.get(&index) {
+                Some(TypeData::Bitfield(b)) => index = b.underlying_type,
+                Some(TypeData::Modifier(m)) if !m.constant && !m.volatile => {
+                    index = m.underlying_type;
+                }
+                _ => break,
+            }
+        }
+        if index.0 < FIRST_RECORD && self.primitives.insert(index) {
+            self.record_primitive(index);
+        }
+        TypeId(offset(index))
+    }
+
+    /// Records the primitive type with index `index`.
+    fn record_primitive(&mut self, index: TypeIndex) {
+        let offset = offset(index);
+        let Some(p) = self.primitive(index) else {
+            warn!(self.builder, "unknown primitive type {:#x}", index.0);
+            return;
+        };
+        match p.indirection {
+            Some(indirection) => {
+                let type_id = self.type_id(TypeIndex(index.0 & 0xff));
+                self.builder.record_type(Pointer {
+                    type_id,
+                    name: None,
+                    byte_size: Some(indirection_size(indirection)),
+                    address_class: None,
+                    offset,
+                });
+            }
+            None => {
+                let Some((name, encoding, byte_size)) = primitive_info(p.kind) else {
+                    warn!(self.builder, "unsupported primitive type {:?}", p.kind);
+                    return;
+                };
+                self.builder.record_type(Base {
+                    name: name.into(),
+                    encoding,
+                    byte_size,
+                    alignment: None,
+                    offset,
+                });
+            }
+        }
+    }
+
+    /// Decodes the primitive type index `index`.
+    fn primitive(&self, index: TypeIndex) -> Option<PrimitiveType> {
+        match self.finder.find(index).and_then(|t| t.parse()) {
+            Ok(TypeData::Primitive(p)) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Gets the ID of a return type, or `None` for `void`, which is how the
+    /// model represents functions that return nothing.
+    fn return_type(&mut self, index: TypeIndex) -> Option<TypeId> {
+        let nothing = index.0 < FIRST_RECORD && self.primitive(index).is_some_and(|p| {
+            p.indirection.is_none()
+                && matches!(p.kind, PrimitiveKind::Void | PrimitiveKind::NoType)
+        });
+        (!nothing).then(|| self.type_id(index))
+    }
+
+    /// Records a subroutine type at `offset`. Member functions pass the type
+    /// of `this`, which becomes the first parameter, as it does in DWARF.
+    fn record_subroutine(
+        &mut self,
+        offset: gimli::UnitSectionOffset,
+        return_type_id: Option<TypeId>,
+        this: Option<TypeIndex>,
+        argument_list: TypeIndex,
+    ) {
+        let arguments = match self.records.get(&argument_list) {
+            Some(TypeData::ArgumentList(list)) => &list.arguments[..],
+            _ => &[],
+        };
+        // A trailing argument with no type stands for `...`.
+        let variadic = arguments.last() == Some(&TypeIndex(0));
+        let arguments = &arguments[..arguments.len() - usize::from(variadic)];
+
+        let formal_parameters = this.into_iter()
+            .chain(arguments.iter().copied())
+            .map(|t| self.type_id(t))
+            .collect::<Vec<_>>();
+        self.builder.record_type(Subroutine {
+            return_type_id,
+            parameter_names: vec![None; formal_parameters.len()],
+            formal_parameters,
+            variadic,
+            prototyped: true,
+            calling_convention: None,
+            offset,
+        });
+    }
+
+    /// Collects the data members from the field list at `index`. Base
+    /// classes, methods, nested types and static members are left out, as
+    /// they are from DWARF.
+    fn members(&mut self, index: TypeIndex) -> Vec<Member> {
+        self.field_list(index)
+            .into_iter()
+            .filter_map(|f| match f {
+                TypeData::Member(m) => Some(Member {
+                    name: Some((&*m.name.to_string()).into()),
+                    artificial: false,
+                    type_id: self.type_id(m.field_type),
+                    alignment: None,
+                    location: m.offset,
+                    offset: offset(index),
+                    decl_coord: DeclCoord::default(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Gets the fields of the field list at `index`, including those in any
+    /// continuations of it.
+    fn field_list(&mut self, mut index: TypeIndex) -> Vec<&'a TypeData<'t>> {
+        let records = self.records;
+        let mut fields = vec![];
+        for _ in 0..records.len() {
+            let Some(TypeData::FieldList(list)) = records.get(&index) else {
+                warn!(self.builder, "field list {:#x} not found", index.0);
+                break;
+            };
+            fields.extend(&list.fields);
+            match list.continuation {
+                Some(next) => index = next,
+                None => break,
+            }
+        }
+        fields
+    }
+
+    /// Works out the size in bytes of the type at `index`, as far as the
+    /// records say.
+    fn size_of(&self, index: TypeIndex) -> Option<u64> {
+        if index.0 < FIRST_RECORD {
+            let p = self.primitive(index)?;
+            return match p.indirection {
+                Some(indirection) => Some(indirection_size(indirection)),
+                None => Some(primitive_info(p.kind)?.2),
+            };
+        }
+        match self.records.get(&index)? {
+            TypeData::Class(c) => {
+                self.defined_size(c.properties, c.name, c.unique_name, c.size)
+            }
+            TypeData::Union(u) => {
+                self.defined_size(u.properties, u.name, u.unique_name, u.size)
+            }
+            TypeData::Enumeration(e) => self.size_of(e.underlying_type),
+            TypeData::Pointer(p) => Some(u64::from(p.attributes.size())),
+            TypeData::Modifier(m) => self.size_of(m.underlying_type),
+            TypeData::Bitfield(b) => self.size_of(b.underlying_type),
+            TypeData::Array(a) => a.dimensions.last().map(|&d| u64::from(d)),
+            _ => None,
+        }
+    }
+
+    /// Gets the size of a class or union, which for a forward reference is
+    /// the size of its definition, if there is one.
+    fn defined_size(
+        &self,
+        properties: ::pdb::TypeProperties,
+        name: ::pdb::RawString<'_>,
+        unique_name: Option<::pdb::RawString<'_>>,
+        size: u64,
+    ) -> Option<u64> {
+        if properties.forward_reference() {
+            let name = unique_name.unwrap_or(name).to_string();
+            self.sizes_by_name.get(&*name).copied()
+        } else {
+            Some(size)
+        }
+    }
+
+    /// Records `qualifiers`, outermost first, as a chain of `Qualified`
+    /// types starting at `offset` and ending at `inner`.
+    fn record_qualifiers(
+        &mut self,
+        mut offset: gimli::UnitSectionOffset,
+        qualifiers: &[Qualifier],
+        inner: TypeId,
+    ) {
+        for (i, &qualifier) in qualifiers.iter().enumerate() {
+            let type_id = if i + 1 == qualifiers.len() {
+                inner
+            } else {
+                TypeId(self.synthetic())
+            };
+            self.builder.record_type(Qualified {
+                qualifier,
+                type_id,
+                offset,
+            });
+            offset = type_id.0;
+        }
+    }
+
+    /// Makes up an ID for a type with no record of its own.
+    fn synthetic(&mut self) -> gimli::UnitSectionOffset {
+        let offset = gimli::DebugTypesOffset(self.next_synthetic).into();
+        self.next_synthetic += 1;
+        offset
+    }
+}
+
+/// Gets the ID for the type record at `index`.
+fn offset(index: TypeIndex) -> gimli::UnitSectionOffset {
+    gimli::DebugInfoOffset(index.0 as usize).into()
+}
+
+/// Gets the name of a class, union or enum, using `anonymous` in place of the
+/// names MSVC makes up for unnamed ones, such as `<unnamed-tag>`.
+fn type_name(name: ::pdb::RawString, anonymous: &str) -> String {
+    let name = name.to_string();
+    if name.is_empty()
+        || name.starts_with("<unnamed-")
+        || name.starts_with("<anonymous-")
+        || name == "__unnamed"
+    {
+        anonymous.to_string()
+    } else {
+        name.into_owned()
+    }
+}
+
+/// Gets the value of an enumerator, sign-extending negative values to 64
+/// bits as DWARF enumerators are.
+fn variant_value(v: ::pdb::Variant) -> u64 {
+    use ::pdb::Variant;
+    match v {
+        Variant::U8(v) => u64::from(v),
+        Variant::U16(v) => u64::from(v),
+        Variant::U32(v) => u64::from(v),
+        Variant::U64(v) => v,
+        Variant::I8(v) => i64::from(v) as u64,
+        Variant::I16(v) => i64::from(v) as u64,
+        Variant::I32(v) => i64::from(v) as u64,
+        Variant::I64(v) => v as u64,
+    }
+}
+
+/// Gets the size in bytes of a pointer with the given indirection.
+fn indirection_size(indirection: Indirection) -> u64 {
+    match indirection {
+        Indirection::Near16 => 2,
+        Indirection::Far16 | Indirection::Huge16 | Indirection::Near32 => 4,
+        Indirection::Far32 => 6,
+        Indirection::Near64 => 8,
+        Indirection::Near128 => 16,
+    }
+}
+
+/// Describes a primitive type: its name as MSVC spells it, encoding, and
+/// size in bytes. Returns `None` for `NoType`, which isn't a type.
+fn primitive_info(kind: PrimitiveKind) -> Option<(&'static str, Encoding, u64)> {
+    use Encoding::*;
+    Some(match kind {
+        PrimitiveKind::Void => ("void", Unsigned, 0),
+        PrimitiveKind::Char => ("signed char", SignedChar, 1),
+        PrimitiveKind::UChar => ("unsigned char", UnsignedChar, 1),
+        PrimitiveKind::RChar => ("char", SignedChar, 1),
+        PrimitiveKind::WChar => ("wchar_t", UtfChar, 2),
+        PrimitiveKind::RChar16 => ("char16_t", UtfChar, 2),
+        PrimitiveKind::RChar32 => ("char32_t", UtfChar, 4),
+        PrimitiveKind::I8 => ("__int8", Signed, 1),
+        PrimitiveKind::U8 => ("unsigned __int8", Unsigned, 1),
+        PrimitiveKind::Short | PrimitiveKind::I16 => ("short", Signed, 2),
+        PrimitiveKind::UShort | PrimitiveKind::U16 => ("unsigned short", Unsigned, 2),
+        PrimitiveKind::Long => ("long", Signed, 4),
+        PrimitiveKind::ULong => ("unsigned long", Unsigned, 4),
+        PrimitiveKind::I32 => ("int", Signed, 4),
+        PrimitiveKind::U32 => ("unsigned int", Unsigned, 4),
+        PrimitiveKind::Quad | PrimitiveKind::I64 => ("__int64", Signed, 8),
+        PrimitiveKind::UQuad | PrimitiveKind::U64 => ("unsigned __int64", Unsigned, 8),
+        PrimitiveKind::Octa | PrimitiveKind::I128 => ("__int128", Signed, 16),
+        PrimitiveKind::UOcta | PrimitiveKind::U128 => ("unsigned __int128", Unsigned, 16),
+        PrimitiveKind::F16 => ("__half", Float, 2),
+        PrimitiveKind::F32 | PrimitiveKind::F32PP => ("float", Float, 4),
+        PrimitiveKind::F48 => ("__float48", Float, 6),
+        PrimitiveKind::F64 => ("double", Float, 8),
+        PrimitiveKind::F80 => ("long double", Float, 10),
+        PrimitiveKind::F128 => ("__float128", Float, 16),
+        PrimitiveKind::Complex32 => ("_Complex float", ComplexFloat, 8),
+        PrimitiveKind::Complex64 => ("_Complex double", ComplexFloat, 16),
+        PrimitiveKind::Complex80 => ("_Complex long double", ComplexFloat, 20),
+        PrimitiveKind::Complex128 => ("_Complex __float128", ComplexFloat, 32),
+        PrimitiveKind::Bool8 => ("bool", Boolean, 1),
+        PrimitiveKind::Bool16 => ("__bool16", Boolean, 2),
+        PrimitiveKind::Bool32 => ("__bool32", Boolean, 4),
+        PrimitiveKind::Bool64 => ("__bool64", Boolean, 8),
+        PrimitiveKind::HRESULT => ("HRESULT", Signed, 4),
+        _ => return None,
+    })
+}
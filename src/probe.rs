@@ -0,0 +1,99 @@
+//! Reading memory from a live target through a debug probe attached with
+//! probe-rs, without a GDB server in between.
+//!
+//! `ProbeTarget` implements `Machine`, like `GdbClient`, so values can be
+//! loaded from the target in the same way as from a memory image.
+
+use std::cell::RefCell;
+
+use probe_rs::architecture::arm::DapError;
+use probe_rs::architecture::riscv::communication_interface::{
+    AbstractCommandErrorKind, RiscvError,
+};
+use probe_rs::{MemoryInterface, Session, SessionConfig};
+
+use crate::load::Machine;
+
+/// Bytes read from the target in one request. Reads of a whole chunk fail if
+/// any part of it can't be accessed, so this also bounds how far reading
+/// falls back to single bytes to find where a hole starts.
+const CHUNK_SIZE: usize = 1024;
+
+pub type ProbeError = probe_rs::Error;
+
+/// A session with a debug probe, reading memory through one of the target's
+/// cores.
+///
+/// As with `GdbClient`, the session is kept in a `RefCell` so that memory can
+/// be read through `&self`.
+pub struct ProbeTarget {
+    session: RefCell<Session>,
+    core: usize,
+}
+
+impl ProbeTarget {
+    /// Attaches to `chip`, named as in probe-rs's target list (such as
+    /// `STM32F407VGTx`), through the first probe found, and reads memory
+    /// through its first core. The target isn't halted.
+    pub fn attach(chip: &str) -> Result<Self, ProbeError> {
+        let session = Session::auto_attach(chip, SessionConfig::default())?;
+        Ok(Self::from_session(session, 0))
+    }
+
+    /// Reads memory through core `core` of a session that's already open,
+    /// for callers that need to choose the probe or its settings.
+    pub fn from_session(session: Session, core: usize) -> Self {
+        Self {
+            session: RefCell::new(session),
+            core,
+        }
+    }
+}
+
+impl Machine for ProbeTarget {
+    type Error = ProbeError;
+
+    fn read_memory(&self, address: u64, dest: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut session = self.session.borrow_mut();
+        let mut core = session.core(self.core)?;
+        let mut done = 0;
+        for chunk in dest.chunks_mut(CHUNK_SIZE) {
+            let addr = address.wrapping_add(done as u64);
+            match core.read(addr, chunk) {
+                Ok(()) => done += chunk.len(),
+                // The chunk runs into memory that can't be accessed, which
+                // is a hole in the address space rather than a failure. Find
+                // out how much of it comes before the hole.
+                Err(e) if is_fault(&e) => {
+                    for byte in chunk.iter_mut() {
+                        let addr = address.wrapping_add(done as u64);
+                        match core.read_8(addr, std::slice::from_mut(byte)) {
+                            Ok(()) => done += 1,
+                            Err(e) if is_fault(&e) => break,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(done)
+    }
+}
+
+/// Checks whether an error means that the target refused the access, as
+/// opposed to the probe failing. How this shows up depends on the
+/// architecture, and it's usually wrapped in a few layers of other errors.
+fn is_fault(e: &ProbeError) -> bool {
+    std::iter::successors(Some(e as &(dyn std::error::Error + 'static)), |e| e.source())
+        .any(|e| {
+            matches!(e.downcast_ref(), Some(DapError::FaultResponse))
+                || matches!(
+                    e.downcast_ref(),
+                    Some(RiscvError::AbstractCommand(
+                        AbstractCommandErrorKind::Bus | AbstractCommandErrorKind::Exception
+                    ))
+                )
+        })
+}
@@ -0,0 +1,504 @@
+//! Aggregate statistics about the types in a program, for reporting tools.
+
+use crate::typename::{self, Token};
+use crate::{DebugDb, Dependency, InlinedSubroutine, ProgramId, Type, TypeId, VarId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+/// Number of types in some category, and the total of their sizes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Totals {
+    /// Number of types.
+    pub count: usize,
+    /// Sum of the sizes of the types, in bytes. Types without a size don't
+    /// contribute.
+    pub byte_size: u64,
+}
+
+impl Totals {
+    fn add(&mut self, byte_size: Option<u64>) {
+        self.count += 1;
+        self.byte_size += byte_size.unwrap_or(0);
+    }
+}
+
+/// Types defined in a single compilation unit; see `Stats::by_unit`.
+#[derive(Clone, Debug)]
+pub struct UnitStats {
+    /// Location of the unit in the debug info.
+    pub offset: gimli::UnitSectionOffset,
+    /// Name of the unit, if it has one.
+    pub name: Option<String>,
+    pub totals: Totals,
+}
+
+/// Summary of the types in a `DebugDb`, as produced by `DebugDb::stats`.
+///
+/// Apart from `type_count`, only canonical types are counted, so a type that
+/// was defined identically in several compilation units counts once, in the
+/// unit where its canonical instance was found.
+#[derive(Clone, Debug)]
+pub struct Stats {
+    /// Number of types in the debug info, including duplicates.
+    pub type_count: usize,
+    /// Number of distinct (canonical) types.
+    pub canonical_type_count: usize,
+    /// Totals for each kind of type, keyed by `Type::kind_name`.
+    pub by_kind: BTreeMap<&'static str, Totals>,
+    /// Totals for each compilation unit that defines any canonical types, in
+    /// order of location in the debug info.
+    pub by_unit: Vec<UnitStats>,
+    /// The largest types and their sizes in bytes, largest first.
+    pub largest: Vec<(TypeId, u64)>,
+    /// The most deeply nested types and their nesting depths, deepest first.
+    ///
+    /// The nesting depth of a type is the number of levels of types stored
+    /// inline inside one another (see `Dependency::Contains`) below it: zero
+    /// for a type with no members, one for a struct of integers, and so on.
+    /// Typedefs and qualifiers don't add a level.
+    pub deepest: Vec<(TypeId, usize)>,
+}
+
+/// Which types `DebugDb::largest_types` ranks.
+#[derive(Clone, Debug)]
+pub enum TopScope {
+    /// Every distinct type in the debug info, including generic types that
+    /// were instantiated but never used.
+    All,
+    /// Types that some static variable has, or contains inline.
+    Statics,
+    /// Types reachable from the given types, by containing them inline or
+    /// pointing to them.
+    ReachableFrom(Vec<TypeId>),
+}
+
+/// The static variables in one section of the program; see
+/// `DebugDb::static_memory_map`.
+#[derive(Clone, Debug)]
+pub struct SectionStatics {
+    /// Name of the section, e.g. `.bss`, or `None` for variables whose
+    /// address isn't in any allocated section.
+    pub name: Option<String>,
+    /// Address range of the whole section, if it's known.
+    pub address_range: Option<Range<u64>>,
+    /// Sum of the sizes of the variables, in bytes. Variables whose type has
+    /// no size don't contribute.
+    pub byte_size: u64,
+    /// The variables in the section and their sizes, largest first.
+    pub variables: Vec<(VarId, Option<u64>)>,
+}
+
+/// How `DebugDb::code_size` groups machine code.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CodeGrouping {
+    /// By the compilation unit the subprogram is in.
+    Unit,
+    /// By the source file the subprogram was declared in.
+    File,
+    /// By crate: the first component of the subprogram's module path.
+    Crate,
+}
+
+/// The machine code in one group; see `DebugDb::code_size`.
+#[derive(Clone, Debug)]
+pub struct CodeSize {
+    /// Name of the group: the unit's name, the source file, or the crate.
+    /// This is `None` for code that couldn't be put in a named group.
+    pub name: Option<String>,
+    /// Total size of the code, in bytes.
+    pub byte_size: u64,
+    /// Number of subprograms with code in the group.
+    pub subprograms: usize,
+}
+
+/// The instantiations of one generic type; see `DebugDb::generic_instances`.
+#[derive(Clone, Debug)]
+pub struct GenericGroup {
+    /// Name of the generic type without its arguments, e.g.
+    /// `heapless::vec::Vec`.
+    pub base_name: String,
+    /// The distinct instantiations and their sizes in bytes, largest first.
+    pub instances: Vec<(TypeId, Option<u64>)>,
+}
+
+impl GenericGroup {
+    /// Sum of the sizes of the instantiations, in bytes.
+    pub fn byte_size(&self) -> u64 {
+        self.instances.iter().filter_map(|&(_, size)| size).sum()
+    }
+}
+
+/// Removes the generic arguments from a type name, giving the name shared by
+/// all instantiations of the type: `heapless::vec::Vec<u8, 16>` becomes
+/// `heapless::vec::Vec`, and `Foo<u8>::Bar<i32>` becomes `Foo::Bar`. Returns
+/// `None` for names without generic arguments, for qualified paths like
+/// `<T as Trait>::Output`, and for tuples and slices, which aren't instances of
+/// a generic type even if their elements are.
+pub fn generic_base_name(name: &str) -> Option<String> {
+    if name.starts_with(['<', '(', '[']) {
+        return None;
+    }
+    let mut base = Vec::new();
+    let mut generic = false;
+    let mut depth = 0usize;
+    // Tokenizing keeps the `>` of `->` from closing an argument list.
+    for t in typename::tokenize(name) {
+        match t {
+            Token::Punct("<") => {
+                generic = true;
+                depth += 1;
+            }
+            Token::Punct(">") => depth = depth.saturating_sub(1),
+            t if depth == 0 => base.push(t.as_str()),
+            _ => (),
+        }
+    }
+    generic.then(|| typename::canonicalize(&base.join(" ")))
+}
+
+/// Where one function was inlined; see `DebugDb::inlining`.
+#[derive(Clone, Debug)]
+pub struct Inlining {
+    /// The function that was inlined. This is its abstract instance, which
+    /// holds its name and declaration.
+    pub callee: ProgramId,
+    /// Number of places it was inlined.
+    pub count: usize,
+    /// Total size of the code of all the inlined copies, in bytes, including
+    /// anything inlined into them in turn.
+    pub byte_size: u64,
+    /// The functions it was inlined into directly, with the number of copies
+    /// in each, most first. Where a function was inlined into another inlined
+    /// function, the caller is the latter.
+    pub callers: Vec<(ProgramId, usize)>,
+}
+
+impl DebugDb {
+    /// Groups the distinct structs, unions and enums that are instantiations
+    /// of the same generic type (or C++ template), by `generic_base_name`.
+    /// The structs holding enum variants' fields are left out.
+    /// Groups with the most instantiations come first.
+    pub fn generic_instances(&self) -> Vec<GenericGroup> {
+        let variants = self.variant_payloads();
+        let mut groups: BTreeMap<String, Vec<(TypeId, Option<u64>)>> = BTreeMap::new();
+        for (id, ty) in self.canonical_types() {
+            if !matches!(ty, Type::Struct(_) | Type::Union(_) | Type::Enum(_))
+                || variants.contains(&id)
+            {
+                continue;
+            }
+            let Some(base) = self.type_name(id).and_then(|n| generic_base_name(&n)) else {
+                continue;
+            };
+            groups.entry(base).or_default().push((id, ty.byte_size(self)));
+        }
+        let mut groups = groups.into_iter()
+            .map(|(base_name, mut instances)| {
+                instances.sort_by_key(|&(id, size)| (std::cmp::Reverse(size), id));
+                GenericGroup { base_name, instances }
+            })
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|g| std::cmp::Reverse(g.instances.len()));
+        groups
+    }
+
+    /// Reports how each function was inlined, from the inlined subroutines
+    /// recorded in the subprograms that contain them. Functions come in order
+    /// of the total size of their inlined code, largest first; functions that
+    /// were never inlined are left out.
+    pub fn inlining(&self) -> Vec<Inlining> {
+        let mut callees: BTreeMap<ProgramId, (usize, u64, BTreeMap<ProgramId, usize>)> =
+            BTreeMap::new();
+        let mut work = vec![];
+        for (id, p) in self.subprograms() {
+            // Name the caller by its abstract instance too, so that all the
+            // concrete copies of a function count as one.
+            let caller = p.abstract_origin.unwrap_or(id);
+            work.extend(p.inlines.iter().map(|inl| (caller, inl)));
+        }
+        while let Some((caller, inl)) = work.pop() {
+            let Some(callee) = inl.abstract_origin else { continue };
+            let entry = callees.entry(callee).or_default();
+            entry.0 += 1;
+            entry.1 += inlined_size(inl);
+            *entry.2.entry(caller).or_default() += 1;
+            work.extend(inl.inlines.iter().map(|inner| (callee, inner)));
+        }
+
+        let mut inlining = callees.into_iter()
+            .map(|(callee, (count, byte_size, callers))| {
+                let mut callers = callers.into_iter().collect::<Vec<_>>();
+                callers.sort_by_key(|&(id, n)| (std::cmp::Reverse(n), id));
+                Inlining { callee, count, byte_size, callers }
+            })
+            .collect::<Vec<_>>();
+        inlining.sort_by_key(|i| (std::cmp::Reverse(i.byte_size), std::cmp::Reverse(i.count), i.callee));
+        inlining
+    }
+
+    /// Works out how much machine code each unit, source file or crate
+    /// contributes to the program, from the address ranges of subprograms
+    /// (but not those of their inlined callees, which are counted where
+    /// they're inlined). Groups come largest first.
+    ///
+    /// Code claimed by more than one subprogram is only counted for the
+    /// first, in debug info order.
+    pub fn code_size(&self, grouping: CodeGrouping) -> Vec<CodeSize> {
+        let mut groups: BTreeMap<Option<String>, CodeSize> = BTreeMap::new();
+        let mut claimed: BTreeMap<u64, u64> = BTreeMap::new();
+        for (id, p) in self.subprograms() {
+            let mut byte_size = 0;
+            for r in &p.pc_ranges {
+                // Inlined-only subprograms can have ranges at zero.
+                if r.start == 0 || r.start >= r.end {
+                    continue;
+                }
+                let overlaps = claimed.range(..r.end)
+                    .next_back()
+                    .is_some_and(|(_, &end)| end > r.start);
+                if !overlaps {
+                    claimed.insert(r.start, r.end);
+                    byte_size += r.end - r.start;
+                }
+            }
+            if byte_size == 0 {
+                continue;
+            }
+            let name = self.code_group(id, grouping);
+            let group = groups.entry(name.clone()).or_insert_with(|| CodeSize {
+                name,
+                byte_size: 0,
+                subprograms: 0,
+            });
+            group.byte_size += byte_size;
+            group.subprograms += 1;
+        }
+        let mut groups = groups.into_values().collect::<Vec<_>>();
+        groups.sort_by(|a, b| b.byte_size.cmp(&a.byte_size).then_with(|| a.name.cmp(&b.name)));
+        groups
+    }
+
+    /// Names the group that subprogram `id` belongs to for `code_size`.
+    /// Concrete instances of functions often leave their declaration and
+    /// namespace to their abstract origin, so that's consulted as well.
+    fn code_group(&self, id: ProgramId, grouping: CodeGrouping) -> Option<String> {
+        let origin = self.subprograms[&id].abstract_origin;
+        let mut ids = std::iter::once(id).chain(origin);
+        match grouping {
+            CodeGrouping::Unit => self.unit_containing(id)?.1.name.clone(),
+            CodeGrouping::File => ids
+                .filter_map(|id| self.subprogram_by_id(id))
+                .find_map(|p| p.decl_coord.file.clone()),
+            CodeGrouping::Crate => ids
+                .find_map(|id| self.subprogram_module(id))
+                .map(|m| m.split("::").next().unwrap_or(m).to_string()),
+        }
+    }
+
+    /// Groups the static variables by the section they're in, like a
+    /// type-aware `nm --size-sort`. Sections come in address order, followed
+    /// by variables outside any section; sections without any variables are
+    /// left out.
+    pub fn static_memory_map(&self) -> Vec<SectionStatics> {
+        let mut sections: BTreeMap<Option<u64>, SectionStatics> = BTreeMap::new();
+        for (id, v) in self.static_variables() {
+            let section = self.sections_by_address
+                .range(..=v.location)
+                .next_back()
+                .filter(|(_, (range, _))| range.contains(&v.location));
+            let size = self.type_by_id(v.type_id).and_then(|t| t.byte_size(self));
+            let entry = sections.entry(section.map(|(&start, _)| start))
+                .or_insert_with(|| SectionStatics {
+                    name: section.map(|(_, (_, name))| name.clone()),
+                    address_range: section.map(|(_, (range, _))| range.clone()),
+                    byte_size: 0,
+                    variables: vec![],
+                });
+            entry.byte_size += size.unwrap_or(0);
+            entry.variables.push((id, size));
+        }
+        // `None` sorts first, but variables outside any section are the least
+        // interesting, so they go last.
+        let mut map = sections.into_values().collect::<Vec<_>>();
+        if map.first().is_some_and(|s| s.name.is_none()) {
+            map.rotate_left(1);
+        }
+        for section in &mut map {
+            section.variables.sort_by_key(|&(id, size)| (std::cmp::Reverse(size), id));
+        }
+        map
+    }
+
+    /// Ranks the types in `scope` by size, returning the `n` largest with
+    /// their sizes in bytes, largest first. Types without a size are left out.
+    pub fn largest_types(&self, n: usize, scope: &TopScope) -> Vec<(TypeId, u64)> {
+        let reachable = match scope {
+            TopScope::All => None,
+            TopScope::Statics => Some(self.reachable_types(
+                self.static_variables().map(|(_, v)| v.type_id),
+                false,
+            )),
+            TopScope::ReachableFrom(roots) => {
+                Some(self.reachable_types(roots.iter().copied(), true))
+            }
+        };
+        let mut largest = self.canonical_types()
+            .filter(|(id, _)| reachable.as_ref().is_none_or(|r| r.contains(id)))
+            .filter_map(|(id, ty)| Some((id, ty.byte_size(self)?)))
+            .collect::<Vec<_>>();
+        largest.sort_by_key(|&(id, size)| (std::cmp::Reverse(size), id));
+        largest.truncate(n);
+        largest
+    }
+
+    /// Finds the canonical types reachable from `roots` by following
+    /// containment and aliases, and if `pointers` is true, pointers too.
+    fn reachable_types(
+        &self,
+        roots: impl Iterator<Item = TypeId>,
+        pointers: bool,
+    ) -> BTreeSet<TypeId> {
+        let canon = |id| self.type_canon.get(&id).copied().unwrap_or(id);
+        let mut seen = BTreeSet::new();
+        let mut work = roots.map(canon).collect::<Vec<_>>();
+        while let Some(id) = work.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(ty) = self.type_by_id(id) else { continue };
+            for (dep, next) in ty.dependencies() {
+                let follow = match dep {
+                    Dependency::Contains | Dependency::Aliases => true,
+                    Dependency::PointsTo => pointers,
+                    Dependency::Signature | Dependency::Parameter => false,
+                };
+                if follow {
+                    work.push(canon(next));
+                }
+            }
+        }
+        seen
+    }
+
+    /// Computes statistics about the types in the program. The `largest` and
+    /// `deepest` lists are limited to `top` entries each.
+    pub fn stats(&self, top: usize) -> Stats {
+        let mut by_kind: BTreeMap<_, Totals> = BTreeMap::new();
+        let mut by_unit: BTreeMap<_, Totals> = BTreeMap::new();
+        let mut largest = vec![];
+        let mut canonical_type_count = 0;
+        for (id, ty) in self.canonical_types() {
+            canonical_type_count += 1;
+            let size = ty.byte_size(self);
+            by_kind.entry(ty.kind_name()).or_default().add(size);
+            if let Some((&unit, _)) = self.units.range(..=id.0).next_back() {
+                by_unit.entry(unit).or_default().add(size);
+            }
+            if let Some(size) = size {
+                largest.push((id, size));
+            }
+        }
+        largest.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+        largest.truncate(top);
+
+        let mut depths = BTreeMap::new();
+        for (id, _) in self.canonical_types() {
+            self.nesting_depth(id, &mut depths);
+        }
+        let mut deepest = depths.into_iter()
+            .filter_map(|(id, depth)| Some((id, depth?)))
+            .filter(|(id, _)| !self.type_canon.contains_key(id))
+            .collect::<Vec<_>>();
+        deepest.sort_by_key(|&(_, depth)| std::cmp::Reverse(depth));
+        deepest.truncate(top);
+
+        Stats {
+            type_count: self.types.len(),
+            canonical_type_count,
+            by_kind,
+            by_unit: by_unit.into_iter()
+                .map(|(offset, totals)| UnitStats {
+                    offset,
+                    name: self.units[&offset].name.clone(),
+                    totals,
+                })
+                .collect(),
+            largest,
+            deepest,
+        }
+    }
+
+    /// Works out the nesting depth of `id` (see `Stats::deepest`), recording
+    /// it and those of the types inside it in `depths`.
+    ///
+    /// Types are recorded as `None` while they're being worked on, so that a
+    /// type that contains itself -- which is impossible, but might be claimed
+    /// by broken debug info -- doesn't send this into a loop.
+    fn nesting_depth(
+        &self,
+        id: TypeId,
+        depths: &mut BTreeMap<TypeId, Option<usize>>,
+    ) -> usize {
+        if let Some(&depth) = depths.get(&id) {
+            return depth.unwrap_or(0);
+        }
+        depths.insert(id, None);
+        let depth = self.type_by_id(id)
+            .map(|ty| ty.dependencies())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(dep, next)| {
+                let levels = match dep {
+                    Dependency::Contains => 1,
+                    Dependency::Aliases => 0,
+                    _ => return None,
+                };
+                Some(self.nesting_depth(next, depths) + levels)
+            })
+            .max()
+            .unwrap_or(0);
+        depths.insert(id, Some(depth));
+        depth
+    }
+}
+
+/// Adds up the code in an inlined subroutine's address ranges.
+fn inlined_size(inl: &InlinedSubroutine) -> u64 {
+    inl.pc_ranges.iter()
+        .filter(|r| r.start != 0)
+        .map(|r| r.end.saturating_sub(r.start))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generic_base_name;
+
+    #[test]
+    fn generic_base_names() {
+        let base = |name| generic_base_name(name);
+        assert_eq!(base("heapless::vec::Vec<u8, 16>").as_deref(), Some("heapless::vec::Vec"));
+        assert_eq!(base("Foo<u8>::Bar<i32>").as_deref(), Some("Foo::Bar"));
+        assert_eq!(base("Option<Vec<u8>>").as_deref(), Some("Option"));
+        assert_eq!(base("u8"), None);
+        assert_eq!(base("<T as Trait>::Output"), None);
+    }
+
+    #[test]
+    fn tuples_and_slices_have_no_base_name() {
+        assert_eq!(generic_base_name("(u8, Vec<u8>)"), None);
+        assert_eq!(generic_base_name("(Vec<u8>, Vec<u16>)"), None);
+        assert_eq!(generic_base_name("[Vec<u8>]"), None);
+    }
+
+    #[test]
+    fn arrows_dont_close_arguments() {
+        let base = |name| generic_base_name(name);
+        assert_eq!(base("Box<dyn Fn(u8) -> u8>").as_deref(), Some("Box"));
+        assert_eq!(base("Foo<fn() -> u8>::Bar<u8>").as_deref(), Some("Foo::Bar"));
+        assert_eq!(
+            base("core::pin::Pin<Box<dyn Fn() -> Option<u8>>>").as_deref(),
+            Some("core::pin::Pin"),
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Support for turning `Value`s back into bytes, laid out the way the program
+//! being analyzed expects them.
+//!
+//! This is the inverse of `load`: given a value and the type it's supposed to
+//! have, produce an image of that value that could be written into the
+//! program's memory.
+
+use crate::value::{self, Value};
+use crate::{DebugDb, Type, TypeId, VariantShape};
+use gimli::Endianity;
+use thiserror::Error;
+use std::convert::TryFrom;
+
+#[derive(Clone, Debug, Error)]
+pub enum StoreError {
+    #[error("value of type {value} can't be stored as a {ty}")]
+    WrongKind { value: String, ty: &'static str },
+    #[error("expected type with size {expected}, but value had size {got}")]
+    WrongSize { expected: u64, got: u64 },
+    #[error("type has no defined size")]
+    Unsized,
+    #[error("type too big for this platform: {0} bytes")]
+    TypeTooBig(u64),
+    #[error("expected struct/tuple with {expected} members, found {got}")]
+    WrongMemberCount { expected: usize, got: usize },
+    #[error("expected member `{expected}`, found `{got}`")]
+    WrongMemberName { expected: String, got: String },
+    #[error("expected array with {expected} elements, found {got}")]
+    WrongElementCount { expected: u64, got: usize },
+    #[error("array type is not finite and can't be stored")]
+    InfiniteArray,
+    #[error("variant `{0}` not found in type")]
+    UnknownVariant(String),
+    #[error("member `{0}` not found in type")]
+    UnknownMember(String),
+    #[error("member at offset {offset} with size {size} doesn't fit in {total} bytes")]
+    OutOfBounds { offset: u64, size: u64, total: u64 },
+    #[error("{size}-byte {ty} can't be stored; only 1, 2, 4 and 8 bytes are supported")]
+    UnsupportedSize { ty: &'static str, size: usize },
+    #[error("type {0:?} not found in the debug info")]
+    MissingType(TypeId),
+}
+
+/// Looks up type `id`, which the debug info refers to but may not define.
+fn type_by_id(world: &DebugDb, id: TypeId) -> Result<&Type, StoreError> {
+    world.type_by_id(id).ok_or(StoreError::MissingType(id))
+}
+
+/// Produces the byte representation of `value` as a value of type `ty`.
+///
+/// Padding bytes, and any other bytes not covered by the value, are zero.
+pub fn store(
+    value: &Value,
+    world: &DebugDb,
+    ty: &Type,
+) -> Result<Vec<u8>, StoreError> {
+    let size = ty.byte_size(world).ok_or(StoreError::Unsized)?;
+    let size = usize::try_from(size).map_err(|_| StoreError::TypeTooBig(size))?;
+    let mut buffer = vec![0; size];
+    store_into(value, world, ty, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Writes the byte representation of `value`, as a value of type `ty`, into
+/// the start of `dest`.
+///
+/// Bytes in `dest` that are not part of the value's representation (such as
+/// padding) are left unchanged.
+pub fn store_into(
+    value: &Value,
+    world: &DebugDb,
+    ty: &Type,
+    dest: &mut [u8],
+) -> Result<(), StoreError> {
+    let wrong_kind = |ty| StoreError::WrongKind {
+        value: value.type_name().into_owned(),
+        ty,
+    };
+
+    match (value, ty) {
+        (_, Type::Typedef(_) | Type::Qualified(_)) => {
+            let target = type_by_id(world, world.resolve_alias(ty.id()))?;
+            store_into(value, world, target, dest)
+        }
+
+        (Value::Base(b), Type::Base(t)) => {
+            let size = usize::try_from(t.byte_size)
+                .map_err(|_| StoreError::TypeTooBig(t.byte_size))?;
+            let bytes = base_bytes(*b, world.endian());
+            if bytes.len() != size {
+                return Err(StoreError::WrongSize {
+                    expected: t.byte_size,
+                    got: bytes.len() as u64,
+                });
+            }
+            subslice(dest, 0, t.byte_size)?.copy_from_slice(&bytes);
+            Ok(())
+        }
+        (Value::Base(_), _) => Err(wrong_kind("base type")),
+
+        (Value::Struct(s), Type::Struct(_)) => store_struct(s, world, ty, dest),
+        (Value::Struct(_), _) => Err(wrong_kind("struct")),
+
+        (Value::Union(s), Type::Union(t)) => {
+            // Each interpretation is written in turn, so if they disagree, the
+            // last one wins. Ones produced by loading a union all agree.
+            for (name, v) in &s.members {
+                let m = t.members.iter()
+                    .find(|m| m.name.as_deref() == name.as_deref())
+                    .ok_or_else(|| {
+                        StoreError::UnknownMember(name.clone().unwrap_or_default())
+                    })?;
+                let mty = type_by_id(world, m.type_id)?;
+                let msize = mty.byte_size(world).ok_or(StoreError::Unsized)?;
+                let dest = subslice(dest, m.location, msize)?;
+                store_into(v, world, mty, dest)?;
+            }
+            Ok(())
+        }
+        (Value::Union(_), _) => Err(wrong_kind("union")),
+
+        (Value::Array(elts), Type::Array(t)) => {
+            let count = t.count.ok_or(StoreError::InfiniteArray)?;
+            if elts.len() as u64 != count {
+                return Err(StoreError::WrongElementCount {
+                    expected: count,
+                    got: elts.len(),
+                });
+            }
+            let elty = type_by_id(world, t.element_type_id)?;
+            let elt_size = elty.byte_size(world).ok_or(StoreError::Unsized)?;
+            // This matches the element stride used when loading.
+            let stride = elt_size.max(elty.alignment(world).unwrap_or(0));
+            for (i, v) in (0..).zip(elts) {
+                let dest = subslice(dest, i * stride, elt_size)?;
+                store_into(v, world, elty, dest)?;
+            }
+            Ok(())
+        }
+        (Value::Array(_), _) => Err(wrong_kind("array")),
+
+        (Value::CEnum(e), Type::CEnum(t)) => {
+            let disc = t.enumerators.values()
+                .find(|en| en.name == e.disc)
+                .ok_or_else(|| StoreError::UnknownVariant(e.disc.clone()))?;
+            let dest = subslice(dest, 0, t.byte_size)?;
+            store_unsigned(world.endian(), disc.const_value, dest, "C-like enum")
+        }
+        (Value::CEnum(_), _) => Err(wrong_kind("C-like enum")),
+
+        (Value::Enum(e), Type::Enum(t)) => {
+            let (disc, variant) = match &t.shape {
+                VariantShape::Zero => {
+                    return Err(StoreError::UnknownVariant(e.disc.clone()));
+                }
+                VariantShape::One(v) => {
+                    if v.member.name.as_deref() != Some(&e.disc) {
+                        return Err(StoreError::UnknownVariant(e.disc.clone()));
+                    }
+                    (None, v)
+                }
+                VariantShape::Many { member, variants, .. } => {
+                    let (d, v) = variants.iter()
+                        .find(|(_, v)| v.member.name.as_deref() == Some(&e.disc))
+                        .ok_or_else(|| StoreError::UnknownVariant(e.disc.clone()))?;
+                    // The default variant (`None` key) is used for niche
+                    // layouts, where the discriminator is encoded in the body
+                    // and gets written along with it.
+                    (d.map(|d| (member, d)), v)
+                }
+            };
+            // Write the body first, so that an explicit discriminator wins if
+            // the two overlap.
+            let vty = type_by_id(world, variant.member.type_id)?;
+            let vsize = vty.byte_size(world).ok_or(StoreError::Unsized)?;
+            let vdest = subslice(dest, variant.member.location, vsize)?;
+            store_struct(&e.value, world, vty, vdest)?;
+
+            if let Some((member, d)) = disc {
+                let dty = type_by_id(world, member.type_id)?;
+                let dsize = dty.byte_size(world).ok_or(StoreError::Unsized)?;
+                let ddest = subslice(dest, member.location, dsize)?;
+                store_unsigned(world.endian(), d, ddest, "enum discriminator")?;
+            }
+            Ok(())
+        }
+        (Value::Enum(_), _) => Err(wrong_kind("enum")),
+
+        (Value::Pointer(p), Type::Pointer(t)) => {
+            let size = world.pointer_byte_size(t);
+            let dest = subslice(dest, 0, size)?;
+            store_unsigned(world.endian(), p.value, dest, "pointer")
+        }
+        (Value::Pointer(_), _) => Err(wrong_kind("pointer")),
+    }
+}
+
+fn store_struct(
+    s: &value::Struct,
+    world: &DebugDb,
+    ty: &Type,
+    dest: &mut [u8],
+) -> Result<(), StoreError> {
+    let Type::Struct(t) = ty else {
+        return Err(StoreError::WrongKind {
+            value: s.name.clone(),
+            ty: "struct",
+        });
+    };
+    if s.members.len() != t.members.len() {
+        return Err(StoreError::WrongMemberCount {
+            expected: t.members.len(),
+            got: s.members.len(),
+        });
+    }
+    for ((name, v), m) in s.members.iter().zip(&t.members) {
+        if name.as_deref() != m.name.as_deref() {
+            return Err(StoreError::WrongMemberName {
+                expected: m.name.as_deref().unwrap_or_default().to_string(),
+                got: name.clone().unwrap_or_default(),
+            });
+        }
+        let mty = type_by_id(world, m.type_id)?;
+        let msize = mty.byte_size(world).ok_or(StoreError::Unsized)?;
+        let dest = subslice(dest, m.location, msize)?;
+        store_into(v, world, mty, dest)?;
+    }
+    Ok(())
+}
+
+/// Returns the subslice of `buffer` at `offset` of length `size`, or an error
+/// if it's not entirely in bounds.
+fn subslice(
+    buffer: &mut [u8],
+    offset: u64,
+    size: u64,
+) -> Result<&mut [u8], StoreError> {
+    let oob = || StoreError::OutOfBounds {
+        offset,
+        size,
+        total: buffer.len() as u64,
+    };
+    let start = usize::try_from(offset).map_err(|_| oob())?;
+    let end = offset.checked_add(size)
+        .and_then(|end| usize::try_from(end).ok())
+        .ok_or_else(oob)?;
+    if end > buffer.len() {
+        return Err(oob());
+    }
+    Ok(&mut buffer[start..end])
+}
+
+/// Writes `value` as an unsigned integer filling `dest`, which holds a `ty`
+/// (for error messages).
+fn store_unsigned(
+    endian: gimli::RunTimeEndian,
+    value: u64,
+    dest: &mut [u8],
+    ty: &'static str,
+) -> Result<(), StoreError> {
+    match dest.len() {
+        1 => dest[0] = value as u8,
+        2 => endian.write_u16(dest, value as u16),
+        4 => endian.write_u32(dest, value as u32),
+        8 => endian.write_u64(dest, value),
+        size => return Err(StoreError::UnsupportedSize { ty, size }),
+    }
+    Ok(())
+}
+
+fn base_bytes(b: value::Base, endian: gimli::RunTimeEndian) -> Vec<u8> {
+    macro_rules! bytes {
+        ($x:expr) => {
+            if endian.is_big_endian() {
+                $x.to_be_bytes().to_vec()
+            } else {
+                $x.to_le_bytes().to_vec()
+            }
+        };
+    }
+    match b {
+        value::Base::Unit => vec![],
+        value::Base::U8(x) => vec![x],
+        value::Base::U16(x) => bytes!(x),
+        value::Base::U32(x) => bytes!(x),
+        value::Base::U64(x) => bytes!(x),
+        value::Base::U128(x) => bytes!(x),
+        value::Base::I8(x) => bytes!(x),
+        value::Base::I16(x) => bytes!(x),
+        value::Base::I32(x) => bytes!(x),
+        value::Base::I64(x) => bytes!(x),
+        value::Base::I128(x) => bytes!(x),
+        value::Base::F32(x) => bytes!(x),
+        value::Base::F64(x) => bytes!(x),
+        value::Base::Bool(x) => vec![x],
+        value::Base::Char(x) => bytes!(x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestTypes;
+    use crate::{CEnum, Enumerator, Pointer};
+
+    /// Records `struct S { a: u8, b: u16 }`, with a byte of padding after `a`.
+    fn padded_struct(types: &mut TestTypes) -> TypeId {
+        let u8_id = types.base("u8", 1);
+        let u16_id = types.base("u16", 2);
+        types.structure("S", 4, &[("a", u8_id, 0), ("b", u16_id, 2)])
+    }
+
+    fn struct_value(members: &[(&str, Value)]) -> Value {
+        Value::Struct(value::Struct {
+            name: "S".to_string(),
+            members: members.iter()
+                .map(|(name, v)| (Some(name.to_string()), v.clone()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn struct_with_padding() {
+        let mut types = TestTypes::new(gimli::RunTimeEndian::Little, false);
+        let id = padded_struct(&mut types);
+        let world = types.build();
+        let ty = world.type_by_id(id).unwrap();
+
+        let v = struct_value(&[
+            ("a", Value::Base(value::Base::U8(0xAB))),
+            ("b", Value::Base(value::Base::U16(0x1234))),
+        ]);
+        let bytes = store(&v, &world, ty).unwrap();
+        assert_eq!(bytes, [0xAB, 0, 0x34, 0x12]);
+
+        let loaded = Value::from_bytes(&bytes, &world, ty).unwrap();
+        assert_eq!(store(&loaded, &world, ty).unwrap(), bytes);
+    }
+
+    #[test]
+    fn big_endian() {
+        let mut types = TestTypes::new(gimli::RunTimeEndian::Big, false);
+        let id = padded_struct(&mut types);
+        let world = types.build();
+        let ty = world.type_by_id(id).unwrap();
+
+        let v = struct_value(&[
+            ("a", Value::Base(value::Base::U8(1))),
+            ("b", Value::Base(value::Base::U16(0x1234))),
+        ]);
+        assert_eq!(store(&v, &world, ty).unwrap(), [1, 0, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn wrong_members() {
+        let mut types = TestTypes::new(gimli::RunTimeEndian::Little, false);
+        let id = padded_struct(&mut types);
+        let world = types.build();
+        let ty = world.type_by_id(id).unwrap();
+
+        let v = struct_value(&[("a", Value::Base(value::Base::U8(1)))]);
+        assert!(matches!(
+            store(&v, &world, ty),
+            Err(StoreError::WrongMemberCount { expected: 2, got: 1 })
+        ));
+
+        let v = struct_value(&[
+            ("a", Value::Base(value::Base::U8(1))),
+            ("c", Value::Base(value::Base::U16(2))),
+        ]);
+        assert!(matches!(
+            store(&v, &world, ty),
+            Err(StoreError::WrongMemberName { .. })
+        ));
+
+        let v = struct_value(&[
+            ("a", Value::Base(value::Base::U8(1))),
+            ("b", Value::Base(value::Base::U32(2))),
+        ]);
+        assert!(matches!(
+            store(&v, &world, ty),
+            Err(StoreError::WrongSize { expected: 2, got: 4 })
+        ));
+    }
+
+    #[test]
+    fn pointers() {
+        let mut types = TestTypes::new(gimli::RunTimeEndian::Little, true);
+        let u8_id = types.base("u8", 1);
+        // One sized by the program's address size, and a 16-bit one, as on
+        // AVR.
+        let [wide, narrow] = [None, Some(2)].map(|byte_size| {
+            types.record(|offset| Pointer {
+                type_id: u8_id,
+                name: Some("*const u8".into()),
+                byte_size,
+                address_class: None,
+                offset,
+            })
+        });
+        let world = types.build();
+
+        let v = Value::Pointer(value::Pointer {
+            name: "*const u8".to_string(),
+            dest_type_id: u8_id,
+            value: 0x1234,
+        });
+        let wide = world.type_by_id(wide).unwrap();
+        assert_eq!(store(&v, &world, wide).unwrap(), [0x34, 0x12, 0, 0, 0, 0, 0, 0]);
+        let narrow = world.type_by_id(narrow).unwrap();
+        assert_eq!(store(&v, &world, narrow).unwrap(), [0x34, 0x12]);
+    }
+
+    #[test]
+    fn c_enum() {
+        let mut types = TestTypes::little();
+        let [two, three] = [2, 3].map(|byte_size| {
+            let enumerators = [(0, "A"), (7, "B")].into_iter()
+                .map(|(value, name)| (value, Enumerator {
+                    name: name.into(),
+                    const_value: value,
+                    offset: types.offset(),
+                }))
+                .collect();
+            types.record(|offset| CEnum {
+                name: "E".into(),
+                enum_class: false,
+                byte_size,
+                alignment: None,
+                enumerators,
+                offset,
+            })
+        });
+        let world = types.build();
+
+        let v = Value::CEnum(value::CEnum { name: "E".to_string(), disc: "B".to_string() });
+        let ty = world.type_by_id(two).unwrap();
+        assert_eq!(store(&v, &world, ty).unwrap(), [7, 0]);
+
+        let v = Value::CEnum(value::CEnum { name: "E".to_string(), disc: "C".to_string() });
+        assert!(matches!(
+            store(&v, &world, ty),
+            Err(StoreError::UnknownVariant(d)) if d == "C"
+        ));
+
+        let v = Value::CEnum(value::CEnum { name: "E".to_string(), disc: "A".to_string() });
+        let ty = world.type_by_id(three).unwrap();
+        let err = store(&v, &world, ty).unwrap_err();
+        assert!(matches!(err, StoreError::UnsupportedSize { ty: "C-like enum", size: 3 }));
+        assert_eq!(
+            err.to_string(),
+            "3-byte C-like enum can't be stored; only 1, 2, 4 and 8 bytes are supported",
+        );
+    }
+}
@@ -0,0 +1,633 @@
+//! Support for CMSIS-SVD files, which describe the memory-mapped peripherals
+//! of a microcontroller: where each peripheral lives, and the registers and
+//! bit fields within it.
+//!
+//! Clusters and `dim` arrays are flattened, so each peripheral is just a list
+//! of registers, with names like `CH[2].CR` for registers inside them.
+//! Peripherals may be `derivedFrom` another; registers may not.
+
+use std::ops::Range;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error)]
+pub enum SvdError {
+    #[error("malformed XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("no <device> element")]
+    NoDevice,
+    #[error("{0} has no <{1}>")]
+    Missing(String, &'static str),
+    #[error("bad number `{0}`")]
+    BadNumber(String),
+    #[error("peripheral {0} is derived from unknown peripheral {1}")]
+    UnknownBase(String, String),
+}
+
+/// A microcontroller, as described by an SVD file.
+#[derive(Clone, Debug)]
+pub struct Device {
+    pub name: String,
+    pub peripherals: Vec<Peripheral>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Peripheral {
+    pub name: String,
+    /// Name shared by peripherals of the same kind, such as `GPIO` for
+    /// `GPIOA` and `GPIOB`.
+    pub group_name: Option<String>,
+    pub description: Option<String>,
+    pub base_address: u64,
+    /// Registers in order of offset.
+    pub registers: Vec<Register>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Register {
+    pub name: String,
+    pub description: Option<String>,
+    /// Offset from the peripheral's base address, in bytes.
+    pub address_offset: u64,
+    /// Size in bits.
+    pub size: u32,
+    /// Fields in order of bit offset.
+    pub fields: Vec<Field>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    pub bit_offset: u32,
+    pub bit_width: u32,
+}
+
+impl Device {
+    /// Reads the contents of an SVD file.
+    pub fn parse(text: &str) -> Result<Self, SvdError> {
+        let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+        let doc = roxmltree::Document::parse_with_options(text, options)?;
+        let root = doc.root_element();
+        let device = if root.tag_name().name() == "device" {
+            root
+        } else {
+            child(root, "device").ok_or(SvdError::NoDevice)?
+        };
+        let name = child_text(device, "name").unwrap_or_default().to_string();
+        let size = child_text(device, "size").map(parse_number).transpose()?;
+
+        let mut peripherals = vec![];
+        let mut bases = vec![];
+        for p in children_named(device, "peripherals").flat_map(|ps| children_named(ps, "peripheral")) {
+            let name = child_text(p, "name")
+                .ok_or_else(|| SvdError::Missing("peripheral".into(), "name"))?
+                .to_string();
+            let base_address = parse_number(child_text(p, "baseAddress")
+                .ok_or_else(|| SvdError::Missing(name.clone(), "baseAddress"))?)?;
+            let mut registers = vec![];
+            let size = child_text(p, "size").map(parse_number).transpose()?.or(size);
+            for rs in children_named(p, "registers") {
+                collect_registers(rs, "", 0, size, &mut registers)?;
+            }
+            registers.sort_by_key(|r| r.address_offset);
+            bases.push(p.attribute("derivedFrom").map(str::to_string));
+            peripherals.push(Peripheral {
+                group_name: child_text(p, "groupName").map(str::to_string),
+                description: child_text(p, "description").map(clean_text),
+                name,
+                base_address,
+                registers,
+            });
+        }
+
+        // Derived peripherals take anything they don't give themselves from
+        // their base.
+        for (i, base) in bases.into_iter().enumerate() {
+            let Some(base) = base else { continue };
+            let b = peripherals.iter().position(|p| p.name == base)
+                .ok_or_else(|| SvdError::UnknownBase(peripherals[i].name.clone(), base))?;
+            let base = peripherals[b].clone();
+            let p = &mut peripherals[i];
+            if p.registers.is_empty() {
+                p.registers = base.registers;
+            }
+            if p.group_name.is_none() {
+                p.group_name = base.group_name;
+            }
+            if p.description.is_none() {
+                p.description = base.description;
+            }
+        }
+
+        peripherals.sort_by_key(|p| p.base_address);
+        Ok(Device { name, peripherals })
+    }
+
+    /// Finds a peripheral by name, ignoring case.
+    pub fn peripheral(&self, name: &str) -> Option<&Peripheral> {
+        self.peripherals.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Finds the register occupying `address`, and its peripheral.
+    pub fn register_at(&self, address: u64) -> Option<(&Peripheral, &Register)> {
+        self.peripherals.iter()
+            .filter(|p| p.base_address <= address)
+            .find_map(|p| {
+                let r = p.registers.iter()
+                    .find(|r| r.byte_range().contains(&(address - p.base_address)))?;
+                Some((p, r))
+            })
+    }
+}
+
+impl Peripheral {
+    /// Returns the registers that start within `offsets`, measured from the
+    /// peripheral's base address.
+    pub fn registers_in(&self, offsets: Range<u64>) -> impl Iterator<Item = &Register> {
+        self.registers.iter().filter(move |r| offsets.contains(&r.address_offset))
+    }
+}
+
+impl Register {
+    /// Returns the offsets from the peripheral's base address that the
+    /// register occupies.
+    pub fn byte_range(&self) -> Range<u64> {
+        self.address_offset..self.address_offset + u64::from(self.size.div_ceil(8))
+    }
+}
+
+impl std::fmt::Display for Field {
+    /// Formats the field like `NAME[msb:lsb]`, or `NAME[bit]` for a single
+    /// bit.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.bit_width == 1 {
+            write!(f, "{}[{}]", self.name, self.bit_offset)
+        } else {
+            write!(f, "{}[{}:{}]", self.name,
+                self.bit_offset + self.bit_width.saturating_sub(1), self.bit_offset)
+        }
+    }
+}
+
+/// Adds the registers in `parent` (a `<registers>` or `<cluster>` element) to
+/// `out`, with `prefix` added to their names and `offset` to their offsets.
+/// `size` is the register size inherited from enclosing elements.
+fn collect_registers(
+    parent: Node<'_>,
+    prefix: &str,
+    offset: u64,
+    size: Option<u64>,
+    out: &mut Vec<Register>,
+) -> Result<(), SvdError> {
+    for e in parent.children().filter(Node::is_element) {
+        let kind = e.tag_name().name();
+        if kind != "register" && kind != "cluster" {
+            continue;
+        }
+        let name = child_text(e, "name")
+            .ok_or_else(|| SvdError::Missing(format!("{prefix}{kind}"), "name"))?;
+        let address_offset = offset + parse_number(child_text(e, "addressOffset")
+            .ok_or_else(|| SvdError::Missing(format!("{prefix}{name}"), "addressOffset"))?)?;
+        let size = child_text(e, "size").map(parse_number).transpose()?.or(size);
+
+        for (name, address_offset) in expand_dim(e, name, address_offset)? {
+            let name = format!("{prefix}{name}");
+            if kind == "cluster" {
+                collect_registers(e, &format!("{name}."), address_offset, size, out)?;
+                continue;
+            }
+            let mut fields = vec![];
+            for f in children_named(e, "fields").flat_map(|fs| children_named(fs, "field")) {
+                let (bit_offset, bit_width) = field_bits(f)?;
+                fields.push(Field {
+                    name: child_text(f, "name").unwrap_or("?").to_string(),
+                    description: child_text(f, "description").map(clean_text),
+                    bit_offset,
+                    bit_width,
+                });
+            }
+            fields.sort_by_key(|f| f.bit_offset);
+            out.push(Register {
+                name,
+                description: child_text(e, "description").map(clean_text),
+                address_offset,
+                size: size.unwrap_or(32) as u32,
+                fields,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Expands a register or cluster with a `<dim>` into one name and offset for
+/// each element. Array-style names like `CH[%s]` are numbered from zero;
+/// others like `CH%s` use `<dimIndex>`.
+fn expand_dim(
+    e: Node<'_>,
+    name: &str,
+    offset: u64,
+) -> Result<Vec<(String, u64)>, SvdError> {
+    let Some(dim) = child_text(e, "dim") else {
+        return Ok(vec![(name.to_string(), offset)]);
+    };
+    let dim = parse_number(dim)?;
+    let increment = parse_number(child_text(e, "dimIncrement")
+        .ok_or_else(|| SvdError::Missing(name.to_string(), "dimIncrement"))?)?;
+    let indices: Vec<String> = match child_text(e, "dimIndex") {
+        Some(index) if !name.contains("[%s]") => {
+            match index.split_once('-') {
+                Some((a, b)) if !index.contains(',') => {
+                    match (a.trim().parse::<u64>(), b.trim().parse::<u64>()) {
+                        (Ok(a), Ok(b)) => (a..=b).map(|i| i.to_string()).collect(),
+                        // Letter ranges like A-D.
+                        _ => (a.trim().bytes().next().unwrap_or(b'A')..=b.trim().bytes().next().unwrap_or(b'A'))
+                            .map(|c| (c as char).to_string())
+                            .collect(),
+                    }
+                }
+                _ => index.split(',').map(|s| s.trim().to_string()).collect(),
+            }
+        }
+        _ => (0..dim).map(|i| i.to_string()).collect(),
+    };
+    Ok(indices.into_iter()
+        .take(dim as usize)
+        .enumerate()
+        .map(|(i, index)| (name.replace("%s", &index), offset + i as u64 * increment))
+        .collect())
+}
+
+/// Finds the bit offset and width of a `<field>`, which may be given in any of
+/// three ways.
+fn field_bits(f: Node<'_>) -> Result<(u32, u32), SvdError> {
+    if let Some(offset) = child_text(f, "bitOffset") {
+        let width = child_text(f, "bitWidth").map(parse_number).transpose()?.unwrap_or(1);
+        return Ok((parse_number(offset)? as u32, width as u32));
+    }
+    if let (Some(lsb), Some(msb)) = (child_text(f, "lsb"), child_text(f, "msb")) {
+        let (lsb, msb) = (parse_number(lsb)?, parse_number(msb)?);
+        return Ok((lsb as u32, (msb.saturating_sub(lsb) + 1) as u32));
+    }
+    if let Some(range) = child_text(f, "bitRange") {
+        let (msb, lsb) = range.trim_matches(['[', ']'])
+            .split_once(':')
+            .ok_or_else(|| SvdError::BadNumber(range.to_string()))?;
+        let (lsb, msb) = (parse_number(lsb)?, parse_number(msb)?);
+        return Ok((lsb as u32, (msb.saturating_sub(lsb) + 1) as u32));
+    }
+    let name = child_text(f, "name").unwrap_or("field").to_string();
+    Err(SvdError::Missing(name, "bitOffset"))
+}
+
+/// Parses an SVD number: decimal, hex with `0x`, or binary with `#`.
+fn parse_number(s: &str) -> Result<u64, SvdError> {
+    let t = s.trim();
+    let result = if let Some(hex) = t.strip_prefix("0x").or_else(|| t.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = t.strip_prefix('#') {
+        u64::from_str_radix(bin, 2)
+    } else {
+        t.parse()
+    };
+    result.map_err(|_| SvdError::BadNumber(s.to_string()))
+}
+
+/// Collapses the line breaks and indentation that descriptions tend to have.
+fn clean_text(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+type Node<'a> = roxmltree::Node<'a, 'a>;
+
+/// Returns an iterator over the child elements of `e` called `name`, ignoring
+/// namespaces.
+fn children_named<'a>(e: Node<'a>, name: &'a str) -> impl Iterator<Item = Node<'a>> {
+    e.children().filter(move |c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn child<'a>(e: Node<'a>, name: &str) -> Option<Node<'a>> {
+    e.children().find(|c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn child_text<'a>(e: Node<'a>, name: &str) -> Option<&'a str> {
+    Some(child(e, name)?.text().unwrap_or_default().trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An excerpt from the `ARM_Example.svd` that comes with CMSIS.
+    const ARM_EXAMPLE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+
+<!-- File naming: <vendor>_<part/series name>.svd -->
+
+<device schemaVersion="1.3" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance" xs:noNamespaceSchemaLocation="CMSIS-SVD.xsd" >
+  <vendor>ARM Ltd.</vendor>
+  <vendorID>ARM</vendorID>
+  <name>ARM_Example</name>
+  <series>ARMCM3</series>
+  <version>1.2</version>
+  <description>ARM 32-bit Cortex-M3 Microcontroller based device, CPU clock up to 80MHz, etc. </description>
+  <cpu>
+    <name>CM3</name>
+    <revision>r1p0</revision>
+    <endian>little</endian>
+    <mpuPresent>true</mpuPresent>
+    <fpuPresent>false</fpuPresent>
+    <nvicPrioBits>3</nvicPrioBits>
+    <vendorSystickConfig>false</vendorSystickConfig>
+  </cpu>
+  <addressUnitBits>8</addressUnitBits>
+  <width>32</width>
+  <size>32</size>
+  <access>read-write</access>
+  <resetValue>0x00000000</resetValue>
+  <resetMask>0xFFFFFFFF</resetMask>
+
+  <peripherals>
+    <!-- Timer 0 -->
+    <peripheral>
+      <name>TIMER0</name>
+      <version>1.0</version>
+      <description>32 Timer / Counter, counting up or down from different sources</description>
+      <groupName>TIMER</groupName>
+      <baseAddress>0x40010000</baseAddress>
+      <size>32</size>
+      <access>read-write</access>
+
+      <addressBlock>
+        <offset>0</offset>
+        <size>0x100</size>
+        <usage>registers</usage>
+      </addressBlock>
+
+      <interrupt>
+        <name>TIMER0</name>
+        <description>Timer 0 interrupt</description>
+        <value>0</value>
+      </interrupt>
+
+      <registers>
+      <!-- CR: Control Register -->
+        <register>
+          <name>CR</name>
+          <description>Control Register</description>
+          <addressOffset>0x00</addressOffset>
+          <size>32</size>
+          <access>read-write</access>
+          <resetValue>0x00000000</resetValue>
+          <resetMask>0x1337F7F</resetMask>
+
+          <fields>
+            <!-- EN: Enable -->
+            <field>
+              <name>EN</name>
+              <description>Enable</description>
+              <bitRange>[0:0]</bitRange>
+              <access>read-write</access>
+              <enumeratedValues>
+                <enumeratedValue>
+                  <name>Disable</name>
+                  <description>Timer is disabled and does not operate</description>
+                  <value>0</value>
+                </enumeratedValue>
+                <enumeratedValue>
+                  <name>Enable</name>
+                  <description>Timer is enabled and can operate</description>
+                  <value>1</value>
+                </enumeratedValue>
+              </enumeratedValues>
+            </field>
+
+            <!-- CNT: Counting Direction -->
+            <field>
+              <name>CNT</name>
+              <description>Counting direction</description>
+              <bitRange>[3:2]</bitRange>
+              <access>read-write</access>
+            </field>
+
+            <!-- RST: Reset -->
+            <field>
+              <name>RST</name>
+              <description>Reset Timer</description>
+              <bitRange>[1:1]</bitRange>
+              <access>write-only</access>
+            </field>
+
+            <!-- MODE: Operation Mode -->
+            <field>
+              <name>MODE</name>
+              <description>Operation Mode</description>
+              <bitRange>[6:4]</bitRange>
+              <access>read-write</access>
+            </field>
+          </fields>
+        </register>
+
+      <!-- SR: Status Register -->
+        <register>
+          <name>SR</name>
+          <description>Status Register</description>
+          <addressOffset>0x04</addressOffset>
+          <size>16</size>
+          <access>read-write</access>
+          <resetValue>0x00000000</resetValue>
+          <resetMask>0xD701</resetMask>
+
+          <fields>
+            <!-- RUN: Shows if Timer is running -->
+            <field>
+              <name>RUN</name>
+              <description>Shows if Timer is running or not</description>
+              <bitRange>[0:0]</bitRange>
+              <access>read-only</access>
+            </field>
+
+            <!-- MATCH: Shows if a Match was hit -->
+            <field>
+              <name>MATCH</name>
+              <description>Shows if the MATCH was hit</description>
+              <bitRange>[8:8]</bitRange>
+              <access>read-write</access>
+            </field>
+          </fields>
+        </register>
+
+      <!-- COUNT: Counter Register -->
+        <register>
+          <name>COUNT</name>
+          <description>The Counter Register reflects the actual Value of the Timer/Counter</description>
+          <addressOffset>0x20</addressOffset>
+          <size>32</size>
+          <access>read-write</access>
+          <resetValue>0x00000000</resetValue>
+          <resetMask>0xFFFFFFFF</resetMask>
+        </register>
+
+      <!-- RELOAD: Array of Reload Register with 4 elements-->
+        <register>
+          <dim>4</dim>
+          <dimIncrement>4</dimIncrement>
+          <dimIndex>0,1,2,3</dimIndex>
+          <name>RELOAD%s</name>
+          <description>The Reload Register is copied into the actual Timer/Counter Register when the counter reaches the reload value</description>
+          <addressOffset>0x50</addressOffset>
+          <size>32</size>
+          <access>read-write</access>
+          <resetValue>0x00000000</resetValue>
+          <resetMask>0xFFFFFFFF</resetMask>
+        </register>
+      </registers>
+    </peripheral>   <!-- End of TIMER 0 -->
+
+    <!-- Timer 1 -->
+    <peripheral derivedFrom="TIMER0">
+      <name>TIMER1</name>
+      <baseAddress>0x40010100</baseAddress>
+      <interrupt>
+        <name>TIMER1</name>
+        <description>Timer 2 interrupt</description>
+        <value>4</value>
+      </interrupt>
+    </peripheral>
+
+    <!-- Timer 2 -->
+    <peripheral derivedFrom="TIMER0">
+      <name>TIMER2</name>
+      <baseAddress>0x40010200</baseAddress>
+      <interrupt>
+        <name>TIMER2</name>
+        <description>Timer 2 interrupt</description>
+        <value>6</value>
+      </interrupt>
+    </peripheral>
+  </peripherals>
+</device>
+"#;
+
+    #[test]
+    fn arm_example() {
+        let device = Device::parse(ARM_EXAMPLE).unwrap();
+        assert_eq!(device.name, "ARM_Example");
+        let names: Vec<_> = device.peripherals.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["TIMER0", "TIMER1", "TIMER2"]);
+
+        let timer0 = device.peripheral("timer0").unwrap();
+        assert_eq!(timer0.base_address, 0x4001_0000);
+        assert_eq!(timer0.group_name.as_deref(), Some("TIMER"));
+        let registers: Vec<_> = timer0.registers.iter()
+            .map(|r| (r.name.as_str(), r.address_offset, r.size))
+            .collect();
+        assert_eq!(registers, [
+            ("CR", 0x00, 32),
+            ("SR", 0x04, 16),
+            ("COUNT", 0x20, 32),
+            ("RELOAD0", 0x50, 32),
+            ("RELOAD1", 0x54, 32),
+            ("RELOAD2", 0x58, 32),
+            ("RELOAD3", 0x5c, 32),
+        ]);
+
+        let cr = &timer0.registers[0];
+        assert_eq!(cr.description.as_deref(), Some("Control Register"));
+        let fields: Vec<_> = cr.fields.iter().map(|f| f.to_string()).collect();
+        assert_eq!(fields, ["EN[0]", "RST[1]", "CNT[3:2]", "MODE[6:4]"]);
+
+        // Derived peripherals get the registers and group of their base.
+        let timer2 = device.peripheral("TIMER2").unwrap();
+        assert_eq!(timer2.registers.len(), 7);
+        assert_eq!(timer2.group_name.as_deref(), Some("TIMER"));
+
+        let (p, r) = device.register_at(0x4001_0105).unwrap();
+        assert_eq!((p.name.as_str(), r.name.as_str()), ("TIMER1", "SR"));
+        // SR is only 16 bits wide.
+        assert!(device.register_at(0x4001_0106).is_none());
+        let in_range: Vec<_> = timer0.registers_in(0x20..0x58).map(|r| r.name.as_str()).collect();
+        assert_eq!(in_range, ["COUNT", "RELOAD0", "RELOAD1"]);
+    }
+
+    #[test]
+    fn clusters_and_fields() {
+        let device = Device::parse(r#"<?xml version="1.0"?>
+            <svd:device xmlns:svd="http://example.com/svd">
+              <svd:name>Demo</svd:name>
+              <svd:peripherals>
+                <svd:peripheral>
+                  <svd:name>DMA</svd:name>
+                  <svd:description><![CDATA[Direct <memory>]]> access
+                    &amp; more</svd:description>
+                  <svd:baseAddress>#1000</svd:baseAddress>
+                  <svd:registers>
+                    <svd:cluster>
+                      <svd:dim>2</svd:dim>
+                      <svd:dimIncrement>0x10</svd:dimIncrement>
+                      <svd:name>CH[%s]</svd:name>
+                      <svd:addressOffset>0x100</svd:addressOffset>
+                      <svd:register>
+                        <svd:name>CR</svd:name>
+                        <svd:addressOffset>4</svd:addressOffset>
+                        <svd:size>8</svd:size>
+                        <svd:fields>
+                          <svd:field>
+                            <svd:name>PRIO</svd:name>
+                            <svd:lsb>1</svd:lsb>
+                            <svd:msb>2</svd:msb>
+                          </svd:field>
+                          <svd:field>
+                            <svd:name>EN</svd:name>
+                            <svd:bitOffset>0</svd:bitOffset>
+                          </svd:field>
+                        </svd:fields>
+                      </svd:register>
+                    </svd:cluster>
+                    <svd:register>
+                      <svd:dim>2</svd:dim>
+                      <svd:dimIncrement>4</svd:dimIncrement>
+                      <svd:dimIndex>A-B</svd:dimIndex>
+                      <svd:name>PORT%s</svd:name>
+                      <svd:addressOffset>0</svd:addressOffset>
+                    </svd:register>
+                  </svd:registers>
+                </svd:peripheral>
+              </svd:peripherals>
+            </svd:device>"#).unwrap();
+
+        let dma = device.peripheral("DMA").unwrap();
+        assert_eq!(dma.base_address, 8);
+        assert_eq!(dma.description.as_deref(), Some("Direct <memory> access & more"));
+        let registers: Vec<_> = dma.registers.iter()
+            .map(|r| (r.name.as_str(), r.address_offset, r.size))
+            .collect();
+        assert_eq!(registers, [
+            ("PORTA", 0, 32),
+            ("PORTB", 4, 32),
+            ("CH[0].CR", 0x104, 8),
+            ("CH[1].CR", 0x114, 8),
+        ]);
+        let fields: Vec<_> = dma.registers[2].fields.iter().map(|f| f.to_string()).collect();
+        assert_eq!(fields, ["EN[0]", "PRIO[2:1]"]);
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |peripheral: &str| Device::parse(&format!(
+            "<device><peripherals>{peripheral}</peripherals></device>"
+        ));
+        assert!(matches!(Device::parse("<device><name>x</device>"), Err(SvdError::Xml(_))));
+        assert!(matches!(Device::parse("<other/>"), Err(SvdError::NoDevice)));
+        assert!(matches!(
+            parse("<peripheral><name>P</name></peripheral>"),
+            Err(SvdError::Missing(p, "baseAddress")) if p == "P"
+        ));
+        assert!(matches!(
+            parse("<peripheral><name>P</name><baseAddress>0xZZ</baseAddress></peripheral>"),
+            Err(SvdError::BadNumber(n)) if n == "0xZZ"
+        ));
+        assert!(matches!(
+            parse(r#"<peripheral derivedFrom="Q"><name>P</name><baseAddress>0</baseAddress></peripheral>"#),
+            Err(SvdError::UnknownBase(p, q)) if p == "P" && q == "Q"
+        ));
+    }
+}
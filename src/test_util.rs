@@ -0,0 +1,112 @@
+//! Helpers for building small databases by hand in tests.
+
+use crate::{Base, DebugDb, DebugDbBuilder, Encoding, Member, Struct, Type, TypeId, Union};
+use std::sync::Arc;
+
+/// Records types into a `DebugDbBuilder`, making up an offset for each.
+pub(crate) struct TestTypes {
+    pub builder: DebugDbBuilder,
+    next_offset: usize,
+}
+
+impl TestTypes {
+    pub fn new(endian: gimli::RunTimeEndian, is_64: bool) -> Self {
+        let debug_frame = gimli::DebugFrame::from(gimli::EndianReader::new(
+            Arc::from(&[][..]),
+            endian,
+        ));
+        Self {
+            builder: DebugDbBuilder::new(endian, is_64, debug_frame),
+            next_offset: 1,
+        }
+    }
+
+    /// A new 32-bit little-endian database.
+    pub fn little() -> Self {
+        Self::new(gimli::RunTimeEndian::Little, false)
+    }
+
+    pub fn offset(&mut self) -> gimli::UnitSectionOffset {
+        let n = self.next_offset;
+        self.next_offset += 1;
+        gimli::DebugInfoOffset(n).into()
+    }
+
+    /// Records the type made by `make` from its offset.
+    pub fn record<T: Into<Type>>(
+        &mut self,
+        make: impl FnOnce(gimli::UnitSectionOffset) -> T,
+    ) -> TypeId {
+        let offset = self.offset();
+        self.builder.record_type(make(offset));
+        offset.into()
+    }
+
+    /// Records an unsigned integer type.
+    pub fn base(&mut self, name: &str, byte_size: u64) -> TypeId {
+        self.record(|offset| Base {
+            name: name.into(),
+            encoding: Encoding::Unsigned,
+            byte_size,
+            alignment: None,
+            offset,
+        })
+    }
+
+    pub fn member(&mut self, name: &str, type_id: TypeId, location: u64) -> Member {
+        Member {
+            name: Some(name.into()),
+            artificial: false,
+            type_id,
+            alignment: None,
+            location,
+            offset: self.offset(),
+            decl_coord: Default::default(),
+        }
+    }
+
+    fn members(&mut self, members: &[(&str, TypeId, u64)]) -> Vec<Member> {
+        members.iter()
+            .map(|&(name, type_id, location)| self.member(name, type_id, location))
+            .collect()
+    }
+
+    /// Records a struct with the given `(name, type, offset)` members.
+    pub fn structure(
+        &mut self,
+        name: &str,
+        byte_size: u64,
+        members: &[(&str, TypeId, u64)],
+    ) -> TypeId {
+        let members = self.members(members);
+        self.record(|offset| Struct {
+            name: name.into(),
+            byte_size: Some(byte_size),
+            alignment: None,
+            template_type_parameters: vec![],
+            tuple_like: false,
+            members,
+            offset,
+            decl_coord: Default::default(),
+        })
+    }
+
+    /// Records a union with the given `(name, type)` members.
+    pub fn union(&mut self, name: &str, byte_size: u64, members: &[(&str, TypeId)]) -> TypeId {
+        let members = members.iter()
+            .map(|&(name, type_id)| self.member(name, type_id, 0))
+            .collect();
+        self.record(|offset| Union {
+            name: name.into(),
+            byte_size,
+            alignment: None,
+            template_type_parameters: vec![],
+            members,
+            offset,
+        })
+    }
+
+    pub fn build(self) -> DebugDb {
+        self.builder.build().unwrap()
+    }
+}
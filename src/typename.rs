@@ -0,0 +1,251 @@
+//! Taking type names apart, so that different spellings of the same name can
+//! be compared.
+//!
+//! Names in the debug info are written the way the compiler writes them, e.g.
+//! `core::option::Option<&mut [u8]>`, but people typing them in won't always
+//! space them the same way, and may write generic arguments Rust expression
+//! style, as in `Option::<u8>`. `canonicalize` rewrites a name in the
+//! compiler's style so the two can be compared as strings.
+
+/// A piece of a type name, as produced by `tokenize`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Token<'a> {
+    /// An identifier, keyword, number or lifetime, e.g. `u8`, `mut`, `16` or
+    /// `'static`. Braced path segments that rustc makes up, like
+    /// `{closure_env#0}`, count as a single word.
+    Word(&'a str),
+    /// Anything else: `::`, `->`, or a single punctuation character.
+    Punct(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// Gets the text of the token.
+    pub fn as_str(self) -> &'a str {
+        match self {
+            Self::Word(s) | Self::Punct(s) => s,
+        }
+    }
+}
+
+/// Iterator over the tokens in a type name; see `tokenize`.
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+        self.rest = self.rest.trim_start();
+        let c = self.rest.chars().next()?;
+        let len = if c == '{' {
+            // Runs to the matching brace, or the end if there isn't one.
+            let mut depth = 0usize;
+            self.rest.char_indices()
+                .find_map(|(i, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Some(i + 1);
+                            }
+                        }
+                        _ => (),
+                    }
+                    None
+                })
+                .unwrap_or(self.rest.len())
+        } else if is_word(c) || c == '\'' {
+            c.len_utf8() + self.rest[c.len_utf8()..]
+                .find(|c| !is_word(c))
+                .unwrap_or(self.rest.len() - c.len_utf8())
+        } else if self.rest.starts_with("::") || self.rest.starts_with("->") {
+            2
+        } else {
+            c.len_utf8()
+        };
+
+        let (token, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(if c == '{' || c == '\'' || is_word(c) {
+            Token::Word(token)
+        } else {
+            Token::Punct(token)
+        })
+    }
+}
+
+/// Splits a type name into tokens, skipping whitespace.
+pub fn tokenize(name: &str) -> Tokens<'_> {
+    Tokens { rest: name }
+}
+
+/// Rewrites a type name with the spacing rustc uses -- `(u8, &mut [u16])`,
+/// `fn(u8) -> u8`, `dyn Any + Send` -- and without any `::` before generic
+/// arguments, so that names that differ only in those ways come out the
+/// same. Works on partial names too, such as `Option::<&` while it's being
+/// typed.
+pub fn canonicalize(name: &str) -> String {
+    let tokens = tokenize(name).collect::<Vec<_>>();
+    let mut out = String::with_capacity(name.len());
+    let mut prev: Option<Token> = None;
+    for (i, &t) in tokens.iter().enumerate() {
+        // `Vec::<u8>` is `Vec<u8>`.
+        if t == Token::Punct("::") && i > 0 && tokens.get(i + 1) == Some(&Token::Punct("<")) {
+            continue;
+        }
+        let space = match (prev, t) {
+            (None, _) => false,
+            (Some(Token::Word(_)), Token::Word(_)) => true,
+            (_, Token::Punct("->" | "+")) | (Some(Token::Punct("->" | "+")), _) => true,
+            (_, Token::Word("as")) => true,
+            (Some(Token::Punct("," | ";")), t) => !matches!(t, Token::Punct(")" | "]" | ">")),
+            // `&mut [u8]`, `*const (u8, u8)`.
+            (Some(Token::Word("mut" | "const" | "dyn" | "as")), Token::Punct(p)) => {
+                !matches!(p, "," | ";" | ")" | "]" | ">")
+            }
+            _ => false,
+        };
+        if space {
+            out.push(' ');
+        }
+        out.push_str(t.as_str());
+        prev = Some(t);
+    }
+    out
+}
+
+/// Generic arguments that Rust fills in by default, and so are left out of
+/// names by `abbreviate`.
+const DEFAULT_TYPE_ARGS: &[&str] = &[
+    "alloc::alloc::Global",
+    "std::collections::hash::map::RandomState",
+    "std::hash::random::RandomState",
+];
+
+/// Shortens a type name to how it would usually be written in source, by
+/// leaving out module paths and default generic arguments (such as the
+/// allocator): `alloc::vec::Vec<u8, alloc::alloc::Global>` becomes
+/// `Vec<u8>`, and `core::option::Option<alloc::string::String>` becomes
+/// `Option<String>`. Qualified paths like `<T as Trait>::Output` are kept.
+/// The result is canonical, as by `canonicalize`.
+pub fn abbreviate(name: &str) -> String {
+    let mut name = canonicalize(name);
+    for arg in DEFAULT_TYPE_ARGS {
+        let pattern = format!(", {arg}>");
+        if name.contains(&pattern) {
+            name = name.replace(&pattern, ">");
+        }
+    }
+
+    let mut out = String::with_capacity(name.len());
+    // Where the path being written began, and the same for each enclosing
+    // generic argument list.
+    let mut path_start = 0;
+    let mut outer = vec![];
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                if out[path_start..].starts_with('<') {
+                    out.push_str("::");
+                } else {
+                    out.truncate(path_start);
+                }
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                out.push_str("->");
+                path_start = out.len();
+            }
+            '<' => {
+                out.push(c);
+                outer.push(path_start);
+                path_start = out.len();
+            }
+            '>' => {
+                out.push(c);
+                path_start = outer.pop().unwrap_or(0);
+            }
+            ',' | '(' | ')' | '[' | ']' | ';' | '&' | '*' | '+' | ' ' | '=' => {
+                out.push(c);
+                path_start = out.len();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("fn(&'static mut [u8; 4]) -> core::option::Option<{closure_env#0}<u8>>")
+            .collect::<Vec<_>>();
+        use Token::{Punct, Word};
+        assert_eq!(tokens, [
+            Word("fn"), Punct("("), Punct("&"), Word("'static"), Word("mut"), Punct("["),
+            Word("u8"), Punct(";"), Word("4"), Punct("]"), Punct(")"), Punct("->"),
+            Word("core"), Punct("::"), Word("option"), Punct("::"), Word("Option"),
+            Punct("<"), Word("{closure_env#0}"), Punct("<"), Word("u8"), Punct(">"),
+            Punct(">"),
+        ]);
+    }
+
+    #[test]
+    fn unclosed_brace_runs_to_the_end() {
+        assert_eq!(tokenize("{impl#0").collect::<Vec<_>>(), [Token::Word("{impl#0")]);
+    }
+
+    #[test]
+    fn canonical_spacing() {
+        for (name, canonical) in [
+            ("( u8,&mut [ u16 ;4] )", "(u8, &mut [u16; 4])"),
+            ("fn(u8)->u8", "fn(u8) -> u8"),
+            ("&dyn core::any::Any+Send", "&dyn core::any::Any + Send"),
+            ("*const(u8,u8)", "*const (u8, u8)"),
+            ("<T as  Trait>::Output", "<T as Trait>::Output"),
+            ("HashMap<u8,u16,>", "HashMap<u8, u16,>"),
+            ("(u8,)", "(u8,)"),
+        ] {
+            assert_eq!(canonicalize(name), canonical, "{name}");
+        }
+    }
+
+    #[test]
+    fn turbofish() {
+        assert_eq!(canonicalize("Option::<Vec::<u8>>"), "Option<Vec<u8>>");
+        assert_eq!(canonicalize("Option::<&"), "Option<&");
+        // A leading `::` is part of the path, not a turbofish.
+        assert_eq!(canonicalize("::<u8>"), "::<u8>");
+    }
+
+    #[test]
+    fn abbreviations() {
+        for (name, short) in [
+            ("alloc::vec::Vec<u8, alloc::alloc::Global>", "Vec<u8>"),
+            ("core::option::Option<alloc::string::String>", "Option<String>"),
+            (
+                "std::collections::hash::map::HashMap<u8, u16, std::hash::random::RandomState>",
+                "HashMap<u8, u16>",
+            ),
+            ("&mut [core::cell::Cell<u8>]", "&mut [Cell<u8>]"),
+            ("fn(core::ffi::c_void) -> alloc::boxed::Box<u8>", "fn(c_void) -> Box<u8>"),
+            (
+                "<alloc::vec::Vec<u8> as core::ops::deref::Deref>::Target",
+                "<Vec<u8> as Deref>::Target",
+            ),
+            ("u8", "u8"),
+        ] {
+            assert_eq!(abbreviate(name), short, "{name}");
+        }
+    }
+}
@@ -250,7 +250,9 @@ impl Unify for Enum {
 
 impl Unify for Pointer {
     fn try_unify(&self, other: &Self, state: &mut State<'_>) -> bool {
-        if self.name != other.name {
+        let self_easy = (&self.name, self.byte_size, self.address_class);
+        let other_easy = (&other.name, other.byte_size, other.address_class);
+        if self_easy != other_easy {
             // TODO: should this allow for one unnamed type?
             return false;
         }
@@ -259,6 +261,26 @@ impl Unify for Pointer {
     }
 }
 
+impl Unify for Typedef {
+    fn try_unify(&self, other: &Self, state: &mut State<'_>) -> bool {
+        if self.name != other.name {
+            return false;
+        }
+
+        self.type_id.try_unify(&other.type_id, state)
+    }
+}
+
+impl Unify for Qualified {
+    fn try_unify(&self, other: &Self, state: &mut State<'_>) -> bool {
+        if self.qualifier != other.qualifier {
+            return false;
+        }
+
+        self.type_id.try_unify(&other.type_id, state)
+    }
+}
+
 impl Unify for Base {
     fn try_unify(&self, other: &Self, _state: &mut State<'_>) -> bool {
         let self_easy = (&self.name, self.encoding, self.byte_size, self.alignment);
@@ -300,6 +322,12 @@ impl Unify for CEnum {
 
 impl Unify for Subroutine {
     fn try_unify(&self, other: &Self, state: &mut State<'_>) -> bool {
+        let self_easy = (self.variadic, self.prototyped, self.calling_convention);
+        let other_easy = (other.variadic, other.prototyped, other.calling_convention);
+        if self_easy != other_easy {
+            return false;
+        }
+
         state.checkpoint(|state| {
             self.return_type_id.try_unify(&other.return_type_id, state)
                 && self.formal_parameters.try_unify(&other.formal_parameters, state)
@@ -318,6 +346,8 @@ impl Unify for Type {
             (Self::CEnum(a), Self::CEnum(b)) => a.try_unify(b, state),
             (Self::Union(a), Self::Union(b)) => a.try_unify(b, state),
             (Self::Subroutine(a), Self::Subroutine(b)) => a.try_unify(b, state),
+            (Self::Typedef(a), Self::Typedef(b)) => a.try_unify(b, state),
+            (Self::Qualified(a), Self::Qualified(b)) => a.try_unify(b, state),
             _ => false,
         }
     }
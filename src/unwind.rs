@@ -0,0 +1,535 @@
+//! Stack unwinding using call frame information (CFI).
+//!
+//! Programs describe how to find each function's caller in `.debug_frame`,
+//! `.eh_frame`, or both. Given the registers of one frame and access to the
+//! program's memory, `DebugDb::unwind_frame` uses that description to recover
+//! the registers of the calling frame.
+//!
+//! Registers are identified by their DWARF register numbers, which are
+//! architecture-specific; `gimli` has constants for the common ones (e.g.
+//! `gimli::Arm::SP`).
+
+use crate::load::Machine;
+use crate::{DebugDb, FrameBase, ProgramId, RtArcReader};
+use gimli::UnwindSection;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Register state of a single stack frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Program counter. For frames produced by unwinding, this is the return
+    /// address.
+    pub pc: u64,
+    /// Whether `pc` is a return address, rather than the address of the
+    /// instruction being executed. Return addresses may point just past the
+    /// end of the calling function, so they're looked up as `pc - 1`.
+    pub pc_is_return_address: bool,
+    /// Values of registers, by DWARF register number. Registers not listed
+    /// here are unknown.
+    pub registers: BTreeMap<gimli::Register, u64>,
+}
+
+impl Frame {
+    /// Creates the innermost frame of a stack, given the current program
+    /// counter and stack pointer.
+    pub fn new(pc: u64, sp_register: gimli::Register, sp: u64) -> Self {
+        Self {
+            pc,
+            pc_is_return_address: false,
+            registers: [(sp_register, sp)].into_iter().collect(),
+        }
+    }
+
+    /// Gets the address that unwind information for this frame should be
+    /// looked up by.
+    pub fn lookup_pc(&self) -> u64 {
+        if self.pc_is_return_address {
+            self.pc.saturating_sub(1)
+        } else {
+            self.pc
+        }
+    }
+}
+
+/// Gets the DWARF register number of the stack pointer on `arch`, if we know
+/// it.
+pub fn stack_pointer_register(arch: object::Architecture) -> Option<gimli::Register> {
+    use object::Architecture as A;
+    match arch {
+        A::Arm => Some(gimli::Arm::SP),
+        A::Aarch64 => Some(gimli::AArch64::SP),
+        A::I386 => Some(gimli::X86::ESP),
+        A::X86_64 => Some(gimli::X86_64::RSP),
+        A::Riscv32 | A::Riscv64 => Some(gimli::RiscV::SP),
+        _ => None,
+    }
+}
+
+/// How much stack a function uses for its own frame, as worked out from its
+/// CFI by `DebugDb::frame_size`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FrameSize {
+    /// The most stack the function uses at any point, in bytes: the largest
+    /// distance between the stack pointer and the CFA, which is the stack
+    /// pointer in the caller. Where calls push the return address (as on x86),
+    /// this includes it, like GCC's `-fstack-usage`.
+    pub byte_size: u64,
+    /// Whether the function may use more than `byte_size`. This is the case
+    /// when its frame is located through a frame pointer, because the CFI
+    /// then doesn't track the stack pointer, which may be moved by any amount
+    /// (e.g. by `alloca` or variable-length arrays).
+    pub dynamic: bool,
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum UnwindError<E> {
+    #[error("no unwind information for address {0:#x}")]
+    NoUnwindInfo(u64),
+    #[error("value of register #{} is required but unknown", .0.0)]
+    UnknownRegister(gimli::Register),
+    #[error("memory at {0:#x} is not available")]
+    MissingMemory(u64),
+    #[error("unsupported unwind rule: {0}")]
+    Unsupported(String),
+    #[error("DWARF error")]
+    Dwarf(#[from] gimli::Error),
+    #[error("access error")]
+    Access(E),
+}
+
+impl DebugDb {
+    /// Finds the frame that called `frame`, using `sp_register` as the stack
+    /// pointer.
+    ///
+    /// `.debug_frame` is tried first, then `.eh_frame`. The caller's stack
+    /// pointer is taken to be the canonical frame address, which holds on all
+    /// common architectures.
+    ///
+    /// Returns `Ok(None)` if the return address is undefined, which is how CFI
+    /// marks the outermost frame of a stack.
+    pub fn unwind_frame<M: Machine>(
+        &self,
+        machine: &M,
+        sp_register: gimli::Register,
+        frame: &Frame,
+    ) -> Result<Option<Frame>, UnwindError<M::Error>> {
+        let pc = frame.lookup_pc();
+        let mut ctx = gimli::UnwindContext::new();
+
+        let debug_frame_bases = gimli::BaseAddresses::default();
+        let found = unwind_row(&self.debug_frame, &debug_frame_bases, &mut ctx, pc)?;
+        let (row, ra_register) = match found {
+            Some(found) => found,
+            None => {
+                let Some((eh_frame, bases)) = &self.eh_frame else {
+                    return Err(UnwindError::NoUnwindInfo(pc));
+                };
+                unwind_row(eh_frame, bases, &mut ctx, pc)?
+                    .ok_or(UnwindError::NoUnwindInfo(pc))?
+            }
+        };
+
+        let cfa = match row.cfa() {
+            gimli::CfaRule::RegisterAndOffset { register, offset } => {
+                let base = frame.registers.get(register)
+                    .ok_or(UnwindError::UnknownRegister(*register))?;
+                base.wrapping_add(*offset as u64)
+            }
+            gimli::CfaRule::Expression(e) => {
+                self.evaluate_cfi_expr(machine, frame, e.clone(), None)?
+            }
+        };
+
+        let mut caller = Frame {
+            pc: 0,
+            pc_is_return_address: true,
+            registers: BTreeMap::new(),
+        };
+        // Registers with no rule keep their values, as if they were marked
+        // SameValue. This matches what producers assume in practice.
+        for (&reg, &value) in &frame.registers {
+            caller.registers.insert(reg, value);
+        }
+        caller.registers.insert(sp_register, cfa);
+
+        for (reg, rule) in row.registers() {
+            let value = match rule {
+                gimli::RegisterRule::Undefined => {
+                    caller.registers.remove(reg);
+                    continue;
+                }
+                gimli::RegisterRule::SameValue => continue,
+                gimli::RegisterRule::Offset(n) => {
+                    self.read_word(machine, cfa.wrapping_add(*n as u64))?
+                }
+                gimli::RegisterRule::ValOffset(n) => cfa.wrapping_add(*n as u64),
+                gimli::RegisterRule::Register(r) => {
+                    *frame.registers.get(r).ok_or(UnwindError::UnknownRegister(*r))?
+                }
+                gimli::RegisterRule::Expression(e) => {
+                    let addr = self.evaluate_cfi_expr(machine, frame, e.clone(), Some(cfa))?;
+                    self.read_word(machine, addr)?
+                }
+                gimli::RegisterRule::ValExpression(e) => {
+                    self.evaluate_cfi_expr(machine, frame, e.clone(), Some(cfa))?
+                }
+                gimli::RegisterRule::Architectural => {
+                    return Err(UnwindError::Unsupported(format!(
+                        "architectural rule for register #{}", reg.0,
+                    )));
+                }
+            };
+            caller.registers.insert(*reg, value);
+        }
+
+        match caller.registers.get(&ra_register) {
+            Some(&ra) if ra != 0 => {
+                caller.pc = ra;
+                Ok(Some(caller))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Unwinds the stack starting at `frame`, returning it and each of its
+    /// callers in turn, until the outermost frame is reached or unwinding
+    /// stops making progress.
+    ///
+    /// An error part way up the stack ends the backtrace early; the frames
+    /// found up to that point are returned alongside it.
+    pub fn backtrace<M: Machine>(
+        &self,
+        machine: &M,
+        sp_register: gimli::Register,
+        frame: Frame,
+    ) -> (Vec<Frame>, Option<UnwindError<M::Error>>) {
+        let mut frames = vec![frame];
+        loop {
+            let current = frames.last().unwrap();
+            match self.unwind_frame(machine, sp_register, current) {
+                Ok(Some(caller)) => {
+                    // A frame identical to its callee would repeat forever.
+                    if caller.pc == current.pc
+                        && caller.registers.get(&sp_register) == current.registers.get(&sp_register)
+                    {
+                        return (frames, None);
+                    }
+                    frames.push(caller);
+                }
+                Ok(None) => return (frames, None),
+                Err(e) => return (frames, Some(e)),
+            }
+        }
+    }
+
+    /// Works out the stack frame size of subprogram `id` from its CFI, using
+    /// `sp_register` as the stack pointer. `.debug_frame` is tried first, then
+    /// `.eh_frame`.
+    ///
+    /// Returns `Ok(None)` if the subprogram has no code, or no CFI covers it.
+    pub fn frame_size(
+        &self,
+        sp_register: gimli::Register,
+        id: ProgramId,
+    ) -> Result<Option<FrameSize>, gimli::Error> {
+        let Some(p) = self.subprogram_by_id(id) else {
+            return Ok(None);
+        };
+        let mut ctx = gimli::UnwindContext::new();
+        let debug_frame_bases = gimli::BaseAddresses::default();
+        let mut size = None::<FrameSize>;
+        for range in &p.pc_ranges {
+            // Inlined-only subprograms can have ranges at zero.
+            if range.start == 0 || range.start >= range.end {
+                continue;
+            }
+            let mut found = frame_rows(&self.debug_frame, &debug_frame_bases, &mut ctx, range, sp_register)?;
+            if found.is_none() {
+                if let Some((eh_frame, bases)) = &self.eh_frame {
+                    found = frame_rows(eh_frame, bases, &mut ctx, range, sp_register)?;
+                }
+            }
+            if let Some(found) = found {
+                let size = size.get_or_insert_with(FrameSize::default);
+                size.byte_size = size.byte_size.max(found.byte_size);
+                size.dynamic |= found.dynamic;
+            }
+        }
+        // A frame base in some register other than the stack pointer is a
+        // frame pointer, even if the CFI hasn't said so.
+        if let (Some(size), Some(FrameBase::Register(r) | FrameBase::RegisterOffset(r, _))) =
+            (&mut size, p.frame_base)
+        {
+            size.dynamic |= r != sp_register;
+        }
+        Ok(size)
+    }
+
+    fn read_word<M: Machine>(
+        &self,
+        machine: &M,
+        address: u64,
+    ) -> Result<u64, UnwindError<M::Error>> {
+        let mut buf = [0; 8];
+        let size = self.pointer_size();
+        let n = machine.read_memory(address, &mut buf[..size])
+            .map_err(UnwindError::Access)?;
+        if n != size {
+            return Err(UnwindError::MissingMemory(address));
+        }
+        use gimli::Endianity;
+        Ok(match size {
+            8 => self.endian.read_u64(&buf[..8]),
+            _ => u64::from(self.endian.read_u32(&buf[..4])),
+        })
+    }
+
+    /// Evaluates a DWARF expression from CFI against the registers of `frame`
+    /// and the memory of `machine`, with `initial` (usually the CFA) pushed on
+    /// the stack first.
+    fn evaluate_cfi_expr<M: Machine>(
+        &self,
+        machine: &M,
+        frame: &Frame,
+        expr: gimli::Expression<RtArcReader>,
+        initial: Option<u64>,
+    ) -> Result<u64, UnwindError<M::Error>> {
+        let encoding = gimli::Encoding {
+            address_size: self.pointer_size() as u8,
+            format: gimli::Format::Dwarf32,
+            version: 4,
+        };
+        let mut eval = expr.evaluation(encoding);
+        if let Some(v) = initial {
+            eval.set_initial_value(v);
+        }
+        let mut result = eval.evaluate()?;
+        loop {
+            match result {
+                gimli::EvaluationResult::Complete => break,
+                gimli::EvaluationResult::RequiresMemory { address, size, .. } => {
+                    let mut buf = [0; 8];
+                    let size = usize::from(size).min(8);
+                    let n = machine.read_memory(address, &mut buf[..size])
+                        .map_err(UnwindError::Access)?;
+                    if n != size {
+                        return Err(UnwindError::MissingMemory(address));
+                    }
+                    use gimli::Endianity;
+                    let value = match size {
+                        1 => u64::from(buf[0]),
+                        2 => u64::from(self.endian.read_u16(&buf[..2])),
+                        4 => u64::from(self.endian.read_u32(&buf[..4])),
+                        _ => self.endian.read_u64(&buf[..8]),
+                    };
+                    result = eval.resume_with_memory(gimli::Value::Generic(value))?;
+                }
+                gimli::EvaluationResult::RequiresRegister { register, .. } => {
+                    let value = frame.registers.get(&register)
+                        .ok_or(UnwindError::UnknownRegister(register))?;
+                    result = eval.resume_with_register(gimli::Value::Generic(*value))?;
+                }
+                gimli::EvaluationResult::RequiresRelocatedAddress(a) => {
+                    result = eval.resume_with_relocated_address(a)?;
+                }
+                other => {
+                    return Err(UnwindError::Unsupported(format!("{:?}", other)));
+                }
+            }
+        }
+
+        let pieces = eval.result();
+        let [piece] = &pieces[..] else {
+            return Err(UnwindError::Unsupported("multi-piece expression".into()));
+        };
+        match &piece.location {
+            gimli::Location::Address { address } => Ok(*address),
+            gimli::Location::Value { value } => {
+                let addr_mask = !0 >> (64 - 8 * self.pointer_size() as u32);
+                Ok(value.to_u64(addr_mask)?)
+            }
+            other => Err(UnwindError::Unsupported(format!("{:?}", other))),
+        }
+    }
+}
+
+/// Looks up the unwind table row for `pc` in `section`, along with the
+/// number of the register holding the return address. Returns `Ok(None)` if
+/// the section doesn't cover `pc`.
+fn unwind_row<'ctx, S: UnwindSection<RtArcReader>>(
+    section: &S,
+    bases: &gimli::BaseAddresses,
+    ctx: &'ctx mut gimli::UnwindContext<RtArcReader>,
+    pc: u64,
+) -> Result<Option<(&'ctx gimli::UnwindTableRow<RtArcReader>, gimli::Register)>, gimli::Error> {
+    let fde = match section.fde_for_address(bases, pc, S::cie_from_offset) {
+        Ok(fde) => fde,
+        Err(gimli::Error::NoUnwindInfoForAddress) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let ra_register = fde.cie().return_address_register();
+    let row = fde.unwind_info_for_address(section, bases, ctx, pc)?;
+    Ok(Some((row, ra_register)))
+}
+
+/// Finds the largest stack frame in the unwind table rows of `section` that
+/// cover `range`. Returns `Ok(None)` if the section doesn't cover it.
+fn frame_rows<S: UnwindSection<RtArcReader>>(
+    section: &S,
+    bases: &gimli::BaseAddresses,
+    ctx: &mut gimli::UnwindContext<RtArcReader>,
+    range: &std::ops::Range<u64>,
+    sp_register: gimli::Register,
+) -> Result<Option<FrameSize>, gimli::Error> {
+    let fde = match section.fde_for_address(bases, range.start, S::cie_from_offset) {
+        Ok(fde) => fde,
+        Err(gimli::Error::NoUnwindInfoForAddress) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut size = FrameSize::default();
+    let mut rows = fde.rows(section, bases, ctx)?;
+    while let Some(row) = rows.next_row()? {
+        if row.end_address() <= range.start || row.start_address() >= range.end {
+            continue;
+        }
+        match row.cfa() {
+            gimli::CfaRule::RegisterAndOffset { register, offset } if *register == sp_register => {
+                size.byte_size = size.byte_size.max(u64::try_from(*offset).unwrap_or(0));
+            }
+            _ => size.dynamic = true,
+        }
+    }
+    Ok(Some(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::ImgMachine;
+    use crate::DebugDbBuilder;
+    use gimli::write::{Address, CallFrameInstruction, CommonInformationEntry, FrameDescriptionEntry};
+    use gimli::Arm;
+    use std::sync::Arc;
+
+    /// Builds a 32-bit little-endian database whose `.debug_frame` describes
+    /// a leaf function at 0x1000..0x1010, which keeps its return address in
+    /// LR, and a function at 0x2000..0x2040, which starts with
+    /// `push {r7, lr}`.
+    fn arm_db() -> DebugDb {
+        let encoding = gimli::Encoding {
+            address_size: 4,
+            format: gimli::Format::Dwarf32,
+            version: 1,
+        };
+        let mut table = gimli::write::FrameTable::default();
+        let mut cie = CommonInformationEntry::new(encoding, 2, -4, Arm::LR);
+        cie.add_instruction(CallFrameInstruction::Cfa(Arm::SP, 0));
+        let cie = table.add_cie(cie);
+
+        table.add_fde(cie, FrameDescriptionEntry::new(Address::Constant(0x1000), 0x10));
+
+        let mut fde = FrameDescriptionEntry::new(Address::Constant(0x2000), 0x40);
+        fde.add_instruction(2, CallFrameInstruction::CfaOffset(8));
+        fde.add_instruction(2, CallFrameInstruction::Offset(Arm::R7, -8));
+        fde.add_instruction(2, CallFrameInstruction::Offset(Arm::LR, -4));
+        table.add_fde(cie, fde);
+
+        let mut section = gimli::write::DebugFrame::from(
+            gimli::write::EndianVec::new(gimli::RunTimeEndian::Little),
+        );
+        table.write_debug_frame(&mut section).unwrap();
+        let mut debug_frame = gimli::DebugFrame::from(gimli::EndianReader::new(
+            Arc::from(section.0.into_vec()),
+            gimli::RunTimeEndian::Little,
+        ));
+        debug_frame.set_address_size(4);
+        DebugDbBuilder::new(gimli::RunTimeEndian::Little, false, debug_frame)
+            .build()
+            .unwrap()
+    }
+
+    /// Makes a memory image with the 32-bit little-endian `words` stored at
+    /// the given addresses.
+    fn memory(words: &[(u64, u32)]) -> ImgMachine {
+        let mut mem = vec![0; 0x9000];
+        for &(addr, word) in words {
+            let addr = addr as usize;
+            mem[addr..addr + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        ImgMachine::new(mem)
+    }
+
+    #[test]
+    fn saved_registers() {
+        let db = arm_db();
+        let mem = memory(&[(0x7ff8, 0x1234), (0x7ffc, 0x3001)]);
+        let mut frame = Frame::new(0x2010, Arm::SP, 0x7ff8);
+        frame.registers.insert(Arm::R7, 0xaaaa);
+
+        let caller = db.unwind_frame(&mem, Arm::SP, &frame).unwrap().unwrap();
+        assert_eq!(caller.pc, 0x3001);
+        assert!(caller.pc_is_return_address);
+        assert_eq!(caller.registers.get(&Arm::SP), Some(&0x8000));
+        assert_eq!(caller.registers.get(&Arm::R7), Some(&0x1234));
+        assert_eq!(caller.registers.get(&Arm::LR), Some(&0x3001));
+    }
+
+    #[test]
+    fn before_prologue() {
+        // At the first instruction, nothing has been pushed yet, so the
+        // return address is still in LR.
+        let db = arm_db();
+        let mem = memory(&[]);
+        let mut frame = Frame::new(0x2000, Arm::SP, 0x8000);
+        frame.registers.insert(Arm::LR, 0x3001);
+
+        let caller = db.unwind_frame(&mem, Arm::SP, &frame).unwrap().unwrap();
+        assert_eq!(caller.pc, 0x3001);
+        assert_eq!(caller.registers.get(&Arm::SP), Some(&0x8000));
+    }
+
+    #[test]
+    fn backtrace_ends_at_zero_return_address() {
+        let db = arm_db();
+        // The outer function's caller is recorded as 0, ending the stack.
+        let mem = memory(&[(0x7ffc, 0)]);
+        let mut frame = Frame::new(0x1004, Arm::SP, 0x7ff8);
+        frame.registers.insert(Arm::LR, 0x2011);
+
+        let (frames, err) = db.backtrace(&mem, Arm::SP, frame);
+        assert!(err.is_none(), "{err:?}");
+        let pcs = frames.iter().map(|f| f.pc).collect::<Vec<_>>();
+        assert_eq!(pcs, [0x1004, 0x2011]);
+        // The return address is looked up as the call instruction before it.
+        assert_eq!(frames[1].lookup_pc(), 0x2010);
+        assert_eq!(frames[1].registers.get(&Arm::SP), Some(&0x7ff8));
+    }
+
+    #[test]
+    fn errors() {
+        let db = arm_db();
+        let mem = memory(&[]);
+
+        let frame = Frame::new(0x5000, Arm::SP, 0x8000);
+        assert!(matches!(
+            db.unwind_frame(&mem, Arm::SP, &frame),
+            Err(UnwindError::NoUnwindInfo(0x5000))
+        ));
+
+        // The saved registers are past the end of memory.
+        let frame = Frame::new(0x2010, Arm::SP, 0xfff8);
+        assert!(matches!(
+            db.unwind_frame(&mem, Arm::SP, &frame),
+            Err(UnwindError::MissingMemory(0xfff8))
+        ));
+
+        // Without a stack pointer, the CFA can't be found.
+        let mut frame = Frame::new(0x2010, Arm::SP, 0x8000);
+        frame.registers.clear();
+        assert!(matches!(
+            db.unwind_frame(&mem, Arm::SP, &frame),
+            Err(UnwindError::UnknownRegister(Arm::SP))
+        ));
+    }
+}
@@ -5,10 +5,12 @@
 
 use regex::Regex;
 
-use crate::load::{choose_variant, load_unsigned, Load, LoadError, Machine};
-use crate::{Encoding, Type, DebugDb, TypeId, EntityId};
+use crate::load::{choose_variant, load_unsigned, ImgMachine, Load, LoadError, Machine};
+use crate::store::StoreError;
+use crate::{Encoding, Type, DebugDb, TypeId, EntityId, PointerKind};
+use gimli::Endianity;
 use std::borrow::Cow;
-use std::convert::TryFrom;
+use std::convert::{Infallible, TryFrom};
 use std::fmt::Display;
 use std::collections::{BTreeSet, BTreeMap};
 
@@ -17,22 +19,43 @@ pub enum Value {
     Array(Vec<Value>),
     Base(Base),
     Struct(Struct),
+    /// A union, interpreted as each of its members in turn.
+    Union(Struct),
     CEnum(CEnum),
     Enum(Enum),
     Pointer(Pointer),
 }
 
 impl Value {
+    /// Interprets `bytes` as a value of type `ty`.
+    ///
+    /// The value is expected to start at the beginning of `bytes`. Any
+    /// pointers in the value are left as addresses, since there's no way to
+    /// follow them.
+    pub fn from_bytes(
+        bytes: &[u8],
+        world: &DebugDb,
+        ty: &Type,
+    ) -> Result<Self, LoadError<Infallible>> {
+        Self::from_state(&ImgMachine::new(bytes), 0, world, ty)
+    }
+
+    /// Produces the byte representation of this value as a value of type
+    /// `ty`. This is the inverse of `from_bytes`.
+    pub fn to_bytes(
+        &self,
+        world: &DebugDb,
+        ty: &Type,
+    ) -> Result<Vec<u8>, StoreError> {
+        crate::store::store(self, world, ty)
+    }
+
     pub fn u64_value(&self) -> Option<u64> {
         if let Self::Base(b) = self {
-            match b {
-                Base::U8(x) => return Some(u64::from(*x)),
-                Base::U32(x) => return Some(u64::from(*x)),
-                Base::U64(x) => return Some(*x),
-                _ => (),
-            }
+            b.as_u64()
+        } else {
+            None
         }
-        None
     }
 
     pub fn pointer_value(&self) -> Option<u64> {
@@ -57,12 +80,23 @@ impl Value {
             }
             Self::Base(b) => match b {
                 Base::U8(_) => "u8".into(),
+                Base::U16(_) => "u16".into(),
                 Base::U32(_) => "u32".into(),
                 Base::U64(_) => "u64".into(),
+                Base::U128(_) => "u128".into(),
+                Base::I8(_) => "i8".into(),
+                Base::I16(_) => "i16".into(),
+                Base::I32(_) => "i32".into(),
+                Base::I64(_) => "i64".into(),
+                Base::I128(_) => "i128".into(),
+                Base::F32(_) => "f32".into(),
+                Base::F64(_) => "f64".into(),
                 Base::Bool(_) => "bool".into(),
+                Base::Char(_) => "char".into(),
                 Base::Unit => "()".into(),
             },
             Self::Struct(s) => (&s.name).into(),
+            Self::Union(s) => (&s.name).into(),
             Self::CEnum(s) => (&s.name).into(),
             Self::Enum(s) => (&s.name).into(),
             Self::Pointer(s) => (&s.name).into(),
@@ -75,7 +109,7 @@ impl Value {
                 elt.collect_names(set);
             },
             Self::Base(_) => (),
-            Self::Struct(s) => {
+            Self::Struct(s) | Self::Union(s) => {
                 set.insert(s.name.clone());
                 for (_, value) in &s.members {
                     value.collect_names(set);
@@ -101,11 +135,24 @@ impl Value {
         match self {
             Self::Base(b) => match b {
                 Base::U8(x) => write!(f, "{x}_u8"),
+                Base::U16(x) => write!(f, "{x}_u16"),
                 Base::U32(x) => write!(f, "{x}_u32"),
                 Base::U64(x) => write!(f, "{x}_u64"),
+                Base::U128(x) => write!(f, "{x}_u128"),
+                Base::I8(x) => write!(f, "{x}_i8"),
+                Base::I16(x) => write!(f, "{x}_i16"),
+                Base::I32(x) => write!(f, "{x}_i32"),
+                Base::I64(x) => write!(f, "{x}_i64"),
+                Base::I128(x) => write!(f, "{x}_i128"),
+                Base::F32(x) => write!(f, "{x:?}_f32"),
+                Base::F64(x) => write!(f, "{x:?}_f64"),
                 Base::Bool(0) => write!(f, "false"),
                 Base::Bool(1) => write!(f, "true"),
                 Base::Bool(x) => write!(f, "{x}_bool"),
+                Base::Char(x) => match char::from_u32(*x) {
+                    Some(c) => write!(f, "{c:?}"),
+                    None => write!(f, "/* invalid char */ {x:#x}"),
+                },
                 Base::Unit => write!(f, "()"),
             },
             Self::Pointer(p) => {
@@ -147,6 +194,12 @@ impl Value {
                 }
                 Ok(())
             }
+            Self::Union(s) => {
+                // Only one member can be given in a Rust union expression, so
+                // this isn't valid syntax unless the union has one member.
+                write!(f, "{} /* union */", use_table.rewrite(&s.name))?;
+                fmt_struct_body(s, world, indent, use_table, f)
+            }
             Self::Enum(e) => {
                 write!(f, "{}::{}", use_table.rewrite(&e.name), e.disc)?;
                 fmt_struct_body(&e.value, world, indent, use_table, f)
@@ -280,6 +333,11 @@ impl Load for Value {
         ty: &Type,
     ) -> Result<Self, LoadError<M::Error>> {
         match ty {
+            Type::Typedef(_) | Type::Qualified(_) => {
+                let target = world.type_by_id(world.resolve_alias(ty.id()))
+                    .ok_or(LoadError::UnsupportedType)?;
+                Self::from_state(machine, addr, world, target)
+            }
             Type::Base(_) => {
                 Ok(Self::Base(Base::from_state(machine, addr, world, ty)?))
             }
@@ -289,6 +347,9 @@ impl Load for Value {
             Type::Struct(_) => {
                 Ok(Self::Struct(Struct::from_state(machine, addr, world, ty)?))
             }
+            Type::Union(_) => {
+                Ok(Self::Union(load_union(machine, addr, world, ty)?))
+            }
             Type::CEnum(_) => {
                 Ok(Self::CEnum(CEnum::from_state(machine, addr, world, ty)?))
             }
@@ -298,7 +359,7 @@ impl Load for Value {
             Type::Pointer(_) => Ok(Self::Pointer(Pointer::from_state(
                 machine, addr, world, ty,
             )?)),
-            _ => unimplemented!(),
+            _ => Err(LoadError::UnsupportedType),
         }
     }
 }
@@ -307,17 +368,31 @@ impl Load for Value {
 pub enum Base {
     Unit,
     U8(u8),
+    U16(u16),
     U32(u32),
     U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
     Bool(u8),
+    /// A Unicode scalar value. This is kept as a `u32` because the program
+    /// may contain a value that is not a valid `char`.
+    Char(u32),
 }
 
 impl Base {
     pub fn as_u64(self) -> Option<u64> {
         match self {
-            Self::U8(x) => return Some(u64::from(x)),
-            Self::U32(x) => return Some(u64::from(x)),
-            Self::U64(x) => return Some(x),
+            Self::U8(x) => Some(u64::from(x)),
+            Self::U16(x) => Some(u64::from(x)),
+            Self::U32(x) => Some(u64::from(x)),
+            Self::U64(x) => Some(x),
+            Self::U128(x) => u64::try_from(x).ok(),
             _ => None,
         }
     }
@@ -331,36 +406,43 @@ impl Load for Base {
         ty: &Type,
     ) -> Result<Self, LoadError<M::Error>> {
         let Type::Base(b) = ty else { return Err(LoadError::NotABase); };
+        if b.byte_size == 0 {
+            return Ok(Base::Unit);
+        }
+
+        let mut buffer = [0; 16];
+        let Some(buffer) = usize::try_from(b.byte_size).ok()
+            .and_then(|n| buffer.get_mut(..n))
+            else { return Err(LoadError::UnsupportedType); };
+        if machine.read_memory(addr, buffer)? < buffer.len() {
+            return Err(LoadError::DataUnavailable);
+        }
+        let e = world.endian();
+        let wide = |buffer: &[u8]| {
+            let bytes = <[u8; 16]>::try_from(buffer).unwrap();
+            if e.is_big_endian() {
+                u128::from_be_bytes(bytes)
+            } else {
+                u128::from_le_bytes(bytes)
+            }
+        };
+
         match (b.encoding, b.byte_size) {
-            (Encoding::Unsigned, 1) => Ok(Base::U8(load_unsigned(
-                world.endian(),
-                machine,
-                addr,
-                1,
-            )?.ok_or(LoadError::DataUnavailable)? as u8)),
-            (Encoding::Unsigned, 4) => Ok(Base::U32(load_unsigned(
-                world.endian(),
-                machine,
-                addr,
-                4,
-            )?.ok_or(LoadError::DataUnavailable)? as u32)),
-            (Encoding::Unsigned, 8) => Ok(Base::U64(load_unsigned(
-                world.endian(),
-                machine,
-                addr,
-                8,
-            )?.ok_or(LoadError::DataUnavailable)?)),
-            (Encoding::Boolean, 1) => Ok(Base::Bool(load_unsigned(
-                world.endian(),
-                machine,
-                addr,
-                1,
-            )?.ok_or(LoadError::DataUnavailable)? as u8)),
-            (Encoding::Unsigned, 0) => Ok(Base::Unit),
-            _ => {
-                println!("{:?} {}", b.encoding, b.byte_size);
-                return Err(LoadError::UnsupportedType);
-            },
+            (Encoding::Unsigned | Encoding::UnsignedChar, 1) => Ok(Base::U8(buffer[0])),
+            (Encoding::Unsigned, 2) => Ok(Base::U16(e.read_u16(buffer))),
+            (Encoding::Unsigned, 4) => Ok(Base::U32(e.read_u32(buffer))),
+            (Encoding::Unsigned, 8) => Ok(Base::U64(e.read_u64(buffer))),
+            (Encoding::Unsigned, 16) => Ok(Base::U128(wide(buffer))),
+            (Encoding::Signed | Encoding::SignedChar, 1) => Ok(Base::I8(buffer[0] as i8)),
+            (Encoding::Signed, 2) => Ok(Base::I16(e.read_i16(buffer))),
+            (Encoding::Signed, 4) => Ok(Base::I32(e.read_i32(buffer))),
+            (Encoding::Signed, 8) => Ok(Base::I64(e.read_i64(buffer))),
+            (Encoding::Signed, 16) => Ok(Base::I128(wide(buffer) as i128)),
+            (Encoding::Float, 4) => Ok(Base::F32(e.read_f32(buffer))),
+            (Encoding::Float, 8) => Ok(Base::F64(e.read_f64(buffer))),
+            (Encoding::Boolean, 1) => Ok(Base::Bool(buffer[0])),
+            (Encoding::UtfChar, 4) => Ok(Base::Char(e.read_u32(buffer))),
+            _ => Err(LoadError::UnsupportedType),
         }
     }
 }
@@ -406,6 +488,97 @@ impl Struct {
             .find(|(n, _)| n.as_deref() == Some(name))
             .map(|(_, v)| v)
     }
+
+    /// If this is a Rust slice or `str` fat pointer (see
+    /// `crate::Struct::is_slice`), gets its data pointer and length.
+    pub fn slice_parts(&self) -> Option<(&Pointer, u64)> {
+        if PointerKind::from_name(&self.name) != PointerKind::Slice {
+            return None;
+        }
+        let Some(Value::Pointer(data_ptr)) = self.unique_member_named("data_ptr") else {
+            return None;
+        };
+        let length = self.unique_member_named("length")?.u64_value()?;
+        Some((data_ptr, length))
+    }
+
+    /// Checks whether this is a Rust `str` fat pointer, like `&str`.
+    pub fn is_str(&self) -> bool {
+        crate::model::pointee_name(&self.name).map(str::trim) == Some("str")
+            && self.slice_parts().is_some()
+    }
+
+    /// Follows a slice fat pointer, loading each of its elements from
+    /// `machine`.
+    ///
+    /// Like `Pointer::deref`, this trusts the pointer and the length, so a
+    /// garbage fat pointer will produce garbage or a `LoadError`. A null
+    /// pointer, as in a static that hasn't been initialized yet, gives
+    /// `LoadError::NullPointer`.
+    pub fn deref_slice<M: Machine>(
+        &self,
+        machine: &M,
+        world: &DebugDb,
+    ) -> Result<Vec<Value>, LoadError<M::Error>> {
+        let (data_ptr, length) = self.slice_parts()
+            .ok_or(LoadError::NotASlice)?;
+        if data_ptr.value == 0 {
+            return Err(LoadError::NullPointer);
+        }
+        let elty = world.type_by_id(data_ptr.dest_type_id)
+            .ok_or(LoadError::UnresolvedPointee)?;
+        if let Type::Unresolved(_) = elty {
+            return Err(LoadError::UnresolvedPointee);
+        }
+        let elt_size = elty
+            .byte_size(world)
+            .ok_or(LoadError::UnsizedElement)?;
+        let elt_size = elt_size.max(elty.alignment(world).unwrap_or(0));
+        if length.checked_mul(elt_size).is_none() {
+            return Err(LoadError::ArrayTooBig { count: length, elt_size });
+        }
+
+        let mut elts = vec![];
+        for i in 0..length {
+            let addr = data_ptr.value + i * elt_size;
+            elts.push(Value::from_state(machine, addr, world, elty)?);
+        }
+        Ok(elts)
+    }
+
+    /// Follows a `str` fat pointer, loading the string it refers to from
+    /// `machine`.
+    pub fn deref_str<M: Machine>(
+        &self,
+        machine: &M,
+    ) -> Result<String, LoadError<M::Error>> {
+        if !self.is_str() {
+            return Err(LoadError::NotASlice);
+        }
+        let (data_ptr, length) = self.slice_parts()
+            .ok_or(LoadError::NotASlice)?;
+        if data_ptr.value == 0 {
+            return Err(LoadError::NullPointer);
+        }
+
+        // Read in chunks rather than trusting the length enough to allocate
+        // it all up front; a garbage length will usually run into a hole in
+        // memory before too long.
+        let mut bytes = vec![];
+        let mut chunk = [0; 256];
+        while (bytes.len() as u64) < length {
+            let want = usize::try_from(length - bytes.len() as u64)
+                .unwrap_or(usize::MAX)
+                .min(chunk.len());
+            let addr = data_ptr.value + bytes.len() as u64;
+            let n = machine.read_memory(addr, &mut chunk[..want])?;
+            if n != want {
+                return Err(LoadError::DataUnavailable);
+            }
+            bytes.extend_from_slice(&chunk[..n]);
+        }
+        String::from_utf8(bytes).map_err(|_| LoadError::BadUtf8)
+    }
 }
 
 impl Load for Struct {
@@ -422,16 +595,48 @@ impl Load for Struct {
             let t = world.type_by_id(m.type_id).unwrap();
             let ma = addr + m.location;
             let v = Value::from_state(machine, ma, world, t)?;
-            members.push((m.name.clone(), v));
+            members.push((m.name.as_deref().map(str::to_string), v));
         }
 
         Ok(Self {
-            name: s.name.clone(),
+            name: s.name.to_string(),
             members,
         })
     }
 }
 
+/// Loads a union as each of its members, producing a `Struct` with one member
+/// for each interpretation of the data.
+///
+/// Since at most one member of a union is valid at a time, it's normal for
+/// some interpretations to fail to load -- an enum with a bad discriminator,
+/// say. Those members are left out. Failure to access the machine is still an
+/// error.
+fn load_union<M: Machine>(
+    machine: &M,
+    addr: u64,
+    world: &DebugDb,
+    ty: &Type,
+) -> Result<Struct, LoadError<M::Error>> {
+    let Type::Union(u) = ty else { return Err(LoadError::NotAUnion); };
+    let mut members = vec![];
+
+    for m in &u.members {
+        let t = world.type_by_id(m.type_id).unwrap();
+        let ma = addr + m.location;
+        match Value::from_state(machine, ma, world, t) {
+            Ok(v) => members.push((m.name.as_deref().map(str::to_string), v)),
+            Err(LoadError::Machine(e)) => return Err(LoadError::Machine(e)),
+            Err(_) => (),
+        }
+    }
+
+    Ok(Struct {
+        name: u.name.to_string(),
+        members,
+    })
+}
+
 #[derive(Clone, Debug)]
 pub struct Enum {
     pub name: String,
@@ -455,8 +660,8 @@ impl Load for Enum {
         let value = Struct::from_state(machine, va, world, vty)?;
 
         Ok(Self {
-            name: s.name.clone(),
-            disc: v.member.name.as_ref().unwrap().clone(),
+            name: s.name.to_string(),
+            disc: v.member.name.as_ref().unwrap().to_string(),
             value,
         })
     }
@@ -464,8 +669,8 @@ impl Load for Enum {
 
 #[derive(Clone, Debug)]
 pub struct CEnum {
-    name: String,
-    disc: String,
+    pub name: String,
+    pub disc: String,
 }
 
 impl Load for CEnum {
@@ -485,13 +690,12 @@ impl Load for CEnum {
         )?.ok_or(LoadError::DataUnavailable)?;
 
         let e = s
-            .enumerators
-            .get(&disc_value)
-            .ok_or(LoadError::BadDiscriminator(disc_value))?;
+            .enumerator_for_value(disc_value.into())
+            .ok_or(LoadError::BadDiscriminator(disc_value.into()))?;
 
         Ok(Self {
-            name: s.name.clone(),
-            disc: e.name.clone(),
+            name: s.name.to_string(),
+            disc: e.name.to_string(),
         })
     }
 }
@@ -507,6 +711,23 @@ impl Pointer {
     fn is_probably_mut(&self) -> bool {
         self.name.starts_with("&mut") || self.name.starts_with("*mut") || self.name.starts_with("*_")
     }
+
+    /// Follows this pointer, loading the value it points to from `machine`.
+    ///
+    /// This trusts the pointer: if it's dangling, or points to some other type
+    /// than its declared pointee type, you'll get garbage or a `LoadError`.
+    pub fn deref<M: Machine>(
+        &self,
+        machine: &M,
+        world: &DebugDb,
+    ) -> Result<Value, LoadError<M::Error>> {
+        let ty = world.type_by_id(self.dest_type_id)
+            .ok_or(LoadError::UnresolvedPointee)?;
+        if let Type::Unresolved(_) = ty {
+            return Err(LoadError::UnresolvedPointee);
+        }
+        Value::from_state(machine, self.value, world, ty)
+    }
 }
 
 impl Load for Pointer {
@@ -516,11 +737,13 @@ impl Load for Pointer {
         world: &DebugDb,
         ty: &Type,
     ) -> Result<Self, LoadError<M::Error>> {
-        // TODO support pointer sizes
-
         let Type::Pointer(s) = ty else { return Err(LoadError::NotAPointer); };
 
-        let value = load_unsigned(world.endian(),  machine, addr, world.pointer_size())?
+        let size = match world.pointer_byte_size(s) {
+            n @ (1 | 2 | 4 | 8) => n as usize,
+            _ => return Err(LoadError::UnsupportedType),
+        };
+        let value = load_unsigned(world.endian(),  machine, addr, size)?
             .ok_or(LoadError::DataUnavailable)?;
 
         Ok(Self {
@@ -0,0 +1,132 @@
+//! Support for finding debug information in WebAssembly modules.
+//!
+//! Wasm toolchains store DWARF in custom sections named after the usual ELF
+//! sections (`.debug_info` and friends). Code addresses in that DWARF are
+//! offsets from the start of the module's code section contents, rather than
+//! from the start of the module, which is what browsers and most runtimes
+//! report in stack traces. `WasmModule::code_address` converts between the two.
+
+use thiserror::Error;
+use std::convert::TryFrom;
+
+const MAGIC: &[u8; 4] = b"\0asm";
+const VERSION: u32 = 1;
+const SECTION_CUSTOM: u8 = 0;
+const SECTION_CODE: u8 = 10;
+
+#[derive(Clone, Debug, Error)]
+pub enum WasmError {
+    #[error("not a WebAssembly module")]
+    BadMagic,
+    #[error("unsupported WebAssembly version {0}")]
+    UnsupportedVersion(u32),
+    #[error("module truncated at offset {0:#x}")]
+    Truncated(usize),
+    #[error("bad LEB128 value at offset {0:#x}")]
+    BadLeb128(usize),
+    #[error("custom section name at offset {0:#x} is not UTF-8")]
+    BadSectionName(usize),
+}
+
+/// The sections of a WebAssembly module that matter for debugging.
+#[derive(Clone, Debug)]
+pub struct WasmModule<'data> {
+    custom_sections: Vec<(&'data str, &'data [u8])>,
+    code_offset: Option<u64>,
+}
+
+impl<'data> WasmModule<'data> {
+    /// Checks whether `data` starts like a WebAssembly module.
+    pub fn is_wasm(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// Splits a module binary into sections.
+    pub fn parse(data: &'data [u8]) -> Result<Self, WasmError> {
+        if !Self::is_wasm(data) {
+            return Err(WasmError::BadMagic);
+        }
+        let version = data.get(4..8).ok_or(WasmError::Truncated(4))?;
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != VERSION {
+            return Err(WasmError::UnsupportedVersion(version));
+        }
+
+        let mut custom_sections = vec![];
+        let mut code_offset = None;
+        let mut pos = 8;
+        while pos < data.len() {
+            let id = data[pos];
+            pos += 1;
+            let size = read_leb128_len(data, &mut pos)?;
+            let end = pos.checked_add(size)
+                .filter(|&end| end <= data.len())
+                .ok_or(WasmError::Truncated(pos))?;
+            let contents = &data[pos..end];
+
+            match id {
+                SECTION_CUSTOM => {
+                    let mut npos = 0;
+                    let name_len = read_leb128_len(contents, &mut npos)
+                        .map_err(|_| WasmError::BadLeb128(pos))?;
+                    let name = npos.checked_add(name_len)
+                        .and_then(|nend| contents.get(npos..nend))
+                        .ok_or(WasmError::Truncated(pos))?;
+                    let name = std::str::from_utf8(name)
+                        .map_err(|_| WasmError::BadSectionName(pos))?;
+                    custom_sections.push((name, &contents[npos + name_len..]));
+                }
+                SECTION_CODE => {
+                    code_offset = Some(pos as u64);
+                }
+                _ => (),
+            }
+            pos = end;
+        }
+
+        Ok(Self {
+            custom_sections,
+            code_offset,
+        })
+    }
+
+    /// Returns the contents of the custom section called `name`, if present.
+    pub fn custom_section(&self, name: &str) -> Option<&'data [u8]> {
+        self.custom_sections.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, contents)| *contents)
+    }
+
+    /// Returns the offset within the module of the code section's contents,
+    /// or `None` if the module has no code.
+    pub fn code_offset(&self) -> Option<u64> {
+        self.code_offset
+    }
+
+    /// Converts an offset within the module (as found in a stack trace) into
+    /// a code address as used in the module's DWARF. Returns `None` if the
+    /// offset precedes the code section.
+    pub fn code_address(&self, module_offset: u64) -> Option<u64> {
+        module_offset.checked_sub(self.code_offset?)
+    }
+}
+
+/// Reads an unsigned LEB128 value used as a length or size.
+fn read_leb128_len(data: &[u8], pos: &mut usize) -> Result<usize, WasmError> {
+    let start = *pos;
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).ok_or(WasmError::Truncated(*pos))?;
+        *pos += 1;
+        if shift > 28 {
+            return Err(WasmError::BadLeb128(start));
+        }
+        result |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    usize::try_from(result).map_err(|_| WasmError::BadLeb128(start))
+}